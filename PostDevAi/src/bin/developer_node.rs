@@ -79,6 +79,7 @@ fn connect_to_dragon_node(host: &str, port: u16, app: &mut App) -> Result<(), Bo
             history_store: 0.2,
             metadata_store: 0.1,
         },
+        ..RamLakeConfig::default()
     };
     
     // We would actually connect to the real RAM-Lake via gRPC
@@ -9,10 +9,10 @@ use parking_lot::RwLock;
 use tracing::{info, error};
 use tracing_subscriber::{EnvFilter, fmt};
 
-use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, StoreAllocation};
+use postdevai::core::memory::ramlake::{RamLake, AsyncRamLake, RamLakeConfig, StoreAllocation, BackupRetention, IncrementalBackup, RevisionRetention, BlobCompression, SegmentStorage, EvictionConfig, ColdTier, AdaptiveAllocation, HnswConfig};
 use postdevai::core::network::dragon_node_service::{DragonNodeServiceImpl, DragonNodeServiceServer};
 use postdevai::mlx::models::MLXModelManager;
-use postdevai::utils::config::{load_config, ModelConfig};
+use postdevai::utils::config::{load_config, ModelConfig, StoreAllocationConfig};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -37,11 +37,66 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         max_size: config.ramlake.max_size,
         backup_interval: config.ramlake.backup_interval,
         backup_path: PathBuf::from(&config.ramlake.backup_path),
-        allocation: StoreAllocation {
-            vector_store: config.ramlake.allocation.vector_store,
-            code_store: config.ramlake.allocation.code_store,
-            history_store: config.ramlake.allocation.history_store,
-            metadata_store: config.ramlake.allocation.metadata_store,
+        allocation: store_allocation(&config.ramlake.allocation),
+        wal_checkpoint_threshold: config.ramlake.wal_checkpoint_threshold,
+        repair_on_startup: config.ramlake.repair_on_startup,
+        backup_retention: BackupRetention {
+            keep_last: config.ramlake.backup_retention.keep_last,
+            max_age_secs: config.ramlake.backup_retention.max_age_secs,
+            max_total_bytes: config.ramlake.backup_retention.max_total_bytes,
+        },
+        restore_on_empty: config.ramlake.restore_on_empty,
+        incremental_backup: IncrementalBackup {
+            enabled: config.ramlake.incremental_backup.enabled,
+            full_every: config.ramlake.incremental_backup.full_every,
+            compression_level: config.ramlake.incremental_backup.compression_level,
+        },
+        shutdown_timeout: config.ramlake.shutdown_timeout,
+        namespace_quotas: config.ramlake.namespace_quotas.clone(),
+        revision_retention: RevisionRetention {
+            keep_last: config.ramlake.revision_retention.keep_last,
+            max_age_secs: config.ramlake.revision_retention.max_age_secs,
+        },
+        code_compression: BlobCompression {
+            enabled: config.ramlake.code_compression.enabled,
+            level: config.ramlake.code_compression.level,
+            dictionary: config.ramlake.code_compression.dictionary.as_ref().map(PathBuf::from),
+        },
+        history_compression: BlobCompression {
+            enabled: config.ramlake.history_compression.enabled,
+            level: config.ramlake.history_compression.level,
+            dictionary: config.ramlake.history_compression.dictionary.as_ref().map(PathBuf::from),
+        },
+        segment_storage: SegmentStorage {
+            segment_size: config.ramlake.segment_storage.segment_size,
+            compaction_ratio: config.ramlake.segment_storage.compaction_ratio,
+            compaction_interval: config.ramlake.segment_storage.compaction_interval,
+        },
+        eviction: EvictionConfig {
+            policy: config.ramlake.eviction.policy.parse()?,
+            priorities: config.ramlake.eviction.priorities.iter()
+                .map(|(kind, priority)| kind.parse().map(|kind| (kind, *priority)))
+                .collect::<Result<_, String>>()?,
+        },
+        cold_tier: ColdTier {
+            enabled: config.ramlake.cold_tier.enabled,
+            path: PathBuf::from(&config.ramlake.cold_tier.path),
+            demote_after_secs: config.ramlake.cold_tier.demote_after_secs,
+            check_interval: config.ramlake.cold_tier.check_interval,
+        },
+        adaptive_allocation: AdaptiveAllocation {
+            enabled: config.ramlake.adaptive_allocation.enabled,
+            interval: config.ramlake.adaptive_allocation.interval,
+            growth_horizon_secs: config.ramlake.adaptive_allocation.growth_horizon_secs,
+            headroom: config.ramlake.adaptive_allocation.headroom,
+            min: store_allocation(&config.ramlake.adaptive_allocation.min),
+            max: store_allocation(&config.ramlake.adaptive_allocation.max),
+        },
+        hnsw: HnswConfig {
+            enabled: config.ramlake.hnsw.enabled,
+            m: config.ramlake.hnsw.m,
+            ef_construction: config.ramlake.hnsw.ef_construction,
+            ef_search: config.ramlake.hnsw.ef_search,
         },
     };
     
@@ -49,10 +104,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .map_err(|e| format!("Failed to initialize RAM-Lake: {}", e))?;
     
     // Start RAM-Lake background tasks
-    ram_lake.start()
+    let ramlake_handle = ram_lake.start()
         .map_err(|e| format!("Failed to start RAM-Lake background tasks: {}", e))?;
     
-    let ram_lake = Arc::new(RwLock::new(ram_lake));
+    // Serve RAM-Lake calls off the async runtime
+    let ram_lake = AsyncRamLake::new(ram_lake);
     
     // Setup MLX Model Manager
     info!("Initializing MLX Model Manager...");
@@ -85,9 +141,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     info!("Dragon Node server shutting down...");
     
+    // Stop the RAM-Lake tasks and take a final backup before exiting
+    info!("Flushing RAM-Lake...");
+    match tokio::task::spawn_blocking(move || ramlake_handle.shutdown()).await {
+        Ok(Ok(())) => info!("RAM-Lake flushed"),
+        Ok(Err(e)) => error!("Failed to shut down RAM-Lake cleanly: {}", e),
+        Err(e) => error!("RAM-Lake shutdown task failed: {}", e),
+    }
+    
     Ok(())
 }
 
+fn store_allocation(config: &StoreAllocationConfig) -> StoreAllocation {
+    StoreAllocation {
+        vector_store: config.vector_store,
+        code_store: config.code_store,
+        history_store: config.history_store,
+        metadata_store: config.metadata_store,
+    }
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         signal::ctrl_c()
@@ -1,14 +1,156 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex, RwLock};
+use std::collections::{HashMap, HashSet};
+use std::sync::{mpsc, Arc, Condvar, Mutex, RwLock};
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
-use std::path::PathBuf;
-use parking_lot::RwLock as PLRwLock;
+use std::path::{Path, PathBuf};
+use parking_lot::{RwLock as PLRwLock, RwLockWriteGuard as PLRwLockWriteGuard};
 use uuid::Uuid;
 use serde::{Serialize, Deserialize};
 
 // Import store implementations from the stores module
-mod stores;
-use stores::{VectorStore, CodeStore, HistoryStore, MetadataStore, MemoryManager};
+use super::stores::{VectorStore, NewEmbedding, LexicalIndex, CodeStore, HistoryStore, MetadataStore, MemoryManager, CodeMetadata, EventMetadata, WriteAheadLog, EvictionCandidate, EvictionNeeds, default_collection};
+pub use super::stores::{EmbeddingSource, VectorFilter, CODE_EMBEDDING, EVENT_EMBEDDING, UNKNOWN_EMBEDDING};
+pub use super::stores::{CodeChunk, chunk_code};
+pub use super::stores::{FileRevision, RevisionRetention, BlobCompression, SegmentStorage, ColdTier, StorageTier, HnswConfig};
+pub use super::stores::{VectorCollection, VectorCollectionMetrics, DistanceMetric, Quantization, DEFAULT_COLLECTION};
+pub use super::stores::{EntityKind, EvictionConfig, EvictionPolicyKind, EvictionPolicy, EvictionEvent, LruPolicy, PriorityPolicy};
+
+// Backup management
+mod backup;
+pub use backup::{BackupInfo, BackupRetention, BackupManifest, ManifestEntry, IncrementalBackup, StoreBackupState};
+
+// Async facade for the tokio runtime
+mod async_ramlake;
+pub use async_ramlake::{AsyncRamLake, CancelToken, WriteOp, WriteResult};
+
+// Atomic multi-store transactions
+mod transaction;
+pub use transaction::Transaction;
+
+// Cross-store eviction
+mod eviction;
+
+// Hot/cold storage tiering
+mod tiering;
+
+// Runtime store allocation changes
+mod rebalance;
+pub use rebalance::AdaptiveAllocation;
+use rebalance::Rebalancer;
+
+// Re-embedding of stale embeddings
+mod reembed;
+pub use reembed::{Embedder, StaleEmbedding, StaleReason};
+
+// Hybrid lexical and semantic search
+mod search;
+pub use search::{SearchQuery, SearchWeights, SearchHit, ScoreBreakdown};
+use search::{build_lexical_index, code_text, event_text};
+
+// Chunk-level code embeddings
+mod chunks;
+pub use chunks::CodeHit;
+
+/// Store directories inside the RAM disk
+const STORE_DIRS: [&str; 4] = ["vectors", "code", "history", "metadata"];
+
+/// Namespace of entities stored without naming a project
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Namespace of entities written before namespaces existed
+pub(crate) fn default_namespace() -> String {
+    DEFAULT_NAMESPACE.to_string()
+}
+
+/// Memory accounted for a relation: two UUIDs plus the relation name
+fn relation_size(relation: &str) -> u64 {
+    (2 * std::mem::size_of::<Uuid>() + relation.len()) as u64
+}
+
+/// Namespace of a stored code file or event
+fn entity_namespace(code_store: &CodeStore, history_store: &HistoryStore, id: Uuid) -> Option<String> {
+    code_store.get_file_metadata(id).map(|m| m.namespace)
+        .or_else(|_| history_store.get_event_metadata(id).map(|m| m.namespace))
+        .ok()
+}
+
+/// Namespace and attributes of the code file or event an embedding is computed from
+///
+/// Embeddings share the ID of their code file or event; other embeddings get
+/// the default namespace and an unknown source.
+fn embedding_source(code_store: &CodeStore, history_store: &HistoryStore, id: Uuid) -> (String, EmbeddingSource) {
+    if let Ok(metadata) = code_store.get_file_metadata(id) {
+        let source = EmbeddingSource {
+            source_id: id,
+            embedding_type: CODE_EMBEDDING.to_string(),
+            language: Some(metadata.language),
+            path: Some(metadata.path),
+            hash: Some(metadata.hash),
+            start_line: None,
+            end_line: None,
+        };
+        return (metadata.namespace, source);
+    }
+    if let Ok(metadata) = history_store.get_event_metadata(id) {
+        let source = EmbeddingSource {
+            source_id: id,
+            embedding_type: EVENT_EMBEDDING.to_string(),
+            language: None,
+            path: None,
+            hash: None,
+            start_line: None,
+            end_line: None,
+        };
+        return (metadata.namespace, source);
+    }
+    
+    (default_namespace(), EmbeddingSource::unknown(id))
+}
+
+/// Namespace for a new relation: the source's, else the target's, else the default
+fn relation_namespace(code_store: &CodeStore, history_store: &HistoryStore, source_id: Uuid, target_id: Uuid) -> String {
+    entity_namespace(code_store, history_store, source_id)
+        .or_else(|| entity_namespace(code_store, history_store, target_id))
+        .unwrap_or_else(default_namespace)
+}
+
+/// Gather per-namespace metrics from the stores and the memory manager
+fn collect_namespace_metrics(
+    memory_manager: &MemoryManager,
+    vector_store: &VectorStore,
+    code_store: &CodeStore,
+    history_store: &HistoryStore,
+    metadata_store: &MetadataStore,
+) -> HashMap<String, NamespaceMetrics> {
+    let mut namespaces: HashMap<String, NamespaceMetrics> = HashMap::new();
+    
+    for (namespace, used_size) in memory_manager.get_namespace_usages() {
+        namespaces.entry(namespace).or_default().used_size = used_size;
+    }
+    for (namespace, (count, size)) in code_store.get_namespace_stats() {
+        let m = namespaces.entry(namespace).or_default();
+        m.indexed_files = count;
+        m.code_size = size;
+    }
+    for (namespace, (count, size)) in vector_store.get_namespace_stats() {
+        let m = namespaces.entry(namespace).or_default();
+        m.vector_entries = count;
+        m.vector_size = size;
+    }
+    for (namespace, (count, size)) in history_store.get_namespace_stats() {
+        let m = namespaces.entry(namespace).or_default();
+        m.history_events = count;
+        m.history_size = size;
+    }
+    for (namespace, count) in metadata_store.get_namespace_relation_counts() {
+        namespaces.entry(namespace).or_default().relations = count;
+    }
+    for (namespace, m) in namespaces.iter_mut() {
+        m.quota = memory_manager.get_namespace_quota(namespace);
+    }
+    
+    namespaces
+}
 
 /// Main RAM-Lake implementation for PostDevAI
 /// Provides high-speed memory storage and indexing
@@ -39,6 +181,18 @@ pub struct RamLake {
     
     /// Last backup timestamp
     last_backup: Arc<Mutex<Instant>>,
+    
+    /// Undo journal of the transaction being committed
+    journal: Mutex<WriteAheadLog>,
+    
+    /// Store growth observed by adaptive allocation
+    rebalancer: Arc<Mutex<Rebalancer>>,
+    
+    /// Embedder of each vector collection, for re-embedding stale embeddings
+    embedders: PLRwLock<HashMap<String, Arc<dyn Embedder>>>,
+    
+    /// BM25 index over code and event text, built on open and updated by every write
+    lexical: PLRwLock<LexicalIndex>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +208,100 @@ pub struct RamLakeConfig {
     
     /// Percentage allocation for different stores
     pub allocation: StoreAllocation,
+    
+    /// Number of write-ahead log records after which a store checkpoints its snapshot files
+    #[serde(default = "default_wal_checkpoint_threshold")]
+    pub wal_checkpoint_threshold: usize,
+    
+    /// Run a consistency check on startup and repair any issues found
+    #[serde(default)]
+    pub repair_on_startup: bool,
+    
+    /// Retention policy applied after every backup
+    #[serde(default)]
+    pub backup_retention: BackupRetention,
+    
+    /// Restore the latest backup when the RAM disk is empty (e.g. after a reboot)
+    #[serde(default)]
+    pub restore_on_empty: bool,
+    
+    /// Incremental backup settings
+    #[serde(default)]
+    pub incremental_backup: IncrementalBackup,
+    
+    /// Maximum time in seconds `RamLakeHandle::shutdown` may take
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout: u64,
+    
+    /// Maximum memory in bytes per namespace; namespaces not listed are unlimited
+    #[serde(default)]
+    pub namespace_quotas: HashMap<String, u64>,
+    
+    /// Which code file revisions to keep
+    #[serde(default)]
+    pub revision_retention: RevisionRetention,
+    
+    /// Compression of code file contents
+    #[serde(default)]
+    pub code_compression: BlobCompression,
+    
+    /// Compression of event contents
+    #[serde(default)]
+    pub history_compression: BlobCompression,
+    
+    /// Segment files and compaction of the vector, code and history stores
+    #[serde(default)]
+    pub segment_storage: SegmentStorage,
+    
+    /// What to evict when a store or the RAM-Lake is full
+    #[serde(default)]
+    pub eviction: EvictionConfig,
+    
+    /// Persistent disk tier for rarely accessed entries
+    #[serde(default)]
+    pub cold_tier: ColdTier,
+    
+    /// Move capacity between the stores at runtime
+    #[serde(default)]
+    pub adaptive_allocation: AdaptiveAllocation,
+    
+    /// Approximate nearest-neighbour index of the vector store
+    #[serde(default)]
+    pub hnsw: HnswConfig,
+}
+
+fn default_wal_checkpoint_threshold() -> usize {
+    1000
+}
+
+fn default_shutdown_timeout() -> u64 {
+    30
+}
+
+impl Default for RamLakeConfig {
+    fn default() -> Self {
+        Self {
+            max_size: 200 * 1024 * 1024 * 1024, // 200 GB
+            backup_interval: 3600,
+            backup_path: PathBuf::from("/var/backups/ramlake"),
+            allocation: StoreAllocation::default(),
+            wal_checkpoint_threshold: default_wal_checkpoint_threshold(),
+            repair_on_startup: false,
+            backup_retention: BackupRetention::default(),
+            restore_on_empty: false,
+            incremental_backup: IncrementalBackup::default(),
+            shutdown_timeout: default_shutdown_timeout(),
+            namespace_quotas: HashMap::new(),
+            revision_retention: RevisionRetention::default(),
+            code_compression: BlobCompression::default(),
+            history_compression: BlobCompression::default(),
+            segment_storage: SegmentStorage::default(),
+            eviction: EvictionConfig::default(),
+            cold_tier: ColdTier::default(),
+            adaptive_allocation: AdaptiveAllocation::default(),
+            hnsw: HnswConfig::default(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,6 +319,17 @@ pub struct StoreAllocation {
     pub metadata_store: f32,
 }
 
+impl Default for StoreAllocation {
+    fn default() -> Self {
+        Self {
+            vector_store: 0.4,
+            code_store: 0.3,
+            history_store: 0.2,
+            metadata_store: 0.1,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RamLakeMetrics {
     /// Total RAM-Lake size in bytes
@@ -88,6 +347,14 @@ pub struct RamLakeMetrics {
     /// History store size in bytes
     pub history_store_size: u64,
     
+    /// Uncompressed size of the code store contents in bytes
+    #[serde(default)]
+    pub code_logical_size: u64,
+    
+    /// Uncompressed size of the history store contents in bytes
+    #[serde(default)]
+    pub history_logical_size: u64,
+    
     /// Metadata store size in bytes
     pub metadata_store_size: u64,
     
@@ -99,6 +366,167 @@ pub struct RamLakeMetrics {
     
     /// Number of history events
     pub history_events: usize,
+    
+    /// Metrics per namespace
+    #[serde(default)]
+    pub namespaces: HashMap<String, NamespaceMetrics>,
+    
+    /// Number of entries evicted since startup
+    #[serde(default)]
+    pub evicted_entries: u64,
+    
+    /// Bytes freed by eviction since startup
+    #[serde(default)]
+    pub evicted_size: u64,
+    
+    /// Most recent evictions, newest first
+    #[serde(default)]
+    pub recent_evictions: Vec<EvictionEvent>,
+    
+    /// Stored size of the entries in the cold tier in bytes
+    #[serde(default)]
+    pub cold_size: u64,
+    
+    /// Vector search metrics per collection
+    #[serde(default)]
+    pub vector_collections: HashMap<String, VectorCollectionMetrics>,
+}
+
+/// RAM-Lake metrics of a single namespace
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NamespaceMetrics {
+    /// Memory accounted to the namespace in bytes
+    pub used_size: u64,
+    
+    /// Memory quota of the namespace in bytes
+    pub quota: Option<u64>,
+    
+    /// Size of the namespace's code files in bytes
+    pub code_size: u64,
+    
+    /// Size of the namespace's embeddings in bytes
+    pub vector_size: u64,
+    
+    /// Size of the namespace's events in bytes
+    pub history_size: u64,
+    
+    /// Number of indexed files
+    pub indexed_files: usize,
+    
+    /// Number of vector entries
+    pub vector_entries: usize,
+    
+    /// Number of history events
+    pub history_events: usize,
+    
+    /// Number of relations
+    pub relations: usize,
+}
+
+/// Consistency problem found by `RamLake::verify`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ConsistencyIssue {
+    /// Blob file in a store directory that no metadata entry refers to
+    OrphanedBlob { store: String, file: String },
+    
+    /// Metadata entry whose blob file is missing
+    MissingBlob { store: String, id: Uuid },
+    
+    /// Index `count` does not match the number of indexed IDs
+    CountMismatch { store: String, count: usize, actual: usize },
+    
+    /// Leftover temporary file from an interrupted atomic write
+    StaleTempFile { store: String, file: String },
+    
+    /// Relation whose source or target is not a known entity
+    DanglingRelation { source_id: Uuid, relation: String, target_id: Uuid },
+}
+
+/// Result of a RAM-Lake consistency check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsistencyReport {
+    /// Issues found during the check
+    pub issues: Vec<ConsistencyIssue>,
+    
+    /// Whether the issues were repaired
+    pub repaired: bool,
+}
+
+impl ConsistencyReport {
+    /// Whether the check found no issues
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Number of evictions included in the metrics
+const METRICS_EVICTIONS: usize = 20;
+
+/// Stop signal shared with the background tasks
+type StopSignal = Arc<(Mutex<bool>, Condvar)>;
+
+/// Handle to the RAM-Lake background tasks returned by `RamLake::start`
+///
+/// Dropping the handle leaves the tasks running; call `shutdown` to stop them.
+pub struct RamLakeHandle {
+    /// Stop flag the tasks wait on between ticks
+    stop: StopSignal,
+    
+    /// Running tasks by name
+    tasks: Vec<(&'static str, JoinHandle<()>)>,
+    
+    /// Final checkpoint and backup run once the tasks have stopped
+    final_flush: Box<dyn FnOnce() -> Result<(), String> + Send>,
+    
+    /// Maximum time the shutdown may take
+    timeout: Duration,
+}
+
+impl RamLakeHandle {
+    /// Stop the background tasks and flush the stores with a final backup
+    ///
+    /// Returns an error if the tasks or the final backup do not finish within
+    /// the configured shutdown timeout; the process can then exit regardless.
+    pub fn shutdown(self) -> Result<(), String> {
+        let deadline = Instant::now() + self.timeout;
+        
+        {
+            let (stopped, wakeup) = &*self.stop;
+            *stopped.lock().unwrap() = true;
+            wakeup.notify_all();
+        }
+        
+        // A backup in progress finishes before its task notices the signal
+        for (name, task) in self.tasks {
+            while !task.is_finished() {
+                if Instant::now() >= deadline {
+                    return Err(format!("Timed out waiting for the RAM-Lake {} task to stop", name));
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            
+            task.join()
+                .map_err(|_| format!("RAM-Lake {} task panicked", name))?;
+        }
+        
+        // Run the final flush on a worker so a slow disk cannot hold up exit
+        let (sender, receiver) = mpsc::channel();
+        let final_flush = self.final_flush;
+        std::thread::spawn(move || {
+            let _ = sender.send(final_flush());
+        });
+        
+        receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            .map_err(|_| "Timed out during the final RAM-Lake backup".to_string())?
+    }
+}
+
+/// Wait for the stop signal, returning true once shutdown was requested
+fn wait_for_stop(stop: &StopSignal, timeout: Duration) -> bool {
+    let (stopped, wakeup) = &**stop;
+    let guard = stopped.lock().unwrap();
+    let (guard, _) = wakeup.wait_timeout_while(guard, timeout, |stopped| !*stopped).unwrap();
+    *guard
 }
 
 impl RamLake {
@@ -108,34 +536,33 @@ impl RamLake {
             return Err(format!("RAM disk path does not exist: {:?}", ramdisk_path));
         }
         
-        // Create store directories
-        let vector_path = ramdisk_path.join("vectors");
-        let code_path = ramdisk_path.join("code");
-        let history_path = ramdisk_path.join("history");
-        let metadata_path = ramdisk_path.join("metadata");
-        
-        std::fs::create_dir_all(&vector_path)
-            .map_err(|e| format!("Failed to create vector directory: {}", e))?;
-        std::fs::create_dir_all(&code_path)
-            .map_err(|e| format!("Failed to create code directory: {}", e))?;
-        std::fs::create_dir_all(&history_path)
-            .map_err(|e| format!("Failed to create history directory: {}", e))?;
-        std::fs::create_dir_all(&metadata_path)
-            .map_err(|e| format!("Failed to create metadata directory: {}", e))?;
+        // Warm start: repopulate an empty RAM disk from the latest backup
+        if config.restore_on_empty && Self::stores_empty(&ramdisk_path) {
+            if let Some(latest) = backup::list_backups(&config.backup_path)?.into_iter().next() {
+                backup::restore_backup_files(&latest, &ramdisk_path, &STORE_DIRS)?;
+            }
+        }
         
-        // Calculate size allocations
+        // Create stores (each replays its write-ahead log on open)
         let total_size = config.max_size;
-        let vector_size = (total_size as f64 * config.allocation.vector_store as f64) as u64;
-        let code_size = (total_size as f64 * config.allocation.code_store as f64) as u64;
-        let history_size = (total_size as f64 * config.allocation.history_store as f64) as u64;
-        let metadata_size = (total_size as f64 * config.allocation.metadata_store as f64) as u64;
-        
-        // Create stores
-        let memory_manager = Arc::new(PLRwLock::new(MemoryManager::new(total_size)));
-        let vector_store = Arc::new(PLRwLock::new(VectorStore::new(vector_path, vector_size)?));
-        let code_store = Arc::new(PLRwLock::new(CodeStore::new(code_path, code_size)?));
-        let history_store = Arc::new(PLRwLock::new(HistoryStore::new(history_path, history_size)?));
-        let metadata_store = Arc::new(PLRwLock::new(MetadataStore::new(metadata_path, metadata_size)?));
+        let (vector_store, code_store, history_store, metadata_store) = Self::open_stores(&ramdisk_path, &config)?;
+        
+        // Account for the data that survived from a previous run
+        let mut memory_manager = MemoryManager::new(total_size);
+        Self::account_existing(&mut memory_manager, &vector_store, &code_store, &history_store, &metadata_store);
+        for (namespace, quota) in &config.namespace_quotas {
+            memory_manager.set_namespace_quota(namespace, Some(*quota));
+        }
+        memory_manager.set_eviction_policy(config.eviction.build_policy());
+        memory_manager.set_pinned(eviction::load_pins(&ramdisk_path)?);
+        let memory_manager = Arc::new(PLRwLock::new(memory_manager));
+        
+        let lexical = build_lexical_index(&code_store, &history_store);
+        
+        let vector_store = Arc::new(PLRwLock::new(vector_store));
+        let code_store = Arc::new(PLRwLock::new(code_store));
+        let history_store = Arc::new(PLRwLock::new(history_store));
+        let metadata_store = Arc::new(PLRwLock::new(metadata_store));
         
         let metrics = Arc::new(PLRwLock::new(RamLakeMetrics {
             total_size,
@@ -143,13 +570,24 @@ impl RamLake {
             vector_store_size: 0,
             code_store_size: 0,
             history_store_size: 0,
+            code_logical_size: 0,
+            history_logical_size: 0,
             metadata_store_size: 0,
             indexed_files: 0,
             vector_entries: 0,
             history_events: 0,
+            namespaces: HashMap::new(),
+            evicted_entries: 0,
+            evicted_size: 0,
+            recent_evictions: Vec::new(),
+            cold_size: 0,
+            vector_collections: HashMap::new(),
         }));
         
-        Ok(Self {
+        // Open the transaction journal
+        let journal = WriteAheadLog::open(ramdisk_path.join(transaction::JOURNAL_FILE))?;
+        
+        let ram_lake = Self {
             ramdisk_path,
             config,
             memory_manager,
@@ -159,36 +597,226 @@ impl RamLake {
             metadata_store,
             metrics,
             last_backup: Arc::new(Mutex::new(Instant::now())),
+            journal: Mutex::new(journal),
+            rebalancer: Arc::new(Mutex::new(Rebalancer::default())),
+            embedders: PLRwLock::new(HashMap::new()),
+            lexical: PLRwLock::new(lexical),
+        };
+        
+        // Undo a transaction that was interrupted by a crash
+        ram_lake.recover_transaction()?;
+        
+        // Repair leftovers from a crash before serving requests
+        if ram_lake.config.repair_on_startup {
+            let report = ram_lake.verify(true)?;
+            for issue in &report.issues {
+                eprintln!("Repaired RAM-Lake consistency issue: {:?}", issue);
+            }
+        }
+        
+        // Existing data may exceed a RAM-Lake that shrank since the last run
+        ram_lake.make_room(&[], &HashSet::new())?;
+        
+        Ok(ram_lake)
+    }
+    
+    /// Create the store directories and open the stores
+    fn open_stores(ramdisk_path: &Path, config: &RamLakeConfig) -> Result<(VectorStore, CodeStore, HistoryStore, MetadataStore), String> {
+        // Create store directories
+        let vector_path = ramdisk_path.join("vectors");
+        let code_path = ramdisk_path.join("code");
+        let history_path = ramdisk_path.join("history");
+        let metadata_path = ramdisk_path.join("metadata");
+        
+        std::fs::create_dir_all(&vector_path)
+            .map_err(|e| format!("Failed to create vector directory: {}", e))?;
+        std::fs::create_dir_all(&code_path)
+            .map_err(|e| format!("Failed to create code directory: {}", e))?;
+        std::fs::create_dir_all(&history_path)
+            .map_err(|e| format!("Failed to create history directory: {}", e))?;
+        std::fs::create_dir_all(&metadata_path)
+            .map_err(|e| format!("Failed to create metadata directory: {}", e))?;
+        
+        // Calculate size allocations, preferring the one last set at runtime
+        let allocation = rebalance::load_allocation(ramdisk_path)?.unwrap_or_else(|| config.allocation.clone());
+        let total_size = config.max_size;
+        let vector_size = (total_size as f64 * allocation.vector_store as f64) as u64;
+        let code_size = (total_size as f64 * allocation.code_store as f64) as u64;
+        let history_size = (total_size as f64 * allocation.history_store as f64) as u64;
+        let metadata_size = (total_size as f64 * allocation.metadata_store as f64) as u64;
+        
+        let mut vector_store = VectorStore::new(vector_path, vector_size)?;
+        let mut code_store = CodeStore::new(code_path, code_size)?;
+        let mut history_store = HistoryStore::new(history_path, history_size)?;
+        let mut metadata_store = MetadataStore::new(metadata_path, metadata_size)?;
+        
+        vector_store.set_checkpoint_threshold(config.wal_checkpoint_threshold);
+        code_store.set_checkpoint_threshold(config.wal_checkpoint_threshold);
+        history_store.set_checkpoint_threshold(config.wal_checkpoint_threshold);
+        metadata_store.set_checkpoint_threshold(config.wal_checkpoint_threshold);
+        code_store.set_revision_retention(config.revision_retention.clone());
+        
+        // Entries demoted earlier stay readable while demotion is disabled
+        if config.cold_tier.enabled || config.cold_tier.path.exists() {
+            vector_store.set_cold_tier(&config.cold_tier.path.join("vectors"))?;
+            code_store.set_cold_tier(&config.cold_tier.path.join("code"))?;
+            history_store.set_cold_tier(&config.cold_tier.path.join("history"))?;
+        }
+        vector_store.set_hnsw(&config.hnsw)?;
+        code_store.set_compression(&config.code_compression)?;
+        history_store.set_compression(&config.history_compression)?;
+        vector_store.set_segment_size(config.segment_storage.segment_size);
+        code_store.set_segment_size(config.segment_storage.segment_size);
+        history_store.set_segment_size(config.segment_storage.segment_size);
+        Self::fill_embedding_sources(&mut vector_store, &code_store, &history_store)?;
+        
+        // A store never starts with less room than it already holds
+        vector_store.set_max_size(vector_size.max(vector_store.get_size()));
+        code_store.set_max_size(code_size.max(code_store.get_size()));
+        history_store.set_max_size(history_size.max(history_store.get_size()));
+        metadata_store.set_max_size(metadata_size.max(metadata_store.get_size()));
+        
+        Ok((vector_store, code_store, history_store, metadata_store))
+    }
+    
+    /// Record the source attributes of embeddings stored before they were tracked
+    fn fill_embedding_sources(vector_store: &mut VectorStore, code_store: &CodeStore, history_store: &HistoryStore) -> Result<(), String> {
+        let unknown: Vec<Uuid> = vector_store.get_all_metadata().into_iter()
+            .filter(|m| m.embedding_type == UNKNOWN_EMBEDDING)
+            .map(|m| m.id)
+            .collect();
+        for id in unknown {
+            let (_, source) = embedding_source(code_store, history_store, id);
+            if source.embedding_type != UNKNOWN_EMBEDDING {
+                vector_store.set_source(id, source)?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Allocate the memory held by entities already in the stores
+    fn account_existing(
+        memory_manager: &mut MemoryManager,
+        vector_store: &VectorStore,
+        code_store: &CodeStore,
+        history_store: &HistoryStore,
+        metadata_store: &MetadataStore,
+    ) {
+        // Sizes per (store, namespace); the cold tier takes no RAM
+        let mut sizes: HashMap<(&str, String), u64> = HashMap::new();
+        for (namespace, (_, size)) in vector_store.get_namespace_stats() {
+            *sizes.entry(("vectors", namespace)).or_default() += size;
+        }
+        for (namespace, (_, size)) in code_store.get_namespace_stats() {
+            *sizes.entry(("code", namespace)).or_default() += size;
+        }
+        for (namespace, (_, size)) in history_store.get_namespace_stats() {
+            *sizes.entry(("history", namespace)).or_default() += size;
+        }
+        for (source_id, relation, target_id) in metadata_store.get_all_relations() {
+            let namespace = metadata_store.get_relation_namespace(source_id, &relation, target_id);
+            *sizes.entry(("metadata", namespace)).or_default() += relation_size(&relation);
+        }
+        
+        // Data already stored counts even when it exceeds the quota or the RAM-Lake size
+        for ((store, namespace), size) in sizes {
+            if size > 0 {
+                memory_manager.record_existing(size, &format!("existing:{}", store));
+                memory_manager.record_namespace_usage(&namespace, size);
+            }
+        }
+    }
+    
+    /// Whether none of the store directories contain any files
+    fn stores_empty(ramdisk_path: &Path) -> bool {
+        STORE_DIRS.iter().all(|dir| {
+            std::fs::read_dir(ramdisk_path.join(dir))
+                .map(|mut entries| entries.next().is_none())
+                .unwrap_or(true)
+        })
+    }
+    
+    /// Check the consistency of all stores, optionally repairing the issues found
+    pub fn verify(&self, repair: bool) -> Result<ConsistencyReport, String> {
+        let mut issues = Vec::new();
+        
+        issues.extend(self.vector_store.write().verify(repair)?);
+        issues.extend(self.code_store.write().verify(repair)?);
+        issues.extend(self.history_store.write().verify(repair)?);
+        
+        // Relations may only point at entities that exist in one of the stores
+        let mut known_ids: HashSet<Uuid> = HashSet::new();
+        known_ids.extend(self.vector_store.read().get_ids());
+        known_ids.extend(self.code_store.read().get_ids());
+        known_ids.extend(self.history_store.read().get_ids());
+        
+        issues.extend(self.metadata_store.write().verify(&known_ids, repair)?);
+        
+        // Repairs drop entries without going through the write paths
+        if repair && !issues.is_empty() {
+            let lexical = build_lexical_index(&self.code_store.read(), &self.history_store.read());
+            *self.lexical.write() = lexical;
+        }
+        
+        Ok(ConsistencyReport {
+            issues,
+            repaired: repair,
         })
     }
     
     /// Start the RAM-Lake background tasks
-    pub fn start(&self) -> Result<(), String> {
+    ///
+    /// The returned handle stops the tasks and takes a final backup on shutdown.
+    pub fn start(&self) -> Result<RamLakeHandle, String> {
+        let stop: StopSignal = Arc::new((Mutex::new(false), Condvar::new()));
+        
         // Start backup task
         let backup_interval = Duration::from_secs(self.config.backup_interval);
-        let backup_path = self.config.backup_path.clone();
+        let config = self.config.clone();
         let last_backup = self.last_backup.clone();
         let ramdisk_path = self.ramdisk_path.clone();
+        let vector_store = self.vector_store.clone();
+        let code_store = self.code_store.clone();
+        let history_store = self.history_store.clone();
+        let metadata_store = self.metadata_store.clone();
+        let memory_manager = self.memory_manager.clone();
+        let backup_stop = stop.clone();
         
-        std::thread::spawn(move || {
-            loop {
-                let now = Instant::now();
-                let last = *last_backup.lock().unwrap();
-                
-                if now.duration_since(last) >= backup_interval {
-                    // Perform backup
-                    if let Err(e) = Self::backup_ramlake(&ramdisk_path, &backup_path) {
-                        eprintln!("Failed to backup RAM-Lake: {}", e);
+        let backup_task = std::thread::Builder::new()
+            .name("ramlake-backup".to_string())
+            .spawn(move || {
+                loop {
+                    let now = Instant::now();
+                    let last = *last_backup.lock().unwrap();
+                    
+                    if now.duration_since(last) >= backup_interval {
+                        // Drop expired code revisions before they get backed up
+                        if let Err(e) = Self::prune_code_revisions(&code_store, &memory_manager) {
+                            eprintln!("Failed to prune code revisions: {}", e);
+                        }
+                        
+                        // Perform backup
+                        if let Err(e) = Self::backup_stores(&ramdisk_path, &config, &vector_store, &code_store, &history_store, &metadata_store) {
+                            eprintln!("Failed to backup RAM-Lake: {}", e);
+                        }
+                        
+                        // Apply retention policy
+                        if let Err(e) = backup::prune_backups(&config.backup_path, &config.backup_retention) {
+                            eprintln!("Failed to prune RAM-Lake backups: {}", e);
+                        }
+                        
+                        // Update last backup time
+                        *last_backup.lock().unwrap() = Instant::now();
                     }
                     
-                    // Update last backup time
-                    *last_backup.lock().unwrap() = Instant::now();
+                    // Sleep for a bit
+                    if wait_for_stop(&backup_stop, Duration::from_secs(1)) {
+                        break;
+                    }
                 }
-                
-                // Sleep for a bit
-                std::thread::sleep(Duration::from_secs(1));
-            }
-        });
+            })
+            .map_err(|e| format!("Failed to spawn backup task: {}", e))?;
         
         // Start metrics collection task
         let metrics = self.metrics.clone();
@@ -196,48 +824,319 @@ impl RamLake {
         let code_store = self.code_store.clone();
         let history_store = self.history_store.clone();
         let metadata_store = self.metadata_store.clone();
+        let memory_manager = self.memory_manager.clone();
+        let metrics_stop = stop.clone();
         
-        std::thread::spawn(move || {
-            loop {
-                // Update metrics
-                let mut m = metrics.write();
-                
-                m.vector_store_size = vector_store.read().get_size();
-                m.code_store_size = code_store.read().get_size();
-                m.history_store_size = history_store.read().get_size();
-                m.metadata_store_size = metadata_store.read().get_size();
-                
-                m.used_size = m.vector_store_size + m.code_store_size + m.history_store_size + m.metadata_store_size;
-                
-                m.indexed_files = code_store.read().get_file_count();
-                m.vector_entries = vector_store.read().get_entry_count();
-                m.history_events = history_store.read().get_event_count();
-                
-                // Sleep for a bit
-                std::thread::sleep(Duration::from_secs(1));
-            }
+        let metrics_task = std::thread::Builder::new()
+            .name("ramlake-metrics".to_string())
+            .spawn(move || {
+                loop {
+                    // Collect outside the metrics lock so readers are never blocked on the stores
+                    let vector_store_size = vector_store.read().get_size();
+                    let code_store_size = code_store.read().get_size();
+                    let history_store_size = history_store.read().get_size();
+                    let metadata_store_size = metadata_store.read().get_size();
+                    let code_logical_size = code_store.read().get_logical_size();
+                    let history_logical_size = history_store.read().get_logical_size();
+                    let cold_size = vector_store.read().get_cold_size()
+                        + code_store.read().get_cold_size()
+                        + history_store.read().get_cold_size();
+                    
+                    let indexed_files = code_store.read().get_file_count();
+                    let vector_entries = vector_store.read().get_entry_count();
+                    let vector_collections = vector_store.read().get_collection_metrics();
+                    let history_events = history_store.read().get_event_count();
+                    
+                    let namespaces = {
+                        let vector_store = vector_store.read();
+                        let code_store = code_store.read();
+                        let history_store = history_store.read();
+                        let metadata_store = metadata_store.read();
+                        let memory_manager = memory_manager.read();
+                        collect_namespace_metrics(&memory_manager, &vector_store, &code_store, &history_store, &metadata_store)
+                    };
+                    
+                    let ((evicted_entries, evicted_size), recent_evictions) = {
+                        let memory_manager = memory_manager.read();
+                        (memory_manager.get_eviction_totals(), memory_manager.get_recent_evictions(METRICS_EVICTIONS))
+                    };
+                    
+                    // Update metrics
+                    {
+                        let mut m = metrics.write();
+                        
+                        m.vector_store_size = vector_store_size;
+                        m.code_store_size = code_store_size;
+                        m.history_store_size = history_store_size;
+                        m.metadata_store_size = metadata_store_size;
+                        m.code_logical_size = code_logical_size;
+                        m.history_logical_size = history_logical_size;
+                        
+                        m.used_size = vector_store_size + code_store_size + history_store_size + metadata_store_size;
+                        
+                        m.indexed_files = indexed_files;
+                        m.vector_entries = vector_entries;
+                        m.history_events = history_events;
+                        m.namespaces = namespaces;
+                        m.evicted_entries = evicted_entries;
+                        m.evicted_size = evicted_size;
+                        m.recent_evictions = recent_evictions;
+                        m.cold_size = cold_size;
+                        m.vector_collections = vector_collections;
+                    }
+                    
+                    // Sleep for a bit
+                    if wait_for_stop(&metrics_stop, Duration::from_secs(1)) {
+                        break;
+                    }
+                }
+            })
+            .map_err(|e| format!("Failed to spawn metrics task: {}", e))?;
+        
+        // Start segment compaction task
+        let compaction_interval = Duration::from_secs(self.config.segment_storage.compaction_interval.max(1));
+        let compaction_ratio = self.config.segment_storage.compaction_ratio;
+        let vector_store = self.vector_store.clone();
+        let code_store = self.code_store.clone();
+        let history_store = self.history_store.clone();
+        let compaction_stop = stop.clone();
+        
+        let compaction_task = std::thread::Builder::new()
+            .name("ramlake-compaction".to_string())
+            .spawn(move || {
+                while !wait_for_stop(&compaction_stop, compaction_interval) {
+                    if let Err(e) = Self::compact_stores(&vector_store, &code_store, &history_store, compaction_ratio) {
+                        eprintln!("Failed to compact RAM-Lake segments: {}", e);
+                    }
+                }
+            })
+            .map_err(|e| format!("Failed to spawn compaction task: {}", e))?;
+        
+        let mut tasks = vec![("backup", backup_task), ("metrics", metrics_task), ("compaction", compaction_task)];
+        
+        // Start cold tier demotion task
+        if self.config.cold_tier.enabled {
+            let tiering_interval = Duration::from_secs(self.config.cold_tier.check_interval.max(1));
+            let demote_after_secs = self.config.cold_tier.demote_after_secs;
+            let vector_store = self.vector_store.clone();
+            let code_store = self.code_store.clone();
+            let history_store = self.history_store.clone();
+            let memory_manager = self.memory_manager.clone();
+            let tiering_stop = stop.clone();
+            
+            let tiering_task = std::thread::Builder::new()
+                .name("ramlake-tiering".to_string())
+                .spawn(move || {
+                    while !wait_for_stop(&tiering_stop, tiering_interval) {
+                        if let Err(e) = Self::demote_stores(&vector_store, &code_store, &history_store, &memory_manager, demote_after_secs) {
+                            eprintln!("Failed to demote RAM-Lake entries to the cold tier: {}", e);
+                        }
+                    }
+                })
+                .map_err(|e| format!("Failed to spawn tiering task: {}", e))?;
+            tasks.push(("tiering", tiering_task));
+        }
+        
+        // Start store rebalancing task
+        if self.config.adaptive_allocation.enabled {
+            let rebalance_interval = Duration::from_secs(self.config.adaptive_allocation.interval.max(1));
+            let config = self.config.clone();
+            let vector_store = self.vector_store.clone();
+            let code_store = self.code_store.clone();
+            let history_store = self.history_store.clone();
+            let metadata_store = self.metadata_store.clone();
+            let rebalancer = self.rebalancer.clone();
+            let ramdisk_path = self.ramdisk_path.clone();
+            let rebalance_stop = stop.clone();
+            
+            let rebalance_task = std::thread::Builder::new()
+                .name("ramlake-rebalance".to_string())
+                .spawn(move || {
+                    while !wait_for_stop(&rebalance_stop, rebalance_interval) {
+                        if let Err(e) = Self::rebalance_stores(&ramdisk_path, &vector_store, &code_store, &history_store, &metadata_store, &rebalancer, &config) {
+                            eprintln!("Failed to rebalance RAM-Lake stores: {}", e);
+                        }
+                    }
+                })
+                .map_err(|e| format!("Failed to spawn rebalance task: {}", e))?;
+            tasks.push(("rebalance", rebalance_task));
+        }
+        
+        // Final flush once all tasks are stopped
+        let config = self.config.clone();
+        let last_backup = self.last_backup.clone();
+        let ramdisk_path = self.ramdisk_path.clone();
+        let vector_store = self.vector_store.clone();
+        let code_store = self.code_store.clone();
+        let history_store = self.history_store.clone();
+        let metadata_store = self.metadata_store.clone();
+        
+        let final_flush = Box::new(move || {
+            Self::backup_stores(&ramdisk_path, &config, &vector_store, &code_store, &history_store, &metadata_store)?;
+            backup::prune_backups(&config.backup_path, &config.backup_retention)?;
+            *last_backup.lock().unwrap() = Instant::now();
+            
+            Ok(())
         });
         
+        Ok(RamLakeHandle {
+            stop,
+            tasks,
+            final_flush,
+            timeout: Duration::from_secs(self.config.shutdown_timeout),
+        })
+    }
+    
+    /// Checkpoint all stores, folding their write-ahead logs into the snapshot files
+    pub fn checkpoint(&self) -> Result<(), String> {
+        Self::checkpoint_stores(&self.vector_store, &self.code_store, &self.history_store, &self.metadata_store)
+    }
+    
+    /// Checkpoint the given stores
+    fn checkpoint_stores(
+        vector_store: &PLRwLock<VectorStore>,
+        code_store: &PLRwLock<CodeStore>,
+        history_store: &PLRwLock<HistoryStore>,
+        metadata_store: &PLRwLock<MetadataStore>,
+    ) -> Result<(), String> {
+        vector_store.write().checkpoint()?;
+        code_store.write().checkpoint()?;
+        history_store.write().checkpoint()?;
+        metadata_store.write().checkpoint()?;
+        
         Ok(())
     }
     
-    /// Backup the RAM-Lake to disk
-    fn backup_ramlake(ramdisk_path: &PathBuf, backup_path: &PathBuf) -> Result<(), String> {
-        // Create backup directory if it doesn't exist
-        std::fs::create_dir_all(backup_path)
-            .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+    /// Compact the segment files of the vector, code and history stores
+    ///
+    /// A store is only compacted once the configured fraction of its sealed
+    /// segments is reclaimable. Returns the number of bytes reclaimed.
+    pub fn compact_segments(&self) -> Result<u64, String> {
+        Self::compact_stores(&self.vector_store, &self.code_store, &self.history_store, self.config.segment_storage.compaction_ratio)
+    }
+    
+    /// Compact the segments of the given stores, one store lock at a time
+    fn compact_stores(
+        vector_store: &PLRwLock<VectorStore>,
+        code_store: &PLRwLock<CodeStore>,
+        history_store: &PLRwLock<HistoryStore>,
+        min_dead_ratio: f64,
+    ) -> Result<u64, String> {
+        let mut reclaimed = vector_store.write().compact(min_dead_ratio)?;
+        reclaimed += code_store.write().compact(min_dead_ratio)?;
+        reclaimed += history_store.write().compact(min_dead_ratio)?;
         
-        // Create a timestamped backup directory
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
-        let backup_dir = backup_path.join(format!("ramlake_backup_{}", timestamp));
+        Ok(reclaimed)
+    }
+    
+    /// Checkpoint the given stores and back them up
+    ///
+    /// Incremental backups archive only the blobs that changed since the latest
+    /// backup; otherwise the whole RAM disk is copied. The stores are held from
+    /// the checkpoint until the copy is finished, so neither writes nor
+    /// compaction change their segments and logs while they are read; other
+    /// reads go on meanwhile.
+    fn backup_stores(
+        ramdisk_path: &Path,
+        config: &RamLakeConfig,
+        vector_store: &PLRwLock<VectorStore>,
+        code_store: &PLRwLock<CodeStore>,
+        history_store: &PLRwLock<HistoryStore>,
+        metadata_store: &PLRwLock<MetadataStore>,
+    ) -> Result<BackupInfo, String> {
+        let mut vector_store = vector_store.write();
+        let mut code_store = code_store.write();
+        let mut history_store = history_store.write();
+        let mut metadata_store = metadata_store.write();
         
-        std::fs::create_dir_all(&backup_dir)
-            .map_err(|e| format!("Failed to create timestamped backup directory: {}", e))?;
+        // Fold the write-ahead logs into the snapshots before copying
+        vector_store.checkpoint()?;
+        code_store.checkpoint()?;
+        history_store.checkpoint()?;
+        metadata_store.checkpoint()?;
         
-        // Perform rsync-like backup
-        let options = fs_extra::dir::CopyOptions::new();
-        fs_extra::dir::copy(ramdisk_path, &backup_dir, &options)
-            .map_err(|e| format!("Failed to backup RAM-Lake: {}", e))?;
+        let vector_store = PLRwLockWriteGuard::downgrade(vector_store);
+        let code_store = PLRwLockWriteGuard::downgrade(code_store);
+        let history_store = PLRwLockWriteGuard::downgrade(history_store);
+        let metadata_store = PLRwLockWriteGuard::downgrade(metadata_store);
+        
+        if !config.incremental_backup.enabled {
+            return backup::create_backup(ramdisk_path, &config.backup_path);
+        }
+        
+        let mut states = HashMap::new();
+        states.insert("vectors".to_string(), vector_store.backup_state());
+        states.insert("code".to_string(), code_store.backup_state());
+        states.insert("history".to_string(), history_store.backup_state());
+        states.insert("metadata".to_string(), metadata_store.backup_state());
+        
+        backup::create_incremental_backup(ramdisk_path, &config.backup_path, &STORE_DIRS, &states, &config.incremental_backup)
+    }
+    
+    /// Checkpoint the stores and create a backup now
+    pub fn create_backup(&self) -> Result<BackupInfo, String> {
+        let info = Self::backup_stores(
+            &self.ramdisk_path,
+            &self.config,
+            &self.vector_store,
+            &self.code_store,
+            &self.history_store,
+            &self.metadata_store,
+        )?;
+        backup::prune_backups(&self.config.backup_path, &self.config.backup_retention)?;
+        
+        *self.last_backup.lock().unwrap() = Instant::now();
+        
+        Ok(info)
+    }
+    
+    /// List available backups, newest first
+    pub fn list_backups(&self) -> Result<Vec<BackupInfo>, String> {
+        backup::list_backups(&self.config.backup_path)
+    }
+    
+    /// Remove backups that violate the retention policy
+    pub fn prune_backups(&self) -> Result<Vec<BackupInfo>, String> {
+        backup::prune_backups(&self.config.backup_path, &self.config.backup_retention)
+    }
+    
+    /// Replace the RAM-Lake contents with a backup and reload the stores
+    pub fn restore_backup(&self, id: &str) -> Result<(), String> {
+        let backup = backup::find_backup(&self.config.backup_path, id)?;
+        
+        // Hold every store lock so no write lands while the directories are swapped
+        let mut vector_store = self.vector_store.write();
+        let mut code_store = self.code_store.write();
+        let mut history_store = self.history_store.write();
+        let mut metadata_store = self.metadata_store.write();
+        
+        backup::restore_backup_files(&backup, &self.ramdisk_path, &STORE_DIRS)?;
+        
+        let (mut restored_vector, mut restored_code, mut restored_history, mut restored_metadata) =
+            Self::open_stores(&self.ramdisk_path, &self.config)?;
+        
+        // Keep the allocation changed at runtime over the one in the backup
+        let sizes = [vector_store.get_max_size(), code_store.get_max_size(), history_store.get_max_size(), metadata_store.get_max_size()];
+        rebalance::save_allocation(&self.ramdisk_path, sizes, self.config.max_size)?;
+        restored_vector.set_max_size(sizes[0]);
+        restored_code.set_max_size(sizes[1]);
+        restored_history.set_max_size(sizes[2]);
+        restored_metadata.set_max_size(sizes[3]);
+        *vector_store = restored_vector;
+        *code_store = restored_code;
+        *history_store = restored_history;
+        *metadata_store = restored_metadata;
+        
+        // The BM25 index describes the stores that were replaced
+        *self.lexical.write() = build_lexical_index(&code_store, &history_store);
+        
+        let mut memory_manager = self.memory_manager.write();
+        memory_manager.reset();
+        Self::account_existing(&mut memory_manager, &vector_store, &code_store, &history_store, &metadata_store);
+        memory_manager.set_pinned(eviction::load_pins(&self.ramdisk_path)?);
+        
+        // The old journal file was replaced with the one from the backup
+        *self.journal.lock().unwrap() = WriteAheadLog::open(self.ramdisk_path.join(transaction::JOURNAL_FILE))?;
         
         Ok(())
     }
@@ -247,79 +1146,702 @@ impl RamLake {
         self.metrics.read().clone()
     }
     
-    /// Store a code file in the RAM-Lake
+    /// Reserve memory for new data in a namespace
+    fn reserve(&self, namespace: &str, size: u64, source: &str) -> Result<(), String> {
+        if size == 0 {
+            return Ok(());
+        }
+        
+        self.memory_manager.write().allocate_in_namespace(namespace, size, source)
+            .map_err(|e| format!("Failed to allocate memory: {}", e))
+    }
+    
+    /// Release memory of removed data in a namespace
+    fn release(&self, namespace: &str, size: u64) -> Result<(), String> {
+        if size == 0 {
+            return Ok(());
+        }
+        
+        self.memory_manager.write().free_in_namespace(namespace, size)
+            .map_err(|e| format!("Failed to free memory: {}", e))
+    }
+    
+    /// Get the memory currently accounted by the memory manager in bytes
+    pub fn get_memory_usage(&self) -> u64 {
+        self.memory_manager.read().get_current_usage()
+    }
+    
+    /// Get the memory accounted to a namespace in bytes
+    pub fn get_namespace_usage(&self, namespace: &str) -> u64 {
+        self.memory_manager.read().get_namespace_usage(namespace)
+    }
+    
+    /// Set or clear the memory quota of a namespace
+    ///
+    /// Lowering a quota below the current usage only blocks new writes.
+    pub fn set_namespace_quota(&self, namespace: &str, quota: Option<u64>) {
+        self.memory_manager.write().set_namespace_quota(namespace, quota);
+    }
+    
+    /// List the namespaces that hold any data
+    pub fn list_namespaces(&self) -> Vec<String> {
+        let mut namespaces: Vec<String> = self.get_namespace_metrics().into_keys().collect();
+        namespaces.sort();
+        namespaces
+    }
+    
+    /// Collect up-to-date metrics for every namespace
+    pub fn get_namespace_metrics(&self) -> HashMap<String, NamespaceMetrics> {
+        let vector_store = self.vector_store.read();
+        let code_store = self.code_store.read();
+        let history_store = self.history_store.read();
+        let metadata_store = self.metadata_store.read();
+        let memory_manager = self.memory_manager.read();
+        
+        collect_namespace_metrics(&memory_manager, &vector_store, &code_store, &history_store, &metadata_store)
+    }
+    
+    /// Collect up-to-date vector search metrics for every collection
+    pub fn get_vector_collection_metrics(&self) -> HashMap<String, VectorCollectionMetrics> {
+        self.vector_store.read().get_collection_metrics()
+    }
+    
+    /// Write a new code file, reserving memory for its content first
+    ///
+    /// The full content size is reserved up front; whatever the store did not
+    /// need because the content was already stored, and whatever it freed by
+    /// replacing a file or pruning revisions, is released afterwards.
+    fn insert_code(&self, code_store: &mut CodeStore, id: Uuid, namespace: &str, path: &str, content: &str, language: &str) -> Result<(), String> {
+        let size = content.len() as u64;
+        self.reserve(namespace, size, &format!("code:{}", path))?;
+        let replaced = code_store.get_id_by_path(namespace, path);
+        
+        match code_store.store_file(id, namespace, path, content, language) {
+            Ok((added, freed)) => {
+                {
+                    let mut lexical = self.lexical.write();
+                    if let Some(replaced) = replaced {
+                        lexical.remove(replaced);
+                    }
+                    lexical.insert(id, namespace, &code_text(path, content));
+                }
+                self.memory_manager.read().record_access(id);
+                self.release(namespace, size - added + freed)
+            }
+            Err(e) => {
+                self.release(namespace, size)?;
+                Err(e)
+            }
+        }
+    }
+    
+    /// Put back a deleted code file, reserving memory for its content first
+    fn restore_code(&self, code_store: &mut CodeStore, metadata: CodeMetadata, content: &str) -> Result<(), String> {
+        let size = content.len() as u64;
+        let (id, namespace, text) = (metadata.id, metadata.namespace.clone(), code_text(&metadata.path, content));
+        self.reserve(&namespace, size, &format!("code:{}", metadata.path))?;
+        
+        match code_store.restore_file(metadata, content) {
+            Ok((added, freed)) => {
+                self.lexical.write().insert(id, &namespace, &text);
+                self.release(&namespace, size - added + freed)
+            }
+            Err(e) => {
+                self.release(&namespace, size)?;
+                Err(e)
+            }
+        }
+    }
+    
+    /// Replace the content of a code file, accounting for the blobs added and freed
+    fn replace_code_content(&self, code_store: &mut CodeStore, id: Uuid, content: &str) -> Result<(), String> {
+        let metadata = code_store.get_file_metadata(id)?;
+        let new_size = content.len() as u64;
+        
+        // Reserve the new content before writing
+        self.reserve(&metadata.namespace, new_size, &format!("code:{}", metadata.path))?;
+        
+        match code_store.update_file(id, content) {
+            Ok((added, freed)) => {
+                self.lexical.write().insert(id, &metadata.namespace, &code_text(&metadata.path, content));
+                self.memory_manager.read().record_access(id);
+                self.release(&metadata.namespace, new_size - added + freed)
+            }
+            Err(e) => {
+                self.release(&metadata.namespace, new_size)?;
+                Err(e)
+            }
+        }
+    }
+    
+    /// Remove a code file and release its content once no other file shares it
+    fn remove_code(&self, code_store: &mut CodeStore, id: Uuid) -> Result<(), String> {
+        let metadata = code_store.get_file_metadata(id)?;
+        let freed = code_store.delete_file(id)?;
+        self.lexical.write().remove(id);
+        
+        self.release(&metadata.namespace, freed)
+    }
+    
+    /// Write an embedding, reserving memory for it first
+    fn insert_embedding(&self, vector_store: &mut VectorStore, id: Uuid, collection: &str, namespace: &str, source: EmbeddingSource, embedding: Vec<f32>) -> Result<(), String> {
+        let size = vector_store.new_embedding_size(collection, embedding.len());
+        self.reserve(namespace, size, &format!("embedding:{}", id))?;
+        
+        if let Err(e) = vector_store.store_embedding(id, collection, namespace, source, embedding) {
+            self.release(namespace, size)?;
+            return Err(e);
+        }
+        self.memory_manager.read().record_access(id);
+        
+        Ok(())
+    }
+    
+    /// Remove an embedding and release its memory
+    fn remove_embedding(&self, vector_store: &mut VectorStore, id: Uuid) -> Result<(), String> {
+        let metadata = vector_store.get_embedding_metadata(id)?;
+        let size = if vector_store.is_cold(id) { 0 } else { vector_store.embedding_size(id) };
+        vector_store.delete_embedding(id)?;
+        
+        self.release(&metadata.namespace, size)
+    }
+    
+    /// Write a new event, reserving memory for its content first
+    ///
+    /// The uncompressed size is reserved; what compression saves is released afterwards.
+    fn insert_event(&self, history_store: &mut HistoryStore, id: Uuid, namespace: &str, event_type: &str, content: &str) -> Result<(), String> {
+        let size = content.len() as u64;
+        self.reserve(namespace, size, &format!("event:{}", event_type))?;
+        
+        match history_store.store_event(id, namespace, event_type, content) {
+            Ok(stored) => {
+                self.lexical.write().insert(id, namespace, &event_text(event_type, content));
+                self.memory_manager.read().record_access(id);
+                self.release(namespace, size - stored)
+            }
+            Err(e) => {
+                self.release(namespace, size)?;
+                Err(e)
+            }
+        }
+    }
+    
+    /// Put back a deleted event, reserving memory for its content first
+    fn restore_event(&self, history_store: &mut HistoryStore, metadata: EventMetadata, content: &str) -> Result<(), String> {
+        let size = content.len() as u64;
+        let (id, namespace, text) = (metadata.id, metadata.namespace.clone(), event_text(&metadata.event_type, content));
+        self.reserve(&namespace, size, &format!("event:{}", metadata.event_type))?;
+        
+        match history_store.restore_event(metadata, content) {
+            Ok(stored) => {
+                self.lexical.write().insert(id, &namespace, &text);
+                self.release(&namespace, size - stored)
+            }
+            Err(e) => {
+                self.release(&namespace, size)?;
+                Err(e)
+            }
+        }
+    }
+    
+    /// Remove an event and release its content
+    fn remove_event(&self, history_store: &mut HistoryStore, id: Uuid) -> Result<(), String> {
+        let metadata = history_store.get_event_metadata(id)?;
+        let size = if history_store.is_cold(id) { 0 } else { metadata.blob_size() };
+        history_store.delete_event(id)?;
+        self.lexical.write().remove(id);
+        
+        self.release(&metadata.namespace, size)
+    }
+    
+    /// Store a relation unless it already exists, reserving memory for it first
+    fn insert_relation(&self, metadata_store: &mut MetadataStore, namespace: &str, source_id: Uuid, relation: &str, target_id: Uuid) -> Result<(), String> {
+        // Storing an existing relation is a no-op
+        if metadata_store.relation_exists(source_id, relation, target_id) {
+            return Ok(());
+        }
+        
+        let size = relation_size(relation);
+        self.reserve(namespace, size, &format!("relation:{}", relation))?;
+        
+        if let Err(e) = metadata_store.store_relation(namespace, source_id, relation, target_id) {
+            self.release(namespace, size)?;
+            return Err(e);
+        }
+        
+        Ok(())
+    }
+    
+    /// Remove a relation if it exists and release its memory
+    fn remove_relation(&self, metadata_store: &mut MetadataStore, source_id: Uuid, relation: &str, target_id: Uuid) -> Result<(), String> {
+        // Deleting a missing relation is a no-op
+        if !metadata_store.relation_exists(source_id, relation, target_id) {
+            return Ok(());
+        }
+        
+        let namespace = metadata_store.get_relation_namespace(source_id, relation, target_id);
+        metadata_store.delete_relation(source_id, relation, target_id)?;
+        
+        self.release(&namespace, relation_size(relation))
+    }
+    
+    /// Store a code file in the default namespace
+    ///
+    /// A file already stored under the same path is replaced.
     pub fn store_code(&self, path: &str, content: &str, language: &str) -> Result<Uuid, String> {
+        self.store_code_in(DEFAULT_NAMESPACE, path, content, language)
+    }
+    
+    /// Store a code file in a namespace
+    ///
+    /// A file already stored under the same path in the namespace is replaced.
+    pub fn store_code_in(&self, namespace: &str, path: &str, content: &str, language: &str) -> Result<Uuid, String> {
+        let existing_id = self.code_store.read().get_id_by_path(namespace, path);
+        
         // Generate a unique ID for this code
         let id = Uuid::new_v4();
         
+        // Evict other entries if the content does not fit
+        self.make_room(&[(Some(EntityKind::Code), content.len() as u64)], &HashSet::new())?;
+        
         // Store the code
-        let mut code_store = self.code_store.write();
-        code_store.store_file(id, path, content, language)?;
+        self.insert_code(&mut self.code_store.write(), id, namespace, path, content, language)?;
         
-        // Update memory manager
-        let mut memory_manager = self.memory_manager.write();
-        memory_manager.allocate_with_source(content.len() as u64, &format!("code:{}", path))
-            .map_err(|e| format!("Failed to allocate memory: {}", e))?;
+        // The previous file is now a revision; its embeddings and relations go only once
+        // the new file is in, so a failed store leaves them searchable
+        if let Some(existing_id) = existing_id {
+            self.delete_source_embeddings(existing_id)?;
+            self.delete_entity_relations(existing_id)?;
+        }
         
         Ok(id)
     }
     
-    /// Index a code file for vector search
+    /// Replace the content of a stored code file
+    pub fn update_code(&self, id: Uuid, content: &str) -> Result<(), String> {
+        self.make_room(&[(Some(EntityKind::Code), content.len() as u64)], &HashSet::from([id]))?;
+        
+        self.replace_code_content(&mut self.code_store.write(), id, content)
+    }
+    
+    /// Delete a code file together with its embeddings and relations
+    pub fn delete_code(&self, id: Uuid) -> Result<(), String> {
+        self.remove_code(&mut self.code_store.write(), id)?;
+        self.delete_source_embeddings(id)?;
+        
+        self.delete_entity_relations(id)
+    }
+    
+    /// Delete every embedding computed from a code file, whole or in chunks
+    fn delete_source_embeddings(&self, source_id: Uuid) -> Result<(), String> {
+        let mut vector_store = self.vector_store.write();
+        for id in vector_store.get_ids_by_source(source_id) {
+            self.remove_embedding(&mut vector_store, id)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Index a code file for vector search in the default collection
     pub fn index_code(&self, code_id: Uuid, embeddings: Vec<f32>) -> Result<(), String> {
-        // Store the embedding
+        self.index_entity(DEFAULT_COLLECTION, code_id, embeddings)
+    }
+    
+    /// Index a code file or event for vector search in a collection
+    ///
+    /// The embedding belongs to the namespace of the code file (or event) with
+    /// the same ID, or to the default namespace if there is none, and carries
+    /// its type, language and path for filtered searches.
+    pub fn index_entity(&self, collection: &str, id: Uuid, embedding: Vec<f32>) -> Result<(), String> {
+        let (namespace, source) = embedding_source(&self.code_store.read(), &self.history_store.read(), id);
+        
+        let size = self.vector_store.read().new_embedding_size(collection, embedding.len());
+        self.make_room(&[(Some(EntityKind::Embedding), size)], &HashSet::from([id]))?;
+        
+        self.insert_embedding(&mut self.vector_store.write(), id, collection, &namespace, source, embedding)
+    }
+    
+    /// Index many code files or events for vector search in a collection at once
+    ///
+    /// Each embedding gets the namespace and source attributes `index_entity`
+    /// would give it. The batch is stored whole or not at all, with one WAL
+    /// write instead of one per embedding.
+    pub fn store_embeddings_batch(&self, collection: &str, embeddings: Vec<(Uuid, Vec<f32>)>) -> Result<(), String> {
+        self.put_embeddings(collection, embeddings, false)
+    }
+    
+    /// Index a code file or event in a collection, replacing its existing embedding
+    ///
+    /// Re-embedding a changed file does not need a delete first; an embedding
+    /// in another collection moves to this one.
+    pub fn upsert_embedding(&self, collection: &str, id: Uuid, embedding: Vec<f32>) -> Result<(), String> {
+        self.upsert_embeddings_batch(collection, vec![(id, embedding)])
+    }
+    
+    /// Index many code files or events in a collection at once, replacing existing embeddings
+    pub fn upsert_embeddings_batch(&self, collection: &str, embeddings: Vec<(Uuid, Vec<f32>)>) -> Result<(), String> {
+        self.put_embeddings(collection, embeddings, true)
+    }
+    
+    /// Write a batch of embeddings, replacing existing ones only if `replace` is set
+    fn put_embeddings(&self, collection: &str, embeddings: Vec<(Uuid, Vec<f32>)>, replace: bool) -> Result<(), String> {
+        let embeddings: Vec<NewEmbedding> = {
+            let code_store = self.code_store.read();
+            let history_store = self.history_store.read();
+            embeddings.into_iter()
+                .map(|(id, embedding)| {
+                    let (namespace, source) = embedding_source(&code_store, &history_store, id);
+                    NewEmbedding { id, namespace, source, embedding }
+                })
+                .collect()
+        };
+        
+        self.put_new_embeddings(collection, embeddings, replace)
+    }
+    
+    /// Write a batch of embeddings with known sources, replacing existing ones only if `replace` is set
+    fn put_new_embeddings(&self, collection: &str, embeddings: Vec<NewEmbedding>, replace: bool) -> Result<(), String> {
+        let ids: Vec<Uuid> = embeddings.iter().map(|new| new.id).collect();
+        
+        // Memory each namespace gains; replaced embeddings give back what they held
+        let growth = |vector_store: &VectorStore| {
+            let mut growth: HashMap<String, i64> = HashMap::new();
+            for new in &embeddings {
+                *growth.entry(new.namespace.clone()).or_default() += vector_store.new_embedding_size(collection, new.embedding.len()) as i64;
+                if let Ok(existing) = vector_store.get_embedding_metadata(new.id) {
+                    if !vector_store.is_cold(new.id) {
+                        *growth.entry(existing.namespace).or_default() -= vector_store.embedding_size(new.id) as i64;
+                    }
+                }
+            }
+            growth
+        };
+        let needed = growth(&self.vector_store.read()).values().filter(|&&size| size > 0).sum::<i64>() as u64;
+        // The sources are protected too, so eviction cannot leave the new embeddings without one
+        let protected = ids.iter().copied().chain(embeddings.iter().map(|new| new.source.source_id)).collect();
+        self.make_room(&[(Some(EntityKind::Embedding), needed)], &protected)?;
+        
         let mut vector_store = self.vector_store.write();
-        vector_store.store_embedding(code_id, embeddings)?;
+        let growth = growth(&vector_store);
+        
+        // Reserve what each namespace gains, undoing the reservations if one fails
+        let mut reserved = Vec::with_capacity(growth.len());
+        for (namespace, &size) in growth.iter().filter(|(_, &size)| size > 0) {
+            if let Err(e) = self.reserve(namespace, size as u64, &format!("embeddings:{}", collection)) {
+                for (namespace, size) in reserved {
+                    self.release(namespace, size)?;
+                }
+                return Err(e);
+            }
+            reserved.push((namespace, size as u64));
+        }
+        
+        let stored = if replace {
+            vector_store.upsert_embeddings_batch(collection, embeddings)
+        } else {
+            vector_store.store_embeddings_batch(collection, embeddings)
+        };
+        if let Err(e) = stored {
+            for (namespace, size) in reserved {
+                self.release(namespace, size)?;
+            }
+            return Err(e);
+        }
+        for (namespace, &size) in growth.iter().filter(|(_, &size)| size < 0) {
+            self.release(namespace, size.unsigned_abs())?;
+        }
+        
+        let memory_manager = self.memory_manager.read();
+        for id in ids {
+            memory_manager.record_access(id);
+        }
         
         Ok(())
     }
     
-    /// Store a terminal or system event in history
+    /// Create a vector collection for embeddings of one dimension
+    ///
+    /// Creating a collection that already exists with the same settings does nothing.
+    pub fn create_vector_collection(&self, name: &str, dimension: usize, metric: DistanceMetric, model: Option<String>) -> Result<(), String> {
+        self.vector_store.write().create_collection(name, dimension, metric, model)
+    }
+    
+    /// Delete a vector collection that holds no embeddings
+    pub fn delete_vector_collection(&self, name: &str) -> Result<(), String> {
+        self.vector_store.write().delete_collection(name)
+    }
+    
+    /// Change how the resident vectors of a collection are compressed
+    ///
+    /// Quantized collections keep int8 or product-quantized vectors in memory
+    /// and re-rank the best candidates of each search at full precision.
+    pub fn set_vector_quantization(&self, collection: &str, quantization: Quantization) -> Result<(), String> {
+        let mut vector_store = self.vector_store.write();
+        let previous = vector_store.get_collection(collection)?.quantization;
+        let before = vector_store.get_namespace_stats();
+        vector_store.set_quantization(collection, quantization)?;
+
+        // The resident rows of the collection changed size; settle each namespace's share
+        let mut reserved: Vec<(String, u64)> = Vec::new();
+        for (namespace, (_, size)) in vector_store.get_namespace_stats() {
+            let old_size = before.get(&namespace).map_or(0, |&(_, size)| size);
+            if size < old_size {
+                self.release(&namespace, old_size - size)?;
+                continue;
+            }
+            if let Err(e) = self.reserve(&namespace, size - old_size, &format!("quantization:{}", collection)) {
+                for (namespace, size) in reserved {
+                    self.release(&namespace, size)?;
+                }
+                vector_store.set_quantization(collection, previous)?;
+                return Err(e);
+            }
+            reserved.push((namespace, size - old_size));
+        }
+
+        Ok(())
+    }
+    
+    /// Measure the recall of a collection's searches against exact searches
+    ///
+    /// Up to `samples` stored embeddings are used as queries; the result is also
+    /// reported in the collection's metrics.
+    pub fn measure_vector_recall(&self, collection: &str, samples: usize) -> Result<f32, String> {
+        self.vector_store.write().measure_recall(collection, samples)
+    }
+    
+    /// List all vector collections, ordered by name
+    pub fn list_vector_collections(&self) -> Vec<VectorCollection> {
+        self.vector_store.read().list_collections()
+    }
+    
+    /// Delete an embedding
+    pub fn delete_embedding(&self, id: Uuid) -> Result<(), String> {
+        self.remove_embedding(&mut self.vector_store.write(), id)
+    }
+    
+    /// Store a terminal or system event in the default namespace
     pub fn store_event(&self, event_type: &str, content: &str) -> Result<Uuid, String> {
+        self.store_event_in(DEFAULT_NAMESPACE, event_type, content)
+    }
+    
+    /// Store a terminal or system event in a namespace
+    pub fn store_event_in(&self, namespace: &str, event_type: &str, content: &str) -> Result<Uuid, String> {
         // Generate a unique ID for this event
         let id = Uuid::new_v4();
         
-        // Store the event
-        let mut history_store = self.history_store.write();
-        history_store.store_event(id, event_type, content)?;
+        // Evict other entries if the content does not fit
+        self.make_room(&[(Some(EntityKind::Event), content.len() as u64)], &HashSet::new())?;
         
-        // Update memory manager
-        let mut memory_manager = self.memory_manager.write();
-        memory_manager.allocate_with_source(content.len() as u64, &format!("event:{}", event_type))
-            .map_err(|e| format!("Failed to allocate memory: {}", e))?;
+        // Store the event
+        self.insert_event(&mut self.history_store.write(), id, namespace, event_type, content)?;
         
         Ok(id)
     }
     
+    /// Delete an event together with its relations
+    pub fn delete_event(&self, id: Uuid) -> Result<(), String> {
+        self.remove_event(&mut self.history_store.write(), id)?;
+        
+        // Event embeddings share the ID of the event
+        let has_embedding = self.vector_store.read().get_embedding_metadata(id).is_ok();
+        if has_embedding {
+            self.delete_embedding(id)?;
+        }
+        
+        self.delete_entity_relations(id)
+    }
+    
     /// Store metadata about relations between entities
+    ///
+    /// The relation belongs to the namespace of its source entity, else of its
+    /// target, else to the default namespace.
     pub fn store_metadata(&self, source_id: Uuid, relation: &str, target_id: Uuid) -> Result<(), String> {
-        // Store the metadata
-        let mut metadata_store = self.metadata_store.write();
-        metadata_store.store_relation(source_id, relation, target_id)?;
+        let namespace = relation_namespace(&self.code_store.read(), &self.history_store.read(), source_id, target_id);
+        
+        self.make_room(&[(None, relation_size(relation))], &HashSet::from([source_id, target_id]))?;
+        
+        self.insert_relation(&mut self.metadata_store.write(), &namespace, source_id, relation, target_id)
+    }
+    
+    /// Delete a relation between entities
+    pub fn delete_relation(&self, source_id: Uuid, relation: &str, target_id: Uuid) -> Result<(), String> {
+        self.remove_relation(&mut self.metadata_store.write(), source_id, relation, target_id)
+    }
+    
+    /// Delete every relation that involves an entity
+    fn delete_entity_relations(&self, id: Uuid) -> Result<(), String> {
+        self.remove_entity_relations(&mut self.metadata_store.write(), id)?;
         
         Ok(())
     }
     
-    /// Search for similar code by vector embedding
-    pub fn search_similar(&self, embedding: Vec<f32>, limit: usize) -> Result<Vec<(Uuid, f32)>, String> {
+    /// Remove every relation that involves an entity, returning the bytes released
+    fn remove_entity_relations(&self, metadata_store: &mut MetadataStore, id: Uuid) -> Result<u64, String> {
+        // A self-relation shows up both as forward and backward relation
+        let relations: HashSet<(Uuid, String, Uuid)> = metadata_store.get_relations(id, None)?
+            .into_iter()
+            .collect();
+        if relations.is_empty() {
+            return Ok(0);
+        }
+        
+        // Memory to release per namespace
+        let mut sizes: HashMap<String, u64> = HashMap::new();
+        for (source_id, relation, target_id) in &relations {
+            let namespace = metadata_store.get_relation_namespace(*source_id, relation, *target_id);
+            *sizes.entry(namespace).or_default() += relation_size(relation);
+        }
+        
+        metadata_store.delete_entity_relations(id)?;
+        
+        let mut released = 0;
+        for (namespace, size) in sizes {
+            self.release(&namespace, size)?;
+            released += size;
+        }
+        
+        Ok(released)
+    }
+    
+    /// Search a vector collection for similar embeddings across all namespaces
+    pub fn search_similar(&self, collection: &str, embedding: Vec<f32>, limit: usize) -> Result<Vec<(Uuid, f32)>, String> {
         // Perform vector search
-        let vector_store = self.vector_store.read();
-        let results = vector_store.search_similar(embedding, limit)?;
+        self.search_similar_where(collection, &VectorFilter::default(), embedding, limit)
+    }
+    
+    /// Search a vector collection for similar embeddings within a namespace
+    pub fn search_similar_in(&self, collection: &str, namespace: &str, embedding: Vec<f32>, limit: usize) -> Result<Vec<(Uuid, f32)>, String> {
+        self.search_similar_where(collection, &VectorFilter::namespace(namespace), embedding, limit)
+    }
+    
+    /// Search a vector collection for similar embeddings matching a filter
+    ///
+    /// The filter is applied while searching, so up to `limit` matching
+    /// embeddings are returned however few of the collection match.
+    pub fn search_similar_where(&self, collection: &str, filter: &VectorFilter, embedding: Vec<f32>, limit: usize) -> Result<Vec<(Uuid, f32)>, String> {
+        let results = self.vector_store.read().search_similar(collection, embedding, limit, filter)?;
+        self.record_accesses(&results);
+        
+        Ok(results)
+    }
+    
+    /// Search a vector collection for the embeddings most similar to each of several queries
+    ///
+    /// Results are in the order of the queries.
+    pub fn search_similar_batch(&self, collection: &str, queries: Vec<Vec<f32>>, limit: usize) -> Result<Vec<Vec<(Uuid, f32)>>, String> {
+        let results = self.vector_store.read().search_similar_batch(collection, queries, limit, &VectorFilter::default())?;
+        self.record_accesses(&results.concat());
         
         Ok(results)
     }
     
-    /// Get a code file by ID
+    /// Search a vector collection by comparing the query with every embedding
+    ///
+    /// Gives the exact results `search_similar` approximates, for measuring its
+    /// recall. Hits are not recorded as accesses.
+    pub fn search_similar_exact(&self, collection: &str, embedding: Vec<f32>, limit: usize) -> Result<Vec<(Uuid, f32)>, String> {
+        self.vector_store.read().search_exact(collection, embedding, limit, &VectorFilter::default())
+    }
+    
+    /// Record search hits as accesses of their embeddings
+    ///
+    /// Cold embeddings are not searched, so every hit is already in RAM.
+    fn record_accesses(&self, results: &[(Uuid, f32)]) {
+        let memory_manager = self.memory_manager.read();
+        for (id, _) in results {
+            memory_manager.record_access(*id);
+        }
+    }
+    
+    /// Get a code file by ID, promoting it if it was in the cold tier
     pub fn get_code(&self, id: Uuid) -> Result<(String, String, String), String> {
         // Get the code
-        let code_store = self.code_store.read();
-        code_store.get_file(id)
+        let (file, cold) = {
+            let code_store = self.code_store.read();
+            (code_store.get_file(id)?, code_store.is_cold(id))
+        };
+        self.memory_manager.read().record_access(id);
+        
+        if cold {
+            self.promote(EntityKind::Code, id);
+        }
+        
+        Ok(file)
+    }
+    
+    /// Get the ID of the code file stored under a path in a namespace
+    pub fn find_code(&self, namespace: &str, path: &str) -> Option<Uuid> {
+        self.code_store.read().get_id_by_path(namespace, path)
+    }
+    
+    /// Get a code file as it was at a point in time
+    ///
+    /// Returns the revision and its content, or `None` if the file did not exist
+    /// then or that part of its history was pruned.
+    pub fn get_code_at(&self, namespace: &str, path: &str, timestamp: chrono::DateTime<chrono::Utc>) -> Result<Option<(FileRevision, String)>, String> {
+        self.code_store.read().get_file_at(namespace, path, timestamp)
+    }
+    
+    /// List the kept revisions of a code file, oldest first
+    pub fn list_code_revisions(&self, namespace: &str, path: &str) -> Vec<FileRevision> {
+        self.code_store.read().list_revisions(namespace, path)
     }
     
-    /// Get event by ID
+    /// Get one revision of a code file and its content
+    pub fn get_code_revision(&self, namespace: &str, path: &str, revision: u32) -> Result<(FileRevision, String), String> {
+        self.code_store.read().get_revision(namespace, path, revision)
+    }
+    
+    /// Unified diff between two revisions of a code file
+    pub fn diff_code_revisions(&self, namespace: &str, path: &str, from: u32, to: u32) -> Result<String, String> {
+        self.code_store.read().diff_revisions(namespace, path, from, to)
+    }
+    
+    /// Drop code file revisions that violate the retention policy, releasing their memory
+    pub fn prune_revisions(&self) -> Result<(), String> {
+        Self::prune_code_revisions(&self.code_store, &self.memory_manager)
+    }
+    
+    /// Apply the revision retention policy to the given code store
+    fn prune_code_revisions(code_store: &PLRwLock<CodeStore>, memory_manager: &PLRwLock<MemoryManager>) -> Result<(), String> {
+        let mut code_store = code_store.write();
+        let freed = code_store.prune_revisions()?;
+        
+        let mut memory_manager = memory_manager.write();
+        for (namespace, size) in freed {
+            memory_manager.free_in_namespace(&namespace, size)
+                .map_err(|e| format!("Failed to free memory: {}", e))?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Train a compression dictionary on the stored code and write it to `path`
+    ///
+    /// Set `code_compression.dictionary` to the written file to compress new code
+    /// blobs with it.
+    pub fn train_code_dictionary(&self, path: &Path, max_size: usize) -> Result<(), String> {
+        let dictionary = self.code_store.read().train_dictionary(max_size)?;
+        
+        std::fs::write(path, dictionary)
+            .map_err(|e| format!("Failed to write compression dictionary: {}", e))
+    }
+    
+    /// Get event by ID, promoting it if it was in the cold tier
     pub fn get_event(&self, id: Uuid) -> Result<(String, String, chrono::DateTime<chrono::Local>), String> {
         // Get the event
-        let history_store = self.history_store.read();
-        history_store.get_event(id)
+        let (event, cold) = {
+            let history_store = self.history_store.read();
+            (history_store.get_event(id)?, history_store.is_cold(id))
+        };
+        self.memory_manager.read().record_access(id);
+        
+        if cold {
+            self.promote(EntityKind::Event, id);
+        }
+        
+        Ok(event)
     }
     
     /// Get related entities by ID and relation type
@@ -328,4 +1850,15 @@ impl RamLake {
         let metadata_store = self.metadata_store.read();
         metadata_store.get_relations(id, relation)
     }
+    
+    /// Get related entities by ID and relation type, limited to relations in a namespace
+    pub fn get_related_in(&self, namespace: &str, id: Uuid, relation: Option<&str>) -> Result<Vec<(Uuid, String, Uuid)>, String> {
+        let metadata_store = self.metadata_store.read();
+        let relations = metadata_store.get_relations(id, relation)?
+            .into_iter()
+            .filter(|(source_id, relation, target_id)| metadata_store.get_relation_namespace(*source_id, relation, *target_id) == namespace)
+            .collect();
+        
+        Ok(relations)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,434 @@
+// Async facade over RAM-Lake for use from the tokio runtime
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use uuid::Uuid;
+
+use super::{RamLake, RamLakeMetrics, BackupInfo, ConsistencyReport, Transaction, FileRevision, StoreAllocation, VectorCollection, DistanceMetric, Quantization, VectorFilter, Embedder, StaleEmbedding, SearchQuery, SearchHit, CodeChunk, CodeHit};
+
+/// Async RAM-Lake API
+///
+/// Every call runs on tokio's blocking thread pool so store I/O never stalls
+/// the runtime's worker threads. Work that has not started yet is skipped once
+/// the calling future is dropped or its `CancelToken` is cancelled.
+#[derive(Clone)]
+pub struct AsyncRamLake {
+    /// Shared synchronous RAM-Lake
+    inner: Arc<RamLake>,
+}
+
+/// Cancellation flag shared between a caller and a running operation
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Create a new token
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether cancellation was requested
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Cancels its token when the awaiting future is dropped before completion
+struct CancelOnDrop {
+    token: CancelToken,
+    armed: bool,
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if self.armed {
+            self.token.cancel();
+        }
+    }
+}
+
+/// Write operation for `AsyncRamLake::write_batch`
+#[derive(Debug, Clone)]
+pub enum WriteOp {
+    /// Store a code file
+    StoreCode { path: String, content: String, language: String },
+
+    /// Replace the content of a code file
+    UpdateCode { id: Uuid, content: String },
+
+    /// Delete a code file
+    DeleteCode { id: Uuid },
+
+    /// Store the embedding of a code file
+    IndexCode { id: Uuid, embedding: Vec<f32> },
+
+    /// Delete an embedding
+    DeleteEmbedding { id: Uuid },
+
+    /// Store an event
+    StoreEvent { event_type: String, content: String },
+
+    /// Delete an event
+    DeleteEvent { id: Uuid },
+
+    /// Store a relation
+    StoreRelation { source_id: Uuid, relation: String, target_id: Uuid },
+
+    /// Delete a relation
+    DeleteRelation { source_id: Uuid, relation: String, target_id: Uuid },
+}
+
+/// Result of a single batched write: the new ID for writes that create one
+pub type WriteResult = Result<Option<Uuid>, String>;
+
+impl WriteOp {
+    /// Apply the operation to the RAM-Lake
+    fn apply(self, ram_lake: &RamLake) -> WriteResult {
+        match self {
+            WriteOp::StoreCode { path, content, language } => ram_lake.store_code(&path, &content, &language).map(Some),
+            WriteOp::UpdateCode { id, content } => ram_lake.update_code(id, &content).map(|_| None),
+            WriteOp::DeleteCode { id } => ram_lake.delete_code(id).map(|_| None),
+            WriteOp::IndexCode { id, embedding } => ram_lake.index_code(id, embedding).map(|_| None),
+            WriteOp::DeleteEmbedding { id } => ram_lake.delete_embedding(id).map(|_| None),
+            WriteOp::StoreEvent { event_type, content } => ram_lake.store_event(&event_type, &content).map(Some),
+            WriteOp::DeleteEvent { id } => ram_lake.delete_event(id).map(|_| None),
+            WriteOp::StoreRelation { source_id, relation, target_id } => ram_lake.store_metadata(source_id, &relation, target_id).map(|_| None),
+            WriteOp::DeleteRelation { source_id, relation, target_id } => ram_lake.delete_relation(source_id, &relation, target_id).map(|_| None),
+        }
+    }
+}
+
+impl AsyncRamLake {
+    /// Wrap a RAM-Lake
+    pub fn new(ram_lake: RamLake) -> Self {
+        Self {
+            inner: Arc::new(ram_lake),
+        }
+    }
+
+    /// Wrap an already shared RAM-Lake
+    pub fn from_arc(ram_lake: Arc<RamLake>) -> Self {
+        Self {
+            inner: ram_lake,
+        }
+    }
+
+    /// Get the underlying synchronous RAM-Lake
+    pub fn inner(&self) -> &Arc<RamLake> {
+        &self.inner
+    }
+
+    /// Run a blocking RAM-Lake call on the blocking thread pool
+    async fn run<T, F>(&self, token: CancelToken, call: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&RamLake, &CancelToken) -> Result<T, String> + Send + 'static,
+    {
+        let ram_lake = self.inner.clone();
+        let mut guard = CancelOnDrop {
+            token: token.clone(),
+            armed: true,
+        };
+
+        let result = tokio::task::spawn_blocking(move || {
+            if token.is_cancelled() {
+                return Err("RAM-Lake operation cancelled".to_string());
+            }
+            call(&ram_lake, &token)
+        })
+        .await
+        .map_err(|e| format!("RAM-Lake task failed: {}", e))?;
+
+        guard.armed = false;
+        result
+    }
+
+    /// Store a code file
+    pub async fn store_code(&self, path: String, content: String, language: String) -> Result<Uuid, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.store_code(&path, &content, &language)).await
+    }
+
+    /// Store a code file in a namespace
+    pub async fn store_code_in(&self, namespace: String, path: String, content: String, language: String) -> Result<Uuid, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.store_code_in(&namespace, &path, &content, &language)).await
+    }
+
+    /// Replace the content of a code file
+    pub async fn update_code(&self, id: Uuid, content: String) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.update_code(id, &content)).await
+    }
+
+    /// Delete a code file together with its embedding and relations
+    pub async fn delete_code(&self, id: Uuid) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.delete_code(id)).await
+    }
+
+    /// Index a code file for vector search
+    pub async fn index_code(&self, code_id: Uuid, embeddings: Vec<f32>) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.index_code(code_id, embeddings)).await
+    }
+
+    /// Index a code file or event for vector search in a collection
+    pub async fn index_entity(&self, collection: String, id: Uuid, embedding: Vec<f32>) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.index_entity(&collection, id, embedding)).await
+    }
+
+    /// Index many code files or events for vector search in a collection at once
+    pub async fn store_embeddings_batch(&self, collection: String, embeddings: Vec<(Uuid, Vec<f32>)>) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.store_embeddings_batch(&collection, embeddings)).await
+    }
+
+    /// Index a code file or event in a collection, replacing its existing embedding
+    pub async fn upsert_embedding(&self, collection: String, id: Uuid, embedding: Vec<f32>) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.upsert_embedding(&collection, id, embedding)).await
+    }
+
+    /// Index many code files or events in a collection at once, replacing existing embeddings
+    pub async fn upsert_embeddings_batch(&self, collection: String, embeddings: Vec<(Uuid, Vec<f32>)>) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.upsert_embeddings_batch(&collection, embeddings)).await
+    }
+
+    /// Set or remove the embedder of a vector collection
+    pub async fn set_embedder(&self, collection: String, embedder: Option<Arc<dyn Embedder>>) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.set_embedder(&collection, embedder)).await
+    }
+
+    /// Find the embeddings of a collection that are out of date
+    pub async fn stale_embeddings(&self, collection: String) -> Result<Vec<StaleEmbedding>, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.stale_embeddings(&collection)).await
+    }
+
+    /// Re-embed up to `limit` stale embeddings of the collections that have an embedder
+    pub async fn reembed_stale(&self, limit: usize) -> Result<usize, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.reembed_stale(limit)).await
+    }
+
+    /// Re-embed stale embeddings in the background until the token is cancelled
+    ///
+    /// Every `interval`, up to `limit` stale embeddings are replaced.
+    pub fn spawn_reembed(&self, interval: std::time::Duration, limit: usize, token: CancelToken) -> tokio::task::JoinHandle<()> {
+        let ram_lake = self.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                if token.is_cancelled() {
+                    break;
+                }
+                if let Err(e) = ram_lake.run(token.clone(), move |ram_lake, _| ram_lake.reembed_stale(limit)).await {
+                    eprintln!("Failed to re-embed stale embeddings: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Create a vector collection for embeddings of one dimension
+    pub async fn create_vector_collection(&self, name: String, dimension: usize, metric: DistanceMetric, model: Option<String>) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.create_vector_collection(&name, dimension, metric, model)).await
+    }
+
+    /// Change how the resident vectors of a collection are compressed
+    pub async fn set_vector_quantization(&self, collection: String, quantization: Quantization) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.set_vector_quantization(&collection, quantization)).await
+    }
+
+    /// Measure the recall of a collection's searches against exact searches
+    pub async fn measure_vector_recall(&self, collection: String, samples: usize) -> Result<f32, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.measure_vector_recall(&collection, samples)).await
+    }
+
+    /// List all vector collections, ordered by name
+    pub async fn list_vector_collections(&self) -> Result<Vec<VectorCollection>, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| Ok(ram_lake.list_vector_collections())).await
+    }
+
+    /// Delete an embedding
+    pub async fn delete_embedding(&self, id: Uuid) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.delete_embedding(id)).await
+    }
+
+    /// Store an event
+    pub async fn store_event(&self, event_type: String, content: String) -> Result<Uuid, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.store_event(&event_type, &content)).await
+    }
+
+    /// Store an event in a namespace
+    pub async fn store_event_in(&self, namespace: String, event_type: String, content: String) -> Result<Uuid, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.store_event_in(&namespace, &event_type, &content)).await
+    }
+
+    /// Delete an event together with its relations
+    pub async fn delete_event(&self, id: Uuid) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.delete_event(id)).await
+    }
+
+    /// Store a relation between entities
+    pub async fn store_metadata(&self, source_id: Uuid, relation: String, target_id: Uuid) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.store_metadata(source_id, &relation, target_id)).await
+    }
+
+    /// Delete a relation between entities
+    pub async fn delete_relation(&self, source_id: Uuid, relation: String, target_id: Uuid) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.delete_relation(source_id, &relation, target_id)).await
+    }
+
+    /// Apply several writes in one blocking task
+    ///
+    /// Returns one result per operation, in order. A failed write does not stop
+    /// the batch.
+    pub async fn write_batch(&self, ops: Vec<WriteOp>) -> Result<Vec<WriteResult>, String> {
+        self.write_batch_with_cancel(ops, CancelToken::new()).await
+    }
+
+    /// Apply several writes in one blocking task, stopping when `token` is cancelled
+    ///
+    /// The call fails if the token is cancelled before the batch starts; writes
+    /// skipped after a cancellation mid-batch report an error.
+    pub async fn write_batch_with_cancel(&self, ops: Vec<WriteOp>, token: CancelToken) -> Result<Vec<WriteResult>, String> {
+        self.run(token, move |ram_lake, token| {
+            let results = ops.into_iter()
+                .map(|op| {
+                    if token.is_cancelled() {
+                        Err("Write cancelled".to_string())
+                    } else {
+                        op.apply(ram_lake)
+                    }
+                })
+                .collect();
+
+            Ok(results)
+        }).await
+    }
+
+    /// Apply every write of a transaction, or none of them
+    pub async fn commit(&self, tx: Transaction) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.commit(tx)).await
+    }
+
+    /// Search a vector collection for similar embeddings
+    pub async fn search_similar(&self, collection: String, embedding: Vec<f32>, limit: usize) -> Result<Vec<(Uuid, f32)>, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.search_similar(&collection, embedding, limit)).await
+    }
+
+    /// Search a vector collection for similar embeddings within a namespace
+    pub async fn search_similar_in(&self, collection: String, namespace: String, embedding: Vec<f32>, limit: usize) -> Result<Vec<(Uuid, f32)>, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.search_similar_in(&collection, &namespace, embedding, limit)).await
+    }
+
+    /// Search a vector collection for similar embeddings matching a filter
+    pub async fn search_similar_where(&self, collection: String, filter: VectorFilter, embedding: Vec<f32>, limit: usize) -> Result<Vec<(Uuid, f32)>, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.search_similar_where(&collection, &filter, embedding, limit)).await
+    }
+
+    /// Search a vector collection for the embeddings most similar to each of several queries
+    pub async fn search_similar_batch(&self, collection: String, queries: Vec<Vec<f32>>, limit: usize) -> Result<Vec<Vec<(Uuid, f32)>>, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.search_similar_batch(&collection, queries, limit)).await
+    }
+
+    /// Search code files and events by text and vector similarity
+    pub async fn search(&self, query: SearchQuery) -> Result<Vec<SearchHit>, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.search(&query)).await
+    }
+
+    /// Split a stored code file into chunks to embed
+    pub async fn chunk_code(&self, code_id: Uuid) -> Result<Vec<CodeChunk>, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.chunk_code(code_id)).await
+    }
+
+    /// Index the chunks of a code file in a collection, replacing its previous embeddings there
+    pub async fn index_code_chunks(&self, collection: String, code_id: Uuid, chunks: Vec<(CodeChunk, Vec<f32>)>) -> Result<Vec<Uuid>, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.index_code_chunks(&collection, code_id, chunks)).await
+    }
+
+    /// Chunk a code file and index its chunks with the collection's embedder
+    pub async fn embed_code_chunks(&self, collection: String, code_id: Uuid) -> Result<Vec<Uuid>, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.embed_code_chunks(&collection, code_id)).await
+    }
+
+    /// Search a vector collection for the code most similar to an embedding
+    pub async fn search_code(&self, collection: String, embedding: Vec<f32>, limit: usize) -> Result<Vec<CodeHit>, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.search_code(&collection, embedding, limit)).await
+    }
+
+    /// Search a vector collection for the code most similar to an embedding, matching a filter
+    pub async fn search_code_where(&self, collection: String, filter: VectorFilter, embedding: Vec<f32>, limit: usize) -> Result<Vec<CodeHit>, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.search_code_where(&collection, &filter, embedding, limit)).await
+    }
+
+    /// Get a code file by ID
+    pub async fn get_code(&self, id: Uuid) -> Result<(String, String, String), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.get_code(id)).await
+    }
+
+    /// Get a code file as it was at a point in time
+    pub async fn get_code_at(&self, namespace: String, path: String, timestamp: chrono::DateTime<chrono::Utc>) -> Result<Option<(FileRevision, String)>, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.get_code_at(&namespace, &path, timestamp)).await
+    }
+
+    /// List the kept revisions of a code file, oldest first
+    pub async fn list_code_revisions(&self, namespace: String, path: String) -> Result<Vec<FileRevision>, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| Ok(ram_lake.list_code_revisions(&namespace, &path))).await
+    }
+
+    /// Unified diff between two revisions of a code file
+    pub async fn diff_code_revisions(&self, namespace: String, path: String, from: u32, to: u32) -> Result<String, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.diff_code_revisions(&namespace, &path, from, to)).await
+    }
+
+    /// Get an event by ID
+    pub async fn get_event(&self, id: Uuid) -> Result<(String, String, chrono::DateTime<chrono::Local>), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.get_event(id)).await
+    }
+
+    /// Get related entities by ID and relation type
+    pub async fn get_related(&self, id: Uuid, relation: Option<String>) -> Result<Vec<(Uuid, String, Uuid)>, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.get_related(id, relation.as_deref())).await
+    }
+
+    /// Get the current RAM-Lake metrics
+    pub fn get_metrics(&self) -> RamLakeMetrics {
+        self.inner.get_metrics()
+    }
+
+    /// Get the share of the RAM-Lake each store currently has
+    pub async fn get_allocation(&self) -> Result<StoreAllocation, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| Ok(ram_lake.get_allocation())).await
+    }
+
+    /// Change the share of the RAM-Lake each store has without restarting
+    pub async fn set_allocation(&self, allocation: StoreAllocation) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.set_allocation(allocation)).await
+    }
+
+    /// Check the consistency of all stores
+    pub async fn verify(&self, repair: bool) -> Result<ConsistencyReport, String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.verify(repair)).await
+    }
+
+    /// Checkpoint all stores
+    pub async fn checkpoint(&self) -> Result<(), String> {
+        self.run(CancelToken::new(), |ram_lake, _| ram_lake.checkpoint()).await
+    }
+
+    /// Checkpoint the stores and create a backup now
+    pub async fn create_backup(&self) -> Result<BackupInfo, String> {
+        self.run(CancelToken::new(), |ram_lake, _| ram_lake.create_backup()).await
+    }
+
+    /// List available backups, newest first
+    pub async fn list_backups(&self) -> Result<Vec<BackupInfo>, String> {
+        self.run(CancelToken::new(), |ram_lake, _| ram_lake.list_backups()).await
+    }
+
+    /// Replace the RAM-Lake contents with a backup
+    pub async fn restore_backup(&self, id: String) -> Result<(), String> {
+        self.run(CancelToken::new(), move |ram_lake, _| ram_lake.restore_backup(&id)).await
+    }
+}
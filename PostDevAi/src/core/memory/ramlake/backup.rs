@@ -0,0 +1,642 @@
+// Backup management for RAM-Lake
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::{Serialize, Deserialize};
+use walkdir::WalkDir;
+
+use crate::utils::filesystem::{temp_path_for, write_json_atomic};
+
+/// Prefix of backup directory names
+const BACKUP_PREFIX: &str = "ramlake_backup_";
+
+/// Timestamp format used for backup IDs
+const BACKUP_ID_FORMAT: &str = "%Y%m%d_%H%M%S_%3f";
+
+/// Timestamp format of backup IDs created before they had millisecond precision
+const LEGACY_BACKUP_ID_FORMAT: &str = "%Y%m%d_%H%M%S";
+
+/// Manifest file of an incremental backup
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Compressed blob archive of an incremental backup
+const ARCHIVE_FILE: &str = "blobs.tar.zst";
+
+/// Information about a RAM-Lake backup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    /// Backup ID (the timestamp part of the directory name)
+    pub id: String,
+
+    /// Path to the backup directory
+    pub path: PathBuf,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Local>,
+
+    /// Size of the backup in bytes
+    pub size: u64,
+}
+
+/// Retention policy for RAM-Lake backups
+///
+/// Limits are combined: a backup is pruned as soon as it violates any of them.
+/// The most recent backup is never pruned.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackupRetention {
+    /// Keep at most this many backups
+    #[serde(default)]
+    pub keep_last: Option<usize>,
+
+    /// Remove backups older than this many seconds
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+
+    /// Keep the total size of all backups below this many bytes
+    #[serde(default)]
+    pub max_total_bytes: Option<u64>,
+}
+
+/// Incremental backup settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncrementalBackup {
+    /// Only archive blobs that changed since the previous backup
+    ///
+    /// When disabled every backup is a plain copy of the RAM disk.
+    #[serde(default = "default_incremental_enabled")]
+    pub enabled: bool,
+
+    /// Take a full backup after this many incremental backups in a row
+    #[serde(default = "default_full_every")]
+    pub full_every: usize,
+
+    /// zstd compression level of the blob archives
+    #[serde(default = "default_compression_level")]
+    pub compression_level: i32,
+}
+
+fn default_incremental_enabled() -> bool {
+    true
+}
+
+fn default_full_every() -> usize {
+    24
+}
+
+fn default_compression_level() -> i32 {
+    3
+}
+
+impl Default for IncrementalBackup {
+    fn default() -> Self {
+        Self {
+            enabled: default_incremental_enabled(),
+            full_every: default_full_every(),
+            compression_level: default_compression_level(),
+        }
+    }
+}
+
+/// Store state used to skip hashing the files of an unchanged store
+#[derive(Debug, Clone, Default)]
+pub struct StoreBackupState {
+    /// Current index version of the store, if it tracks one
+    pub version: Option<u32>,
+}
+
+/// Manifest describing the full RAM disk state captured by an incremental backup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Backup ID
+    pub id: String,
+
+    /// Previous backup in the chain (None for a full backup)
+    pub parent: Option<String>,
+
+    /// Number of incremental backups since the last full backup
+    pub chain_length: usize,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Local>,
+
+    /// Store index versions at backup time
+    pub store_versions: BTreeMap<String, u32>,
+
+    /// Every file on the RAM disk, keyed by path relative to the RAM disk root
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+/// File recorded in a backup manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// SHA-256 hash of the file content
+    pub digest: String,
+
+    /// Size of the file in bytes
+    pub size: u64,
+
+    /// Modification time in nanoseconds since the Unix epoch
+    pub modified: u64,
+
+    /// Backup whose archive holds the content
+    pub archive: String,
+}
+
+/// Copy the RAM disk contents into a new timestamped backup directory
+pub fn create_backup(ramdisk_path: &Path, backup_path: &Path) -> Result<BackupInfo, String> {
+    // Create backup directory if it doesn't exist
+    fs::create_dir_all(backup_path)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+    // Create a timestamped backup directory
+    let (id, created_at, backup_dir) = new_backup_dir(backup_path);
+
+    fs::create_dir_all(&backup_dir)
+        .map_err(|e| format!("Failed to create timestamped backup directory: {}", e))?;
+
+    // Copy the store directories
+    let mut options = fs_extra::dir::CopyOptions::new();
+    options.content_only = true;
+    fs_extra::dir::copy(ramdisk_path, &backup_dir, &options)
+        .map_err(|e| format!("Failed to backup RAM-Lake: {}", e))?;
+
+    let size = fs_extra::dir::get_size(&backup_dir)
+        .map_err(|e| format!("Failed to get backup size: {}", e))?;
+
+    Ok(BackupInfo {
+        id,
+        path: backup_dir,
+        created_at,
+        size,
+    })
+}
+
+/// Pick the ID, timestamp and directory of a new backup
+///
+/// A backup taken within the same millisecond as an existing one waits for
+/// the next millisecond, so IDs stay unique and ordered by creation time.
+fn new_backup_dir(backup_path: &Path) -> (String, DateTime<Local>, PathBuf) {
+    loop {
+        let created_at = Local::now();
+        let id = created_at.format(BACKUP_ID_FORMAT).to_string();
+        let backup_dir = backup_path.join(format!("{}{}", BACKUP_PREFIX, id));
+        if !backup_dir.exists() {
+            return (id, created_at, backup_dir);
+        }
+
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Create an incremental backup of the given store directories
+///
+/// Files are compared against the manifest of the latest backup: unchanged files
+/// point at the archive that already holds them and only new content is written
+/// to this backup's compressed archive, stored once per distinct hash.
+pub fn create_incremental_backup(
+    ramdisk_path: &Path,
+    backup_path: &Path,
+    store_dirs: &[&str],
+    states: &HashMap<String, StoreBackupState>,
+    settings: &IncrementalBackup,
+) -> Result<BackupInfo, String> {
+    fs::create_dir_all(backup_path)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+    let (id, created_at, backup_dir) = new_backup_dir(backup_path);
+
+    // Chain onto the latest backup unless it is time for a full one
+    let parent = match list_backups(backup_path)?.into_iter().next() {
+        Some(latest) => load_manifest(&latest.path)?
+            .filter(|manifest| manifest.chain_length + 1 < settings.full_every.max(1)),
+        None => None,
+    };
+
+    // Content already available from the chain
+    let mut available: HashMap<String, String> = parent.as_ref()
+        .map(|manifest| {
+            manifest.files.values()
+                .map(|entry| (entry.digest.clone(), entry.archive.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut manifest = BackupManifest {
+        id: id.clone(),
+        parent: parent.as_ref().map(|manifest| manifest.id.clone()),
+        chain_length: parent.as_ref().map_or(0, |manifest| manifest.chain_length + 1),
+        created_at,
+        store_versions: BTreeMap::new(),
+        files: BTreeMap::new(),
+    };
+
+    // Files whose content goes into this backup's archive, keyed by hash
+    let mut new_blobs: BTreeMap<String, PathBuf> = BTreeMap::new();
+
+    for store_dir in store_dirs {
+        let state = states.get(*store_dir).cloned().unwrap_or_default();
+        if let Some(version) = state.version {
+            manifest.store_versions.insert(store_dir.to_string(), version);
+        }
+
+        // An unchanged version means no mutation happened, only snapshot rewrites
+        let store_unchanged = state.version.is_some()
+            && parent.as_ref().and_then(|p| p.store_versions.get(*store_dir)) == state.version.as_ref();
+
+        for (relative, path) in list_store_files(ramdisk_path, store_dir)? {
+            let file_metadata = fs::metadata(&path)
+                .map_err(|e| format!("Failed to read file metadata: {}", e))?;
+            let size = file_metadata.len();
+            let modified = file_metadata.modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |duration| duration.as_nanos() as u64);
+
+            let previous = parent.as_ref().and_then(|p| p.files.get(&relative));
+
+            let digest = match previous {
+                Some(entry) if entry.size == size && (entry.modified == modified || store_unchanged) => entry.digest.clone(),
+                _ => sha256::try_digest(path.as_path())
+                    .map_err(|e| format!("Failed to hash {}: {}", relative, e))?,
+            };
+
+            let archive = available.entry(digest.clone())
+                .or_insert_with(|| {
+                    new_blobs.insert(digest.clone(), path.clone());
+                    id.clone()
+                })
+                .clone();
+
+            manifest.files.insert(relative, ManifestEntry {
+                digest,
+                size,
+                modified,
+                archive,
+            });
+        }
+    }
+
+    fs::create_dir_all(&backup_dir)
+        .map_err(|e| format!("Failed to create timestamped backup directory: {}", e))?;
+
+    let result = write_archive(&backup_dir.join(ARCHIVE_FILE), &new_blobs, settings.compression_level)
+        .and_then(|_| write_json_atomic(backup_dir.join(MANIFEST_FILE), &manifest));
+    if let Err(e) = result {
+        let _ = fs::remove_dir_all(&backup_dir);
+        return Err(e);
+    }
+
+    let size = fs_extra::dir::get_size(&backup_dir)
+        .map_err(|e| format!("Failed to get backup size: {}", e))?;
+
+    Ok(BackupInfo {
+        id,
+        path: backup_dir,
+        created_at,
+        size,
+    })
+}
+
+/// Load the manifest of a backup (None for plain copy backups)
+pub fn load_manifest(backup_dir: &Path) -> Result<Option<BackupManifest>, String> {
+    let manifest_path = backup_dir.join(MANIFEST_FILE);
+    if !manifest_path.exists() {
+        return Ok(None);
+    }
+
+    let file = fs::File::open(&manifest_path)
+        .map_err(|e| format!("Failed to open backup manifest: {}", e))?;
+    let manifest = serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("Failed to parse backup manifest: {}", e))?;
+
+    Ok(Some(manifest))
+}
+
+/// Files in a store directory as (path relative to the RAM disk, absolute path)
+fn list_store_files(ramdisk_path: &Path, store_dir: &str) -> Result<Vec<(String, PathBuf)>, String> {
+    let store_path = ramdisk_path.join(store_dir);
+    if !store_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    for entry in WalkDir::new(&store_path) {
+        let entry = entry.map_err(|e| format!("Failed to read {} store: {}", store_dir, e))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        // Leftovers of interrupted atomic writes are not part of the store
+        if entry.file_name().to_string_lossy().ends_with(".tmp") {
+            continue;
+        }
+
+        let relative = entry.path().strip_prefix(ramdisk_path)
+            .map_err(|e| format!("Failed to resolve backup path: {}", e))?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        files.push((relative, entry.path().to_path_buf()));
+    }
+
+    Ok(files)
+}
+
+/// Write blobs into a zstd-compressed tar archive, named by their hash
+fn write_archive(archive_path: &Path, blobs: &BTreeMap<String, PathBuf>, level: i32) -> Result<(), String> {
+    if blobs.is_empty() {
+        return Ok(());
+    }
+
+    let temp_path = temp_path_for(archive_path);
+    let file = fs::File::create(&temp_path)
+        .map_err(|e| format!("Failed to create backup archive: {}", e))?;
+    let encoder = zstd::Encoder::new(BufWriter::new(file), level)
+        .map_err(|e| format!("Failed to create backup compressor: {}", e))?;
+
+    let mut builder = tar::Builder::new(encoder);
+    for (digest, path) in blobs {
+        builder.append_path_with_name(path, digest)
+            .map_err(|e| format!("Failed to archive {}: {}", path.display(), e))?;
+    }
+
+    let mut writer = builder.into_inner()
+        .map_err(|e| format!("Failed to finish backup archive: {}", e))?
+        .finish()
+        .map_err(|e| format!("Failed to finish backup compression: {}", e))?;
+    writer.flush()
+        .map_err(|e| format!("Failed to write backup archive: {}", e))?;
+    writer.get_ref().sync_all()
+        .map_err(|e| format!("Failed to sync backup archive: {}", e))?;
+    drop(writer);
+
+    fs::rename(&temp_path, archive_path)
+        .map_err(|e| format!("Failed to finalize backup archive: {}", e))
+}
+
+/// Rebuild the store directories described by a manifest into `target`
+///
+/// Every archive in the chain is read once; blobs shared by several files are
+/// copied from the first extracted file.
+fn restore_manifest(backup_path: &Path, manifest: &BackupManifest, target: &Path) -> Result<(), String> {
+    // Group the files by the archive that holds their content
+    let mut archives: HashMap<&str, HashMap<&str, Vec<&str>>> = HashMap::new();
+    for (relative, entry) in &manifest.files {
+        archives.entry(entry.archive.as_str())
+            .or_default()
+            .entry(entry.digest.as_str())
+            .or_default()
+            .push(relative.as_str());
+    }
+
+    for (archive_id, mut wanted) in archives {
+        let archive_path = backup_path
+            .join(format!("{}{}", BACKUP_PREFIX, archive_id))
+            .join(ARCHIVE_FILE);
+        let file = fs::File::open(&archive_path)
+            .map_err(|e| format!("Backup chain is incomplete, cannot open archive of {}: {}", archive_id, e))?;
+        let decoder = zstd::Decoder::new(file)
+            .map_err(|e| format!("Failed to open backup decompressor: {}", e))?;
+        let mut archive = tar::Archive::new(decoder);
+
+        for entry in archive.entries().map_err(|e| format!("Failed to read backup archive: {}", e))? {
+            let mut entry = entry.map_err(|e| format!("Failed to read backup archive entry: {}", e))?;
+            let digest = entry.path()
+                .map_err(|e| format!("Failed to read backup archive entry: {}", e))?
+                .to_string_lossy()
+                .to_string();
+
+            let files = match wanted.remove(digest.as_str()) {
+                Some(files) => files,
+                None => continue,
+            };
+
+            let first = target.join(files[0]);
+            if let Some(parent) = first.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create restore directory: {}", e))?;
+            }
+
+            let mut output = fs::File::create(&first)
+                .map_err(|e| format!("Failed to create restored file: {}", e))?;
+            std::io::copy(&mut entry, &mut output)
+                .map_err(|e| format!("Failed to restore {}: {}", files[0], e))?;
+
+            for other in &files[1..] {
+                let path = target.join(other);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| format!("Failed to create restore directory: {}", e))?;
+                }
+                fs::copy(&first, &path)
+                    .map_err(|e| format!("Failed to restore {}: {}", other, e))?;
+            }
+        }
+
+        if let Some(digest) = wanted.keys().next() {
+            return Err(format!("Backup chain is incomplete: blob {} missing from archive of {}", digest, archive_id));
+        }
+    }
+
+    Ok(())
+}
+
+/// List all backups, newest first
+pub fn list_backups(backup_path: &Path) -> Result<Vec<BackupInfo>, String> {
+    if !backup_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+
+    for entry in fs::read_dir(backup_path).map_err(|e| format!("Failed to read backup directory: {}", e))? {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        let id = match name.strip_prefix(BACKUP_PREFIX) {
+            Some(id) if path.is_dir() => id.to_string(),
+            _ => continue,
+        };
+
+        // Prefer the timestamp encoded in the ID, fall back to the directory mtime
+        let created_at = NaiveDateTime::parse_from_str(&id, BACKUP_ID_FORMAT)
+            .or_else(|_| NaiveDateTime::parse_from_str(&id, LEGACY_BACKUP_ID_FORMAT))
+            .ok()
+            .and_then(|naive| Local.from_local_datetime(&naive).single())
+            .or_else(|| {
+                entry.metadata()
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .map(DateTime::<Local>::from)
+            })
+            .unwrap_or_else(Local::now);
+
+        let size = fs_extra::dir::get_size(&path)
+            .map_err(|e| format!("Failed to get backup size: {}", e))?;
+
+        backups.push(BackupInfo {
+            id,
+            path,
+            created_at,
+            size,
+        });
+    }
+
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.id.cmp(&a.id)));
+
+    Ok(backups)
+}
+
+/// Find a backup by ID
+pub fn find_backup(backup_path: &Path, id: &str) -> Result<BackupInfo, String> {
+    list_backups(backup_path)?
+        .into_iter()
+        .find(|backup| backup.id == id)
+        .ok_or_else(|| format!("Backup {} not found", id))
+}
+
+/// Replace the given store directories on the RAM disk with the contents of a backup
+pub fn restore_backup_files(backup: &BackupInfo, ramdisk_path: &Path, store_dirs: &[&str]) -> Result<(), String> {
+    if let Some(manifest) = load_manifest(&backup.path)? {
+        return restore_incremental(backup, &manifest, ramdisk_path, store_dirs);
+    }
+
+    let source = backup_content_dir(&backup.path, store_dirs);
+
+    for store_dir in store_dirs {
+        let target = ramdisk_path.join(store_dir);
+        if target.exists() {
+            fs::remove_dir_all(&target)
+                .map_err(|e| format!("Failed to clear {} store: {}", store_dir, e))?;
+        }
+
+        let backup_store = source.join(store_dir);
+        if backup_store.exists() {
+            let mut options = fs_extra::dir::CopyOptions::new();
+            options.copy_inside = true;
+            fs_extra::dir::copy(&backup_store, &target, &options)
+                .map_err(|e| format!("Failed to restore {} store: {}", store_dir, e))?;
+        } else {
+            fs::create_dir_all(&target)
+                .map_err(|e| format!("Failed to create {} store directory: {}", store_dir, e))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Restore an incremental backup by reassembling its chain
+///
+/// The stores are rebuilt in a staging directory first so a broken chain leaves
+/// the RAM disk untouched.
+fn restore_incremental(backup: &BackupInfo, manifest: &BackupManifest, ramdisk_path: &Path, store_dirs: &[&str]) -> Result<(), String> {
+    let backup_path = backup.path.parent()
+        .ok_or_else(|| format!("Backup {} has no parent directory", backup.id))?;
+
+    let staging = ramdisk_path.join(".restore");
+    if staging.exists() {
+        fs::remove_dir_all(&staging)
+            .map_err(|e| format!("Failed to clear restore staging directory: {}", e))?;
+    }
+
+    if let Err(e) = restore_manifest(backup_path, manifest, &staging) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    for store_dir in store_dirs {
+        let target = ramdisk_path.join(store_dir);
+        if target.exists() {
+            fs::remove_dir_all(&target)
+                .map_err(|e| format!("Failed to clear {} store: {}", store_dir, e))?;
+        }
+
+        let staged = staging.join(store_dir);
+        if staged.exists() {
+            fs::rename(&staged, &target)
+                .map_err(|e| format!("Failed to restore {} store: {}", store_dir, e))?;
+        } else {
+            fs::create_dir_all(&target)
+                .map_err(|e| format!("Failed to create {} store directory: {}", store_dir, e))?;
+        }
+    }
+
+    fs::remove_dir_all(&staging)
+        .map_err(|e| format!("Failed to remove restore staging directory: {}", e))
+}
+
+/// Directory inside a backup that holds the store directories
+///
+/// Older backups copied the RAM disk directory itself, so the stores sit one level deeper.
+fn backup_content_dir(backup_dir: &Path, store_dirs: &[&str]) -> PathBuf {
+    if store_dirs.iter().any(|dir| backup_dir.join(dir).exists()) {
+        return backup_dir.to_path_buf();
+    }
+
+    fs::read_dir(backup_dir)
+        .ok()
+        .and_then(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .find(|path| store_dirs.iter().any(|dir| path.join(dir).exists()))
+        })
+        .unwrap_or_else(|| backup_dir.to_path_buf())
+}
+
+/// Remove backups that violate the retention policy, returning the removed backups
+///
+/// Backups whose archives are still referenced by a retained incremental backup
+/// are kept until the chain no longer needs them.
+pub fn prune_backups(backup_path: &Path, retention: &BackupRetention) -> Result<Vec<BackupInfo>, String> {
+    let backups = list_backups(backup_path)?;
+    let now = Local::now();
+
+    let mut total_size = 0u64;
+    let mut removed = Vec::new();
+    let mut referenced: HashSet<String> = HashSet::new();
+
+    for (position, backup) in backups.into_iter().enumerate() {
+        total_size += backup.size;
+
+        // Always keep the newest backup and anything a kept backup depends on
+        if position == 0 || referenced.contains(&backup.id) {
+            add_references(&backup, &mut referenced)?;
+            continue;
+        }
+
+        let too_many = retention.keep_last.is_some_and(|keep| position >= keep);
+        let too_old = retention.max_age_secs.is_some_and(|max_age| {
+            now.signed_duration_since(backup.created_at).num_seconds() > max_age as i64
+        });
+        let too_large = retention.max_total_bytes.is_some_and(|max_bytes| total_size > max_bytes);
+
+        if too_many || too_old || too_large {
+            fs::remove_dir_all(&backup.path)
+                .map_err(|e| format!("Failed to remove backup {}: {}", backup.id, e))?;
+            total_size -= backup.size;
+            removed.push(backup);
+        } else {
+            add_references(&backup, &mut referenced)?;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Record the backups whose archives the given backup needs for a restore
+fn add_references(backup: &BackupInfo, referenced: &mut HashSet<String>) -> Result<(), String> {
+    if let Some(manifest) = load_manifest(&backup.path)? {
+        referenced.extend(manifest.files.into_values().map(|entry| entry.archive));
+    }
+
+    Ok(())
+}
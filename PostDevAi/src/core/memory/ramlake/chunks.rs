@@ -0,0 +1,178 @@
+// Chunk-level code embeddings
+
+use uuid::Uuid;
+use serde::{Serialize, Deserialize};
+
+use super::{RamLake, Embedder, CodeChunk, EmbeddingSource, NewEmbedding, VectorFilter, chunk_code, embedding_source, CODE_EMBEDDING};
+
+/// Lines of a code file found by `RamLake::search_code`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodeHit {
+    /// ID of the embedding that matched
+    pub embedding_id: Uuid,
+
+    /// ID of the code file
+    pub code_id: Uuid,
+
+    /// Path of the code file
+    pub path: String,
+
+    /// First line of the span, from 1
+    pub start_line: usize,
+
+    /// Last line of the span, inclusive
+    pub end_line: usize,
+
+    /// Vector similarity of the embedding
+    pub similarity: f32,
+}
+
+impl RamLake {
+    /// Split a stored code file into chunks to embed
+    ///
+    /// Cold files are read without being promoted.
+    pub fn chunk_code(&self, code_id: Uuid) -> Result<Vec<CodeChunk>, String> {
+        let (_, content, language) = self.code_store.read().get_file(code_id)?;
+
+        Ok(chunk_code(&content, &language))
+    }
+
+    /// Index the chunks of a code file for vector search in a collection
+    ///
+    /// Each chunk gets an embedding with an ID of its own that records the
+    /// file as its source and the chunk's lines. The file's previous
+    /// embeddings in the collection, whole or in chunks, are replaced. Returns
+    /// the IDs of the chunk embeddings in the order of the chunks.
+    pub fn index_code_chunks(&self, collection: &str, code_id: Uuid, chunks: Vec<(CodeChunk, Vec<f32>)>) -> Result<Vec<Uuid>, String> {
+        self.replace_code_chunks(collection, code_id, None, chunks)
+    }
+
+    /// Chunk a code file and index its chunks with the collection's embedder
+    pub fn embed_code_chunks(&self, collection: &str, code_id: Uuid) -> Result<Vec<Uuid>, String> {
+        let embedder = self.embedders.read().get(collection).cloned()
+            .ok_or_else(|| format!("Vector collection '{}' has no embedder", collection))?;
+
+        self.embed_chunks_with(collection, code_id, embedder.as_ref())
+    }
+
+    /// Chunk, embed and index a code file with an embedder
+    ///
+    /// Nothing is indexed if the file changes while its chunks are embedded.
+    pub(super) fn embed_chunks_with(&self, collection: &str, code_id: Uuid, embedder: &dyn Embedder) -> Result<Vec<Uuid>, String> {
+        let (hash, chunks) = {
+            let code_store = self.code_store.read();
+            let hash = code_store.get_file_metadata(code_id)?.hash;
+            let (_, content, language) = code_store.get_file(code_id)?;
+            (hash, chunk_code(&content, &language))
+        };
+
+        let texts: Vec<String> = chunks.iter().map(|chunk| chunk.text.clone()).collect();
+        let embeddings = if texts.is_empty() { Vec::new() } else { embedder.embed(&texts)? };
+        if embeddings.len() != texts.len() {
+            return Err(format!(
+                "Embedder {} returned {} embeddings for {} texts",
+                embedder.model(), embeddings.len(), texts.len()
+            ));
+        }
+
+        self.replace_code_chunks(collection, code_id, Some(&hash), chunks.into_iter().zip(embeddings).collect())
+    }
+
+    /// Replace the embeddings of a code file in a collection with chunk embeddings
+    ///
+    /// With `hash` set, nothing is done unless the file still has that hash.
+    fn replace_code_chunks(&self, collection: &str, code_id: Uuid, hash: Option<&str>, chunks: Vec<(CodeChunk, Vec<f32>)>) -> Result<Vec<Uuid>, String> {
+        let (namespace, source) = {
+            let code_store = self.code_store.read();
+            let metadata = code_store.get_file_metadata(code_id)?;
+            if hash.is_some_and(|hash| hash != metadata.hash) {
+                return Ok(Vec::new());
+            }
+
+            let (_, content, _) = code_store.get_file(code_id)?;
+            let lines = content.lines().count();
+            let invalid = chunks.iter().find(|(chunk, _)| chunk.start_line == 0 || chunk.start_line > chunk.end_line || chunk.end_line > lines);
+            if let Some((chunk, _)) = invalid {
+                return Err(format!(
+                    "Invalid chunk lines {}-{} for code file {} with {} lines",
+                    chunk.start_line, chunk.end_line, code_id, lines
+                ));
+            }
+
+            embedding_source(&code_store, &self.history_store.read(), code_id)
+        };
+
+        let previous: Vec<Uuid> = {
+            let vector_store = self.vector_store.read();
+            vector_store.get_ids_by_source(code_id).into_iter()
+                .filter(|&id| vector_store.get_embedding_metadata(id).is_ok_and(|metadata| metadata.collection == collection))
+                .collect()
+        };
+
+        let embeddings: Vec<NewEmbedding> = chunks.into_iter()
+            .map(|(chunk, embedding)| NewEmbedding {
+                id: Uuid::new_v4(),
+                namespace: namespace.clone(),
+                source: EmbeddingSource {
+                    start_line: Some(chunk.start_line),
+                    end_line: Some(chunk.end_line),
+                    ..source.clone()
+                },
+                embedding,
+            })
+            .collect();
+        let ids = embeddings.iter().map(|new| new.id).collect();
+
+        // The new chunks are stored before the old embeddings go, so a failure keeps the file searchable
+        self.put_new_embeddings(collection, embeddings, false)?;
+        for id in previous {
+            self.delete_embedding(id)?;
+        }
+
+        Ok(ids)
+    }
+
+    /// Search a vector collection for the code most similar to an embedding across all namespaces
+    pub fn search_code(&self, collection: &str, embedding: Vec<f32>, limit: usize) -> Result<Vec<CodeHit>, String> {
+        self.search_code_where(collection, &VectorFilter::default(), embedding, limit)
+    }
+
+    /// Search a vector collection for the code most similar to an embedding, matching a filter
+    ///
+    /// Only code embeddings are searched. A chunk embedding resolves to the
+    /// chunk's lines and a whole-file embedding to every line of its file, so
+    /// several hits may point into the same file.
+    pub fn search_code_where(&self, collection: &str, filter: &VectorFilter, embedding: Vec<f32>, limit: usize) -> Result<Vec<CodeHit>, String> {
+        let filter = VectorFilter {
+            embedding_type: Some(CODE_EMBEDDING.to_string()),
+            ..filter.clone()
+        };
+        let results = self.search_similar_where(collection, &filter, embedding, limit)?;
+
+        let vector_store = self.vector_store.read();
+        let code_store = self.code_store.read();
+        let mut hits = Vec::with_capacity(results.len());
+        for (embedding_id, similarity) in results {
+            let Ok(metadata) = vector_store.get_embedding_metadata(embedding_id) else {
+                continue;
+            };
+            let (start_line, end_line) = match (metadata.start_line, metadata.end_line) {
+                (Some(start_line), Some(end_line)) => (start_line, end_line),
+                _ => match code_store.get_file(metadata.source_id) {
+                    Ok((_, content, _)) => (1, content.lines().count().max(1)),
+                    Err(_) => continue,
+                },
+            };
+            hits.push(CodeHit {
+                embedding_id,
+                code_id: metadata.source_id,
+                path: metadata.path.unwrap_or_default(),
+                start_line,
+                end_line,
+                similarity,
+            });
+        }
+
+        Ok(hits)
+    }
+}
@@ -0,0 +1,247 @@
+// Cross-store eviction for RAM-Lake
+
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use super::transaction::TxStores;
+use super::{RamLake, VectorStore, CodeStore, HistoryStore, MemoryManager, EntityKind, EvictionCandidate, EvictionNeeds, EvictionEvent, EvictionPolicy};
+use crate::utils::filesystem::write_json_atomic;
+
+/// File listing pinned entities, inside the metadata store directory so backups include it
+pub(super) const PINS_FILE: &str = "metadata/pinned.json";
+
+impl RamLake {
+    /// Make room for new data, evicting entries if a store or the RAM-Lake is full
+    ///
+    /// `writes` lists the bytes about to be written per entity type, with `None`
+    /// for relations. Entities in `protected` are never evicted.
+    pub(super) fn make_room(&self, writes: &[(Option<EntityKind>, u64)], protected: &HashSet<Uuid>) -> Result<(), String> {
+        // Only take every store lock when something has to go
+        let needed = {
+            let vector_store = self.vector_store.read();
+            let code_store = self.code_store.read();
+            let history_store = self.history_store.read();
+            !self.eviction_needs(&vector_store, &code_store, &history_store, writes).is_satisfied()
+        };
+        if !needed || !self.memory_manager.read().eviction_enabled() {
+            return Ok(());
+        }
+
+        let mut vector_store = self.vector_store.write();
+        let mut code_store = self.code_store.write();
+        let mut history_store = self.history_store.write();
+        let mut metadata_store = self.metadata_store.write();
+
+        let mut stores = TxStores {
+            vector_store: &mut vector_store,
+            code_store: &mut code_store,
+            history_store: &mut history_store,
+            metadata_store: &mut metadata_store,
+        };
+
+        self.make_room_in(&mut stores, writes, protected)
+    }
+
+    /// Make room for new data while holding every store lock
+    ///
+    /// Evicts in rounds until the writes fit or nothing evictable is left; a
+    /// write that still does not fit fails when it reserves its memory.
+    pub(super) fn make_room_in(&self, stores: &mut TxStores, writes: &[(Option<EntityKind>, u64)], protected: &HashSet<Uuid>) -> Result<(), String> {
+        loop {
+            let needs = self.eviction_needs(stores.vector_store, stores.code_store, stores.history_store, writes);
+            if needs.is_satisfied() {
+                return Ok(());
+            }
+
+            let candidates = self.eviction_candidates(stores, protected);
+            let victims = self.memory_manager.read().select_victims(candidates, needs);
+            if victims.is_empty() {
+                return Ok(());
+            }
+
+            for victim in victims {
+                // An embedding goes with its code file, which may have been evicted before it
+                if victim.kind == EntityKind::Embedding && stores.vector_store.get_embedding_metadata(victim.id).is_err() {
+                    continue;
+                }
+
+                let freed = self.evict(stores, &victim)?;
+                self.memory_manager.write().record_eviction(&victim, freed);
+            }
+        }
+    }
+
+    /// Space that has to be freed before the writes fit
+    fn eviction_needs(&self, vector_store: &VectorStore, code_store: &CodeStore, history_store: &HistoryStore, writes: &[(Option<EntityKind>, u64)]) -> EvictionNeeds {
+        let mut needs = EvictionNeeds::default();
+
+        let mut total = 0;
+        for (kind, size) in writes {
+            total += size;
+            if let Some(kind) = kind {
+                *needs.per_kind.entry(*kind).or_default() += size;
+            }
+        }
+
+        // Compare each store's share of the writes with the room it has left
+        for (kind, bytes) in needs.per_kind.iter_mut() {
+            let (used, max) = match kind {
+                EntityKind::Code => (code_store.get_size(), code_store.get_max_size()),
+                EntityKind::Embedding => (vector_store.get_size(), vector_store.get_max_size()),
+                EntityKind::Event => (history_store.get_size(), history_store.get_max_size()),
+            };
+            *bytes = (used + *bytes).saturating_sub(max);
+        }
+
+        let memory_manager = self.memory_manager.read();
+        needs.total = (memory_manager.get_current_usage() + total).saturating_sub(memory_manager.get_max_size());
+
+        needs
+    }
+
+    /// Every unprotected code file, embedding and event in RAM with its last access
+    fn eviction_candidates(&self, stores: &TxStores, protected: &HashSet<Uuid>) -> Vec<EvictionCandidate> {
+        let memory_manager = self.memory_manager.read();
+
+        // Drop access times of entities deleted since the last eviction
+        let ids: HashSet<Uuid> = stores.code_store.get_ids().into_iter()
+            .chain(stores.vector_store.get_ids())
+            .chain(stores.history_store.get_ids())
+            .collect();
+        memory_manager.retain_access_times(&ids);
+
+        let mut candidates = hot_entries(&memory_manager, stores.vector_store, stores.code_store, stores.history_store);
+        candidates.retain(|c| !protected.contains(&c.id));
+        candidates
+    }
+
+    /// Evict one entry, returning the bytes freed across all stores
+    fn evict(&self, stores: &mut TxStores, candidate: &EvictionCandidate) -> Result<u64, String> {
+        let id = candidate.id;
+        let mut freed = 0;
+
+        match candidate.kind {
+            EntityKind::Code => {
+                let code_freed = stores.code_store.evict_file(id)?;
+                self.lexical.write().remove(id);
+                self.release(&candidate.namespace, code_freed)?;
+                freed += code_freed;
+
+                // Whole-file and chunk embeddings record the code file as their source
+                for embedding_id in stores.vector_store.get_ids_by_source(id) {
+                    if !stores.vector_store.is_cold(embedding_id) {
+                        freed += stores.vector_store.embedding_size(embedding_id);
+                    }
+                    self.remove_embedding(stores.vector_store, embedding_id)?;
+                }
+                freed += self.remove_entity_relations(stores.metadata_store, id)?;
+            }
+            EntityKind::Embedding => {
+                self.remove_embedding(stores.vector_store, id)?;
+                freed += candidate.size;
+            }
+            EntityKind::Event => {
+                self.remove_event(stores.history_store, id)?;
+                freed += candidate.size;
+                freed += self.remove_entity_relations(stores.metadata_store, id)?;
+            }
+        }
+
+        Ok(freed)
+    }
+
+    /// Protect an entity from eviction
+    ///
+    /// A code file and its embedding share an ID, so pinning one pins both.
+    pub fn pin(&self, id: Uuid) -> Result<(), String> {
+        let mut memory_manager = self.memory_manager.write();
+        memory_manager.pin(id);
+
+        write_json_atomic(self.ramdisk_path.join(PINS_FILE), &memory_manager.get_pinned())
+    }
+
+    /// Allow a pinned entity to be evicted again
+    pub fn unpin(&self, id: Uuid) -> Result<(), String> {
+        let mut memory_manager = self.memory_manager.write();
+        if memory_manager.unpin(id) {
+            write_json_atomic(self.ramdisk_path.join(PINS_FILE), &memory_manager.get_pinned())?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether an entity is protected from eviction
+    pub fn is_pinned(&self, id: Uuid) -> bool {
+        self.memory_manager.read().is_pinned(id)
+    }
+
+    /// Replace the configured eviction policy, or disable eviction with `None`
+    pub fn set_eviction_policy(&self, policy: Option<Box<dyn EvictionPolicy>>) {
+        self.memory_manager.write().set_eviction_policy(policy);
+    }
+
+    /// Get recent evictions, newest first
+    pub fn get_recent_evictions(&self, limit: usize) -> Vec<EvictionEvent> {
+        self.memory_manager.read().get_recent_evictions(limit)
+    }
+}
+
+/// Every code file, embedding and event held in RAM with its last access
+///
+/// Entries in the cold tier take no RAM and are left out. Entities never
+/// accessed since startup count as accessed when they were last written.
+pub(super) fn hot_entries(memory_manager: &MemoryManager, vector_store: &VectorStore, code_store: &CodeStore, history_store: &HistoryStore) -> Vec<EvictionCandidate> {
+    let mut entries = Vec::new();
+
+    for m in code_store.get_all_metadata() {
+        if code_store.is_cold(m.id) {
+            continue;
+        }
+        entries.push(EvictionCandidate {
+            id: m.id,
+            kind: EntityKind::Code,
+            last_access: memory_manager.get_last_access(m.id).unwrap_or(m.modified_at),
+            size: m.blob_size(),
+            namespace: m.namespace,
+        });
+    }
+    for m in vector_store.get_all_metadata() {
+        if vector_store.is_cold(m.id) {
+            continue;
+        }
+        entries.push(EvictionCandidate {
+            id: m.id,
+            kind: EntityKind::Embedding,
+            last_access: memory_manager.get_last_access(m.id).unwrap_or(m.created_at),
+            size: vector_store.embedding_size(m.id),
+            namespace: m.namespace,
+        });
+    }
+    for m in history_store.get_all_metadata() {
+        if history_store.is_cold(m.id) {
+            continue;
+        }
+        entries.push(EvictionCandidate {
+            id: m.id,
+            kind: EntityKind::Event,
+            last_access: memory_manager.get_last_access(m.id).unwrap_or(m.timestamp),
+            size: m.blob_size(),
+            namespace: m.namespace,
+        });
+    }
+
+    entries
+}
+
+/// Load the pinned entities saved in the RAM disk
+pub(super) fn load_pins(ramdisk_path: &std::path::Path) -> Result<HashSet<Uuid>, String> {
+    let path = ramdisk_path.join(PINS_FILE);
+    if !path.exists() {
+        return Ok(HashSet::new());
+    }
+
+    let json = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read pinned entities: {}", e))?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse pinned entities: {}", e))
+}
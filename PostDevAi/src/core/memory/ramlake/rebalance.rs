@@ -0,0 +1,319 @@
+// Runtime rebalancing of the store allocation
+
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+use parking_lot::RwLock as PLRwLock;
+use serde::{Serialize, Deserialize};
+
+use super::{RamLake, RamLakeConfig, StoreAllocation, VectorStore, CodeStore, HistoryStore, MetadataStore};
+use crate::utils::filesystem::write_json_atomic;
+
+/// Store names in allocation order
+const STORE_NAMES: [&str; 4] = ["vector", "code", "history", "metadata"];
+
+/// File holding the allocation set at runtime, inside the metadata store directory so backups include it
+const ALLOCATION_FILE: &str = "metadata/allocation.json";
+
+/// Weight of the latest growth measurement in the smoothed growth rate
+const GROWTH_SMOOTHING: f64 = 0.5;
+
+/// Adaptive allocation settings
+///
+/// When enabled, capacity moves between the stores based on their usage and
+/// growth rate. `RamLakeConfig::allocation` is only the starting point.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdaptiveAllocation {
+    /// Rebalance the stores in the background
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Seconds between rebalancing runs
+    #[serde(default = "default_rebalance_interval")]
+    pub interval: u64,
+
+    /// Seconds of growth at the observed rate each store should have room for
+    #[serde(default = "default_growth_horizon")]
+    pub growth_horizon_secs: u64,
+
+    /// Fraction of its usage each store keeps free on top of the expected growth
+    #[serde(default = "default_headroom")]
+    pub headroom: f32,
+
+    /// Smallest share of the RAM-Lake per store
+    #[serde(default = "default_min_allocation")]
+    pub min: StoreAllocation,
+
+    /// Largest share of the RAM-Lake per store
+    #[serde(default = "default_max_allocation")]
+    pub max: StoreAllocation,
+}
+
+fn default_rebalance_interval() -> u64 {
+    60
+}
+
+fn default_growth_horizon() -> u64 {
+    3600
+}
+
+fn default_headroom() -> f32 {
+    0.2
+}
+
+fn default_min_allocation() -> StoreAllocation {
+    StoreAllocation {
+        vector_store: 0.05,
+        code_store: 0.05,
+        history_store: 0.05,
+        metadata_store: 0.02,
+    }
+}
+
+fn default_max_allocation() -> StoreAllocation {
+    StoreAllocation {
+        vector_store: 0.7,
+        code_store: 0.7,
+        history_store: 0.5,
+        metadata_store: 0.3,
+    }
+}
+
+impl Default for AdaptiveAllocation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: default_rebalance_interval(),
+            growth_horizon_secs: default_growth_horizon(),
+            headroom: default_headroom(),
+            min: default_min_allocation(),
+            max: default_max_allocation(),
+        }
+    }
+}
+
+impl StoreAllocation {
+    /// Shares in store order: vector, code, history, metadata
+    fn to_shares(&self) -> [f64; 4] {
+        [self.vector_store, self.code_store, self.history_store, self.metadata_store].map(|share| share as f64)
+    }
+
+    /// Allocation from shares in store order
+    fn from_shares(shares: [f64; 4]) -> Self {
+        Self {
+            vector_store: shares[0] as f32,
+            code_store: shares[1] as f32,
+            history_store: shares[2] as f32,
+            metadata_store: shares[3] as f32,
+        }
+    }
+
+    /// Allocation of stores with the given sizes in store order
+    fn from_sizes(sizes: [u64; 4], total: u64) -> Self {
+        let total = total.max(1) as f64;
+        Self::from_shares(sizes.map(|size| size as f64 / total))
+    }
+}
+
+/// Load the allocation saved by the last `set_allocation` or rebalancing run
+pub(super) fn load_allocation(ramdisk_path: &Path) -> Result<Option<StoreAllocation>, String> {
+    let path = ramdisk_path.join(ALLOCATION_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let json = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read store allocation: {}", e))?;
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse store allocation: {}", e))
+}
+
+/// Save the allocation of stores with the given sizes so the next start reapplies it
+pub(super) fn save_allocation(ramdisk_path: &Path, sizes: [u64; 4], total: u64) -> Result<(), String> {
+    write_json_atomic(ramdisk_path.join(ALLOCATION_FILE), &StoreAllocation::from_sizes(sizes, total))
+}
+
+/// Growth observed by the rebalancer between runs
+#[derive(Debug, Default)]
+pub(super) struct Rebalancer {
+    /// Time and store sizes of the previous run
+    last_sample: Option<(Instant, [u64; 4])>,
+
+    /// Smoothed growth per store in bytes per second
+    growth: [f64; 4],
+}
+
+impl Rebalancer {
+    /// Record the current store sizes, updating the growth rates
+    fn observe(&mut self, used: [u64; 4]) -> [f64; 4] {
+        let now = Instant::now();
+        if let Some((at, previous)) = self.last_sample {
+            let secs = now.duration_since(at).as_secs_f64();
+            if secs > 0.0 {
+                for i in 0..4 {
+                    // Shrinking stores are treated as not growing
+                    let rate = used[i].saturating_sub(previous[i]) as f64 / secs;
+                    self.growth[i] = GROWTH_SMOOTHING * rate + (1.0 - GROWTH_SMOOTHING) * self.growth[i];
+                }
+            }
+        }
+        self.last_sample = Some((now, used));
+
+        self.growth
+    }
+}
+
+/// Plan the shares of the stores from their usage and growth
+///
+/// Each store asks for its usage plus headroom and expected growth, bounded by
+/// the configured minimum and maximum share but never below its usage. Demand
+/// above the total is taken from the stores' room above their floor; capacity
+/// left over is spread over the stores in proportion to their share.
+fn plan_shares(used: [u64; 4], growth: [f64; 4], total: u64, config: &AdaptiveAllocation) -> [f64; 4] {
+    let total = total.max(1) as f64;
+    let (min, max) = (config.min.to_shares(), config.max.to_shares());
+
+    let mut floor = [0.0; 4];
+    let mut shares = [0.0; 4];
+    for i in 0..4 {
+        let used_share = used[i] as f64 / total;
+        let demand = used_share * (1.0 + config.headroom as f64) + growth[i] * config.growth_horizon_secs as f64 / total;
+        floor[i] = min[i].max(used_share).min(max[i]);
+        shares[i] = demand.clamp(floor[i], max[i]);
+    }
+
+    let sum: f64 = shares.iter().sum();
+    if sum > 1.0 {
+        let room: f64 = (0..4).map(|i| shares[i] - floor[i]).sum();
+        if room > 0.0 {
+            let scale = ((sum - 1.0) / room).min(1.0);
+            for i in 0..4 {
+                shares[i] -= (shares[i] - floor[i]) * scale;
+            }
+        }
+    } else {
+        // Stores reaching their maximum hand the rest to the others
+        for _ in 0..4 {
+            let surplus = 1.0 - shares.iter().sum::<f64>();
+            let open: f64 = (0..4).filter(|&i| shares[i] < max[i]).map(|i| shares[i]).sum();
+            if surplus <= f64::EPSILON || open <= 0.0 {
+                break;
+            }
+
+            shares = std::array::from_fn(|i| {
+                if shares[i] < max[i] { (shares[i] + surplus * shares[i] / open).min(max[i]) } else { shares[i] }
+            });
+        }
+    }
+
+    shares
+}
+
+impl RamLake {
+    /// Get the share of the RAM-Lake each store currently has
+    pub fn get_allocation(&self) -> StoreAllocation {
+        let sizes = [
+            self.vector_store.read().get_max_size(),
+            self.code_store.read().get_max_size(),
+            self.history_store.read().get_max_size(),
+            self.metadata_store.read().get_max_size(),
+        ];
+
+        StoreAllocation::from_sizes(sizes, self.config.max_size)
+    }
+
+    /// Change the share of the RAM-Lake each store has without restarting
+    ///
+    /// The shares may not add up to more than the whole RAM-Lake, and no store
+    /// may get less than it already holds. The allocation is kept across
+    /// restarts; with adaptive allocation enabled, the next rebalancing run
+    /// replaces it again.
+    pub fn set_allocation(&self, allocation: StoreAllocation) -> Result<(), String> {
+        let shares = allocation.to_shares();
+        if shares.iter().any(|&share| share < 0.0) {
+            return Err("Store allocation must not be negative".to_string());
+        }
+        if shares.iter().sum::<f64>() > 1.0 + 1e-6 {
+            return Err("Store allocation must not exceed the RAM-Lake size".to_string());
+        }
+
+        Self::apply_allocation(&self.ramdisk_path, &self.vector_store, &self.code_store, &self.history_store, &self.metadata_store, self.config.max_size, |_| shares)?;
+
+        Ok(())
+    }
+
+    /// Move capacity between the stores based on their usage and growth
+    ///
+    /// Returns the new allocation.
+    pub fn rebalance(&self) -> Result<StoreAllocation, String> {
+        Self::rebalance_stores(
+            &self.ramdisk_path,
+            &self.vector_store,
+            &self.code_store,
+            &self.history_store,
+            &self.metadata_store,
+            &self.rebalancer,
+            &self.config,
+        )
+    }
+
+    /// Rebalance the given stores
+    pub(super) fn rebalance_stores(
+        ramdisk_path: &Path,
+        vector_store: &PLRwLock<VectorStore>,
+        code_store: &PLRwLock<CodeStore>,
+        history_store: &PLRwLock<HistoryStore>,
+        metadata_store: &PLRwLock<MetadataStore>,
+        rebalancer: &Mutex<Rebalancer>,
+        config: &RamLakeConfig,
+    ) -> Result<StoreAllocation, String> {
+        let total = config.max_size;
+        let shares = Self::apply_allocation(ramdisk_path, vector_store, code_store, history_store, metadata_store, total, |used| {
+            let growth = rebalancer.lock().unwrap().observe(used);
+            plan_shares(used, growth, total, &config.adaptive_allocation)
+        })?;
+
+        Ok(StoreAllocation::from_shares(shares))
+    }
+
+    /// Resize the stores to the shares planned from their current usage
+    ///
+    /// The stores stay locked from reading their usage until they are resized.
+    /// Fails without resizing anything if a store would end up below its usage.
+    /// The new allocation is saved before the stores are resized.
+    fn apply_allocation(
+        ramdisk_path: &Path,
+        vector_store: &PLRwLock<VectorStore>,
+        code_store: &PLRwLock<CodeStore>,
+        history_store: &PLRwLock<HistoryStore>,
+        metadata_store: &PLRwLock<MetadataStore>,
+        total: u64,
+        plan: impl FnOnce([u64; 4]) -> [f64; 4],
+    ) -> Result<[f64; 4], String> {
+        let mut vector_store = vector_store.write();
+        let mut code_store = code_store.write();
+        let mut history_store = history_store.write();
+        let mut metadata_store = metadata_store.write();
+
+        let used = [vector_store.get_size(), code_store.get_size(), history_store.get_size(), metadata_store.get_size()];
+        let shares = plan(used);
+        let sizes = shares.map(|share| (total as f64 * share).round() as u64);
+        for i in 0..4 {
+            if sizes[i] < used[i] {
+                return Err(format!(
+                    "Allocation of the {} store ({} bytes) is below its usage ({} bytes)",
+                    STORE_NAMES[i], sizes[i], used[i]
+                ));
+            }
+        }
+
+        save_allocation(ramdisk_path, sizes, total)?;
+        vector_store.set_max_size(sizes[0]);
+        code_store.set_max_size(sizes[1]);
+        history_store.set_max_size(sizes[2]);
+        metadata_store.set_max_size(sizes[3]);
+
+        Ok(shares)
+    }
+}
@@ -0,0 +1,182 @@
+// Re-embedding of stale embeddings
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use uuid::Uuid;
+use serde::{Serialize, Deserialize};
+
+use super::{RamLake, CODE_EMBEDDING, EVENT_EMBEDDING};
+
+/// Model that computes the embeddings of a vector collection
+pub trait Embedder: Send + Sync {
+    /// Identifier and version of the model, recorded with every embedding it produces
+    fn model(&self) -> String;
+
+    /// Compute the embeddings of several texts, in order
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String>;
+}
+
+/// Why an embedding no longer matches what it would be computed as now
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StaleReason {
+    /// The code file changed since it was embedded
+    SourceChanged,
+
+    /// The embedding was produced by another model than the collection's
+    ModelChanged,
+}
+
+/// Embedding that needs to be computed again
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StaleEmbedding {
+    /// ID of the embedding
+    pub id: Uuid,
+
+    /// Why the embedding is stale
+    pub reason: StaleReason,
+}
+
+impl RamLake {
+    /// Set or remove the embedder of a vector collection
+    ///
+    /// The collection's model becomes the embedder's, so embeddings produced
+    /// by any other model turn stale. Removing the embedder keeps the model.
+    pub fn set_embedder(&self, collection: &str, embedder: Option<Arc<dyn Embedder>>) -> Result<(), String> {
+        match embedder {
+            Some(embedder) => {
+                self.vector_store.write().set_collection_model(collection, Some(embedder.model()))?;
+                self.embedders.write().insert(collection.to_string(), embedder);
+            }
+            None => {
+                self.embedders.write().remove(collection);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Find the embeddings of a collection that are out of date
+    ///
+    /// A code embedding is stale once the file's hash differs from the one it
+    /// was computed from; embeddings from before hashes were recorded count as
+    /// stale too. Any embedding is stale if the collection names a model and
+    /// the embedding was produced by another.
+    pub fn stale_embeddings(&self, collection: &str) -> Result<Vec<StaleEmbedding>, String> {
+        let vector_store = self.vector_store.read();
+        let code_store = self.code_store.read();
+        let model = vector_store.get_collection(collection)?.model;
+
+        let mut stale: Vec<StaleEmbedding> = vector_store.get_all_metadata().into_iter()
+            .filter(|metadata| metadata.collection == collection)
+            .filter_map(|metadata| {
+                let source_changed = metadata.embedding_type == CODE_EMBEDDING
+                    && code_store.get_file_metadata(metadata.source_id)
+                        .is_ok_and(|code| metadata.source_hash.as_ref() != Some(&code.hash));
+                let reason = if source_changed {
+                    StaleReason::SourceChanged
+                } else if model.is_some() && metadata.model != model {
+                    StaleReason::ModelChanged
+                } else {
+                    return None;
+                };
+                Some(StaleEmbedding { id: metadata.id, reason })
+            })
+            .collect();
+        stale.sort_by_key(|stale| stale.id);
+
+        Ok(stale)
+    }
+
+    /// Re-embed up to `limit` stale embeddings of the collections that have an embedder
+    ///
+    /// The content of each code file or event is embedded again and replaces
+    /// the stale embedding; a file indexed in chunks is chunked and embedded
+    /// again as a whole. Embeddings without a stored source are left as they
+    /// are. Returns the number of embeddings stored.
+    pub fn reembed_stale(&self, limit: usize) -> Result<usize, String> {
+        let embedders: Vec<(String, Arc<dyn Embedder>)> = self.embedders.read()
+            .iter()
+            .map(|(collection, embedder)| (collection.clone(), embedder.clone()))
+            .collect();
+
+        let mut reembedded = 0;
+        for (collection, embedder) in embedders {
+            if reembedded >= limit {
+                break;
+            }
+
+            // Read the sources, noting the hash of each code file and which files are chunked
+            let mut sources = Vec::new();
+            let mut texts = Vec::new();
+            let mut chunked = Vec::new();
+            let mut seen = HashSet::new();
+            for stale in self.stale_embeddings(&collection)? {
+                if sources.len() + chunked.len() + reembedded >= limit {
+                    break;
+                }
+                let chunk_source = self.vector_store.read().get_embedding_metadata(stale.id).ok()
+                    .filter(|metadata| metadata.start_line.is_some())
+                    .map(|metadata| metadata.source_id);
+                if let Some(code_id) = chunk_source {
+                    if seen.insert(code_id) {
+                        chunked.push(code_id);
+                    }
+                    continue;
+                }
+                if let Some((hash, text)) = self.embedding_text(stale.id) {
+                    sources.push((stale.id, hash));
+                    texts.push(text);
+                }
+            }
+            for code_id in chunked {
+                if reembedded >= limit {
+                    break;
+                }
+                reembedded += self.embed_chunks_with(&collection, code_id, embedder.as_ref())?.len();
+            }
+            if texts.is_empty() {
+                continue;
+            }
+
+            let embeddings = embedder.embed(&texts)?;
+            if embeddings.len() != texts.len() {
+                return Err(format!(
+                    "Embedder {} returned {} embeddings for {} texts",
+                    embedder.model(), embeddings.len(), texts.len()
+                ));
+            }
+
+            // Code changed while embedding stays stale for the next run
+            let batch: Vec<(Uuid, Vec<f32>)> = {
+                let code_store = self.code_store.read();
+                sources.into_iter()
+                    .zip(embeddings)
+                    .filter(|((id, hash), _)| hash.is_none() || code_store.get_file_metadata(*id).is_ok_and(|code| hash.as_ref() == Some(&code.hash)))
+                    .map(|((id, _), embedding)| (id, embedding))
+                    .collect()
+            };
+            reembedded += batch.len();
+            self.upsert_embeddings_batch(&collection, batch)?;
+        }
+
+        Ok(reembedded)
+    }
+
+    /// Hash (for code) and content of the code file or event an embedding was computed from
+    ///
+    /// Cold entries are read without being promoted.
+    fn embedding_text(&self, id: Uuid) -> Option<(Option<String>, String)> {
+        let embedding_type = self.vector_store.read().get_embedding_metadata(id).ok()?.embedding_type;
+        if embedding_type == CODE_EMBEDDING {
+            let code_store = self.code_store.read();
+            let hash = code_store.get_file_metadata(id).ok()?.hash;
+            let (_, content, _) = code_store.get_file(id).ok()?;
+            Some((Some(hash), content))
+        } else if embedding_type == EVENT_EMBEDDING {
+            let (_, content, _) = self.history_store.read().get_event(id).ok()?;
+            Some((None, content))
+        } else {
+            None
+        }
+    }
+}
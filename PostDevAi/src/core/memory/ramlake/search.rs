@@ -0,0 +1,221 @@
+// Hybrid lexical and semantic search
+
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+use serde::{Serialize, Deserialize};
+
+use super::{RamLake, LexicalIndex, CodeStore, HistoryStore, VectorFilter, default_collection};
+
+/// Candidates taken from each ranking per requested result
+const FUSION_DEPTH: usize = 4;
+
+/// Weights of the rankings fused by `RamLake::search`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SearchWeights {
+    /// Weight of the BM25 ranking
+    pub lexical: f32,
+
+    /// Weight of the vector similarity ranking
+    pub semantic: f32,
+
+    /// Rank offset of reciprocal rank fusion; larger values flatten the gap between top ranks
+    pub rrf_k: f32,
+}
+
+impl Default for SearchWeights {
+    fn default() -> Self {
+        Self {
+            lexical: 1.0,
+            semantic: 1.0,
+            rrf_k: 60.0,
+        }
+    }
+}
+
+/// Query for `RamLake::search`
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    /// Text matched against code files and events by BM25
+    pub text: String,
+
+    /// Embedding of the query; without one the search is lexical only
+    pub embedding: Option<Vec<f32>>,
+
+    /// Vector collection the embedding is compared with
+    pub collection: String,
+
+    /// Only code files and events in this namespace
+    pub namespace: Option<String>,
+
+    /// Maximum number of hits
+    pub limit: usize,
+
+    /// Weights of the fused rankings
+    pub weights: SearchWeights,
+}
+
+impl Default for SearchQuery {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            embedding: None,
+            collection: default_collection(),
+            namespace: None,
+            limit: 10,
+            weights: SearchWeights::default(),
+        }
+    }
+}
+
+/// Why a hit was returned
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    /// Position in the BM25 ranking, from 1, if the text matched
+    pub lexical_rank: Option<usize>,
+
+    /// BM25 score, if the text matched
+    pub lexical_score: Option<f32>,
+
+    /// Query terms found in the text
+    pub matched_terms: Vec<String>,
+
+    /// Position in the vector similarity ranking, from 1, if the embedding was close enough
+    pub semantic_rank: Option<usize>,
+
+    /// Vector similarity, if the embedding was close enough
+    pub similarity: Option<f32>,
+
+    /// Part of the fused score from the BM25 ranking
+    pub lexical_contribution: f32,
+
+    /// Part of the fused score from the vector similarity ranking
+    pub semantic_contribution: f32,
+}
+
+/// Code file or event found by `RamLake::search`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchHit {
+    /// ID of the code file or event
+    pub id: Uuid,
+
+    /// Fused score; higher is better
+    pub score: f32,
+
+    /// First line of the code chunk whose embedding matched, from 1
+    pub start_line: Option<usize>,
+
+    /// Last line of the code chunk whose embedding matched, inclusive
+    pub end_line: Option<usize>,
+
+    /// How each ranking contributed to the score
+    pub breakdown: ScoreBreakdown,
+}
+
+/// Build the BM25 index over the text of every code file and event in the stores
+///
+/// Code files are indexed by path and content, events by type and content.
+/// Entries whose content cannot be read are left out.
+pub(super) fn build_lexical_index(code_store: &CodeStore, history_store: &HistoryStore) -> LexicalIndex {
+    let mut index = LexicalIndex::default();
+    for metadata in code_store.get_all_metadata() {
+        if let Ok((path, content, _)) = code_store.get_file(metadata.id) {
+            index.insert(metadata.id, &metadata.namespace, &code_text(&path, &content));
+        }
+    }
+    for metadata in history_store.get_all_metadata() {
+        if let Ok((event_type, content, _)) = history_store.get_event(metadata.id) {
+            index.insert(metadata.id, &metadata.namespace, &event_text(&event_type, &content));
+        }
+    }
+    index
+}
+
+/// Text a code file is indexed by
+pub(super) fn code_text(path: &str, content: &str) -> String {
+    format!("{}\n{}", path, content)
+}
+
+/// Text an event is indexed by
+pub(super) fn event_text(event_type: &str, content: &str) -> String {
+    format!("{}\n{}", event_type, content)
+}
+
+impl RamLake {
+    /// Search code files and events by text and, with an embedding, by vector similarity
+    ///
+    /// Both rankings are fused by weighted reciprocal rank fusion: a hit
+    /// scores `weight / (rrf_k + rank)` for each ranking it appears in. Exact
+    /// identifiers and error codes are found by the BM25 ranking even when
+    /// their embeddings are not close. A code file indexed in chunks is ranked
+    /// by its best chunk, whose lines the hit carries.
+    pub fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>, String> {
+        let weights = query.weights;
+        if weights.lexical < 0.0 || weights.semantic < 0.0 || weights.rrf_k <= 0.0 {
+            return Err(format!("Invalid search weights: {:?}", weights));
+        }
+        if query.limit == 0 {
+            return Ok(Vec::new());
+        }
+        let depth = query.limit * FUSION_DEPTH;
+
+        let lexical = self.lexical.read().search(&query.text, depth, query.namespace.as_deref());
+        let semantic = match &query.embedding {
+            Some(embedding) => {
+                let filter = VectorFilter {
+                    namespace: query.namespace.clone(),
+                    ..VectorFilter::default()
+                };
+                let vector_store = self.vector_store.read();
+                let results = vector_store.search_similar(&query.collection, embedding.clone(), depth, &filter)?;
+
+                // Chunk embeddings rank their code file by its best chunk
+                let mut sources = HashSet::new();
+                results.into_iter()
+                    .filter_map(|(id, similarity)| {
+                        let metadata = vector_store.get_embedding_metadata(id).ok()?;
+                        let lines = metadata.start_line.zip(metadata.end_line);
+                        sources.insert(metadata.source_id).then_some((metadata.source_id, similarity, lines))
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        let mut hits: HashMap<Uuid, ScoreBreakdown> = HashMap::new();
+        for (rank, lexical_match) in lexical.into_iter().enumerate() {
+            let breakdown = hits.entry(lexical_match.id).or_default();
+            breakdown.lexical_rank = Some(rank + 1);
+            breakdown.lexical_score = Some(lexical_match.score);
+            breakdown.matched_terms = lexical_match.terms;
+            breakdown.lexical_contribution = weights.lexical / (weights.rrf_k + (rank + 1) as f32);
+        }
+        let mut lines: HashMap<Uuid, (usize, usize)> = HashMap::new();
+        for (rank, (id, similarity, chunk_lines)) in semantic.into_iter().enumerate() {
+            if let Some(chunk_lines) = chunk_lines {
+                lines.insert(id, chunk_lines);
+            }
+            let breakdown = hits.entry(id).or_default();
+            breakdown.semantic_rank = Some(rank + 1);
+            breakdown.similarity = Some(similarity);
+            breakdown.semantic_contribution = weights.semantic / (weights.rrf_k + (rank + 1) as f32);
+        }
+
+        let mut hits: Vec<SearchHit> = hits.into_iter()
+            .map(|(id, breakdown)| SearchHit {
+                id,
+                score: breakdown.lexical_contribution + breakdown.semantic_contribution,
+                start_line: lines.get(&id).map(|&(start_line, _)| start_line),
+                end_line: lines.get(&id).map(|&(_, end_line)| end_line),
+                breakdown,
+            })
+            .filter(|hit| hit.score > 0.0)
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+        hits.truncate(query.limit);
+
+        let accessed: Vec<(Uuid, f32)> = hits.iter().map(|hit| (hit.id, hit.score)).collect();
+        self.record_accesses(&accessed);
+
+        Ok(hits)
+    }
+}
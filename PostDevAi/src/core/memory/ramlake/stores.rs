@@ -1,171 +0,0 @@
-// Simplified placeholder implementations for stores module
-
-use std::path::PathBuf;
-use parking_lot::RwLock;
-use uuid::Uuid;
-use chrono::Local;
-
-/// Memory manager for RAM-Lake
-pub struct MemoryManager {
-    max_size: u64,
-    used_size: u64,
-}
-
-impl MemoryManager {
-    /// Create a new memory manager
-    pub fn new(max_size: u64) -> Self {
-        Self {
-            max_size,
-            used_size: 0,
-        }
-    }
-    
-    /// Allocate memory with a source identifier
-    pub fn allocate_with_source(&mut self, size: u64, _source: &str) -> Result<(), String> {
-        if self.used_size + size > self.max_size {
-            Err("Not enough memory".to_string())
-        } else {
-            self.used_size += size;
-            Ok(())
-        }
-    }
-}
-
-/// Vector store for embeddings
-pub struct VectorStore {
-    path: PathBuf,
-    max_size: u64,
-}
-
-impl VectorStore {
-    /// Create a new vector store
-    pub fn new(path: PathBuf, max_size: u64) -> Result<Self, String> {
-        Ok(Self {
-            path,
-            max_size,
-        })
-    }
-    
-    /// Store an embedding
-    pub fn store_embedding(&mut self, _id: Uuid, _embedding: Vec<f32>) -> Result<(), String> {
-        Ok(())
-    }
-    
-    /// Search for similar embeddings
-    pub fn search_similar(&self, _embedding: Vec<f32>, _limit: usize) -> Result<Vec<(Uuid, f32)>, String> {
-        Ok(Vec::new())
-    }
-    
-    /// Get the store size
-    pub fn get_size(&self) -> u64 {
-        0
-    }
-    
-    /// Get the number of entries
-    pub fn get_entry_count(&self) -> usize {
-        0
-    }
-}
-
-/// Code store for source code files
-pub struct CodeStore {
-    path: PathBuf,
-    max_size: u64,
-}
-
-impl CodeStore {
-    /// Create a new code store
-    pub fn new(path: PathBuf, max_size: u64) -> Result<Self, String> {
-        Ok(Self {
-            path,
-            max_size,
-        })
-    }
-    
-    /// Store a file
-    pub fn store_file(&mut self, _id: Uuid, _path: &str, _content: &str, _language: &str) -> Result<(), String> {
-        Ok(())
-    }
-    
-    /// Get a file
-    pub fn get_file(&self, _id: Uuid) -> Result<(String, String, String), String> {
-        Ok(("path".to_string(), "content".to_string(), "language".to_string()))
-    }
-    
-    /// Get the store size
-    pub fn get_size(&self) -> u64 {
-        0
-    }
-    
-    /// Get the number of files
-    pub fn get_file_count(&self) -> usize {
-        0
-    }
-}
-
-/// History store for events
-pub struct HistoryStore {
-    path: PathBuf,
-    max_size: u64,
-}
-
-impl HistoryStore {
-    /// Create a new history store
-    pub fn new(path: PathBuf, max_size: u64) -> Result<Self, String> {
-        Ok(Self {
-            path,
-            max_size,
-        })
-    }
-    
-    /// Store an event
-    pub fn store_event(&mut self, _id: Uuid, _event_type: &str, _content: &str) -> Result<(), String> {
-        Ok(())
-    }
-    
-    /// Get an event
-    pub fn get_event(&self, _id: Uuid) -> Result<(String, String, chrono::DateTime<chrono::Local>), String> {
-        Ok(("event_type".to_string(), "content".to_string(), Local::now()))
-    }
-    
-    /// Get the store size
-    pub fn get_size(&self) -> u64 {
-        0
-    }
-    
-    /// Get the number of events
-    pub fn get_event_count(&self) -> usize {
-        0
-    }
-}
-
-/// Metadata store for relations
-pub struct MetadataStore {
-    path: PathBuf,
-    max_size: u64,
-}
-
-impl MetadataStore {
-    /// Create a new metadata store
-    pub fn new(path: PathBuf, max_size: u64) -> Result<Self, String> {
-        Ok(Self {
-            path,
-            max_size,
-        })
-    }
-    
-    /// Store a relation
-    pub fn store_relation(&mut self, _source_id: Uuid, _relation: &str, _target_id: Uuid) -> Result<(), String> {
-        Ok(())
-    }
-    
-    /// Get relations
-    pub fn get_relations(&self, _id: Uuid, _relation: Option<&str>) -> Result<Vec<(Uuid, String, Uuid)>, String> {
-        Ok(Vec::new())
-    }
-    
-    /// Get the store size
-    pub fn get_size(&self) -> u64 {
-        0
-    }
-}
\ No newline at end of file
@@ -0,0 +1,113 @@
+// Hot/cold storage tiering for RAM-Lake
+
+use std::collections::HashSet;
+use parking_lot::RwLock as PLRwLock;
+use uuid::Uuid;
+
+use super::eviction::hot_entries;
+use super::{RamLake, VectorStore, CodeStore, HistoryStore, MemoryManager, EntityKind};
+
+impl RamLake {
+    /// Move entries not read or written for `cold_tier.demote_after_secs` to the cold tier
+    ///
+    /// Pinned entries stay in RAM. Returns the number of bytes moved out of RAM.
+    pub fn demote_idle(&self) -> Result<u64, String> {
+        Self::demote_stores(&self.vector_store, &self.code_store, &self.history_store, &self.memory_manager, self.config.cold_tier.demote_after_secs)
+    }
+
+    /// Demote the idle entries of the given stores, releasing their memory
+    pub(super) fn demote_stores(
+        vector_store: &PLRwLock<VectorStore>,
+        code_store: &PLRwLock<CodeStore>,
+        history_store: &PLRwLock<HistoryStore>,
+        memory_manager: &PLRwLock<MemoryManager>,
+        demote_after_secs: u64,
+    ) -> Result<u64, String> {
+        let cutoff = chrono::Utc::now() - chrono::Duration::seconds(demote_after_secs as i64);
+
+        let mut vector_store = vector_store.write();
+        let mut code_store = code_store.write();
+        let mut history_store = history_store.write();
+
+        let idle: Vec<_> = {
+            let memory_manager = memory_manager.read();
+            hot_entries(&memory_manager, &vector_store, &code_store, &history_store)
+                .into_iter()
+                .filter(|entry| entry.last_access < cutoff && !memory_manager.is_pinned(entry.id))
+                .collect()
+        };
+
+        let mut demoted = 0;
+        for entry in idle {
+            let freed = match entry.kind {
+                EntityKind::Code => code_store.demote_file(entry.id)?,
+                EntityKind::Embedding => vector_store.demote_embedding(entry.id)?,
+                EntityKind::Event => history_store.demote_event(entry.id)?,
+            };
+            if freed > 0 {
+                memory_manager.write().free_in_namespace(&entry.namespace, freed)
+                    .map_err(|e| format!("Failed to free memory: {}", e))?;
+                demoted += freed;
+            }
+        }
+
+        Ok(demoted)
+    }
+
+    /// Move an entry that was just read back from the cold tier
+    ///
+    /// Makes room like any other write; an entry that still does not fit stays
+    /// cold and keeps being read from there. Cold embeddings are left out of
+    /// searches, so those computed from a code file or event come back with it.
+    pub(super) fn promote(&self, kind: EntityKind, id: Uuid) {
+        // Reads never fail because an entry could not be promoted
+        let _ = self.try_promote(kind, id);
+
+        if kind != EntityKind::Embedding {
+            let cold: Vec<Uuid> = {
+                let vector_store = self.vector_store.read();
+                vector_store.get_ids_by_source(id).into_iter().filter(|&id| vector_store.is_cold(id)).collect()
+            };
+            for embedding_id in cold {
+                let _ = self.try_promote(EntityKind::Embedding, embedding_id);
+            }
+        }
+    }
+
+    /// Move an entry back from the cold tier, reserving its memory first
+    fn try_promote(&self, kind: EntityKind, id: Uuid) -> Result<(), String> {
+        let (namespace, size) = match kind {
+            EntityKind::Code => {
+                let metadata = self.code_store.read().get_file_metadata(id)?;
+                (metadata.namespace.clone(), metadata.blob_size())
+            }
+            EntityKind::Embedding => {
+                let vector_store = self.vector_store.read();
+                let metadata = vector_store.get_embedding_metadata(id)?;
+                (metadata.namespace, vector_store.embedding_size(id))
+            }
+            EntityKind::Event => {
+                let metadata = self.history_store.read().get_event_metadata(id)?;
+                (metadata.namespace.clone(), metadata.blob_size())
+            }
+        };
+
+        self.make_room(&[(Some(kind), size)], &HashSet::from([id]))?;
+        self.reserve(&namespace, size, &format!("promote:{}", id))?;
+
+        let promoted = match kind {
+            EntityKind::Code => self.code_store.write().promote_file(id),
+            EntityKind::Embedding => self.vector_store.write().promote_embedding(id),
+            EntityKind::Event => self.history_store.write().promote_event(id),
+        };
+
+        match promoted {
+            // Nothing was moved when another reader promoted the entry first
+            Ok(added) => self.release(&namespace, size.saturating_sub(added)),
+            Err(e) => {
+                self.release(&namespace, size)?;
+                Err(e)
+            }
+        }
+    }
+}
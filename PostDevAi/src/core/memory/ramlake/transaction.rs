@@ -0,0 +1,510 @@
+// Atomic multi-store transactions for RAM-Lake
+
+use std::collections::HashSet;
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use super::{RamLake, VectorStore, CodeStore, HistoryStore, MetadataStore, CodeMetadata, EventMetadata, WriteAheadLog, EntityKind, EmbeddingSource};
+use super::{embedding_source, relation_namespace, relation_size, default_namespace, DEFAULT_NAMESPACE, default_collection, DEFAULT_COLLECTION};
+
+/// Transaction journal, inside the metadata store directory so backups include it
+pub(super) const JOURNAL_FILE: &str = "metadata/transaction.log";
+
+/// A set of writes that is applied to the stores all together or not at all
+///
+/// Writes are only staged here; nothing touches the stores until the
+/// transaction is passed to `RamLake::commit`. IDs of new entities are assigned
+/// while staging so later writes in the same transaction can refer to them.
+#[derive(Debug, Clone, Default)]
+pub struct Transaction {
+    /// Staged writes, in order
+    ops: Vec<TxOp>,
+}
+
+/// Staged write of a transaction
+#[derive(Debug, Clone)]
+enum TxOp {
+    StoreCode { id: Uuid, namespace: String, path: String, content: String, language: String },
+    UpdateCode { id: Uuid, content: String },
+    DeleteCode { id: Uuid },
+    IndexCode { id: Uuid, collection: String, embedding: Vec<f32> },
+    DeleteEmbedding { id: Uuid },
+    StoreEvent { id: Uuid, namespace: String, event_type: String, content: String },
+    DeleteEvent { id: Uuid },
+    StoreRelation { source_id: Uuid, relation: String, target_id: Uuid },
+    DeleteRelation { source_id: Uuid, relation: String, target_id: Uuid },
+}
+
+/// Journal record describing how to undo one applied write
+///
+/// Records are journaled before the write they undo, so every record must be
+/// safe to apply whether or not that write actually happened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum UndoRecord {
+    /// Remove a code file that was stored
+    RemoveCode(Uuid),
+
+    /// Put back a code file that was deleted
+    RestoreCode { metadata: CodeMetadata, content: String },
+
+    /// Put back the previous content of an updated code file
+    RestoreCodeContent { id: Uuid, content: String },
+
+    /// Remove an embedding that was stored, if it is still in its collection
+    RemoveEmbedding {
+        id: Uuid,
+        #[serde(default = "default_collection")]
+        collection: String,
+    },
+
+    /// Put back an embedding that was deleted
+    RestoreEmbedding {
+        id: Uuid,
+        #[serde(default = "default_namespace")]
+        namespace: String,
+        #[serde(default = "default_collection")]
+        collection: String,
+        #[serde(default)]
+        source: Option<EmbeddingSource>,
+        embedding: Vec<f32>,
+    },
+
+    /// Remove an event that was stored
+    RemoveEvent(Uuid),
+
+    /// Put back an event that was deleted
+    RestoreEvent { metadata: EventMetadata, content: String },
+
+    /// Remove a relation that was stored
+    RemoveRelation { source_id: Uuid, relation: String, target_id: Uuid },
+
+    /// Put back a relation that was deleted
+    RestoreRelation {
+        #[serde(default = "default_namespace")]
+        namespace: String,
+        source_id: Uuid,
+        relation: String,
+        target_id: Uuid,
+    },
+}
+
+/// Write access to every store, held for the whole commit or eviction
+pub(super) struct TxStores<'a> {
+    pub(super) vector_store: &'a mut VectorStore,
+    pub(super) code_store: &'a mut CodeStore,
+    pub(super) history_store: &'a mut HistoryStore,
+    pub(super) metadata_store: &'a mut MetadataStore,
+}
+
+impl Transaction {
+    /// Create an empty transaction
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stage storing a code file in the default namespace, replacing any file under the same path
+    pub fn store_code(&mut self, path: &str, content: &str, language: &str) -> Uuid {
+        self.store_code_in(DEFAULT_NAMESPACE, path, content, language)
+    }
+
+    /// Stage storing a code file in a namespace, replacing any file under the same path there
+    pub fn store_code_in(&mut self, namespace: &str, path: &str, content: &str, language: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        self.ops.push(TxOp::StoreCode {
+            id,
+            namespace: namespace.to_string(),
+            path: path.to_string(),
+            content: content.to_string(),
+            language: language.to_string(),
+        });
+        id
+    }
+
+    /// Stage replacing the content of a code file
+    pub fn update_code(&mut self, id: Uuid, content: &str) {
+        self.ops.push(TxOp::UpdateCode { id, content: content.to_string() });
+    }
+
+    /// Stage deleting a code file together with its embeddings and relations
+    pub fn delete_code(&mut self, id: Uuid) {
+        self.ops.push(TxOp::DeleteCode { id });
+    }
+
+    /// Stage indexing a code file for vector search in the default collection
+    pub fn index_code(&mut self, code_id: Uuid, embedding: Vec<f32>) {
+        self.index_entity(DEFAULT_COLLECTION, code_id, embedding);
+    }
+
+    /// Stage indexing a code file or event for vector search in a collection
+    pub fn index_entity(&mut self, collection: &str, id: Uuid, embedding: Vec<f32>) {
+        self.ops.push(TxOp::IndexCode { id, collection: collection.to_string(), embedding });
+    }
+
+    /// Stage deleting an embedding
+    pub fn delete_embedding(&mut self, id: Uuid) {
+        self.ops.push(TxOp::DeleteEmbedding { id });
+    }
+
+    /// Stage storing an event in the default namespace
+    pub fn store_event(&mut self, event_type: &str, content: &str) -> Uuid {
+        self.store_event_in(DEFAULT_NAMESPACE, event_type, content)
+    }
+
+    /// Stage storing an event in a namespace
+    pub fn store_event_in(&mut self, namespace: &str, event_type: &str, content: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        self.ops.push(TxOp::StoreEvent {
+            id,
+            namespace: namespace.to_string(),
+            event_type: event_type.to_string(),
+            content: content.to_string(),
+        });
+        id
+    }
+
+    /// Stage deleting an event together with its relations
+    pub fn delete_event(&mut self, id: Uuid) {
+        self.ops.push(TxOp::DeleteEvent { id });
+    }
+
+    /// Stage storing a relation between entities
+    pub fn store_metadata(&mut self, source_id: Uuid, relation: &str, target_id: Uuid) {
+        self.ops.push(TxOp::StoreRelation { source_id, relation: relation.to_string(), target_id });
+    }
+
+    /// Stage deleting a relation between entities
+    pub fn delete_relation(&mut self, source_id: Uuid, relation: &str, target_id: Uuid) {
+        self.ops.push(TxOp::DeleteRelation { source_id, relation: relation.to_string(), target_id });
+    }
+
+    /// Number of staged writes
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Whether no writes are staged
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Bytes the staged writes add per entity type (`None` for relations), and the entities they touch
+    fn space_needed(&self, vector_store: &VectorStore) -> (Vec<(Option<EntityKind>, u64)>, HashSet<Uuid>) {
+        let mut writes = Vec::new();
+        let mut touched = HashSet::new();
+
+        for op in &self.ops {
+            match op {
+                TxOp::StoreCode { id, content, .. } | TxOp::UpdateCode { id, content } => {
+                    writes.push((Some(EntityKind::Code), content.len() as u64));
+                    touched.insert(*id);
+                }
+                TxOp::IndexCode { id, collection, embedding } => {
+                    writes.push((Some(EntityKind::Embedding), vector_store.new_embedding_size(collection, embedding.len())));
+                    touched.insert(*id);
+                }
+                TxOp::StoreEvent { id, content, .. } => {
+                    writes.push((Some(EntityKind::Event), content.len() as u64));
+                    touched.insert(*id);
+                }
+                TxOp::StoreRelation { source_id, relation, target_id } => {
+                    writes.push((None, relation_size(relation)));
+                    touched.insert(*source_id);
+                    touched.insert(*target_id);
+                }
+                TxOp::DeleteCode { id } | TxOp::DeleteEmbedding { id } | TxOp::DeleteEvent { id } => {
+                    touched.insert(*id);
+                }
+                TxOp::DeleteRelation { source_id, target_id, .. } => {
+                    touched.insert(*source_id);
+                    touched.insert(*target_id);
+                }
+            }
+        }
+
+        (writes, touched)
+    }
+}
+
+impl RamLake {
+    /// Stage writes with `build` and commit them as one transaction
+    ///
+    /// Nothing is written if `build` returns an error.
+    pub fn transaction<T, F>(&self, build: F) -> Result<T, String>
+    where
+        F: FnOnce(&mut Transaction) -> Result<T, String>,
+    {
+        let mut tx = Transaction::new();
+        let value = build(&mut tx)?;
+        self.commit(tx)?;
+
+        Ok(value)
+    }
+
+    /// Apply every write of a transaction, or none of them
+    ///
+    /// All store locks are held for the whole commit so readers never see a
+    /// partially applied transaction. If a write fails, the writes applied
+    /// before it are undone and the error is returned. The undo journal lets a
+    /// commit interrupted by a crash be rolled back when the RAM-Lake reopens.
+    ///
+    /// Entries evicted to make room for the transaction stay evicted if it is
+    /// rolled back; entities the transaction touches are never evicted for it.
+    pub fn commit(&self, tx: Transaction) -> Result<(), String> {
+        if tx.is_empty() {
+            return Ok(());
+        }
+
+        // Same lock order as `restore_backup`
+        let mut vector_store = self.vector_store.write();
+        let mut code_store = self.code_store.write();
+        let mut history_store = self.history_store.write();
+        let mut metadata_store = self.metadata_store.write();
+        let mut journal = self.journal.lock().unwrap();
+
+        let mut stores = TxStores {
+            vector_store: &mut vector_store,
+            code_store: &mut code_store,
+            history_store: &mut history_store,
+            metadata_store: &mut metadata_store,
+        };
+
+        // Make room before anything is journaled
+        let (writes, touched) = tx.space_needed(stores.vector_store);
+        self.make_room_in(&mut stores, &writes, &touched)?;
+
+        let mut undo = Vec::new();
+        for op in tx.ops {
+            if let Err(e) = self.apply_op(&mut stores, &mut journal, &mut undo, op) {
+                // Keep the journal if the rollback fails so the next start retries it
+                self.roll_back(&mut stores, undo)
+                    .map_err(|rollback_error| format!("Failed to roll back transaction after error '{}': {}", e, rollback_error))?;
+                journal.reset()?;
+
+                return Err(format!("Transaction rolled back: {}", e));
+            }
+        }
+
+        journal.reset()
+    }
+
+    /// Roll back a transaction that was interrupted by a crash
+    pub(super) fn recover_transaction(&self) -> Result<(), String> {
+        let mut vector_store = self.vector_store.write();
+        let mut code_store = self.code_store.write();
+        let mut history_store = self.history_store.write();
+        let mut metadata_store = self.metadata_store.write();
+        let mut journal = self.journal.lock().unwrap();
+
+        let undo: Vec<UndoRecord> = journal.replay()?;
+        if undo.is_empty() {
+            return Ok(());
+        }
+
+        let mut stores = TxStores {
+            vector_store: &mut vector_store,
+            code_store: &mut code_store,
+            history_store: &mut history_store,
+            metadata_store: &mut metadata_store,
+        };
+
+        let count = undo.len();
+        self.roll_back(&mut stores, undo)?;
+        journal.reset()?;
+
+        eprintln!("Rolled back {} writes of an interrupted RAM-Lake transaction", count);
+
+        Ok(())
+    }
+
+    /// Apply one staged write, journaling how to undo it first
+    fn apply_op(&self, stores: &mut TxStores, journal: &mut WriteAheadLog, undo: &mut Vec<UndoRecord>, op: TxOp) -> Result<(), String> {
+        match op {
+            TxOp::StoreCode { id, namespace, path, content, language } => {
+                // A file already stored under the same path is replaced by a new revision
+                if let Some(existing_id) = stores.code_store.get_id_by_path(&namespace, &path) {
+                    self.detach_code_in(stores, journal, undo, existing_id)?;
+                }
+
+                log_undo(journal, undo, UndoRecord::RemoveCode(id))?;
+                self.insert_code(stores.code_store, id, &namespace, &path, &content, &language)
+            }
+            TxOp::UpdateCode { id, content } => {
+                let (_, previous, _) = stores.code_store.get_file(id)?;
+
+                log_undo(journal, undo, UndoRecord::RestoreCodeContent { id, content: previous })?;
+                self.replace_code_content(stores.code_store, id, &content)
+            }
+            TxOp::DeleteCode { id } => self.delete_code_in(stores, journal, undo, id),
+            TxOp::IndexCode { id, collection, embedding } => {
+                // Undoing must not remove an embedding that was there before
+                if stores.vector_store.get_embedding_metadata(id).is_ok() {
+                    return Err(format!("Embedding with ID {} already exists", id));
+                }
+
+                let (namespace, source) = embedding_source(stores.code_store, stores.history_store, id);
+
+                log_undo(journal, undo, UndoRecord::RemoveEmbedding { id, collection: collection.clone() })?;
+                self.insert_embedding(stores.vector_store, id, &collection, &namespace, source, embedding)
+            }
+            TxOp::DeleteEmbedding { id } => self.delete_embedding_in(stores, journal, undo, id),
+            TxOp::StoreEvent { id, namespace, event_type, content } => {
+                log_undo(journal, undo, UndoRecord::RemoveEvent(id))?;
+                self.insert_event(stores.history_store, id, &namespace, &event_type, &content)
+            }
+            TxOp::DeleteEvent { id } => {
+                let metadata = stores.history_store.get_event_metadata(id)?;
+                let (_, content, _) = stores.history_store.get_event(id)?;
+
+                log_undo(journal, undo, UndoRecord::RestoreEvent { metadata, content })?;
+                self.remove_event(stores.history_store, id)?;
+
+                self.delete_entity_relations_in(stores, journal, undo, id)
+            }
+            TxOp::StoreRelation { source_id, relation, target_id } => {
+                // Storing an existing relation is a no-op
+                if stores.metadata_store.relation_exists(source_id, &relation, target_id) {
+                    return Ok(());
+                }
+
+                let namespace = relation_namespace(stores.code_store, stores.history_store, source_id, target_id);
+
+                log_undo(journal, undo, UndoRecord::RemoveRelation { source_id, relation: relation.clone(), target_id })?;
+                self.insert_relation(stores.metadata_store, &namespace, source_id, &relation, target_id)
+            }
+            TxOp::DeleteRelation { source_id, relation, target_id } => {
+                self.delete_relation_in(stores, journal, undo, source_id, relation, target_id)
+            }
+        }
+    }
+
+    /// Delete a code file with its embeddings and relations inside a transaction
+    fn delete_code_in(&self, stores: &mut TxStores, journal: &mut WriteAheadLog, undo: &mut Vec<UndoRecord>, id: Uuid) -> Result<(), String> {
+        self.detach_code_in(stores, journal, undo, id)?;
+        self.remove_code(stores.code_store, id)
+    }
+
+    /// Delete the embeddings and relations of a code file that is about to go, journaling how to restore the file
+    fn detach_code_in(&self, stores: &mut TxStores, journal: &mut WriteAheadLog, undo: &mut Vec<UndoRecord>, id: Uuid) -> Result<(), String> {
+        let metadata = stores.code_store.get_file_metadata(id)?;
+        let (_, content, _) = stores.code_store.get_file(id)?;
+
+        log_undo(journal, undo, UndoRecord::RestoreCode { metadata, content })?;
+
+        // Whole-file and chunk embeddings record the code file as their source
+        for embedding_id in stores.vector_store.get_ids_by_source(id) {
+            self.delete_embedding_in(stores, journal, undo, embedding_id)?;
+        }
+
+        self.delete_entity_relations_in(stores, journal, undo, id)
+    }
+
+    /// Delete an embedding inside a transaction
+    fn delete_embedding_in(&self, stores: &mut TxStores, journal: &mut WriteAheadLog, undo: &mut Vec<UndoRecord>, id: Uuid) -> Result<(), String> {
+        let metadata = stores.vector_store.get_embedding_metadata(id)?;
+        let embedding = stores.vector_store.load_embedding(id)?;
+
+        log_undo(journal, undo, UndoRecord::RestoreEmbedding {
+            id,
+            source: Some(metadata.source()),
+            namespace: metadata.namespace,
+            collection: metadata.collection,
+            embedding,
+        })?;
+        self.remove_embedding(stores.vector_store, id)
+    }
+
+    /// Delete a relation inside a transaction
+    fn delete_relation_in(
+        &self,
+        stores: &mut TxStores,
+        journal: &mut WriteAheadLog,
+        undo: &mut Vec<UndoRecord>,
+        source_id: Uuid,
+        relation: String,
+        target_id: Uuid,
+    ) -> Result<(), String> {
+        // Deleting a missing relation is a no-op
+        if !stores.metadata_store.relation_exists(source_id, &relation, target_id) {
+            return Ok(());
+        }
+
+        let namespace = stores.metadata_store.get_relation_namespace(source_id, &relation, target_id);
+
+        log_undo(journal, undo, UndoRecord::RestoreRelation { namespace, source_id, relation: relation.clone(), target_id })?;
+        self.remove_relation(stores.metadata_store, source_id, &relation, target_id)
+    }
+
+    /// Delete every relation that involves an entity inside a transaction
+    fn delete_entity_relations_in(&self, stores: &mut TxStores, journal: &mut WriteAheadLog, undo: &mut Vec<UndoRecord>, id: Uuid) -> Result<(), String> {
+        // A self-relation shows up both as forward and backward relation
+        let relations: HashSet<(Uuid, String, Uuid)> = stores.metadata_store.get_relations(id, None)?
+            .into_iter()
+            .collect();
+
+        for (source_id, relation, target_id) in relations {
+            self.delete_relation_in(stores, journal, undo, source_id, relation, target_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply undo records in reverse order
+    fn roll_back(&self, stores: &mut TxStores, undo: Vec<UndoRecord>) -> Result<(), String> {
+        for record in undo.into_iter().rev() {
+            match record {
+                UndoRecord::RemoveCode(id) => {
+                    if stores.code_store.get_file_metadata(id).is_ok() {
+                        self.remove_code(stores.code_store, id)?;
+                    }
+                }
+                UndoRecord::RestoreCode { metadata, content } => {
+                    if stores.code_store.get_file_metadata(metadata.id).is_err() {
+                        self.restore_code(stores.code_store, metadata, &content)?;
+                    }
+                }
+                UndoRecord::RestoreCodeContent { id, content } => {
+                    if stores.code_store.get_file_metadata(id).is_ok() {
+                        self.replace_code_content(stores.code_store, id, &content)?;
+                    }
+                }
+                UndoRecord::RemoveEmbedding { id, collection } => {
+                    if stores.vector_store.get_embedding_metadata(id).is_ok_and(|metadata| metadata.collection == collection) {
+                        self.remove_embedding(stores.vector_store, id)?;
+                    }
+                }
+                UndoRecord::RestoreEmbedding { id, namespace, collection, source, embedding } => {
+                    if stores.vector_store.get_embedding_metadata(id).is_err() {
+                        let source = source.unwrap_or_else(|| EmbeddingSource::unknown(id));
+                        self.insert_embedding(stores.vector_store, id, &collection, &namespace, source, embedding)?;
+                    }
+                }
+                UndoRecord::RemoveEvent(id) => {
+                    if stores.history_store.get_event_metadata(id).is_ok() {
+                        self.remove_event(stores.history_store, id)?;
+                    }
+                }
+                UndoRecord::RestoreEvent { metadata, content } => {
+                    if stores.history_store.get_event_metadata(metadata.id).is_err() {
+                        self.restore_event(stores.history_store, metadata, &content)?;
+                    }
+                }
+                UndoRecord::RemoveRelation { source_id, relation, target_id } => {
+                    self.remove_relation(stores.metadata_store, source_id, &relation, target_id)?;
+                }
+                UndoRecord::RestoreRelation { namespace, source_id, relation, target_id } => {
+                    self.insert_relation(stores.metadata_store, &namespace, source_id, &relation, target_id)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Journal an undo record and keep it for an in-process rollback
+fn log_undo(journal: &mut WriteAheadLog, undo: &mut Vec<UndoRecord>, record: UndoRecord) -> Result<(), String> {
+    journal.append(&record)?;
+    undo.push(record);
+
+    Ok(())
+}
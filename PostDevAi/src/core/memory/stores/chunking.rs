@@ -0,0 +1,211 @@
+use serde::{Serialize, Deserialize};
+
+/// Lines a definition may span before it is split into windows
+const MAX_CHUNK_LINES: usize = 200;
+
+/// Lines of each sliding window
+const WINDOW_LINES: usize = 60;
+
+/// Lines shared by consecutive sliding windows
+const WINDOW_OVERLAP: usize = 10;
+
+/// Span of a code file that is embedded on its own
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodeChunk {
+    /// First line of the span, from 1
+    pub start_line: usize,
+
+    /// Last line of the span, inclusive
+    pub end_line: usize,
+
+    /// Text of the span
+    pub text: String,
+}
+
+/// Split a code file into chunks to embed separately
+///
+/// Chunks start at the top-level definitions (functions, impls, classes, ...)
+/// recognised by the keywords of the language, and take the comments,
+/// attributes and decorators right above a definition with it. Code before
+/// the first definition is a chunk of its own. A definition longer than
+/// `MAX_CHUNK_LINES` that contains others (an impl, class, trait, module, ...)
+/// is chunked the same way at the indentation of its body. Other long
+/// definitions, and files of languages without known keywords or without
+/// definitions, are split into overlapping windows instead.
+pub fn chunk_code(content: &str, language: &str) -> Vec<CodeChunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    let keywords = definition_keywords(language);
+
+    let mut chunks = Vec::new();
+    if !push_definitions(&lines, 0, lines.len(), 0, keywords, &mut chunks) {
+        push_windows(&lines, 0, lines.len(), &mut chunks);
+    }
+
+    chunks
+}
+
+/// Keywords of a language that open definitions and that may precede them
+struct Keywords {
+    /// Keywords that open a definition
+    definitions: &'static [&'static str],
+
+    /// Keywords that open a definition containing others
+    containers: &'static [&'static str],
+
+    /// Modifiers that may precede a keyword
+    modifiers: &'static [&'static str],
+}
+
+/// Chunk lines `start..end` at the definitions indented by `indent`
+///
+/// Returns false, without adding chunks, if there are no such definitions.
+fn push_definitions(lines: &[&str], start: usize, end: usize, indent: usize, keywords: &Keywords, chunks: &mut Vec<CodeChunk>) -> bool {
+    let mut starts = vec![start];
+    let mut found = false;
+    for i in start..end {
+        if definition_keyword(lines[i], indent, keywords).is_none() {
+            continue;
+        }
+        found = true;
+        let mut definition_start = i;
+        while definition_start > start && is_preamble(lines[definition_start - 1]) {
+            definition_start -= 1;
+        }
+        if definition_start > starts[starts.len() - 1] {
+            starts.push(definition_start);
+        }
+    }
+    if !found {
+        return false;
+    }
+
+    let ends = starts.iter().skip(1).copied().chain([end]);
+    for (start, end) in starts.iter().copied().zip(ends) {
+        if end - start <= MAX_CHUNK_LINES {
+            push_chunk(lines, start, end, chunks);
+        } else if !push_container(lines, start, end, indent, keywords, chunks) {
+            push_windows(lines, start, end, chunks);
+        }
+    }
+
+    true
+}
+
+/// Chunk lines `start..end` at the definitions in their body if they hold a container definition
+///
+/// The container's opening lines, up to its first inner definition, are a
+/// chunk of their own. Returns false, without adding chunks, if the lines
+/// are no container or it holds no definitions.
+fn push_container(lines: &[&str], start: usize, end: usize, indent: usize, keywords: &Keywords, chunks: &mut Vec<CodeChunk>) -> bool {
+    let Some(header) = (start..end).find(|&i| definition_keyword(lines[i], indent, keywords).is_some()) else {
+        return false;
+    };
+    if !definition_keyword(lines[header], indent, keywords).is_some_and(|keyword| keywords.containers.contains(&keyword)) {
+        return false;
+    }
+
+    let body_indent = lines[header + 1..end].iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| indentation(line))
+        .find(|&body_indent| body_indent > indent);
+
+    body_indent.is_some_and(|body_indent| push_definitions(lines, start, end, body_indent, keywords, chunks))
+}
+
+/// Keywords of a language
+fn definition_keywords(language: &str) -> &'static Keywords {
+    match language.to_ascii_lowercase().as_str() {
+        "rust" | "rs" => &Keywords {
+            definitions: &["fn", "impl", "struct", "enum", "trait", "mod", "union", "macro_rules!"],
+            containers: &["impl", "trait", "mod"],
+            modifiers: &["pub", "async", "unsafe", "const", "extern", "default"],
+        },
+        "python" | "py" => &Keywords {
+            definitions: &["def", "class"],
+            containers: &["class"],
+            modifiers: &["async"],
+        },
+        "javascript" | "js" | "jsx" | "typescript" | "ts" | "tsx" => &Keywords {
+            definitions: &["function", "function*", "class", "interface", "enum"],
+            containers: &["class"],
+            modifiers: &["export", "default", "async", "abstract", "declare"],
+        },
+        "go" | "golang" => &Keywords {
+            definitions: &["func", "type"],
+            containers: &[],
+            modifiers: &[],
+        },
+        "java" | "kotlin" | "kt" | "csharp" | "c#" | "cs" | "scala" | "swift" => &Keywords {
+            definitions: &["class", "interface", "enum", "record", "struct", "object", "trait", "fun", "func", "def", "namespace"],
+            containers: &["class", "interface", "enum", "record", "struct", "object", "trait", "namespace"],
+            modifiers: &["public", "private", "protected", "internal", "static", "final", "abstract", "sealed", "open", "data", "partial"],
+        },
+        "ruby" | "rb" => &Keywords {
+            definitions: &["def", "class", "module"],
+            containers: &["class", "module"],
+            modifiers: &[],
+        },
+        _ => &Keywords {
+            definitions: &[],
+            containers: &[],
+            modifiers: &[],
+        },
+    }
+}
+
+/// Keyword of the definition a line opens, if it is indented by `indent`
+fn definition_keyword(line: &str, indent: usize, keywords: &Keywords) -> Option<&'static str> {
+    if line.trim().is_empty() || indentation(line) != indent {
+        return None;
+    }
+
+    // `pub(crate)` is a modifier and `impl<T>` opens a definition
+    let word = line.split_whitespace()
+        .map(|word| word.split(['<', '(', '{', ':']).next().unwrap_or(word))
+        .find(|word| !keywords.modifiers.contains(word))?;
+
+    keywords.definitions.iter().copied().find(|&keyword| keyword == word)
+}
+
+/// Number of whitespace characters a line starts with
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Whether a line is a comment, attribute or decorator that belongs to the definition below it
+fn is_preamble(line: &str) -> bool {
+    let line = line.trim_start();
+    ["//", "/*", "*", "#", "@", "--"].iter().any(|prefix| line.starts_with(prefix))
+}
+
+/// Split lines `start..end` into overlapping windows
+fn push_windows(lines: &[&str], start: usize, end: usize, chunks: &mut Vec<CodeChunk>) {
+    let mut window_start = start;
+    while window_start < end {
+        let window_end = (window_start + WINDOW_LINES).min(end);
+        push_chunk(lines, window_start, window_end, chunks);
+        if window_end == end {
+            break;
+        }
+        window_start = window_end - WINDOW_OVERLAP;
+    }
+}
+
+/// Add lines `start..end` as a chunk, without leading and trailing blank lines
+fn push_chunk(lines: &[&str], mut start: usize, mut end: usize, chunks: &mut Vec<CodeChunk>) {
+    while start < end && lines[start].trim().is_empty() {
+        start += 1;
+    }
+    while end > start && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+    if start == end {
+        return;
+    }
+
+    chunks.push(CodeChunk {
+        start_line: start + 1,
+        end_line: end,
+        text: lines[start..end].join("\n"),
+    });
+}
@@ -1,14 +1,26 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
-use std::io::{Read, Write};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 use serde::{Serialize, Deserialize};
 use parking_lot::RwLock;
 
+use super::wal::{WriteAheadLog, DEFAULT_CHECKPOINT_THRESHOLD};
+use super::consistency::check_temp_files;
+use super::compression::{BlobCodec, BlobCompression, train_dictionary};
+use super::segment::DEFAULT_SEGMENT_SIZE;
+use super::tiering::TieredLog;
+use crate::core::memory::ramlake::{ConsistencyIssue, StoreBackupState, default_namespace};
+use crate::utils::filesystem::write_json_atomic;
+
 /// Code Store for RAM-Lake
 /// 
-/// Stores code files and their metadata
+/// Stores code files and their metadata. File contents are stored once per
+/// namespace and SHA-256 hash, so files with identical content share a blob.
+/// Every change to a path is kept as a revision referencing the blob of its
+/// content, bounded by the revision retention policy. Blobs are kept in an
+/// append-only segment log, compressed when the store's compression is enabled.
+/// Blobs demoted to the cold tier do not count against the store size.
 pub struct CodeStore {
     /// Path to store code files
     path: PathBuf,
@@ -16,14 +28,140 @@ pub struct CodeStore {
     /// Maximum size of the store in bytes
     max_size: u64,
     
-    /// Current size of the store in bytes
+    /// Stored size of the blobs in the hot tier in bytes
     current_size: u64,
     
+    /// Uncompressed size of all blobs in both tiers in bytes
+    logical_size: u64,
+    
+    /// Blob compression
+    codec: BlobCodec,
+    
     /// Index of code files
     index: RwLock<CodeIndex>,
     
     /// Mapping of UUIDs to code metadata
     metadata: RwLock<HashMap<Uuid, CodeMetadata>>,
+    
+    /// Revisions per namespace and path, oldest first
+    revisions: RwLock<RevisionMap>,
+    
+    /// Number of files and revisions referencing each blob
+    blob_refs: RwLock<HashMap<String, usize>>,
+    
+    /// Blob contents
+    segments: TieredLog,
+    
+    /// Which revisions to keep
+    retention: RevisionRetention,
+    
+    /// Write-ahead log of changes since the last checkpoint
+    wal: WriteAheadLog,
+    
+    /// Number of WAL records that triggers a checkpoint
+    checkpoint_threshold: usize,
+}
+
+/// Code Store WAL record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CodeWalRecord {
+    /// A file was stored or updated
+    Put(CodeMetadata),
+    
+    /// A file was deleted
+    Delete(Uuid),
+    
+    /// A revision of a path was recorded
+    Revision {
+        namespace: String,
+        path: String,
+        revision: FileRevision,
+    },
+    
+    /// Revisions of a path were dropped by the retention policy
+    PruneRevisions {
+        namespace: String,
+        path: String,
+        revisions: Vec<u32>,
+    },
+}
+
+/// Revisions per namespace and path
+type RevisionMap = HashMap<String, HashMap<String, Vec<FileRevision>>>;
+
+/// A version of a code file at one point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileRevision {
+    /// Revision number, increasing per path
+    pub revision: u32,
+    
+    /// ID of the code file at this revision
+    pub id: Uuid,
+    
+    /// When the change was made
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    
+    /// Whether the file was deleted in this revision
+    pub deleted: bool,
+    
+    /// Programming language
+    pub language: String,
+    
+    /// Size of the content in bytes
+    pub size: u64,
+    
+    /// Size of the content blob in the store in bytes, after compression
+    #[serde(default)]
+    pub stored_size: Option<u64>,
+    
+    /// SHA-256 hash of the content
+    pub hash: String,
+    
+    /// Name of the content blob in the store
+    pub file_path: String,
+}
+
+impl CodeMetadata {
+    /// Size of the content blob in the store in bytes
+    pub fn blob_size(&self) -> u64 {
+        self.stored_size.unwrap_or(self.size)
+    }
+}
+
+impl FileRevision {
+    /// Size of the content blob in the store in bytes
+    pub fn blob_size(&self) -> u64 {
+        self.stored_size.unwrap_or(self.size)
+    }
+}
+
+/// Retention policy for code file revisions
+///
+/// Limits are combined: a revision is dropped as soon as it violates any of
+/// them. The current revision of a file that still exists is always kept, and a
+/// revision's age counts from the moment it was superseded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevisionRetention {
+    /// Keep at most this many revisions per path
+    #[serde(default = "default_keep_revisions")]
+    pub keep_last: Option<usize>,
+    
+    /// Drop revisions superseded more than this many seconds ago
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+}
+
+fn default_keep_revisions() -> Option<usize> {
+    Some(10)
+}
+
+impl Default for RevisionRetention {
+    fn default() -> Self {
+        Self {
+            keep_last: default_keep_revisions(),
+            max_age_secs: None,
+        }
+    }
 }
 
 /// Code Index
@@ -38,8 +176,9 @@ pub struct CodeIndex {
     /// UUIDs of code files
     pub ids: Vec<Uuid>,
     
-    /// Path to UUID mapping
-    pub path_map: HashMap<String, Uuid>,
+    /// Path to UUID mapping per namespace
+    #[serde(default)]
+    pub paths: HashMap<String, HashMap<String, Uuid>>,
 }
 
 /// Code Metadata
@@ -48,6 +187,10 @@ pub struct CodeMetadata {
     /// ID of the code file
     pub id: Uuid,
     
+    /// Namespace (project) the file belongs to
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    
     /// Path of the code file
     pub path: String,
     
@@ -57,7 +200,13 @@ pub struct CodeMetadata {
     /// Size of the file in bytes
     pub size: u64,
     
-    /// Path to the code file in the store
+    /// Size of the content blob in the store in bytes, after compression
+    ///
+    /// Missing for blobs written before compression existed, which are raw.
+    #[serde(default)]
+    pub stored_size: Option<u64>,
+    
+    /// Name of the content blob in the store
     pub file_path: String,
     
     /// Creation timestamp
@@ -81,7 +230,7 @@ impl CodeStore {
         
         // Load or create index
         let index_path = path.join("index.json");
-        let index = if index_path.exists() {
+        let mut index: CodeIndex = if index_path.exists() {
             let file = fs::File::open(&index_path)
                 .map_err(|e| format!("Failed to open index file: {}", e))?;
             serde_json::from_reader(file)
@@ -91,13 +240,13 @@ impl CodeStore {
                 count: 0,
                 version: 1,
                 ids: Vec::new(),
-                path_map: HashMap::new(),
+                paths: HashMap::new(),
             }
         };
         
         // Load metadata
         let metadata_path = path.join("metadata.json");
-        let metadata = if metadata_path.exists() {
+        let metadata: HashMap<Uuid, CodeMetadata> = if metadata_path.exists() {
             let file = fs::File::open(&metadata_path)
                 .map_err(|e| format!("Failed to open metadata file: {}", e))?;
             serde_json::from_reader(file)
@@ -106,94 +255,628 @@ impl CodeStore {
             HashMap::new()
         };
         
-        // Calculate current size
-        let mut current_size = 0;
-        for entry in fs::read_dir(&path).map_err(|e| format!("Failed to read code store directory: {}", e))? {
-            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-            let metadata = entry.metadata().map_err(|e| format!("Failed to read file metadata: {}", e))?;
-            current_size += metadata.len();
+        // Indexes written before namespaces existed only have a flat path map
+        if index.paths.is_empty() && !metadata.is_empty() {
+            index.paths = Self::build_paths(metadata.values());
         }
         
-        Ok(Self {
+        // Load revisions; stores written before revisions existed start with the current files
+        let revisions_path = path.join("revisions.json");
+        let revisions: RevisionMap = if revisions_path.exists() {
+            let file = fs::File::open(&revisions_path)
+                .map_err(|e| format!("Failed to open revisions file: {}", e))?;
+            serde_json::from_reader(file)
+                .map_err(|e| format!("Failed to parse revisions file: {}", e))?
+        } else {
+            Self::initial_revisions(metadata.values())
+        };
+        
+        // Open blob segments
+        let segments = TieredLog::open(&path, DEFAULT_SEGMENT_SIZE)?;
+        
+        // Count blob references
+        let mut blob_refs: HashMap<String, usize> = HashMap::new();
+        for m in metadata.values() {
+            *blob_refs.entry(m.file_path.clone()).or_insert(0) += 1;
+        }
+        for revision in revisions.values().flat_map(|paths| paths.values()).flatten() {
+            if !revision.deleted {
+                *blob_refs.entry(revision.file_path.clone()).or_insert(0) += 1;
+            }
+        }
+        
+        // Open write-ahead log
+        let wal = WriteAheadLog::open(path.join("wal.log"))?;
+        
+        let mut store = Self {
             path,
             max_size,
-            current_size,
+            current_size: 0,
+            logical_size: 0,
+            codec: BlobCodec::default(),
             index: RwLock::new(index),
             metadata: RwLock::new(metadata),
+            revisions: RwLock::new(revisions),
+            blob_refs: RwLock::new(blob_refs),
+            segments,
+            retention: RevisionRetention::default(),
+            wal,
+            checkpoint_threshold: DEFAULT_CHECKPOINT_THRESHOLD,
+        };
+        
+        // Replay changes made since the last checkpoint
+        let records: Vec<CodeWalRecord> = store.wal.replay()?;
+        for record in &records {
+            store.apply_record(record);
+        }
+        
+        // Stores written before segments keep one file per blob
+        let files: Vec<String> = store.blob_refs.read().keys().cloned().collect();
+        store.segments.import_files(files)?;
+        
+        (store.current_size, store.logical_size) = store.compute_sizes();
+        
+        Ok(store)
+    }
+    
+    /// Apply a WAL record to the in-memory index and metadata
+    fn apply_record(&self, record: &CodeWalRecord) {
+        let mut index = self.index.write();
+        let mut metadata = self.metadata.write();
+        let mut revisions = self.revisions.write();
+        let mut blob_refs = self.blob_refs.write();
+        
+        match record {
+            CodeWalRecord::Put(file_metadata) => {
+                let id = file_metadata.id;
+                *blob_refs.entry(file_metadata.file_path.clone()).or_insert(0) += 1;
+                match metadata.insert(id, file_metadata.clone()) {
+                    Some(previous) => Self::drop_blob_ref(&mut blob_refs, &previous.file_path),
+                    None => {
+                        index.ids.push(id);
+                        index.count += 1;
+                    }
+                }
+                index.paths
+                    .entry(file_metadata.namespace.clone())
+                    .or_default()
+                    .insert(file_metadata.path.clone(), id);
+            }
+            CodeWalRecord::Delete(id) => {
+                if let Some(file_metadata) = metadata.remove(id) {
+                    Self::drop_blob_ref(&mut blob_refs, &file_metadata.file_path);
+                    index.ids.retain(|i| i != id);
+                    if let Some(paths) = index.paths.get_mut(&file_metadata.namespace) {
+                        if paths.get(&file_metadata.path) == Some(id) {
+                            paths.remove(&file_metadata.path);
+                        }
+                        if paths.is_empty() {
+                            index.paths.remove(&file_metadata.namespace);
+                        }
+                    }
+                    index.count -= 1;
+                }
+            }
+            CodeWalRecord::Revision { namespace, path, revision } => {
+                if !revision.deleted {
+                    *blob_refs.entry(revision.file_path.clone()).or_insert(0) += 1;
+                }
+                revisions.entry(namespace.clone())
+                    .or_default()
+                    .entry(path.clone())
+                    .or_default()
+                    .push(revision.clone());
+            }
+            CodeWalRecord::PruneRevisions { namespace, path, revisions: dropped } => {
+                if let Some(paths) = revisions.get_mut(namespace) {
+                    if let Some(history) = paths.get_mut(path) {
+                        for revision in history.iter().filter(|r| dropped.contains(&r.revision) && !r.deleted) {
+                            Self::drop_blob_ref(&mut blob_refs, &revision.file_path);
+                        }
+                        history.retain(|r| !dropped.contains(&r.revision));
+                        if history.is_empty() {
+                            paths.remove(path);
+                        }
+                    }
+                    if paths.is_empty() {
+                        revisions.remove(namespace);
+                    }
+                }
+            }
+        }
+        
+        index.version += 1;
+    }
+    
+    /// Append a record to the WAL and apply it, checkpointing when the log grows too long
+    fn log_record(&mut self, record: CodeWalRecord) -> Result<(), String> {
+        self.wal.append(&record)?;
+        self.apply_record(&record);
+        
+        if self.wal.record_count() >= self.checkpoint_threshold {
+            self.checkpoint()?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Write the index and metadata snapshots and truncate the WAL
+    pub fn checkpoint(&mut self) -> Result<(), String> {
+        self.segments.sync()?;
+        self.persist_index()?;
+        self.persist_metadata()?;
+        self.persist_revisions()?;
+        self.wal.reset()
+    }
+    
+    /// Set the number of WAL records that triggers a checkpoint
+    pub fn set_checkpoint_threshold(&mut self, threshold: usize) {
+        self.checkpoint_threshold = threshold.max(1);
+    }
+    
+    /// Set the size after which the active segment is sealed
+    pub fn set_segment_size(&mut self, segment_size: u64) {
+        self.segments.set_segment_size(segment_size);
+    }
+    
+    /// Compact the sealed segments once `min_dead_ratio` of them is reclaimable
+    ///
+    /// Returns the number of bytes reclaimed.
+    pub fn compact(&mut self, min_dead_ratio: f64) -> Result<u64, String> {
+        self.segments.compact(min_dead_ratio)
+    }
+    
+    /// Set the compression of new blobs, loading its dictionary
+    pub fn set_compression(&mut self, compression: &BlobCompression) -> Result<(), String> {
+        self.codec = BlobCodec::new(compression)?;
+        Ok(())
+    }
+    
+    /// Set the revision retention policy
+    ///
+    /// The policy is applied to a path whenever it changes and to every path by
+    /// `prune_revisions`.
+    pub fn set_revision_retention(&mut self, retention: RevisionRetention) {
+        self.retention = retention;
+    }
+    
+    /// Stored size of the referenced blobs in the hot tier and uncompressed size of all of them
+    fn compute_sizes(&self) -> (u64, u64) {
+        let metadata = self.metadata.read();
+        let revisions = self.revisions.read();
+        
+        let mut blobs = HashMap::new();
+        for m in metadata.values() {
+            blobs.insert(&m.file_path, (m.blob_size(), m.size));
+        }
+        for revision in revisions.values().flat_map(|paths| paths.values()).flatten() {
+            if !revision.deleted {
+                blobs.insert(&revision.file_path, (revision.blob_size(), revision.size));
+            }
+        }
+        
+        blobs.iter().fold((0, 0), |(stored, logical), (file_name, (blob_stored, blob_logical))| {
+            let hot_stored = if self.segments.is_cold(file_name) { 0 } else { *blob_stored };
+            (stored + hot_stored, logical + blob_logical)
         })
     }
     
-    /// Store a code file
-    pub fn store_file(&mut self, id: Uuid, path: &str, content: &str, language: &str) -> Result<(), String> {
-        // Calculate size
-        let content_size = content.len() as u64;
+    /// Open the cold tier of the store in a directory on persistent disk
+    pub fn set_cold_tier(&mut self, path: &Path) -> Result<(), String> {
+        self.segments.open_cold(path, DEFAULT_SEGMENT_SIZE)?;
+        (self.current_size, self.logical_size) = self.compute_sizes();
+        
+        Ok(())
+    }
+    
+    /// Whether the content of a code file is held by the cold tier
+    pub fn is_cold(&self, id: Uuid) -> bool {
+        self.metadata.read()
+            .get(&id)
+            .is_some_and(|m| self.segments.is_cold(&m.file_path))
+    }
+    
+    /// Move the content of a code file and every revision of its path to the cold tier
+    ///
+    /// Files sharing one of the blobs read it from the cold tier as well.
+    /// Returns the number of bytes freed in the store.
+    pub fn demote_file(&mut self, id: Uuid) -> Result<u64, String> {
+        let metadata = self.get_file_metadata(id)?;
+        let mut blobs: HashSet<String> = self.list_revisions(&metadata.namespace, &metadata.path)
+            .into_iter()
+            .filter(|r| !r.deleted)
+            .map(|r| r.file_path)
+            .collect();
+        blobs.insert(metadata.file_path);
+        
+        let mut freed = 0;
+        for file_name in blobs {
+            freed += self.segments.demote(&file_name)?;
+        }
+        self.current_size = self.current_size.saturating_sub(freed);
         
-        // Check if we have enough space
-        if self.current_size + content_size > self.max_size {
+        Ok(freed)
+    }
+    
+    /// Move the content of a code file back from the cold tier
+    ///
+    /// Returns the number of bytes added to the store.
+    pub fn promote_file(&mut self, id: Uuid) -> Result<u64, String> {
+        let metadata = self.get_file_metadata(id)?;
+        if !self.segments.is_cold(&metadata.file_path) {
+            return Ok(0);
+        }
+        if self.current_size + metadata.blob_size() > self.max_size {
             return Err("Not enough space in code store".to_string());
         }
         
-        // Check if path already exists (and get existing ID if it does)
-        let existing_id = {
-            let index = self.index.read();
-            index.path_map.get(path).cloned()
+        let added = self.segments.promote(&metadata.file_path)?;
+        self.current_size += added;
+        
+        Ok(added)
+    }
+    
+    /// Get the stored size of the blobs in the cold tier
+    pub fn get_cold_size(&self) -> u64 {
+        self.segments.cold_size()
+    }
+    
+    /// Name of the blob holding content with the given hash
+    ///
+    /// Identical content shares one blob across namespaces; the reference
+    /// counts keep it until the last file or revision using it goes. The
+    /// namespace that first writes the content is charged for it.
+    fn blob_name(hash: &str) -> String {
+        format!("{}.code", hash)
+    }
+    
+    /// Remove one reference to a blob
+    fn drop_blob_ref(blob_refs: &mut HashMap<String, usize>, file_name: &str) {
+        if let Some(refs) = blob_refs.get_mut(file_name) {
+            *refs -= 1;
+            if *refs == 0 {
+                blob_refs.remove(file_name);
+            }
+        }
+    }
+    
+    /// Number of files and revisions referencing a blob
+    fn blob_ref_count(&self, file_name: &str) -> usize {
+        self.blob_refs.read().get(file_name).cloned().unwrap_or(0)
+    }
+    
+    /// Write a blob unless it is already stored
+    ///
+    /// Returns the bytes added and the stored size of the blob.
+    fn write_blob(&mut self, file_name: &str, content: &str) -> Result<(u64, u64), String> {
+        if self.blob_ref_count(file_name) > 0 {
+            let stored_size = self.segments.blob_len(file_name)
+                .ok_or_else(|| format!("Code blob {} not found", file_name))?;
+            return Ok((0, stored_size));
+        }
+        
+        let data = self.codec.encode(content)?;
+        let stored_size = data.len() as u64;
+        if self.current_size + stored_size > self.max_size {
+            return Err("Not enough space in code store".to_string());
+        }
+        
+        self.segments.put(file_name, &data)?;
+        self.current_size += stored_size;
+        self.logical_size += content.len() as u64;
+        
+        Ok((stored_size, stored_size))
+    }
+    
+    /// Remove a blob once no file or revision references it, returning the bytes freed
+    ///
+    /// Blobs in the cold tier free no space in the store.
+    fn release_blob(&mut self, file_name: &str, size: u64, stored_size: u64) -> Result<u64, String> {
+        if self.blob_ref_count(file_name) > 0 {
+            return Ok(0);
+        }
+        
+        if !self.segments.contains(file_name) {
+            return Ok(0);
+        }
+        let freed = if self.segments.is_cold(file_name) { 0 } else { stored_size };
+        self.segments.remove(file_name)?;
+        self.current_size = self.current_size.saturating_sub(freed);
+        self.logical_size = self.logical_size.saturating_sub(size);
+        
+        Ok(freed)
+    }
+    
+    /// First revision of every file in stores written before revisions existed
+    fn initial_revisions<'a>(metadata: impl Iterator<Item = &'a CodeMetadata>) -> RevisionMap {
+        let mut revisions: RevisionMap = HashMap::new();
+        for m in metadata {
+            revisions.entry(m.namespace.clone())
+                .or_default()
+                .insert(m.path.clone(), vec![Self::revision_of(m, 1)]);
+        }
+        revisions
+    }
+    
+    /// Revision describing the current state of a file
+    fn revision_of(metadata: &CodeMetadata, revision: u32) -> FileRevision {
+        FileRevision {
+            revision,
+            id: metadata.id,
+            timestamp: metadata.modified_at,
+            deleted: false,
+            language: metadata.language.clone(),
+            size: metadata.size,
+            stored_size: metadata.stored_size,
+            hash: metadata.hash.clone(),
+            file_path: metadata.file_path.clone(),
+        }
+    }
+    
+    /// Number of the next revision of a path
+    fn next_revision(&self, namespace: &str, path: &str) -> u32 {
+        self.revisions.read()
+            .get(namespace)
+            .and_then(|paths| paths.get(path))
+            .and_then(|history| history.last())
+            .map_or(1, |last| last.revision + 1)
+    }
+    
+    /// Record the current state of a file as a new revision, then apply the retention policy to its path
+    ///
+    /// Returns the number of bytes freed by pruning.
+    fn record_revision(&mut self, metadata: &CodeMetadata) -> Result<u64, String> {
+        let revision = Self::revision_of(metadata, self.next_revision(&metadata.namespace, &metadata.path));
+        self.log_record(CodeWalRecord::Revision {
+            namespace: metadata.namespace.clone(),
+            path: metadata.path.clone(),
+            revision,
+        })?;
+        
+        self.prune_path(&metadata.namespace, &metadata.path, chrono::Utc::now())
+    }
+    
+    /// Record the deletion of a file, then apply the retention policy to its path
+    ///
+    /// Returns the number of bytes freed by pruning.
+    fn record_deletion(&mut self, metadata: &CodeMetadata) -> Result<u64, String> {
+        let now = chrono::Utc::now();
+        let revision = FileRevision {
+            revision: self.next_revision(&metadata.namespace, &metadata.path),
+            id: metadata.id,
+            timestamp: now,
+            deleted: true,
+            language: metadata.language.clone(),
+            size: 0,
+            stored_size: None,
+            hash: String::new(),
+            file_path: String::new(),
         };
+        self.log_record(CodeWalRecord::Revision {
+            namespace: metadata.namespace.clone(),
+            path: metadata.path.clone(),
+            revision,
+        })?;
         
-        // If path exists, need to delete old file first
-        if let Some(existing_id) = existing_id {
-            self.delete_file(existing_id)?;
+        self.prune_path(&metadata.namespace, &metadata.path, now)
+    }
+    
+    /// Revisions of a path that violate the retention policy
+    ///
+    /// When only deletions would remain, the whole history of the path goes.
+    fn expired_revisions(&self, history: &[FileRevision], now: chrono::DateTime<chrono::Utc>) -> Vec<u32> {
+        let count = history.len();
+        let first_kept = self.retention.keep_last.map_or(0, |keep| count.saturating_sub(keep));
+        
+        let mut kept = Vec::new();
+        let mut expired = Vec::new();
+        for (i, revision) in history.iter().enumerate() {
+            let live = i + 1 == count && !revision.deleted;
+            let superseded_at = history.get(i + 1).map_or(revision.timestamp, |next| next.timestamp);
+            let too_old = self.retention.max_age_secs
+                .is_some_and(|max_age| (now - superseded_at).num_seconds() > max_age as i64);
+            
+            if live || (i >= first_kept && !too_old) {
+                kept.push(revision);
+            } else {
+                expired.push(revision.revision);
+            }
+        }
+        
+        if kept.iter().all(|r| r.deleted) {
+            return history.iter().map(|r| r.revision).collect();
+        }
+        
+        expired
+    }
+    
+    /// Apply the retention policy to one path, returning the bytes freed
+    fn prune_path(&mut self, namespace: &str, path: &str, now: chrono::DateTime<chrono::Utc>) -> Result<u64, String> {
+        let dropped: Vec<FileRevision> = {
+            let revisions = self.revisions.read();
+            let history = match revisions.get(namespace).and_then(|paths| paths.get(path)) {
+                Some(history) => history,
+                None => return Ok(0),
+            };
+            let expired = self.expired_revisions(history, now);
+            history.iter().filter(|r| expired.contains(&r.revision)).cloned().collect()
+        };
+        
+        self.drop_revisions(namespace, path, dropped)
+    }
+    
+    /// Drop revisions of a path, returning the bytes freed
+    fn drop_revisions(&mut self, namespace: &str, path: &str, dropped: Vec<FileRevision>) -> Result<u64, String> {
+        if dropped.is_empty() {
+            return Ok(0);
         }
         
-        // Generate file path
-        let file_name = format!("{}.code", id);
-        let file_path = self.path.join(&file_name);
+        self.log_record(CodeWalRecord::PruneRevisions {
+            namespace: namespace.to_string(),
+            path: path.to_string(),
+            revisions: dropped.iter().map(|r| r.revision).collect(),
+        })?;
+        
+        // Remove blobs no file or revision references anymore
+        let mut freed = 0;
+        let mut released = HashSet::new();
+        for revision in dropped.iter().filter(|r| !r.deleted) {
+            if released.insert(revision.file_path.clone()) {
+                freed += self.release_blob(&revision.file_path, revision.size, revision.blob_size())?;
+            }
+        }
+        
+        Ok(freed)
+    }
+    
+    /// Apply the retention policy to every path
+    ///
+    /// Returns the number of bytes freed per namespace.
+    pub fn prune_revisions(&mut self) -> Result<HashMap<String, u64>, String> {
+        let now = chrono::Utc::now();
+        let paths: Vec<(String, String)> = self.revisions.read()
+            .iter()
+            .flat_map(|(namespace, paths)| paths.keys().map(move |path| (namespace.clone(), path.clone())))
+            .collect();
+        
+        let mut freed: HashMap<String, u64> = HashMap::new();
+        for (namespace, path) in paths {
+            let bytes = self.prune_path(&namespace, &path, now)?;
+            if bytes > 0 {
+                *freed.entry(namespace).or_insert(0) += bytes;
+            }
+        }
+        
+        Ok(freed)
+    }
+    
+    /// List the revisions of a path in a namespace, oldest first
+    pub fn list_revisions(&self, namespace: &str, path: &str) -> Vec<FileRevision> {
+        self.revisions.read()
+            .get(namespace)
+            .and_then(|paths| paths.get(path))
+            .cloned()
+            .unwrap_or_default()
+    }
+    
+    /// Get one revision of a path and its content
+    pub fn get_revision(&self, namespace: &str, path: &str, revision: u32) -> Result<(FileRevision, String), String> {
+        let found = self.list_revisions(namespace, path)
+            .into_iter()
+            .find(|r| r.revision == revision)
+            .ok_or_else(|| format!("Revision {} of {} not found in namespace {}", revision, path, namespace))?;
+        
+        let content = if found.deleted {
+            String::new()
+        } else {
+            self.read_blob(&found.file_path)?
+        };
+        
+        Ok((found, content))
+    }
+    
+    /// Get a path as it was at a point in time
+    ///
+    /// Returns `None` when the file did not exist then or that part of its history
+    /// was already pruned.
+    pub fn get_file_at(&self, namespace: &str, path: &str, timestamp: chrono::DateTime<chrono::Utc>) -> Result<Option<(FileRevision, String)>, String> {
+        let history = self.list_revisions(namespace, path);
+        
+        // A pruned predecessor leaves the time before the oldest kept revision unknown
+        let found = match history.iter().rev().find(|r| r.timestamp <= timestamp) {
+            Some(found) if !found.deleted => found.clone(),
+            _ => return Ok(None),
+        };
         
-        // Write content to file
-        let mut file = fs::File::create(&file_path)
-            .map_err(|e| format!("Failed to create code file: {}", e))?;
+        let content = self.read_blob(&found.file_path)?;
+        Ok(Some((found, content)))
+    }
+    
+    /// Unified diff between two revisions of a path
+    pub fn diff_revisions(&self, namespace: &str, path: &str, from: u32, to: u32) -> Result<String, String> {
+        let (_, old) = self.get_revision(namespace, path, from)?;
+        let (_, new) = self.get_revision(namespace, path, to)?;
+        
+        Ok(similar::TextDiff::from_lines(&old, &new)
+            .unified_diff()
+            .header(&format!("{}@{}", path, from), &format!("{}@{}", path, to))
+            .to_string())
+    }
+    
+    /// Read and decompress the content of a blob
+    fn read_blob(&self, file_name: &str) -> Result<String, String> {
+        let data = self.segments.get(file_name)
+            .map_err(|e| format!("Failed to read code content: {}", e))?;
         
-        file.write_all(content.as_bytes())
-            .map_err(|e| format!("Failed to write code content: {}", e))?;
+        self.codec.decode(data)
+    }
+    
+    /// Train a compression dictionary from the current files
+    pub fn train_dictionary(&self, max_size: usize) -> Result<Vec<u8>, String> {
+        let mut seen = HashSet::new();
+        let samples = self.get_all_metadata()
+            .into_iter()
+            .filter(|m| seen.insert(m.file_path.clone()))
+            .map(|m| self.read_blob(&m.file_path))
+            .collect::<Result<Vec<String>, String>>()?;
         
-        // Calculate hash
+        train_dictionary(&samples, max_size)
+    }
+    
+    /// Build the per-namespace path map from file metadata
+    fn build_paths<'a>(metadata: impl Iterator<Item = &'a CodeMetadata>) -> HashMap<String, HashMap<String, Uuid>> {
+        let mut paths: HashMap<String, HashMap<String, Uuid>> = HashMap::new();
+        for m in metadata {
+            paths.entry(m.namespace.clone()).or_default().insert(m.path.clone(), m.id);
+        }
+        paths
+    }
+    
+    /// Store a code file
+    ///
+    /// Storing a path that already exists replaces the file with a new revision.
+    /// Returns the number of bytes added and freed; nothing is added when a file
+    /// with identical content is already stored.
+    pub fn store_file(&mut self, id: Uuid, namespace: &str, path: &str, content: &str, language: &str) -> Result<(u64, u64), String> {
+        // Calculate size
+        let content_size = content.len() as u64;
+        let mut freed = 0;
+        
+        // Write the content blob unless identical content is already stored
         let hash = sha256::digest(content);
+        let file_name = Self::blob_name(&hash);
+        let (added, stored_size) = self.write_blob(&file_name, content)?;
+        
+        // Check if path already exists (and get existing ID if it does)
+        let existing_id = self.get_id_by_path(namespace, path);
+        
+        // If path exists, the previous file goes; its content stays in the revisions
+        if let Some(existing_id) = existing_id {
+            let existing = self.get_file_metadata(existing_id)?;
+            self.log_record(CodeWalRecord::Delete(existing_id))?;
+            freed += self.release_blob(&existing.file_path, existing.size, existing.blob_size())?;
+        }
         
         // Create metadata
         let now = chrono::Utc::now();
         let metadata = CodeMetadata {
             id,
+            namespace: namespace.to_string(),
             path: path.to_string(),
             language: language.to_string(),
             size: content_size,
+            stored_size: Some(stored_size),
             file_path: file_name,
             created_at: now,
             modified_at: now,
             hash,
         };
         
-        // Update index
-        {
-            let mut index = self.index.write();
-            index.ids.push(id);
-            index.path_map.insert(path.to_string(), id);
-            index.count += 1;
-            index.version += 1;
-        }
+        // Record the change
+        self.log_record(CodeWalRecord::Put(metadata.clone()))?;
+        freed += self.record_revision(&metadata)?;
         
-        // Update metadata
-        {
-            let mut metadata_lock = self.metadata.write();
-            metadata_lock.insert(id, metadata);
-        }
-        
-        // Update size
-        self.current_size += content_size;
-        
-        // Persist index and metadata
-        self.persist_index()?;
-        self.persist_metadata()?;
-        
-        Ok(())
+        Ok((added, freed))
     }
     
     /// Persist index to disk
@@ -201,13 +884,7 @@ impl CodeStore {
         let index_path = self.path.join("index.json");
         let index = self.index.read();
         
-        let file = fs::File::create(&index_path)
-            .map_err(|e| format!("Failed to create index file: {}", e))?;
-        
-        serde_json::to_writer_pretty(file, &*index)
-            .map_err(|e| format!("Failed to write index file: {}", e))?;
-        
-        Ok(())
+        write_json_atomic(&index_path, &*index)
     }
     
     /// Persist metadata to disk
@@ -215,33 +892,26 @@ impl CodeStore {
         let metadata_path = self.path.join("metadata.json");
         let metadata = self.metadata.read();
         
-        let file = fs::File::create(&metadata_path)
-            .map_err(|e| format!("Failed to create metadata file: {}", e))?;
-        
-        serde_json::to_writer_pretty(file, &*metadata)
-            .map_err(|e| format!("Failed to write metadata file: {}", e))?;
+        write_json_atomic(&metadata_path, &*metadata)
+    }
+    
+    /// Persist revisions to disk
+    fn persist_revisions(&self) -> Result<(), String> {
+        let revisions_path = self.path.join("revisions.json");
+        let revisions = self.revisions.read();
         
-        Ok(())
+        write_json_atomic(&revisions_path, &*revisions)
     }
     
     /// Get a code file by UUID
     pub fn get_file(&self, id: Uuid) -> Result<(String, String, String), String> {
         // Get metadata
-        let metadata_lock = self.metadata.read();
-        let metadata = metadata_lock.get(&id)
-            .ok_or_else(|| format!("Code file with ID {} not found", id))?;
-        
-        // Open file
-        let file_path = self.path.join(&metadata.file_path);
-        let mut file = fs::File::open(&file_path)
-            .map_err(|e| format!("Failed to open code file: {}", e))?;
+        let metadata = self.get_file_metadata(id)?;
         
         // Read content
-        let mut content = String::new();
-        file.read_to_string(&mut content)
-            .map_err(|e| format!("Failed to read code content: {}", e))?;
+        let content = self.read_blob(&metadata.file_path)?;
         
-        Ok((metadata.path.clone(), content, metadata.language.clone()))
+        Ok((metadata.path, content, metadata.language))
     }
     
     /// Get code file metadata by UUID
@@ -252,16 +922,43 @@ impl CodeStore {
             .ok_or_else(|| format!("Code file with ID {} not found", id))
     }
     
-    /// Get code file by path
-    pub fn get_file_by_path(&self, path: &str) -> Result<(Uuid, String, String), String> {
-        // Get UUID from path
-        let id = {
-            let index = self.index.read();
-            index.path_map.get(path)
-                .cloned()
-                .ok_or_else(|| format!("Code file with path {} not found", path))?
+    /// Put back a previously deleted code file with its original metadata
+    ///
+    /// The restore is recorded as a new revision. Returns the number of bytes
+    /// added and freed.
+    pub fn restore_file(&mut self, metadata: CodeMetadata, content: &str) -> Result<(u64, u64), String> {
+        if self.metadata.read().contains_key(&metadata.id) {
+            return Err(format!("Code file with ID {} already exists", metadata.id));
+        }
+        
+        let (added, stored_size) = self.write_blob(&metadata.file_path, content)?;
+        let metadata = CodeMetadata {
+            size: content.len() as u64,
+            stored_size: Some(stored_size),
+            ..metadata
         };
         
+        // Record the change
+        self.log_record(CodeWalRecord::Put(metadata.clone()))?;
+        let freed = self.record_revision(&metadata)?;
+        
+        Ok((added, freed))
+    }
+    
+    /// Get the UUID of the code file stored under a path in a namespace
+    pub fn get_id_by_path(&self, namespace: &str, path: &str) -> Option<Uuid> {
+        self.index.read().paths
+            .get(namespace)
+            .and_then(|paths| paths.get(path))
+            .cloned()
+    }
+    
+    /// Get code file by path in a namespace
+    pub fn get_file_by_path(&self, namespace: &str, path: &str) -> Result<(Uuid, String, String), String> {
+        // Get UUID from path
+        let id = self.get_id_by_path(namespace, path)
+            .ok_or_else(|| format!("Code file with path {} not found in namespace {}", path, namespace))?;
+        
         // Get file
         let (_, content, language) = self.get_file(id)?;
         
@@ -269,41 +966,148 @@ impl CodeStore {
     }
     
     /// Delete a code file
-    pub fn delete_file(&mut self, id: Uuid) -> Result<(), String> {
+    ///
+    /// The deletion is recorded as a revision. Returns the number of bytes freed,
+    /// which is zero while other files or revisions share its content.
+    pub fn delete_file(&mut self, id: Uuid) -> Result<u64, String> {
         // Get metadata
-        let mut metadata_lock = self.metadata.write();
-        let metadata = metadata_lock.get(&id)
-            .ok_or_else(|| format!("Code file with ID {} not found", id))?;
+        let metadata = self.get_file_metadata(id)?;
         
-        // Remove file
-        let file_path = self.path.join(&metadata.file_path);
-        fs::remove_file(&file_path)
-            .map_err(|e| format!("Failed to remove code file: {}", e))?;
+        // Record the change
+        self.log_record(CodeWalRecord::Delete(id))?;
+        let freed = self.record_deletion(&metadata)?;
         
-        // Get path for index update
-        let path = metadata.path.clone();
+        // Remove the blob once nothing references it
+        Ok(freed + self.release_blob(&metadata.file_path, metadata.size, metadata.blob_size())?)
+    }
+    
+    /// Evict a code file together with the whole revision history of its path
+    ///
+    /// Unlike `delete_file` no deletion is recorded; the path is forgotten.
+    /// Returns the number of bytes freed.
+    pub fn evict_file(&mut self, id: Uuid) -> Result<u64, String> {
+        let metadata = self.get_file_metadata(id)?;
         
-        // Update size
-        self.current_size -= metadata.size;
+        self.log_record(CodeWalRecord::Delete(id))?;
+        let history = self.list_revisions(&metadata.namespace, &metadata.path);
+        let freed = self.drop_revisions(&metadata.namespace, &metadata.path, history)?;
         
-        // Remove from metadata
-        metadata_lock.remove(&id);
-        drop(metadata_lock);
+        Ok(freed + self.release_blob(&metadata.file_path, metadata.size, metadata.blob_size())?)
+    }
+    
+    /// Get the UUIDs of all code files
+    pub fn get_ids(&self) -> Vec<Uuid> {
+        self.index.read().ids.clone()
+    }
+    
+    /// Describe the store for incremental backups
+    pub fn backup_state(&self) -> StoreBackupState {
+        StoreBackupState {
+            version: Some(self.index.read().version),
+        }
+    }
+    
+    /// Check the store for orphaned blobs, missing blobs and index mismatches
+    ///
+    /// With `repair` set, orphaned blobs and temporary files are removed, entries
+    /// whose blob is missing are dropped and the index is rebuilt from the metadata.
+    pub fn verify(&mut self, repair: bool) -> Result<Vec<ConsistencyIssue>, String> {
+        let mut issues = check_temp_files(&self.path, "code", repair)?;
         
-        // Update index
-        {
-            let mut index = self.index.write();
-            index.ids.retain(|&i| i != id);
-            index.path_map.remove(&path);
-            index.count -= 1;
-            index.version += 1;
+        let blobs = self.segments.keys();
+        let referenced: HashSet<String> = self.blob_refs.read()
+            .keys()
+            .cloned()
+            .collect();
+        
+        // Blobs without metadata or revisions
+        for file in blobs.difference(&referenced) {
+            if repair {
+                self.segments.remove(file)?;
+            }
+            issues.push(ConsistencyIssue::OrphanedBlob {
+                store: "code".to_string(),
+                file: file.clone(),
+            });
         }
         
-        // Persist index and metadata
-        self.persist_index()?;
-        self.persist_metadata()?;
+        // Metadata without blobs
+        let missing: Vec<(Uuid, String, u64, u64)> = self.metadata.read()
+            .values()
+            .filter(|m| !blobs.contains(&m.file_path))
+            .map(|m| (m.id, m.file_path.clone(), m.size, m.blob_size()))
+            .collect();
+        let mut released = HashSet::new();
+        for (id, file_name, size, stored_size) in missing {
+            if repair {
+                // Shared blobs were only counted once
+                if released.insert(file_name) {
+                    self.current_size = self.current_size.saturating_sub(stored_size);
+                    self.logical_size = self.logical_size.saturating_sub(size);
+                }
+                self.log_record(CodeWalRecord::Delete(id))?;
+            }
+            issues.push(ConsistencyIssue::MissingBlob {
+                store: "code".to_string(),
+                id,
+            });
+        }
         
-        Ok(())
+        // Index count out of sync with the indexed IDs
+        let (count, actual) = {
+            let index = self.index.read();
+            let metadata = self.metadata.read();
+            let unique_ids: HashSet<&Uuid> = index.ids.iter().collect();
+            let in_sync = index.count == index.ids.len()
+                && unique_ids.len() == index.ids.len()
+                && index.ids.iter().all(|id| metadata.contains_key(id))
+                && metadata.len() == index.ids.len();
+            if in_sync {
+                (None, metadata.len())
+            } else {
+                (Some(index.count), metadata.len())
+            }
+        };
+        if let Some(count) = count {
+            if repair {
+                self.rebuild_index();
+            }
+            issues.push(ConsistencyIssue::CountMismatch {
+                store: "code".to_string(),
+                count,
+                actual,
+            });
+        }
+        
+        // Make the repaired state durable
+        if repair && !issues.is_empty() {
+            self.checkpoint()?;
+        }
+        
+        Ok(issues)
+    }
+    
+    /// Rebuild the index from the metadata
+    fn rebuild_index(&self) {
+        let mut index = self.index.write();
+        let metadata = self.metadata.read();
+        
+        // Keep the existing order for known entries, append the rest by creation time
+        let mut seen = HashSet::new();
+        let mut ids: Vec<Uuid> = index.ids.iter()
+            .filter(|id| metadata.contains_key(id) && seen.insert(**id))
+            .cloned()
+            .collect();
+        let mut remaining: Vec<_> = metadata.values()
+            .filter(|m| !seen.contains(&m.id))
+            .collect();
+        remaining.sort_by_key(|m| m.created_at);
+        ids.extend(remaining.into_iter().map(|m| m.id));
+        
+        index.paths = Self::build_paths(metadata.values());
+        index.count = ids.len();
+        index.ids = ids;
+        index.version += 1;
     }
     
     /// Get the size of the store
@@ -311,6 +1115,21 @@ impl CodeStore {
         self.current_size
     }
     
+    /// Get the maximum size of the store
+    pub fn get_max_size(&self) -> u64 {
+        self.max_size
+    }
+    
+    /// Change the maximum size of the store
+    pub fn set_max_size(&mut self, max_size: u64) {
+        self.max_size = max_size;
+    }
+    
+    /// Get the uncompressed size of all blobs in the store
+    pub fn get_logical_size(&self) -> u64 {
+        self.logical_size
+    }
+    
     /// Get the number of files
     pub fn get_file_count(&self) -> usize {
         self.index.read().count
@@ -335,12 +1154,47 @@ impl CodeStore {
         });
         
         let index = self.index.read();
-        index.path_map.iter()
+        index.paths.values()
+            .flat_map(|paths| paths.iter())
             .filter(|&(path, _)| regex.is_match(path))
             .map(|(_, &id)| id)
             .collect()
     }
     
+    /// Find files in a namespace
+    pub fn find_files_by_namespace(&self, namespace: &str) -> Vec<Uuid> {
+        self.index.read().paths
+            .get(namespace)
+            .map(|paths| paths.values().cloned().collect())
+            .unwrap_or_default()
+    }
+    
+    /// Get the number of files and the stored size of their blobs per namespace
+    ///
+    /// The size includes blobs only kept for past revisions but not blobs in the cold tier.
+    pub fn get_namespace_stats(&self) -> HashMap<String, (usize, u64)> {
+        let metadata_lock = self.metadata.read();
+        let revisions_lock = self.revisions.read();
+        let mut stats: HashMap<String, (usize, u64)> = HashMap::new();
+        let mut blobs = HashSet::new();
+        for metadata in metadata_lock.values() {
+            let entry = stats.entry(metadata.namespace.clone()).or_default();
+            entry.0 += 1;
+            // Shared blobs only count once, blobs in the cold tier not at all
+            if blobs.insert(&metadata.file_path) && !self.segments.is_cold(&metadata.file_path) {
+                entry.1 += metadata.blob_size();
+            }
+        }
+        for (namespace, paths) in revisions_lock.iter() {
+            for revision in paths.values().flatten().filter(|r| !r.deleted) {
+                if blobs.insert(&revision.file_path) && !self.segments.is_cold(&revision.file_path) {
+                    stats.entry(namespace.clone()).or_default().1 += revision.blob_size();
+                }
+            }
+        }
+        stats
+    }
+    
     /// Find files modified after a certain time
     pub fn find_files_modified_after(&self, timestamp: chrono::DateTime<chrono::Utc>) -> Vec<Uuid> {
         let metadata_lock = self.metadata.read();
@@ -351,47 +1205,36 @@ impl CodeStore {
     }
     
     /// Update a code file
-    pub fn update_file(&mut self, id: Uuid, content: &str) -> Result<(), String> {
+    ///
+    /// Returns the number of bytes added and freed. The new content gets its own
+    /// blob (unless an identical one exists) and the old blob is removed once no
+    /// other file or revision shares it.
+    pub fn update_file(&mut self, id: Uuid, content: &str) -> Result<(u64, u64), String> {
         // Get metadata
-        let mut metadata_lock = self.metadata.write();
-        let metadata = metadata_lock.get_mut(&id)
-            .ok_or_else(|| format!("Code file with ID {} not found", id))?;
-        
-        // Calculate size difference
-        let old_size = metadata.size;
-        let new_size = content.len() as u64;
-        let size_diff = new_size as i64 - old_size as i64;
+        let mut metadata = self.get_file_metadata(id)?;
+        let old_file_name = metadata.file_path.clone();
+        let (old_size, old_stored_size) = (metadata.size, metadata.blob_size());
         
-        // Check if we have enough space for the size increase
-        if size_diff > 0 && self.current_size + size_diff as u64 > self.max_size {
-            return Err("Not enough space in code store".to_string());
-        }
-        
-        // Open file
-        let file_path = self.path.join(&metadata.file_path);
-        let mut file = fs::File::create(&file_path)
-            .map_err(|e| format!("Failed to open code file: {}", e))?;
-        
-        // Write content
-        file.write_all(content.as_bytes())
-            .map_err(|e| format!("Failed to write code content: {}", e))?;
+        // Write the new blob before switching the metadata to it
+        let hash = sha256::digest(content);
+        let file_name = Self::blob_name(&hash);
+        let (added, stored_size) = self.write_blob(&file_name, content)?;
         
         // Update metadata
-        metadata.size = new_size;
+        metadata.size = content.len() as u64;
+        metadata.stored_size = Some(stored_size);
         metadata.modified_at = chrono::Utc::now();
-        metadata.hash = sha256::digest(content);
+        metadata.hash = hash;
+        metadata.file_path = file_name;
         
-        // Update size
-        if size_diff > 0 {
-            self.current_size += size_diff as u64;
-        } else {
-            self.current_size -= (-size_diff) as u64;
-        }
+        // Record the change
+        self.log_record(CodeWalRecord::Put(metadata.clone()))?;
+        let mut freed = self.record_revision(&metadata)?;
         
-        // Persist metadata
-        self.persist_metadata()?;
+        // Remove the old blob once nothing references it
+        freed += self.release_blob(&old_file_name, old_size, old_stored_size)?;
         
-        Ok(())
+        Ok((added, freed))
     }
     
     /// Get all file metadata
@@ -0,0 +1,213 @@
+use serde::{Serialize, Deserialize};
+
+use uuid::Uuid;
+
+use super::hnsw::HnswIndex;
+use super::matrix::EmbeddingMatrix;
+use super::quantization::{Quantization, QuantizedMatrix};
+
+/// Collection holding the embeddings stored without naming one
+pub const DEFAULT_COLLECTION: &str = "default";
+
+/// Default collection for records written before collections existed
+pub fn default_collection() -> String {
+    DEFAULT_COLLECTION.to_string()
+}
+
+/// Similarity used to rank the embeddings of a collection
+///
+/// Higher similarities are better for every metric, so L2 reports the
+/// negated Euclidean distance.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceMetric {
+    /// Cosine of the angle between two embeddings
+    #[default]
+    Cosine,
+    
+    /// Dot product of two embeddings
+    Dot,
+    
+    /// Negated Euclidean distance between two embeddings
+    L2,
+}
+
+impl DistanceMetric {
+    /// Similarity of two embeddings
+    pub fn similarity(self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            DistanceMetric::L2 => -a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum::<f32>().sqrt(),
+            DistanceMetric::Cosine | DistanceMetric::Dot => {
+                let dot = a.iter().zip(b).map(|(a, b)| a * b).sum();
+                let norm = |v: &[f32]| v.iter().map(|v| v * v).sum::<f32>().sqrt();
+                self.score(dot, norm(a), norm(b))
+            }
+        }
+    }
+    
+    /// Similarity of two embeddings from their dot product and norms
+    pub(super) fn score(self, dot: f32, a_norm: f32, b_norm: f32) -> f32 {
+        match self {
+            DistanceMetric::Cosine => {
+                let norm = a_norm * b_norm;
+                if norm == 0.0 { 0.0 } else { dot / norm }
+            }
+            DistanceMetric::Dot => dot,
+            DistanceMetric::L2 => -(a_norm * a_norm + b_norm * b_norm - 2.0 * dot).max(0.0).sqrt(),
+        }
+    }
+}
+
+/// Named set of embeddings from one embedding model
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VectorCollection {
+    /// Name of the collection
+    pub name: String,
+
+    /// Dimension of every embedding in the collection
+    pub dimension: usize,
+
+    /// Similarity used to rank the embeddings
+    #[serde(default)]
+    pub metric: DistanceMetric,
+
+    /// Identifier of the model that produces the embeddings, if known
+    #[serde(default)]
+    pub model: Option<String>,
+    
+    /// Compression of the resident vectors
+    #[serde(default)]
+    pub quantization: Quantization,
+}
+
+impl VectorCollection {
+    /// Check that a collection name can be used
+    pub fn validate_name(name: &str) -> Result<(), String> {
+        let valid = !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(format!(
+                "Invalid vector collection name '{}': use letters, digits, '-' and '_'",
+                name
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Bytes of RAM the search row of one resident embedding takes
+    pub fn row_size(&self) -> u64 {
+        match self.quantization {
+            Quantization::None => EmbeddingMatrix::row_size(self.dimension),
+            quantization => QuantizedMatrix::row_size(self.dimension, quantization),
+        }
+    }
+}
+
+/// Vector search metrics of one collection
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VectorCollectionMetrics {
+    /// Number of embeddings in the collection
+    pub entries: usize,
+    
+    /// Similarity used to rank the embeddings
+    pub metric: DistanceMetric,
+    
+    /// Compression of the resident vectors
+    pub quantization: Quantization,
+    
+    /// Bytes of RAM the hot embeddings would take at full precision: their blobs and matrix rows
+    pub full_precision_size: u64,
+    
+    /// Bytes of RAM the hot embeddings take: blobs kept in RAM and the resident vectors
+    pub resident_size: u64,
+    
+    /// Share of the exact top 10 found by searches, when last measured
+    pub recall: Option<f32>,
+}
+
+/// Resident vectors of one collection
+#[derive(Debug)]
+pub(super) enum CollectionVectors {
+    /// Full-precision vectors
+    Full(EmbeddingMatrix),
+    
+    /// Compressed vectors, re-ranked at full precision after searching
+    Quantized(QuantizedMatrix),
+}
+
+/// Search structures of one collection, rebuilt from the stored embeddings on open
+#[derive(Debug)]
+pub(super) struct CollectionIndex {
+    /// Every readable embedding of the collection
+    pub vectors: CollectionVectors,
+    
+    /// Approximate nearest-neighbour graph, if enabled for a full-precision collection
+    pub hnsw: Option<HnswIndex>,
+    
+    /// Recall of the collection's searches, when last measured
+    pub recall: Option<f32>,
+}
+
+impl CollectionIndex {
+    /// Create empty search structures for a collection
+    pub fn new(collection: &VectorCollection) -> Self {
+        let vectors = match collection.quantization {
+            Quantization::None => CollectionVectors::Full(EmbeddingMatrix::default()),
+            quantization => CollectionVectors::Quantized(QuantizedMatrix::new(collection.dimension, quantization)),
+        };
+        
+        Self {
+            vectors,
+            hnsw: None,
+            recall: None,
+        }
+    }
+    
+    /// Whether an embedding is held
+    pub fn contains(&self, id: Uuid) -> bool {
+        match &self.vectors {
+            CollectionVectors::Full(matrix) => matrix.contains(id),
+            CollectionVectors::Quantized(matrix) => matrix.contains(id),
+        }
+    }
+    
+    /// IDs of the embeddings held
+    pub fn ids(&self) -> &[Uuid] {
+        match &self.vectors {
+            CollectionVectors::Full(matrix) => matrix.ids(),
+            CollectionVectors::Quantized(matrix) => matrix.ids(),
+        }
+    }
+    
+    /// Add or replace an embedding
+    pub fn insert(&mut self, id: Uuid, vector: &[f32]) {
+        match &mut self.vectors {
+            CollectionVectors::Full(matrix) => matrix.insert(id, vector),
+            CollectionVectors::Quantized(matrix) => matrix.insert(id, vector),
+        }
+        if let Some(hnsw) = self.hnsw.as_mut() {
+            hnsw.insert(id, vector);
+        }
+    }
+    
+    /// Remove an embedding, returning whether it was held
+    pub fn remove(&mut self, id: Uuid) -> bool {
+        let removed = match &mut self.vectors {
+            CollectionVectors::Full(matrix) => matrix.remove(id),
+            CollectionVectors::Quantized(matrix) => matrix.remove(id),
+        };
+        if let Some(hnsw) = self.hnsw.as_mut() {
+            hnsw.remove(id);
+        }
+        removed
+    }
+    
+    /// Bytes the resident vectors take
+    pub fn resident_size(&self) -> u64 {
+        match &self.vectors {
+            CollectionVectors::Full(matrix) => matrix.size(),
+            CollectionVectors::Quantized(matrix) => matrix.size(),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::path::PathBuf;
+use std::fs;
+use std::io::{Read, Write};
+use serde::{Serialize, Deserialize};
+
+/// Magic number at the start of every zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Compression settings for the blobs of one store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlobCompression {
+    /// Compress blobs on write
+    ///
+    /// Blobs are always readable regardless of this setting, so it can be
+    /// toggled on a store that already holds data.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// zstd compression level
+    #[serde(default = "default_level")]
+    pub level: i32,
+
+    /// Trained zstd dictionary used for new blobs (see `RamLake::train_code_dictionary`)
+    ///
+    /// Blobs written with a dictionary can only be read while it stays configured.
+    #[serde(default)]
+    pub dictionary: Option<PathBuf>,
+}
+
+fn default_level() -> i32 {
+    3
+}
+
+impl Default for BlobCompression {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            level: default_level(),
+            dictionary: None,
+        }
+    }
+}
+
+/// Encodes and decodes store blobs according to a `BlobCompression`
+///
+/// Content is stored raw when compression is disabled or would not make it
+/// smaller. Store content is always valid UTF-8, which can never start with the
+/// zstd magic number, so raw and compressed blobs are told apart by their first
+/// bytes.
+#[derive(Debug, Clone)]
+pub struct BlobCodec {
+    /// Whether new blobs are compressed
+    enabled: bool,
+
+    /// zstd compression level
+    level: i32,
+
+    /// Dictionary for new blobs and blobs that were written with it
+    dictionary: Option<Vec<u8>>,
+}
+
+impl Default for BlobCodec {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            level: default_level(),
+            dictionary: None,
+        }
+    }
+}
+
+impl BlobCodec {
+    /// Create a codec, loading the configured dictionary
+    pub fn new(config: &BlobCompression) -> Result<Self, String> {
+        let dictionary = match &config.dictionary {
+            Some(path) => Some(fs::read(path)
+                .map_err(|e| format!("Failed to read compression dictionary {}: {}", path.display(), e))?),
+            None => None,
+        };
+
+        Ok(Self {
+            enabled: config.enabled,
+            level: config.level,
+            dictionary,
+        })
+    }
+
+    /// Encode content for storage
+    pub fn encode(&self, content: &str) -> Result<Vec<u8>, String> {
+        if !self.enabled {
+            return Ok(content.as_bytes().to_vec());
+        }
+
+        let compressed = match &self.dictionary {
+            Some(dictionary) => {
+                let mut encoder = zstd::stream::write::Encoder::with_dictionary(Vec::new(), self.level, dictionary)
+                    .map_err(|e| format!("Failed to create compressor: {}", e))?;
+                encoder.write_all(content.as_bytes())
+                    .map_err(|e| format!("Failed to compress blob: {}", e))?;
+                encoder.finish()
+                    .map_err(|e| format!("Failed to compress blob: {}", e))?
+            }
+            None => zstd::stream::encode_all(content.as_bytes(), self.level)
+                .map_err(|e| format!("Failed to compress blob: {}", e))?,
+        };
+
+        if compressed.len() < content.len() {
+            Ok(compressed)
+        } else {
+            Ok(content.as_bytes().to_vec())
+        }
+    }
+
+    /// Decode stored bytes back into content
+    pub fn decode(&self, data: Vec<u8>) -> Result<String, String> {
+        let bytes = if data.starts_with(&ZSTD_MAGIC) {
+            self.decompress(&data)?
+        } else {
+            data
+        };
+
+        String::from_utf8(bytes).map_err(|e| format!("Blob is not valid UTF-8: {}", e))
+    }
+
+    /// Decompress a zstd frame, using the dictionary it was written with
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+
+        match zstd::zstd_safe::get_dict_id_from_frame(data) {
+            Some(dict_id) => {
+                let dictionary = self.dictionary.as_ref()
+                    .filter(|dictionary| zstd::zstd_safe::get_dict_id_from_dict(dictionary) == Some(dict_id))
+                    .ok_or_else(|| format!("Blob was compressed with dictionary {} which is not configured", dict_id))?;
+
+                zstd::stream::read::Decoder::with_dictionary(data, dictionary)
+                    .and_then(|mut decoder| decoder.read_to_end(&mut bytes))
+                    .map_err(|e| format!("Failed to decompress blob: {}", e))?;
+            }
+            None => {
+                bytes = zstd::stream::decode_all(data)
+                    .map_err(|e| format!("Failed to decompress blob: {}", e))?;
+            }
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Train a zstd dictionary from sample contents
+pub fn train_dictionary(samples: &[String], max_size: usize) -> Result<Vec<u8>, String> {
+    zstd::dict::from_samples(samples, max_size)
+        .map_err(|e| format!("Failed to train compression dictionary: {}", e))
+}
@@ -0,0 +1,49 @@
+use std::path::Path;
+use std::fs;
+use std::collections::HashSet;
+
+use crate::core::memory::ramlake::ConsistencyIssue;
+
+/// List the names of files in a store directory with the given extension
+pub fn list_files_with_extension(path: &Path, extension: &str) -> Result<HashSet<String>, String> {
+    let mut files = HashSet::new();
+    
+    for entry in fs::read_dir(path).map_err(|e| format!("Failed to read store directory: {}", e))? {
+        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let entry_path = entry.path();
+        
+        if entry_path.is_file() && entry_path.extension().is_some_and(|ext| ext == extension) {
+            files.insert(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    
+    Ok(files)
+}
+
+/// Remove a file from a store directory, returning the number of bytes freed
+pub fn remove_store_file(path: &Path, file: &str) -> Result<u64, String> {
+    let file_path = path.join(file);
+    let size = fs::metadata(&file_path).map(|m| m.len()).unwrap_or(0);
+    
+    fs::remove_file(&file_path)
+        .map_err(|e| format!("Failed to remove {}: {}", file, e))?;
+    
+    Ok(size)
+}
+
+/// Report (and optionally remove) temporary files left over from interrupted atomic writes
+pub fn check_temp_files(path: &Path, store: &str, repair: bool) -> Result<Vec<ConsistencyIssue>, String> {
+    let mut issues = Vec::new();
+    
+    for file in list_files_with_extension(path, "tmp")? {
+        if repair {
+            remove_store_file(path, &file)?;
+        }
+        issues.push(ConsistencyIssue::StaleTempFile {
+            store: store.to_string(),
+            file,
+        });
+    }
+    
+    Ok(issues)
+}
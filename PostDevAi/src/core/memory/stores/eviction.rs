@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+/// Type of an entity that can be evicted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntityKind {
+    /// Code file in the code store, evicted with its revisions, embedding and relations
+    Code,
+
+    /// Embedding in the vector store
+    Embedding,
+
+    /// Event in the history store, evicted with its relations
+    Event,
+}
+
+impl FromStr for EntityKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "code" => Ok(Self::Code),
+            "embedding" => Ok(Self::Embedding),
+            "event" => Ok(Self::Event),
+            _ => Err(format!("Unknown entity type: {}", name)),
+        }
+    }
+}
+
+/// Eviction policy selected in the RAM-Lake configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EvictionPolicyKind {
+    /// Evict the least recently accessed entries first
+    #[default]
+    Lru,
+
+    /// Evict by entity type priority, least recently accessed first within a priority
+    Priority,
+
+    /// Never evict; writes fail once a store or the RAM-Lake is full
+    Disabled,
+}
+
+impl FromStr for EvictionPolicyKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "lru" => Ok(Self::Lru),
+            "priority" => Ok(Self::Priority),
+            "disabled" => Ok(Self::Disabled),
+            _ => Err(format!("Unknown eviction policy: {}", name)),
+        }
+    }
+}
+
+/// Eviction settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvictionConfig {
+    /// Policy choosing which entries make room when a store or the RAM-Lake is full
+    #[serde(default)]
+    pub policy: EvictionPolicyKind,
+
+    /// Priority per entity type for the priority policy; lower priorities are evicted first
+    #[serde(default = "default_priorities")]
+    pub priorities: HashMap<EntityKind, u32>,
+}
+
+fn default_priorities() -> HashMap<EntityKind, u32> {
+    HashMap::from([
+        (EntityKind::Event, 0),
+        (EntityKind::Embedding, 1),
+        (EntityKind::Code, 2),
+    ])
+}
+
+impl Default for EvictionConfig {
+    fn default() -> Self {
+        Self {
+            policy: EvictionPolicyKind::default(),
+            priorities: default_priorities(),
+        }
+    }
+}
+
+impl EvictionConfig {
+    /// Build the configured policy, or `None` when eviction is disabled
+    pub fn build_policy(&self) -> Option<Box<dyn EvictionPolicy>> {
+        match self.policy {
+            EvictionPolicyKind::Lru => Some(Box::new(LruPolicy)),
+            EvictionPolicyKind::Priority => Some(Box::new(PriorityPolicy::new(self.priorities.clone()))),
+            EvictionPolicyKind::Disabled => None,
+        }
+    }
+}
+
+/// Entry that may be evicted to make room
+#[derive(Debug, Clone)]
+pub struct EvictionCandidate {
+    /// ID of the entity
+    pub id: Uuid,
+
+    /// Type of the entity
+    pub kind: EntityKind,
+
+    /// Namespace the entity belongs to
+    pub namespace: String,
+
+    /// Bytes the entity holds in its store
+    pub size: u64,
+
+    /// Last read or write of the entity
+    pub last_access: DateTime<Utc>,
+}
+
+/// Decides the order in which entries are evicted
+///
+/// Pinned entries never reach the policy.
+pub trait EvictionPolicy: Send + Sync {
+    /// Name recorded with every eviction
+    fn name(&self) -> &str;
+
+    /// Sort the candidates so that the ones to evict first come first
+    fn order(&self, candidates: &mut [EvictionCandidate]);
+}
+
+/// Evicts the least recently accessed entries first
+#[derive(Debug, Clone, Default)]
+pub struct LruPolicy;
+
+impl EvictionPolicy for LruPolicy {
+    fn name(&self) -> &str {
+        "lru"
+    }
+
+    fn order(&self, candidates: &mut [EvictionCandidate]) {
+        candidates.sort_by_key(|c| c.last_access);
+    }
+}
+
+/// Evicts entity types with a lower priority first, least recently accessed first within a type
+///
+/// Types without a priority are treated as priority 0.
+#[derive(Debug, Clone)]
+pub struct PriorityPolicy {
+    /// Priority per entity type
+    priorities: HashMap<EntityKind, u32>,
+}
+
+impl PriorityPolicy {
+    /// Create a policy with the given priorities
+    pub fn new(priorities: HashMap<EntityKind, u32>) -> Self {
+        Self { priorities }
+    }
+}
+
+impl EvictionPolicy for PriorityPolicy {
+    fn name(&self) -> &str {
+        "priority"
+    }
+
+    fn order(&self, candidates: &mut [EvictionCandidate]) {
+        candidates.sort_by_key(|c| (self.priorities.get(&c.kind).cloned().unwrap_or(0), c.last_access));
+    }
+}
+
+/// Space eviction has to free before a write fits
+#[derive(Debug, Clone, Default)]
+pub struct EvictionNeeds {
+    /// Bytes to free anywhere in the RAM-Lake
+    pub total: u64,
+
+    /// Bytes to free in the store holding each entity type
+    pub per_kind: HashMap<EntityKind, u64>,
+}
+
+impl EvictionNeeds {
+    /// Whether nothing has to be freed
+    pub fn is_satisfied(&self) -> bool {
+        self.total == 0 && self.per_kind.values().all(|&bytes| bytes == 0)
+    }
+
+    /// Whether evicting an entry of the given type helps
+    pub fn wants(&self, kind: EntityKind) -> bool {
+        self.total > 0 || self.per_kind.get(&kind).is_some_and(|&bytes| bytes > 0)
+    }
+
+    /// Account for an entry chosen for eviction
+    pub fn take(&mut self, kind: EntityKind, size: u64) {
+        self.total = self.total.saturating_sub(size);
+        if let Some(bytes) = self.per_kind.get_mut(&kind) {
+            *bytes = bytes.saturating_sub(size);
+        }
+    }
+}
+
+/// An entry that was evicted to make room
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvictionEvent {
+    /// ID of the evicted entity
+    pub id: Uuid,
+
+    /// Type of the evicted entity
+    pub kind: EntityKind,
+
+    /// Namespace the entity belonged to
+    pub namespace: String,
+
+    /// Bytes freed by the eviction
+    pub size: u64,
+
+    /// Policy that chose the entry
+    pub policy: String,
+
+    /// Time of the eviction
+    pub timestamp: DateTime<Utc>,
+}
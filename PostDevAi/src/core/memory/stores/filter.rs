@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use super::vector_store::EmbeddingMetadata;
+
+/// Embedding type of code file embeddings
+pub const CODE_EMBEDDING: &str = "code";
+
+/// Embedding type of event embeddings
+pub const EVENT_EMBEDDING: &str = "event";
+
+/// Embedding type of embeddings whose source is not stored in the RAM-Lake
+pub const UNKNOWN_EMBEDDING: &str = "unknown";
+
+/// What an embedding was computed from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingSource {
+    /// ID of the code file or event
+    pub source_id: Uuid,
+
+    /// Type of embedding (e.g., "code", "event")
+    pub embedding_type: String,
+
+    /// Language of the source code, for code embeddings
+    pub language: Option<String>,
+
+    /// Path of the source file, for code embeddings
+    pub path: Option<String>,
+
+    /// Hash of the code the embedding was computed from, for code embeddings
+    pub hash: Option<String>,
+
+    /// First line of the code the embedding covers, from 1, for code chunk embeddings
+    pub start_line: Option<usize>,
+
+    /// Last line of the code the embedding covers, inclusive, for code chunk embeddings
+    pub end_line: Option<usize>,
+}
+
+impl EmbeddingSource {
+    /// Source of an embedding with no known code file or event
+    pub fn unknown(source_id: Uuid) -> Self {
+        Self {
+            source_id,
+            embedding_type: UNKNOWN_EMBEDDING.to_string(),
+            language: None,
+            path: None,
+            hash: None,
+            start_line: None,
+            end_line: None,
+        }
+    }
+}
+
+/// Conditions an embedding must meet to be returned by a vector search
+///
+/// Conditions left unset match every embedding. The filter is checked while
+/// the search runs, so a selective filter still returns up to `limit` results.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VectorFilter {
+    /// Only embeddings in this namespace
+    #[serde(default)]
+    pub namespace: Option<String>,
+
+    /// Only embeddings of this type (e.g., "code", "event")
+    #[serde(default)]
+    pub embedding_type: Option<String>,
+
+    /// Only embeddings of code in this language
+    #[serde(default)]
+    pub language: Option<String>,
+
+    /// Only embeddings of files whose path starts with this prefix
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+
+    /// Only embeddings created after this time
+    #[serde(default)]
+    pub created_after: Option<DateTime<Utc>>,
+}
+
+impl VectorFilter {
+    /// Filter matching only the embeddings of one namespace
+    pub fn namespace(namespace: &str) -> Self {
+        Self {
+            namespace: Some(namespace.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Whether the filter matches every embedding
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether an embedding meets every condition of the filter
+    pub fn matches(&self, metadata: &EmbeddingMetadata) -> bool {
+        self.namespace.as_ref().is_none_or(|namespace| *namespace == metadata.namespace)
+            && self.embedding_type.as_ref().is_none_or(|embedding_type| *embedding_type == metadata.embedding_type)
+            && self.language.as_ref().is_none_or(|language| metadata.language.as_ref() == Some(language))
+            && self.path_prefix.as_ref().is_none_or(|prefix| metadata.path.as_ref().is_some_and(|path| path.starts_with(prefix.as_str())))
+            && self.created_after.is_none_or(|after| metadata.created_at > after)
+    }
+}
@@ -1,15 +1,24 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
-use std::io::{Read, Write};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 use serde::{Serialize, Deserialize};
 use parking_lot::RwLock;
 use chrono::{DateTime, Utc, TimeZone};
 
+use super::wal::{WriteAheadLog, DEFAULT_CHECKPOINT_THRESHOLD};
+use super::consistency::check_temp_files;
+use super::compression::{BlobCodec, BlobCompression};
+use super::segment::DEFAULT_SEGMENT_SIZE;
+use super::tiering::TieredLog;
+use crate::core::memory::ramlake::{ConsistencyIssue, StoreBackupState, default_namespace};
+use crate::utils::filesystem::write_json_atomic;
+
 /// History Store for RAM-Lake
 /// 
-/// Stores event history for terminal, logs, errors, etc.
+/// Stores event history for terminal, logs, errors, etc. Event contents are
+/// kept in an append-only segment log, compressed when the store's compression
+/// is enabled. Events demoted to the cold tier do not count against the store size.
 pub struct HistoryStore {
     /// Path to store events
     path: PathBuf,
@@ -17,14 +26,36 @@ pub struct HistoryStore {
     /// Maximum size of the store in bytes
     max_size: u64,
     
-    /// Current size of the store in bytes
+    /// Size of the events in the hot tier in bytes
     current_size: u64,
     
+    /// Blob compression
+    codec: BlobCodec,
+    
     /// Index of events
     index: RwLock<EventIndex>,
     
     /// Mapping of UUIDs to event metadata
     metadata: RwLock<HashMap<Uuid, EventMetadata>>,
+    
+    /// Event contents
+    segments: TieredLog,
+    
+    /// Write-ahead log of changes since the last checkpoint
+    wal: WriteAheadLog,
+    
+    /// Number of WAL records that triggers a checkpoint
+    checkpoint_threshold: usize,
+}
+
+/// History Store WAL record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum HistoryWalRecord {
+    /// An event was stored
+    Put(EventMetadata),
+    
+    /// An event was deleted
+    Delete(Uuid),
 }
 
 /// Event Index
@@ -49,12 +80,22 @@ pub struct EventMetadata {
     /// ID of the event
     pub id: Uuid,
     
+    /// Namespace (project) the event belongs to
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    
     /// Type of the event
     pub event_type: String,
     
     /// Size of the event content in bytes
     pub size: u64,
     
+    /// Size of the event file in the store in bytes, after compression
+    ///
+    /// Missing for events written before compression existed, which are raw.
+    #[serde(default)]
+    pub stored_size: Option<u64>,
+    
     /// Path to the event file in the store
     pub file_path: String,
     
@@ -68,6 +109,13 @@ pub struct EventMetadata {
     pub severity: Option<String>,
 }
 
+impl EventMetadata {
+    /// Size of the event file in the store in bytes
+    pub fn blob_size(&self) -> u64 {
+        self.stored_size.unwrap_or(self.size)
+    }
+}
+
 /// Event with content
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
@@ -114,207 +162,303 @@ impl HistoryStore {
             HashMap::new()
         };
         
-        // Calculate current size
-        let mut current_size = 0;
-        for entry in fs::read_dir(&path).map_err(|e| format!("Failed to read history store directory: {}", e))? {
-            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-            let metadata = entry.metadata().map_err(|e| format!("Failed to read file metadata: {}", e))?;
-            current_size += metadata.len();
-        }
+        // Open event segments
+        let segments = TieredLog::open(&path, DEFAULT_SEGMENT_SIZE)?;
         
-        Ok(Self {
+        // Open write-ahead log
+        let wal = WriteAheadLog::open(path.join("wal.log"))?;
+        
+        let mut store = Self {
             path,
             max_size,
-            current_size,
+            current_size: 0,
+            codec: BlobCodec::default(),
             index: RwLock::new(index),
             metadata: RwLock::new(metadata),
-        })
+            segments,
+            wal,
+            checkpoint_threshold: DEFAULT_CHECKPOINT_THRESHOLD,
+        };
+        
+        // Replay changes made since the last checkpoint
+        let records: Vec<HistoryWalRecord> = store.wal.replay()?;
+        for record in &records {
+            store.apply_record(record);
+        }
+        
+        // Stores written before segments keep one file per event
+        let files: Vec<String> = store.metadata.read().values().map(|m| m.file_path.clone()).collect();
+        store.segments.import_files(files)?;
+        
+        store.current_size = store.hot_size();
+        
+        Ok(store)
+    }
+    
+    /// Stored size of the events not in the cold tier
+    fn hot_size(&self) -> u64 {
+        self.metadata.read()
+            .values()
+            .filter(|m| !self.segments.is_cold(&m.file_path))
+            .map(|m| m.blob_size())
+            .sum()
+    }
+    
+    /// Open the cold tier of the store in a directory on persistent disk
+    pub fn set_cold_tier(&mut self, path: &Path) -> Result<(), String> {
+        self.segments.open_cold(path, DEFAULT_SEGMENT_SIZE)?;
+        self.current_size = self.hot_size();
+        
+        Ok(())
+    }
+    
+    /// Whether an event is held by the cold tier
+    pub fn is_cold(&self, id: Uuid) -> bool {
+        self.metadata.read()
+            .get(&id)
+            .is_some_and(|m| self.segments.is_cold(&m.file_path))
+    }
+    
+    /// Move an event to the cold tier, returning the bytes freed in the store
+    pub fn demote_event(&mut self, id: Uuid) -> Result<u64, String> {
+        let metadata = self.get_event_metadata(id)?;
+        if self.segments.demote(&metadata.file_path)? == 0 {
+            return Ok(0);
+        }
+        
+        self.current_size = self.current_size.saturating_sub(metadata.blob_size());
+        Ok(metadata.blob_size())
+    }
+    
+    /// Move an event back from the cold tier, returning the bytes added to the store
+    pub fn promote_event(&mut self, id: Uuid) -> Result<u64, String> {
+        let metadata = self.get_event_metadata(id)?;
+        if !self.segments.is_cold(&metadata.file_path) {
+            return Ok(0);
+        }
+        if self.current_size + metadata.blob_size() > self.max_size {
+            return Err("Not enough space in history store".to_string());
+        }
+        
+        self.segments.promote(&metadata.file_path)?;
+        self.current_size += metadata.blob_size();
+        Ok(metadata.blob_size())
+    }
+    
+    /// Get the stored size of the events in the cold tier
+    pub fn get_cold_size(&self) -> u64 {
+        self.segments.cold_size()
+    }
+    
+    /// Apply a WAL record to the in-memory index and metadata
+    fn apply_record(&self, record: &HistoryWalRecord) {
+        let mut index = self.index.write();
+        let mut metadata = self.metadata.write();
+        
+        match record {
+            HistoryWalRecord::Put(event_metadata) => {
+                let id = event_metadata.id;
+                if metadata.insert(id, event_metadata.clone()).is_none() {
+                    index.ids.push(id);
+                    index.type_map.entry(event_metadata.event_type.clone())
+                        .or_insert_with(Vec::new)
+                        .push(id);
+                    index.count += 1;
+                }
+            }
+            HistoryWalRecord::Delete(id) => {
+                if let Some(event_metadata) = metadata.remove(id) {
+                    index.ids.retain(|i| i != id);
+                    
+                    // Remove from type map
+                    if let Some(events) = index.type_map.get_mut(&event_metadata.event_type) {
+                        events.retain(|i| i != id);
+                        
+                        // Remove empty type entries
+                        if events.is_empty() {
+                            index.type_map.remove(&event_metadata.event_type);
+                        }
+                    }
+                    
+                    index.count -= 1;
+                }
+            }
+        }
+        
+        index.version += 1;
+    }
+    
+    /// Append a record to the WAL and apply it, checkpointing when the log grows too long
+    fn log_record(&mut self, record: HistoryWalRecord) -> Result<(), String> {
+        self.wal.append(&record)?;
+        self.apply_record(&record);
+        
+        if self.wal.record_count() >= self.checkpoint_threshold {
+            self.checkpoint()?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Write the index and metadata snapshots and truncate the WAL
+    pub fn checkpoint(&mut self) -> Result<(), String> {
+        self.segments.sync()?;
+        self.persist_index()?;
+        self.persist_metadata()?;
+        self.wal.reset()
+    }
+    
+    /// Set the number of WAL records that triggers a checkpoint
+    pub fn set_checkpoint_threshold(&mut self, threshold: usize) {
+        self.checkpoint_threshold = threshold.max(1);
+    }
+    
+    /// Set the size after which the active segment is sealed
+    pub fn set_segment_size(&mut self, segment_size: u64) {
+        self.segments.set_segment_size(segment_size);
+    }
+    
+    /// Compact the sealed segments once `min_dead_ratio` of them is reclaimable
+    ///
+    /// Returns the number of bytes reclaimed.
+    pub fn compact(&mut self, min_dead_ratio: f64) -> Result<u64, String> {
+        self.segments.compact(min_dead_ratio)
+    }
+    
+    /// Set the compression of new events, loading its dictionary
+    pub fn set_compression(&mut self, compression: &BlobCompression) -> Result<(), String> {
+        self.codec = BlobCodec::new(compression)?;
+        Ok(())
+    }
+    
+    /// Read and decompress the content of an event
+    fn read_blob(&self, file_name: &str) -> Result<String, String> {
+        let data = self.segments.get(file_name)
+            .map_err(|e| format!("Failed to read event content: {}", e))?;
+        
+        self.codec.decode(data)
     }
     
     /// Store an event
-    pub fn store_event(&mut self, id: Uuid, event_type: &str, content: &str) -> Result<(), String> {
+    ///
+    /// Returns the number of bytes stored, which is less than the content size
+    /// when the event was compressed.
+    pub fn store_event(&mut self, id: Uuid, namespace: &str, event_type: &str, content: &str) -> Result<u64, String> {
         // Calculate size
         let content_size = content.len() as u64;
+        let data = self.codec.encode(content)?;
+        let stored_size = data.len() as u64;
         
-        // Check if we have enough space
-        if self.current_size + content_size > self.max_size {
-            // Try to free up space by removing oldest events
-            self.remove_oldest_events(content_size)?;
-            
-            // Check again
-            if self.current_size + content_size > self.max_size {
-                return Err("Not enough space in history store".to_string());
-            }
+        // Check if we have enough space; RAM-Lake evicts events to make room beforehand
+        if self.current_size + stored_size > self.max_size {
+            return Err("Not enough space in history store".to_string());
         }
         
-        // Generate file path
+        // Generate blob name
         let file_name = format!("{}.event", id);
-        let file_path = self.path.join(&file_name);
         
-        // Write content to file
-        let mut file = fs::File::create(&file_path)
-            .map_err(|e| format!("Failed to create event file: {}", e))?;
-        
-        file.write_all(content.as_bytes())
+        // Write content to the active segment
+        self.segments.put(&file_name, &data)
             .map_err(|e| format!("Failed to write event content: {}", e))?;
         
         // Create metadata
         let now = Utc::now();
         let metadata = EventMetadata {
             id,
+            namespace: namespace.to_string(),
             event_type: event_type.to_string(),
             size: content_size,
+            stored_size: Some(stored_size),
             file_path: file_name,
             timestamp: now,
             source: None,
             severity: None,
         };
         
-        // Update index
-        {
-            let mut index = self.index.write();
-            index.ids.push(id);
-            
-            // Add to type map
-            index.type_map.entry(event_type.to_string())
-                .or_insert_with(Vec::new)
-                .push(id);
-            
-            index.count += 1;
-            index.version += 1;
-        }
-        
-        // Update metadata
-        {
-            let mut metadata_lock = self.metadata.write();
-            metadata_lock.insert(id, metadata);
-        }
-        
         // Update size
-        self.current_size += content_size;
+        self.current_size += stored_size;
         
-        // Persist index and metadata
-        self.persist_index()?;
-        self.persist_metadata()?;
+        // Record the change
+        self.log_record(HistoryWalRecord::Put(metadata))?;
         
-        Ok(())
+        Ok(stored_size)
     }
     
     /// Store an event with additional metadata
     pub fn store_event_with_metadata(
         &mut self,
         id: Uuid,
+        namespace: &str,
         event_type: &str,
         content: &str,
         source: Option<&str>,
         severity: Option<&str>,
-    ) -> Result<(), String> {
+    ) -> Result<u64, String> {
         // Calculate size
         let content_size = content.len() as u64;
+        let data = self.codec.encode(content)?;
+        let stored_size = data.len() as u64;
         
-        // Check if we have enough space
-        if self.current_size + content_size > self.max_size {
-            // Try to free up space by removing oldest events
-            self.remove_oldest_events(content_size)?;
-            
-            // Check again
-            if self.current_size + content_size > self.max_size {
-                return Err("Not enough space in history store".to_string());
-            }
+        // Check if we have enough space; RAM-Lake evicts events to make room beforehand
+        if self.current_size + stored_size > self.max_size {
+            return Err("Not enough space in history store".to_string());
         }
         
-        // Generate file path
+        // Generate blob name
         let file_name = format!("{}.event", id);
-        let file_path = self.path.join(&file_name);
         
-        // Write content to file
-        let mut file = fs::File::create(&file_path)
-            .map_err(|e| format!("Failed to create event file: {}", e))?;
-        
-        file.write_all(content.as_bytes())
+        // Write content to the active segment
+        self.segments.put(&file_name, &data)
             .map_err(|e| format!("Failed to write event content: {}", e))?;
         
         // Create metadata
         let now = Utc::now();
         let metadata = EventMetadata {
             id,
+            namespace: namespace.to_string(),
             event_type: event_type.to_string(),
             size: content_size,
+            stored_size: Some(stored_size),
             file_path: file_name,
             timestamp: now,
             source: source.map(|s| s.to_string()),
             severity: severity.map(|s| s.to_string()),
         };
         
-        // Update index
-        {
-            let mut index = self.index.write();
-            index.ids.push(id);
-            
-            // Add to type map
-            index.type_map.entry(event_type.to_string())
-                .or_insert_with(Vec::new)
-                .push(id);
-            
-            index.count += 1;
-            index.version += 1;
-        }
-        
-        // Update metadata
-        {
-            let mut metadata_lock = self.metadata.write();
-            metadata_lock.insert(id, metadata);
-        }
-        
         // Update size
-        self.current_size += content_size;
+        self.current_size += stored_size;
         
-        // Persist index and metadata
-        self.persist_index()?;
-        self.persist_metadata()?;
+        // Record the change
+        self.log_record(HistoryWalRecord::Put(metadata))?;
         
-        Ok(())
+        Ok(stored_size)
     }
     
-    /// Remove oldest events to free up space
-    fn remove_oldest_events(&mut self, required_space: u64) -> Result<(), String> {
-        // Calculate how much space to free
-        let space_to_free = required_space;
-        
-        // Get oldest events
-        let mut oldest_events = Vec::new();
-        {
-            let index = self.index.read();
-            let metadata = self.metadata.read();
-            
-            // Get oldest events first
-            for &id in &index.ids {
-                if let Some(event_metadata) = metadata.get(&id) {
-                    oldest_events.push((id, event_metadata.timestamp, event_metadata.size));
-                }
-            }
+    /// Put back a previously deleted event with its original metadata
+    ///
+    /// Returns the number of bytes stored.
+    pub fn restore_event(&mut self, metadata: EventMetadata, content: &str) -> Result<u64, String> {
+        if self.metadata.read().contains_key(&metadata.id) {
+            return Err(format!("Event with ID {} already exists", metadata.id));
         }
         
-        // Sort by timestamp (oldest first)
-        oldest_events.sort_by(|a, b| a.1.cmp(&b.1));
+        let data = self.codec.encode(content)?;
+        let stored_size = data.len() as u64;
+        if self.current_size + stored_size > self.max_size {
+            return Err("Not enough space in history store".to_string());
+        }
         
-        // Remove events until we have freed enough space
-        let mut freed_space = 0;
-        let mut removed_ids = Vec::new();
+        self.segments.put(&metadata.file_path, &data)?;
+        self.current_size += stored_size;
         
-        for (id, _, size) in oldest_events {
-            if freed_space >= space_to_free {
-                break;
-            }
-            
-            // Remove event
-            self.delete_event(id)?;
-            
-            freed_space += size;
-            removed_ids.push(id);
-        }
+        // Record the change
+        self.log_record(HistoryWalRecord::Put(EventMetadata {
+            size: content.len() as u64,
+            stored_size: Some(stored_size),
+            ..metadata
+        }))?;
         
-        Ok(())
+        Ok(stored_size)
     }
     
     /// Persist index to disk
@@ -322,13 +466,7 @@ impl HistoryStore {
         let index_path = self.path.join("index.json");
         let index = self.index.read();
         
-        let file = fs::File::create(&index_path)
-            .map_err(|e| format!("Failed to create index file: {}", e))?;
-        
-        serde_json::to_writer_pretty(file, &*index)
-            .map_err(|e| format!("Failed to write index file: {}", e))?;
-        
-        Ok(())
+        write_json_atomic(&index_path, &*index)
     }
     
     /// Persist metadata to disk
@@ -336,13 +474,7 @@ impl HistoryStore {
         let metadata_path = self.path.join("metadata.json");
         let metadata = self.metadata.read();
         
-        let file = fs::File::create(&metadata_path)
-            .map_err(|e| format!("Failed to create metadata file: {}", e))?;
-        
-        serde_json::to_writer_pretty(file, &*metadata)
-            .map_err(|e| format!("Failed to write metadata file: {}", e))?;
-        
-        Ok(())
+        write_json_atomic(&metadata_path, &*metadata)
     }
     
     /// Get an event by UUID
@@ -352,15 +484,8 @@ impl HistoryStore {
         let metadata = metadata_lock.get(&id)
             .ok_or_else(|| format!("Event with ID {} not found", id))?;
         
-        // Open file
-        let file_path = self.path.join(&metadata.file_path);
-        let mut file = fs::File::open(&file_path)
-            .map_err(|e| format!("Failed to open event file: {}", e))?;
-        
         // Read content
-        let mut content = String::new();
-        file.read_to_string(&mut content)
-            .map_err(|e| format!("Failed to read event content: {}", e))?;
+        let content = self.read_blob(&metadata.file_path)?;
         
         // Convert UTC timestamp to local time
         let local_time = chrono::Local.from_utc_datetime(&metadata.timestamp.naive_utc());
@@ -379,49 +504,143 @@ impl HistoryStore {
     /// Delete an event
     pub fn delete_event(&mut self, id: Uuid) -> Result<(), String> {
         // Get metadata
-        let mut metadata_lock = self.metadata.write();
+        let metadata_lock = self.metadata.read();
         let metadata = metadata_lock.get(&id)
             .ok_or_else(|| format!("Event with ID {} not found", id))?;
         
-        // Store event type for index update
-        let event_type = metadata.event_type.clone();
+        // Events in the cold tier take no space in the store
+        let file_name = metadata.file_path.clone();
+        let stored_size = if self.segments.is_cold(&file_name) { 0 } else { metadata.blob_size() };
+        drop(metadata_lock);
         
-        // Remove file
-        let file_path = self.path.join(&metadata.file_path);
-        fs::remove_file(&file_path)
-            .map_err(|e| format!("Failed to remove event file: {}", e))?;
+        // Remove blob
+        self.segments.remove(&file_name)
+            .map_err(|e| format!("Failed to remove event content: {}", e))?;
         
         // Update size
-        self.current_size -= metadata.size;
+        self.current_size -= stored_size;
         
-        // Remove from metadata
-        metadata_lock.remove(&id);
-        drop(metadata_lock);
+        // Record the change
+        self.log_record(HistoryWalRecord::Delete(id))?;
         
-        // Update index
-        {
-            let mut index = self.index.write();
-            index.ids.retain(|&i| i != id);
-            
-            // Remove from type map
-            if let Some(events) = index.type_map.get_mut(&event_type) {
-                events.retain(|&i| i != id);
-                
-                // Remove empty type entries
-                if events.is_empty() {
-                    index.type_map.remove(&event_type);
-                }
+        Ok(())
+    }
+    
+    /// Get the UUIDs of all events
+    pub fn get_ids(&self) -> Vec<Uuid> {
+        self.index.read().ids.clone()
+    }
+    
+    /// Describe the store for incremental backups
+    pub fn backup_state(&self) -> StoreBackupState {
+        StoreBackupState {
+            version: Some(self.index.read().version),
+        }
+    }
+    
+    /// Check the store for orphaned blobs, missing blobs and index mismatches
+    ///
+    /// With `repair` set, orphaned blobs and temporary files are removed, entries
+    /// whose blob is missing are dropped and the index is rebuilt from the metadata.
+    pub fn verify(&mut self, repair: bool) -> Result<Vec<ConsistencyIssue>, String> {
+        let mut issues = check_temp_files(&self.path, "history", repair)?;
+        
+        let blobs = self.segments.keys();
+        let referenced: HashSet<String> = self.metadata.read()
+            .values()
+            .map(|m| m.file_path.clone())
+            .collect();
+        
+        // Blobs without metadata
+        for file in blobs.difference(&referenced) {
+            if repair {
+                self.segments.remove(file)?;
             }
-            
-            index.count -= 1;
-            index.version += 1;
+            issues.push(ConsistencyIssue::OrphanedBlob {
+                store: "history".to_string(),
+                file: file.clone(),
+            });
         }
         
-        // Persist index and metadata
-        self.persist_index()?;
-        self.persist_metadata()?;
+        // Metadata without blobs
+        let missing: Vec<(Uuid, u64)> = self.metadata.read()
+            .values()
+            .filter(|m| !blobs.contains(&m.file_path))
+            .map(|m| (m.id, m.blob_size()))
+            .collect();
+        for (id, size) in missing {
+            if repair {
+                self.current_size = self.current_size.saturating_sub(size);
+                self.log_record(HistoryWalRecord::Delete(id))?;
+            }
+            issues.push(ConsistencyIssue::MissingBlob {
+                store: "history".to_string(),
+                id,
+            });
+        }
         
-        Ok(())
+        // Index count out of sync with the indexed IDs
+        let (count, actual) = {
+            let index = self.index.read();
+            let metadata = self.metadata.read();
+            let unique_ids: HashSet<&Uuid> = index.ids.iter().collect();
+            let in_sync = index.count == index.ids.len()
+                && unique_ids.len() == index.ids.len()
+                && index.ids.iter().all(|id| metadata.contains_key(id))
+                && metadata.len() == index.ids.len();
+            if in_sync {
+                (None, metadata.len())
+            } else {
+                (Some(index.count), metadata.len())
+            }
+        };
+        if let Some(count) = count {
+            if repair {
+                self.rebuild_index();
+            }
+            issues.push(ConsistencyIssue::CountMismatch {
+                store: "history".to_string(),
+                count,
+                actual,
+            });
+        }
+        
+        // Make the repaired state durable
+        if repair && !issues.is_empty() {
+            self.checkpoint()?;
+        }
+        
+        Ok(issues)
+    }
+    
+    /// Rebuild the index from the metadata
+    fn rebuild_index(&self) {
+        let mut index = self.index.write();
+        let metadata = self.metadata.read();
+        
+        // Keep the existing order for known entries, append the rest by timestamp
+        let mut seen = HashSet::new();
+        let mut ids: Vec<Uuid> = index.ids.iter()
+            .filter(|id| metadata.contains_key(id) && seen.insert(**id))
+            .cloned()
+            .collect();
+        let mut remaining: Vec<_> = metadata.values()
+            .filter(|m| !seen.contains(&m.id))
+            .collect();
+        remaining.sort_by_key(|m| m.timestamp);
+        ids.extend(remaining.into_iter().map(|m| m.id));
+        
+        index.type_map.clear();
+        for id in &ids {
+            if let Some(m) = metadata.get(id) {
+                index.type_map.entry(m.event_type.clone())
+                    .or_insert_with(Vec::new)
+                    .push(*id);
+            }
+        }
+        index.count = ids.len();
+        index.ids = ids;
+        index.version += 1;
     }
     
     /// Get the size of the store
@@ -429,6 +648,21 @@ impl HistoryStore {
         self.current_size
     }
     
+    /// Get the maximum size of the store
+    pub fn get_max_size(&self) -> u64 {
+        self.max_size
+    }
+    
+    /// Change the maximum size of the store
+    pub fn set_max_size(&mut self, max_size: u64) {
+        self.max_size = max_size;
+    }
+    
+    /// Get the uncompressed size of all events in the store
+    pub fn get_logical_size(&self) -> u64 {
+        self.metadata.read().values().map(|m| m.size).sum()
+    }
+    
     /// Get the number of events
     pub fn get_event_count(&self) -> usize {
         self.index.read().count
@@ -468,6 +702,28 @@ impl HistoryStore {
             .collect()
     }
     
+    /// Find events in a namespace
+    pub fn find_events_by_namespace(&self, namespace: &str) -> Vec<Uuid> {
+        let metadata_lock = self.metadata.read();
+        metadata_lock.iter()
+            .filter(|&(_, metadata)| metadata.namespace == namespace)
+            .map(|(&id, _)| id)
+            .collect()
+    }
+    
+    /// Get the number of events and the stored size of those in the hot tier per namespace
+    pub fn get_namespace_stats(&self) -> HashMap<String, (usize, u64)> {
+        let mut stats: HashMap<String, (usize, u64)> = HashMap::new();
+        for metadata in self.metadata.read().values() {
+            let entry = stats.entry(metadata.namespace.clone()).or_default();
+            entry.0 += 1;
+            if !self.segments.is_cold(&metadata.file_path) {
+                entry.1 += metadata.blob_size();
+            }
+        }
+        stats
+    }
+    
     /// Find events by source
     pub fn find_events_by_source(&self, source: &str) -> Vec<Uuid> {
         let metadata_lock = self.metadata.read();
@@ -512,15 +768,8 @@ impl HistoryStore {
         
         for &id in &index.ids {
             if let Some(event_metadata) = metadata.get(&id) {
-                // Open file
-                let file_path = self.path.join(&event_metadata.file_path);
-                let mut file = fs::File::open(&file_path)
-                    .map_err(|e| format!("Failed to open event file: {}", e))?;
-                
                 // Read content
-                let mut content = String::new();
-                file.read_to_string(&mut content)
-                    .map_err(|e| format!("Failed to read event content: {}", e))?;
+                let content = self.read_blob(&event_metadata.file_path)?;
                 
                 // Create event
                 let event = Event {
@@ -0,0 +1,517 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use uuid::Uuid;
+use serde::{Serialize, Deserialize};
+
+use super::collection::DistanceMetric;
+
+/// Highest layer a node can be placed on
+const MAX_LEVEL: usize = 16;
+
+/// HNSW index settings of the vector store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HnswConfig {
+    /// Answer searches from the HNSW graph; when disabled every search is an exact scan
+    #[serde(default = "default_hnsw_enabled")]
+    pub enabled: bool,
+
+    /// Links per node on the upper layers (twice as many on the bottom layer)
+    #[serde(default = "default_m")]
+    pub m: usize,
+
+    /// Candidates considered while inserting a node
+    #[serde(default = "default_ef_construction")]
+    pub ef_construction: usize,
+
+    /// Candidates considered while searching; higher values trade speed for recall
+    #[serde(default = "default_ef_search")]
+    pub ef_search: usize,
+}
+
+fn default_hnsw_enabled() -> bool {
+    true
+}
+
+fn default_m() -> usize {
+    16
+}
+
+fn default_ef_construction() -> usize {
+    200
+}
+
+fn default_ef_search() -> usize {
+    64
+}
+
+impl Default for HnswConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_hnsw_enabled(),
+            m: default_m(),
+            ef_construction: default_ef_construction(),
+            ef_search: default_ef_search(),
+        }
+    }
+}
+
+/// Node of the graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HnswNode {
+    /// ID of the embedding
+    id: Uuid,
+
+    /// Neighbour slots per layer, from the bottom layer up to the node's level
+    links: Vec<Vec<u32>>,
+}
+
+/// Node slot with its similarity to a query, ordered by similarity
+#[derive(Debug, Clone, Copy)]
+struct Scored(f32, u32);
+
+impl PartialEq for Scored {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+/// Hierarchical navigable small world graph for approximate similarity search
+///
+/// Only the graph is persisted; the vectors are kept in memory (normalized
+/// for cosine similarity) and handed back with `restore_vectors` after loading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HnswIndex {
+    /// Links per node on the upper layers
+    m: usize,
+
+    /// Candidates considered while inserting a node
+    ef_construction: usize,
+
+    /// Similarity the graph is built for
+    #[serde(default)]
+    metric: DistanceMetric,
+
+    /// Candidates considered while searching
+    #[serde(skip, default = "default_ef_search")]
+    ef_search: usize,
+
+    /// Nodes by slot; slots of removed nodes are reused
+    nodes: Vec<Option<HnswNode>>,
+
+    /// Slot of the node searches start from
+    entry_point: Option<u32>,
+
+    /// Vectors by slot, prepared for the metric
+    #[serde(skip)]
+    vectors: Vec<Vec<f32>>,
+
+    /// Slots by embedding ID
+    #[serde(skip)]
+    slots: HashMap<Uuid, u32>,
+
+    /// Slots of removed nodes, reused by the next inserts
+    #[serde(skip)]
+    free: Vec<u32>,
+
+    /// Slots of the nodes linking to each slot on any layer
+    #[serde(skip)]
+    backlinks: Vec<HashSet<u32>>,
+}
+
+impl HnswIndex {
+    /// Create an empty index for a similarity metric
+    pub fn new(config: &HnswConfig, metric: DistanceMetric) -> Self {
+        Self {
+            m: config.m.max(2),
+            ef_construction: config.ef_construction.max(1),
+            metric,
+            ef_search: config.ef_search.max(1),
+            nodes: Vec::new(),
+            entry_point: None,
+            vectors: Vec::new(),
+            slots: HashMap::new(),
+            free: Vec::new(),
+            backlinks: Vec::new(),
+        }
+    }
+
+    /// Whether the graph was built with the given settings and metric
+    ///
+    /// `ef_search` only affects searches and can change without a rebuild.
+    pub fn is_built_with(&self, config: &HnswConfig, metric: DistanceMetric) -> bool {
+        self.m == config.m.max(2) && self.ef_construction == config.ef_construction.max(1) && self.metric == metric
+    }
+
+    /// Set the candidates considered while searching
+    pub fn set_ef_search(&mut self, ef_search: usize) {
+        self.ef_search = ef_search.max(1);
+    }
+
+    /// Load the vectors of a deserialized index, dropping nodes whose vector is gone
+    pub fn restore_vectors(&mut self, mut load: impl FnMut(Uuid) -> Option<Vec<f32>>) {
+        self.slots = self.nodes.iter()
+            .enumerate()
+            .filter_map(|(slot, node)| node.as_ref().map(|node| (node.id, slot as u32)))
+            .collect();
+        self.vectors = vec![Vec::new(); self.nodes.len()];
+        self.free = (0..self.nodes.len() as u32).rev()
+            .filter(|&slot| self.nodes[slot as usize].is_none())
+            .collect();
+        self.backlinks = vec![HashSet::new(); self.nodes.len()];
+        for (slot, node) in self.nodes.iter().enumerate() {
+            for &target in node.iter().flat_map(|node| node.links.iter().flatten()) {
+                self.backlinks[target as usize].insert(slot as u32);
+            }
+        }
+
+        let mut lost = Vec::new();
+        for (&id, &slot) in &self.slots {
+            match load(id) {
+                Some(vector) => self.vectors[slot as usize] = self.prepare(&vector),
+                None => lost.push(id),
+            }
+        }
+        for id in lost {
+            self.remove(id);
+        }
+    }
+
+    /// Whether an embedding is in the graph
+    pub fn contains(&self, id: Uuid) -> bool {
+        self.slots.contains_key(&id)
+    }
+
+    /// IDs of all embeddings in the graph
+    pub fn ids(&self) -> Vec<Uuid> {
+        self.slots.keys().cloned().collect()
+    }
+
+    /// Add an embedding to the graph, replacing any node with the same ID
+    pub fn insert(&mut self, id: Uuid, vector: &[f32]) {
+        self.remove(id);
+
+        let level = random_level(id, self.m);
+        let node = HnswNode {
+            id,
+            links: vec![Vec::new(); level + 1],
+        };
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.nodes[slot as usize] = Some(node);
+                self.vectors[slot as usize] = self.prepare(vector);
+                slot
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.vectors.push(self.prepare(vector));
+                self.backlinks.push(HashSet::new());
+                (self.nodes.len() - 1) as u32
+            }
+        };
+        self.slots.insert(id, slot);
+
+        let Some(entry_point) = self.entry_point else {
+            self.entry_point = Some(slot);
+            return;
+        };
+
+        // Descend to the node's level through the layers above it
+        let top = self.level(entry_point);
+        let query = self.vectors[slot as usize].clone();
+        let mut entry_points = vec![entry_point];
+        for layer in (level + 1..=top).rev() {
+            entry_points = self.greedy_closest(&query, entry_points, layer);
+        }
+
+        // Link the node on every layer it shares with the graph
+        for layer in (0..=level.min(top)).rev() {
+            let candidates = self.search_layer(&query, &entry_points, self.ef_construction, layer, &|_| true);
+            let neighbours = self.select_neighbours(&candidates, self.max_links(layer));
+            for &neighbour in &neighbours {
+                self.link(neighbour, slot, layer);
+            }
+            self.set_links(slot, layer, neighbours);
+            entry_points = candidates.iter().map(|scored| scored.1).collect();
+        }
+
+        if level > top {
+            self.entry_point = Some(slot);
+        }
+    }
+
+    /// Remove an embedding from the graph, reconnecting its neighbours
+    ///
+    /// Returns whether the embedding was in the graph.
+    pub fn remove(&mut self, id: Uuid) -> bool {
+        let Some(slot) = self.slots.remove(&id) else {
+            return false;
+        };
+        let removed = self.nodes[slot as usize].take().map(|node| node.links).unwrap_or_default();
+        self.vectors[slot as usize] = Vec::new();
+        for &target in removed.iter().flatten() {
+            self.backlinks[target as usize].remove(&slot);
+        }
+
+        // Nodes that linked to the removed node pick new neighbours from its links
+        let mut linking: Vec<u32> = std::mem::take(&mut self.backlinks[slot as usize]).into_iter().collect();
+        linking.sort_unstable();
+        for other in linking {
+            let levels = self.node(other).links.len();
+            for (layer, removed_links) in removed.iter().enumerate().take(levels) {
+                if !self.node(other).links[layer].contains(&slot) {
+                    continue;
+                }
+
+                let mut candidates: Vec<u32> = self.node(other).links[layer].iter()
+                    .chain(removed_links)
+                    .copied()
+                    .filter(|&candidate| candidate != slot && candidate != other)
+                    .collect();
+                candidates.sort_unstable();
+                candidates.dedup();
+
+                let scored = self.score_against(other, &candidates);
+                let links = self.select_neighbours(&scored, self.max_links(layer));
+                self.set_links(other, layer, links);
+            }
+        }
+        self.free.push(slot);
+
+        if self.entry_point == Some(slot) {
+            self.entry_point = self.nodes.iter()
+                .enumerate()
+                .filter_map(|(slot, node)| node.as_ref().map(|node| (node.links.len(), slot as u32)))
+                .max_by_key(|&(levels, slot)| (levels, Reverse(slot)))
+                .map(|(_, slot)| slot);
+        }
+
+        true
+    }
+
+    /// Find the `limit` most similar embeddings accepted by `accept`, most similar first
+    ///
+    /// Rejected embeddings are still traversed, so a selective filter costs time
+    /// rather than recall.
+    pub fn search(&self, query: &[f32], limit: usize, accept: impl Fn(Uuid) -> bool) -> Vec<(Uuid, f32)> {
+        let Some(entry_point) = self.entry_point else {
+            return Vec::new();
+        };
+        if limit == 0 {
+            return Vec::new();
+        }
+
+        let query = self.prepare(query);
+        let mut entry_points = vec![entry_point];
+        for layer in (1..=self.level(entry_point)).rev() {
+            entry_points = self.greedy_closest(&query, entry_points, layer);
+        }
+
+        let accept_slot = |slot: u32| accept(self.node(slot).id);
+        let mut results = self.search_layer(&query, &entry_points, self.ef_search.max(limit), 0, &accept_slot);
+        results.truncate(limit);
+
+        results.into_iter()
+            .map(|Scored(similarity, slot)| (self.node(slot).id, similarity))
+            .collect()
+    }
+
+    /// Most similar node on a layer, following links greedily from the entry points
+    fn greedy_closest(&self, query: &[f32], entry_points: Vec<u32>, layer: usize) -> Vec<u32> {
+        self.search_layer(query, &entry_points, 1, layer, &|_| true)
+            .first()
+            .map(|scored| vec![scored.1])
+            .unwrap_or(entry_points)
+    }
+
+    /// Best-first search of one layer, returning up to `ef` accepted nodes, most similar first
+    fn search_layer(&self, query: &[f32], entry_points: &[u32], ef: usize, layer: usize, accept: &dyn Fn(u32) -> bool) -> Vec<Scored> {
+        let mut visited: HashSet<u32> = entry_points.iter().copied().collect();
+        let mut candidates = BinaryHeap::new();
+        let mut results: BinaryHeap<Reverse<Scored>> = BinaryHeap::new();
+
+        for &slot in entry_points {
+            let scored = Scored(self.similarity(query, &self.vectors[slot as usize]), slot);
+            candidates.push(scored);
+            if accept(slot) {
+                results.push(Reverse(scored));
+            }
+        }
+        while results.len() > ef {
+            results.pop();
+        }
+
+        while let Some(Scored(similarity, slot)) = candidates.pop() {
+            if results.len() >= ef && results.peek().is_some_and(|worst| similarity < worst.0.0) {
+                break;
+            }
+
+            let Some(links) = self.node(slot).links.get(layer) else {
+                continue;
+            };
+            for &neighbour in links {
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+
+                let scored = Scored(self.similarity(query, &self.vectors[neighbour as usize]), neighbour);
+                if results.len() < ef || results.peek().is_some_and(|worst| scored > worst.0) {
+                    candidates.push(scored);
+                    if accept(neighbour) {
+                        results.push(Reverse(scored));
+                        if results.len() > ef {
+                            results.pop();
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut results: Vec<Scored> = results.into_iter().map(|scored| scored.0).collect();
+        results.sort_by(|a, b| b.cmp(a));
+        results
+    }
+
+    /// Pick up to `max` neighbours from candidates sorted by similarity
+    ///
+    /// Prefers candidates closer to the node than to any neighbour already
+    /// picked, which keeps links spread across clusters, then fills up with the
+    /// most similar of the rest.
+    fn select_neighbours(&self, candidates: &[Scored], max: usize) -> Vec<u32> {
+        let mut selected: Vec<u32> = Vec::with_capacity(max);
+        let mut pruned = Vec::new();
+
+        for &Scored(similarity, candidate) in candidates {
+            if selected.len() >= max {
+                break;
+            }
+            let diverse = selected.iter()
+                .all(|&picked| self.similarity(&self.vectors[candidate as usize], &self.vectors[picked as usize]) < similarity);
+            if diverse {
+                selected.push(candidate);
+            } else {
+                pruned.push(candidate);
+            }
+        }
+
+        let missing = max.saturating_sub(selected.len());
+        selected.extend(pruned.into_iter().take(missing));
+        selected
+    }
+
+    /// Add a link from one node to another, pruning the node's links if it has too many
+    fn link(&mut self, from: u32, to: u32, layer: usize) {
+        let max = self.max_links(layer);
+        let mut links = self.node(from).links[layer].clone();
+        if links.contains(&to) {
+            return;
+        }
+        links.push(to);
+        if links.len() > max {
+            let scored = self.score_against(from, &links);
+            links = self.select_neighbours(&scored, max);
+        }
+
+        self.set_links(from, layer, links);
+    }
+
+    /// Replace the links of a node on a layer, keeping the backlinks in step
+    fn set_links(&mut self, slot: u32, layer: usize, links: Vec<u32>) {
+        let previous = std::mem::replace(&mut self.node_mut(slot).links[layer], links);
+
+        let node = self.node(slot);
+        let dropped: Vec<u32> = previous.into_iter()
+            .filter(|target| !node.links.iter().any(|links| links.contains(target)))
+            .collect();
+        let added = node.links[layer].clone();
+        for target in dropped {
+            self.backlinks[target as usize].remove(&slot);
+        }
+        for target in added {
+            self.backlinks[target as usize].insert(slot);
+        }
+    }
+
+    /// Similarity of each candidate to a node, most similar first
+    fn score_against(&self, slot: u32, candidates: &[u32]) -> Vec<Scored> {
+        let vector = &self.vectors[slot as usize];
+        let mut scored: Vec<Scored> = candidates.iter()
+            .map(|&candidate| Scored(self.similarity(vector, &self.vectors[candidate as usize]), candidate))
+            .collect();
+        scored.sort_by(|a, b| b.cmp(a));
+        scored
+    }
+
+    /// Vector as kept in the graph: normalized for cosine similarity, as is otherwise
+    fn prepare(&self, vector: &[f32]) -> Vec<f32> {
+        match self.metric {
+            DistanceMetric::Cosine => normalize(vector),
+            DistanceMetric::Dot | DistanceMetric::L2 => vector.to_vec(),
+        }
+    }
+
+    /// Similarity of two prepared vectors
+    fn similarity(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self.metric {
+            DistanceMetric::Cosine | DistanceMetric::Dot => dot(a, b),
+            DistanceMetric::L2 => self.metric.similarity(a, b),
+        }
+    }
+
+    /// Maximum number of links per node on a layer
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 { self.m * 2 } else { self.m }
+    }
+
+    /// Highest layer of a node
+    fn level(&self, slot: u32) -> usize {
+        self.node(slot).links.len() - 1
+    }
+
+    fn node(&self, slot: u32) -> &HnswNode {
+        self.nodes[slot as usize].as_ref().expect("HNSW link to a removed node")
+    }
+
+    fn node_mut(&mut self, slot: u32) -> &mut HnswNode {
+        self.nodes[slot as usize].as_mut().expect("HNSW link to a removed node")
+    }
+}
+
+/// Layer of a new node, drawn from the random bits of its ID
+///
+/// Layers are exponentially less likely the higher they are, and the same ID
+/// always lands on the same layer, so rebuilding a graph is deterministic.
+fn random_level(id: Uuid, m: usize) -> usize {
+    // The low 53 bits of a v4 UUID are all random
+    let bits = id.as_u128() as u64 & ((1 << 53) - 1);
+    let uniform = (bits + 1) as f64 / (1u64 << 53) as f64;
+    let level = -uniform.ln() / (m as f64).ln();
+
+    (level as usize).min(MAX_LEVEL)
+}
+
+/// Vector scaled to unit length; zero vectors stay zero
+fn normalize(vector: &[f32]) -> Vec<f32> {
+    let norm = dot(vector, vector).sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|value| value / norm).collect()
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
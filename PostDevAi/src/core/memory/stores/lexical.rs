@@ -0,0 +1,198 @@
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// BM25 term frequency saturation
+const BM25_K1: f32 = 1.2;
+
+/// BM25 document length normalization
+const BM25_B: f32 = 0.75;
+
+/// Longest token indexed; longer runs are data rather than words or identifiers
+const MAX_TOKEN_LEN: usize = 64;
+
+/// Indexed text of one code file or event
+#[derive(Debug, Clone)]
+struct LexicalDocument {
+    /// Namespace (project) the document belongs to
+    namespace: String,
+
+    /// Number of tokens
+    length: u32,
+
+    /// Distinct terms, for removing the document's postings
+    terms: Vec<String>,
+}
+
+/// Document matching a lexical query
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexicalMatch {
+    /// ID of the code file or event
+    pub id: Uuid,
+
+    /// BM25 score of the document for the query
+    pub score: f32,
+
+    /// Query terms found in the document
+    pub terms: Vec<String>,
+}
+
+/// BM25 inverted index over the text of code files and events
+///
+/// Text is split into words and identifiers, which are lowercased. Identifiers
+/// in snake_case or camelCase are also indexed by their parts, so
+/// `remove_oldest_events` matches both itself and `events`.
+#[derive(Debug, Default)]
+pub struct LexicalIndex {
+    /// Term frequency of each term per document
+    postings: HashMap<String, HashMap<Uuid, u32>>,
+
+    /// Indexed documents
+    documents: HashMap<Uuid, LexicalDocument>,
+
+    /// Number of tokens over all documents
+    total_length: u64,
+}
+
+impl LexicalIndex {
+    /// Whether a document is indexed
+    pub fn contains(&self, id: Uuid) -> bool {
+        self.documents.contains_key(&id)
+    }
+
+    /// IDs of the indexed documents
+    pub fn ids(&self) -> Vec<Uuid> {
+        self.documents.keys().copied().collect()
+    }
+
+    /// Number of indexed documents
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Whether no document is indexed
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Add or replace the text of a document
+    pub fn insert(&mut self, id: Uuid, namespace: &str, text: &str) {
+        self.remove(id);
+
+        let tokens = tokenize(text);
+        let mut frequencies: HashMap<String, u32> = HashMap::new();
+        for token in &tokens {
+            *frequencies.entry(token.clone()).or_default() += 1;
+        }
+        for (term, &frequency) in &frequencies {
+            self.postings.entry(term.clone()).or_default().insert(id, frequency);
+        }
+
+        self.total_length += tokens.len() as u64;
+        self.documents.insert(id, LexicalDocument {
+            namespace: namespace.to_string(),
+            length: tokens.len() as u32,
+            terms: frequencies.into_keys().collect(),
+        });
+    }
+
+    /// Remove a document, returning whether it was indexed
+    pub fn remove(&mut self, id: Uuid) -> bool {
+        let Some(document) = self.documents.remove(&id) else {
+            return false;
+        };
+
+        for term in &document.terms {
+            if let Some(postings) = self.postings.get_mut(term) {
+                postings.remove(&id);
+                if postings.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+        self.total_length -= document.length as u64;
+
+        true
+    }
+
+    /// Find the `limit` documents scoring highest for a query by BM25, best first
+    ///
+    /// With a namespace, only documents in it are considered.
+    pub fn search(&self, query: &str, limit: usize, namespace: Option<&str>) -> Vec<LexicalMatch> {
+        if limit == 0 || self.documents.is_empty() {
+            return Vec::new();
+        }
+
+        let mut seen = HashSet::new();
+        let terms: Vec<String> = tokenize(query).into_iter().filter(|term| seen.insert(term.clone())).collect();
+
+        let count = self.documents.len() as f32;
+        let average_length = (self.total_length as f32 / count).max(1.0);
+        let mut matches: HashMap<Uuid, LexicalMatch> = HashMap::new();
+        for term in terms {
+            let Some(postings) = self.postings.get(&term) else {
+                continue;
+            };
+
+            let frequency = postings.len() as f32;
+            let idf = (1.0 + (count - frequency + 0.5) / (frequency + 0.5)).ln();
+            for (&id, &term_frequency) in postings {
+                let document = &self.documents[&id];
+                if namespace.is_some_and(|namespace| document.namespace != namespace) {
+                    continue;
+                }
+
+                let term_frequency = term_frequency as f32;
+                let norm = 1.0 - BM25_B + BM25_B * document.length as f32 / average_length;
+                let entry = matches.entry(id).or_insert_with(|| LexicalMatch {
+                    id,
+                    score: 0.0,
+                    terms: Vec::new(),
+                });
+                entry.score += idf * term_frequency * (BM25_K1 + 1.0) / (term_frequency + BM25_K1 * norm);
+                entry.terms.push(term.clone());
+            }
+        }
+
+        let mut matches: Vec<LexicalMatch> = matches.into_values().collect();
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+        matches.truncate(limit);
+        matches
+    }
+}
+
+/// Lowercased words and identifiers of a text, followed by the parts of compound identifiers
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for word in text.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+        if word.is_empty() || word.len() > MAX_TOKEN_LEN {
+            continue;
+        }
+
+        let parts = identifier_parts(word);
+        tokens.push(word.to_lowercase());
+        if parts.len() > 1 {
+            tokens.extend(parts);
+        }
+    }
+    tokens
+}
+
+/// Lowercased parts of a snake_case or camelCase identifier
+fn identifier_parts(word: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    for piece in word.split('_').filter(|piece| !piece.is_empty()) {
+        let mut part = String::new();
+        let mut previous_lower = false;
+        for c in piece.chars() {
+            if c.is_uppercase() && previous_lower && !part.is_empty() {
+                parts.push(std::mem::take(&mut part));
+            }
+            previous_lower = c.is_lowercase() || c.is_ascii_digit();
+            part.extend(c.to_lowercase());
+        }
+        if !part.is_empty() {
+            parts.push(part);
+        }
+    }
+    parts
+}
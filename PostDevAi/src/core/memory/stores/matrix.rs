@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use uuid::Uuid;
+
+use super::collection::DistanceMetric;
+
+/// Floats per lane, the width of one AVX register
+const LANE_WIDTH: usize = 8;
+
+/// Rows scanned per thread; smaller scans stay on the calling thread
+const PARALLEL_SCAN_ROWS: usize = 4096;
+
+/// Eight floats aligned for aligned SIMD loads
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C, align(32))]
+struct Lane([f32; LANE_WIDTH]);
+
+/// Dot product kernel over two rows of lanes
+type DotKernel = fn(&[Lane], &[Lane]) -> f32;
+
+/// Query prepared for scanning the rows
+struct Query {
+    /// Query split into lanes
+    lanes: Vec<Lane>,
+
+    /// Euclidean norm of the query
+    norm: f32,
+
+    /// Metric the rows are ranked by
+    metric: DistanceMetric,
+
+    /// Dot product kernel to use
+    kernel: DotKernel,
+}
+
+/// Resident matrix holding the full-precision embeddings of a collection
+///
+/// Rows are padded with zeros to a whole number of lanes so every row starts
+/// on an aligned boundary. Removing a row moves the last row into its place.
+#[derive(Debug, Default)]
+pub struct EmbeddingMatrix {
+    /// Dimension of the rows; set by the first row inserted into an empty matrix
+    dimension: usize,
+
+    /// Lanes per row
+    row_lanes: usize,
+
+    /// Rows, one after another
+    data: Vec<Lane>,
+
+    /// Euclidean norm of each row
+    norms: Vec<f32>,
+
+    /// Embedding ID of each row
+    ids: Vec<Uuid>,
+
+    /// Row of each embedding ID
+    rows: HashMap<Uuid, usize>,
+}
+
+impl EmbeddingMatrix {
+    /// Whether an embedding has a row
+    pub fn contains(&self, id: Uuid) -> bool {
+        self.rows.contains_key(&id)
+    }
+
+    /// IDs of the embeddings with a row
+    pub fn ids(&self) -> &[Uuid] {
+        &self.ids
+    }
+
+    /// Copy of the row of an embedding
+    pub fn get(&self, id: Uuid) -> Option<Vec<f32>> {
+        let row = *self.rows.get(&id)?;
+        Some(self.row(row).iter().flat_map(|lane| lane.0).take(self.dimension).collect())
+    }
+
+    /// Add or replace the row of an embedding
+    ///
+    /// The caller checks that the vector has the matrix's dimension.
+    pub fn insert(&mut self, id: Uuid, vector: &[f32]) {
+        self.remove(id);
+        if self.ids.is_empty() {
+            self.dimension = vector.len();
+            self.row_lanes = vector.len().div_ceil(LANE_WIDTH);
+            self.data.clear();
+        }
+
+        let lanes = to_lanes(vector, self.row_lanes);
+        self.norms.push(kernel()(&lanes, &lanes).sqrt());
+        self.data.extend(lanes);
+        self.rows.insert(id, self.ids.len());
+        self.ids.push(id);
+    }
+
+    /// Remove the row of an embedding, returning whether it had one
+    pub fn remove(&mut self, id: Uuid) -> bool {
+        let Some(row) = self.rows.remove(&id) else {
+            return false;
+        };
+
+        let last = self.ids.len() - 1;
+        if row != last {
+            let (start, last_start) = (row * self.row_lanes, last * self.row_lanes);
+            self.data.copy_within(last_start..last_start + self.row_lanes, start);
+            self.rows.insert(self.ids[last], row);
+        }
+        self.ids.swap_remove(row);
+        self.norms.swap_remove(row);
+        self.data.truncate(last * self.row_lanes);
+
+        true
+    }
+
+    /// Bytes a row of a dimension takes with its norm
+    pub fn row_size(dimension: usize) -> u64 {
+        (dimension.div_ceil(LANE_WIDTH) * std::mem::size_of::<Lane>() + std::mem::size_of::<f32>()) as u64
+    }
+
+    /// Bytes held by the rows and their norms
+    pub fn size(&self) -> u64 {
+        (self.data.len() * std::mem::size_of::<Lane>() + self.norms.len() * std::mem::size_of::<f32>()) as u64
+    }
+
+    /// Find the `limit` rows most similar to a query under a metric, most similar first
+    ///
+    /// Only rows whose ID is accepted by `accept` are considered. Large matrices
+    /// are scanned by several threads at once.
+    pub fn search(&self, query: &[f32], limit: usize, metric: DistanceMetric, accept: impl Fn(Uuid) -> bool + Sync) -> Vec<(Uuid, f32)> {
+        self.search_batch(&[query], limit, metric, accept).pop().unwrap_or_default()
+    }
+
+    /// Find the `limit` rows most similar to each of several queries in one pass over the rows
+    ///
+    /// Each row is read and checked with `accept` once for all queries. Queries
+    /// without the matrix's dimension get no results.
+    pub fn search_batch(&self, queries: &[&[f32]], limit: usize, metric: DistanceMetric, accept: impl Fn(Uuid) -> bool + Sync) -> Vec<Vec<(Uuid, f32)>> {
+        if limit == 0 || self.ids.is_empty() {
+            return vec![Vec::new(); queries.len()];
+        }
+
+        let kernel = kernel();
+        let prepared: Vec<Option<Query>> = queries.iter()
+            .map(|query| {
+                (query.len() == self.dimension).then(|| {
+                    let lanes = to_lanes(query, self.row_lanes);
+                    Query {
+                        norm: kernel(&lanes, &lanes).sqrt(),
+                        lanes,
+                        metric,
+                        kernel,
+                    }
+                })
+            })
+            .collect();
+
+        let rows = self.ids.len();
+        let threads = std::thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1)
+            .min(rows.div_ceil(PARALLEL_SCAN_ROWS));
+
+        let mut results = if threads <= 1 {
+            self.scan(0..rows, &prepared, limit, &accept)
+        } else {
+            let chunk = rows.div_ceil(threads);
+            std::thread::scope(|scope| {
+                let scans: Vec<_> = (0..rows).step_by(chunk)
+                    .map(|start| {
+                        let range = start..(start + chunk).min(rows);
+                        let (prepared, accept) = (&prepared, &accept);
+                        scope.spawn(move || self.scan(range, prepared, limit, accept))
+                    })
+                    .collect();
+
+                let mut merged = vec![Vec::new(); prepared.len()];
+                for scan in scans {
+                    let scan = scan.join().expect("Matrix scan thread panicked");
+                    for (merged, results) in merged.iter_mut().zip(scan) {
+                        merged.extend(results);
+                    }
+                }
+                merged
+            })
+        };
+
+        results.iter_mut()
+            .map(|results| {
+                results.sort_by(|a, b| b.1.total_cmp(&a.1));
+                results.truncate(limit);
+                results.iter().map(|&(row, similarity)| (self.ids[row], similarity)).collect()
+            })
+            .collect()
+    }
+
+    /// Best `limit` accepted rows of a range for each query, unordered
+    ///
+    /// Candidates are pruned as they pile up, so a scan holds a bounded number
+    /// of them per query.
+    fn scan(&self, range: Range<usize>, queries: &[Option<Query>], limit: usize, accept: &(impl Fn(Uuid) -> bool + Sync)) -> Vec<Vec<(usize, f32)>> {
+        let capacity = 2 * limit.max(32);
+        let mut results = vec![Vec::new(); queries.len()];
+
+        for row in range.filter(|&row| accept(self.ids[row])) {
+            let lanes = self.row(row);
+            for (query, results) in queries.iter().zip(results.iter_mut()) {
+                let Some(query) = query else {
+                    continue;
+                };
+                let dot = (query.kernel)(&query.lanes, lanes);
+                results.push((row, query.metric.score(dot, query.norm, self.norms[row])));
+                if results.len() >= capacity {
+                    prune(results, limit);
+                }
+            }
+        }
+
+        for results in results.iter_mut() {
+            prune(results, limit);
+        }
+        results
+    }
+
+    /// Lanes of a row
+    fn row(&self, row: usize) -> &[Lane] {
+        &self.data[row * self.row_lanes..(row + 1) * self.row_lanes]
+    }
+}
+
+/// Keep the best `limit` candidates, unordered
+fn prune(results: &mut Vec<(usize, f32)>, limit: usize) {
+    if results.len() > limit {
+        results.select_nth_unstable_by(limit, |a, b| b.1.total_cmp(&a.1));
+        results.truncate(limit);
+    }
+}
+
+/// Vector split into lanes, padded with zeros to `lanes` lanes
+fn to_lanes(vector: &[f32], lanes: usize) -> Vec<Lane> {
+    let mut padded = vec![Lane::default(); lanes];
+    for (lane, values) in padded.iter_mut().zip(vector.chunks(LANE_WIDTH)) {
+        lane.0[..values.len()].copy_from_slice(values);
+    }
+    padded
+}
+
+/// Fastest dot product kernel the CPU supports
+fn kernel() -> DotKernel {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+        return dot_avx2;
+    }
+
+    dot_lanes
+}
+
+/// Dot product that the compiler vectorizes for the target's baseline SIMD (SSE2, NEON)
+fn dot_lanes(a: &[Lane], b: &[Lane]) -> f32 {
+    let mut sum = [0.0; LANE_WIDTH];
+    for (a, b) in a.iter().zip(b) {
+        for ((sum, a), b) in sum.iter_mut().zip(a.0).zip(b.0) {
+            *sum += a * b;
+        }
+    }
+    sum.iter().sum()
+}
+
+/// Dot product with AVX2 fused multiply-add
+#[cfg(target_arch = "x86_64")]
+fn dot_avx2(a: &[Lane], b: &[Lane]) -> f32 {
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn dot(a: &[Lane], b: &[Lane]) -> f32 {
+        use std::arch::x86_64::*;
+
+        let mut sum = _mm256_setzero_ps();
+        for (a, b) in a.iter().zip(b) {
+            sum = _mm256_fmadd_ps(_mm256_load_ps(a.0.as_ptr()), _mm256_load_ps(b.0.as_ptr()), sum);
+        }
+        let mut lanes = Lane::default();
+        _mm256_store_ps(lanes.0.as_mut_ptr(), sum);
+        lanes.0.iter().sum()
+    }
+
+    // SAFETY: only selected by `kernel` when the CPU supports AVX2 and FMA, and
+    // lanes are 32-byte aligned as the aligned loads and stores require.
+    unsafe { dot(a, b) }
+}
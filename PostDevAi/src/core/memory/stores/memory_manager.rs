@@ -1,9 +1,17 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet, VecDeque};
 use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use uuid::Uuid;
+
+use super::eviction::{EvictionPolicy, EvictionCandidate, EvictionNeeds, EvictionEvent, LruPolicy};
+
+/// Number of eviction events kept for display
+const EVICTION_HISTORY: usize = 1000;
 
 /// Memory Manager for RAM-Lake
 /// 
-/// Tracks and manages memory allocations
+/// Tracks and manages memory allocations, and decides which entries are
+/// evicted when the RAM-Lake or one of its stores is full
 pub struct MemoryManager {
     /// Maximum memory size in bytes
     max_size: u64,
@@ -13,6 +21,30 @@ pub struct MemoryManager {
     
     /// Allocation history
     allocations: VecDeque<MemoryAllocation>,
+    
+    /// Allocated memory per namespace in bytes
+    namespace_usage: HashMap<String, u64>,
+    
+    /// Maximum memory per namespace in bytes
+    namespace_quotas: HashMap<String, u64>,
+    
+    /// Policy choosing what to evict; `None` disables eviction
+    eviction_policy: Option<Box<dyn EvictionPolicy>>,
+    
+    /// Entities that are never evicted
+    pinned: HashSet<Uuid>,
+    
+    /// Last read or write per entity, recorded under a shared lock
+    access_times: Mutex<HashMap<Uuid, DateTime<Utc>>>,
+    
+    /// Recent evictions, oldest first
+    evictions: VecDeque<EvictionEvent>,
+    
+    /// Number of entries evicted since startup
+    evicted_entries: u64,
+    
+    /// Bytes freed by eviction since startup
+    evicted_size: u64,
 }
 
 /// Memory Allocation
@@ -36,6 +68,9 @@ pub enum MemoryAllocationError {
     
     #[error("Invalid allocation size")]
     InvalidSize,
+    
+    #[error("Quota of namespace {0} exceeded")]
+    QuotaExceeded(String),
 }
 
 impl MemoryManager {
@@ -45,6 +80,14 @@ impl MemoryManager {
             max_size,
             current_size: 0,
             allocations: VecDeque::new(),
+            namespace_usage: HashMap::new(),
+            namespace_quotas: HashMap::new(),
+            eviction_policy: Some(Box::new(LruPolicy)),
+            pinned: HashSet::new(),
+            access_times: Mutex::new(HashMap::new()),
+            evictions: VecDeque::new(),
+            evicted_entries: 0,
+            evicted_size: 0,
         }
     }
     
@@ -112,6 +155,25 @@ impl MemoryManager {
         Ok(())
     }
     
+    /// Record memory that is already in use without checking the limit
+    ///
+    /// Data that survived a restart with a smaller `max_size` may leave usage
+    /// above the limit until eviction or tiering frees enough of it.
+    pub fn record_existing(&mut self, size: u64, source: &str) {
+        self.allocations.push_back(MemoryAllocation {
+            size,
+            source: source.to_string(),
+            timestamp: Utc::now(),
+        });
+        
+        self.current_size += size;
+        
+        // Limit allocation history
+        if self.allocations.len() > 1000 {
+            self.allocations.pop_front();
+        }
+    }
+    
     /// Free memory
     pub fn free(&mut self, size: u64) -> Result<(), MemoryAllocationError> {
         // Check size
@@ -144,6 +206,171 @@ impl MemoryManager {
         Ok(())
     }
     
+    /// Allocate memory on behalf of a namespace, enforcing its quota
+    pub fn allocate_in_namespace(&mut self, namespace: &str, size: u64, source: &str) -> Result<(), MemoryAllocationError> {
+        let used = self.get_namespace_usage(namespace);
+        if let Some(&quota) = self.namespace_quotas.get(namespace) {
+            if used + size > quota {
+                return Err(MemoryAllocationError::QuotaExceeded(namespace.to_string()));
+            }
+        }
+        
+        self.allocate_with_source(size, source)?;
+        self.namespace_usage.insert(namespace.to_string(), used + size);
+        
+        Ok(())
+    }
+    
+    /// Free memory allocated on behalf of a namespace
+    pub fn free_in_namespace(&mut self, namespace: &str, size: u64) -> Result<(), MemoryAllocationError> {
+        self.free(size)?;
+        
+        let used = self.get_namespace_usage(namespace).saturating_sub(size);
+        if used == 0 {
+            self.namespace_usage.remove(namespace);
+        } else {
+            self.namespace_usage.insert(namespace.to_string(), used);
+        }
+        
+        Ok(())
+    }
+    
+    /// Attribute already allocated memory to a namespace without checking its quota
+    pub fn record_namespace_usage(&mut self, namespace: &str, size: u64) {
+        *self.namespace_usage.entry(namespace.to_string()).or_insert(0) += size;
+    }
+    
+    /// Get the memory allocated on behalf of a namespace
+    pub fn get_namespace_usage(&self, namespace: &str) -> u64 {
+        self.namespace_usage.get(namespace).cloned().unwrap_or(0)
+    }
+    
+    /// Get the memory allocated on behalf of every namespace
+    pub fn get_namespace_usages(&self) -> HashMap<String, u64> {
+        self.namespace_usage.clone()
+    }
+    
+    /// Set or clear the quota of a namespace
+    pub fn set_namespace_quota(&mut self, namespace: &str, quota: Option<u64>) {
+        match quota {
+            Some(quota) => self.namespace_quotas.insert(namespace.to_string(), quota),
+            None => self.namespace_quotas.remove(namespace),
+        };
+    }
+    
+    /// Get the quota of a namespace
+    pub fn get_namespace_quota(&self, namespace: &str) -> Option<u64> {
+        self.namespace_quotas.get(namespace).cloned()
+    }
+    
+    /// Set the eviction policy, or disable eviction with `None`
+    pub fn set_eviction_policy(&mut self, policy: Option<Box<dyn EvictionPolicy>>) {
+        self.eviction_policy = policy;
+    }
+    
+    /// Whether an eviction policy is set
+    pub fn eviction_enabled(&self) -> bool {
+        self.eviction_policy.is_some()
+    }
+    
+    /// Protect an entity from eviction
+    pub fn pin(&mut self, id: Uuid) {
+        self.pinned.insert(id);
+    }
+    
+    /// Allow an entity to be evicted again, returning whether it was pinned
+    pub fn unpin(&mut self, id: Uuid) -> bool {
+        self.pinned.remove(&id)
+    }
+    
+    /// Whether an entity is protected from eviction
+    pub fn is_pinned(&self, id: Uuid) -> bool {
+        self.pinned.contains(&id)
+    }
+    
+    /// Get the entities protected from eviction
+    pub fn get_pinned(&self) -> HashSet<Uuid> {
+        self.pinned.clone()
+    }
+    
+    /// Replace the entities protected from eviction
+    pub fn set_pinned(&mut self, pinned: HashSet<Uuid>) {
+        self.pinned = pinned;
+    }
+    
+    /// Record a read or write of an entity
+    pub fn record_access(&self, id: Uuid) {
+        self.access_times.lock().insert(id, Utc::now());
+    }
+    
+    /// Get the last recorded access of an entity
+    pub fn get_last_access(&self, id: Uuid) -> Option<DateTime<Utc>> {
+        self.access_times.lock().get(&id).cloned()
+    }
+    
+    /// Forget the access times of entities that no longer exist
+    pub fn retain_access_times(&self, ids: &HashSet<Uuid>) {
+        self.access_times.lock().retain(|id, _| ids.contains(id));
+    }
+    
+    /// Choose the entries to evict so that `needs` is met
+    ///
+    /// Pinned entries are skipped. Returns fewer entries than needed when the
+    /// unpinned candidates cannot free enough, and none when eviction is disabled.
+    pub fn select_victims(&self, mut candidates: Vec<EvictionCandidate>, mut needs: EvictionNeeds) -> Vec<EvictionCandidate> {
+        let policy = match &self.eviction_policy {
+            Some(policy) => policy,
+            None => return Vec::new(),
+        };
+        
+        candidates.retain(|c| !self.pinned.contains(&c.id));
+        policy.order(&mut candidates);
+        
+        let mut victims = Vec::new();
+        for candidate in candidates {
+            if needs.is_satisfied() {
+                break;
+            }
+            if needs.wants(candidate.kind) {
+                needs.take(candidate.kind, candidate.size);
+                victims.push(candidate);
+            }
+        }
+        
+        victims
+    }
+    
+    /// Record that an entry was evicted, freeing `freed` bytes
+    pub fn record_eviction(&mut self, candidate: &EvictionCandidate, freed: u64) {
+        let policy = self.eviction_policy.as_ref().map_or("none", |p| p.name()).to_string();
+        
+        self.evictions.push_back(EvictionEvent {
+            id: candidate.id,
+            kind: candidate.kind,
+            namespace: candidate.namespace.clone(),
+            size: freed,
+            policy,
+            timestamp: Utc::now(),
+        });
+        if self.evictions.len() > EVICTION_HISTORY {
+            self.evictions.pop_front();
+        }
+        
+        self.evicted_entries += 1;
+        self.evicted_size += freed;
+        self.access_times.lock().remove(&candidate.id);
+    }
+    
+    /// Get recent evictions, newest first
+    pub fn get_recent_evictions(&self, limit: usize) -> Vec<EvictionEvent> {
+        self.evictions.iter().rev().take(limit).cloned().collect()
+    }
+    
+    /// Get the number of entries evicted and the bytes freed since startup
+    pub fn get_eviction_totals(&self) -> (u64, u64) {
+        (self.evicted_entries, self.evicted_size)
+    }
+    
     /// Get current memory usage
     pub fn get_current_usage(&self) -> u64 {
         self.current_size
@@ -156,7 +383,7 @@ impl MemoryManager {
     
     /// Get available memory
     pub fn get_available_memory(&self) -> u64 {
-        self.max_size - self.current_size
+        self.max_size.saturating_sub(self.current_size)
     }
     
     /// Get memory utilization percentage
@@ -182,6 +409,7 @@ impl MemoryManager {
     pub fn reset(&mut self) {
         self.current_size = 0;
         self.allocations.clear();
+        self.namespace_usage.clear();
     }
     
     /// Increase maximum memory size
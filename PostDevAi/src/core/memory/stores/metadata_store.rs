@@ -5,6 +5,11 @@ use uuid::Uuid;
 use serde::{Serialize, Deserialize};
 use parking_lot::RwLock;
 
+use super::wal::{WriteAheadLog, DEFAULT_CHECKPOINT_THRESHOLD};
+use super::consistency::check_temp_files;
+use crate::core::memory::ramlake::{ConsistencyIssue, StoreBackupState, DEFAULT_NAMESPACE};
+use crate::utils::filesystem::write_json_atomic;
+
 /// Metadata Store for RAM-Lake
 /// 
 /// Stores metadata and relations between entities
@@ -20,6 +25,28 @@ pub struct MetadataStore {
     
     /// Relations between entities
     relations: RwLock<RelationGraph>,
+    
+    /// Write-ahead log of changes since the last checkpoint
+    wal: WriteAheadLog,
+    
+    /// Number of WAL records that triggers a checkpoint
+    checkpoint_threshold: usize,
+}
+
+/// Metadata Store WAL record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MetadataWalRecord {
+    /// A relation (source, relation, target) was added
+    Add(Uuid, String, Uuid),
+    
+    /// A relation (source, relation, target) was added to a namespace other than the default
+    AddInNamespace(String, Uuid, String, Uuid),
+    
+    /// A relation (source, relation, target) was removed
+    Remove(Uuid, String, Uuid),
+    
+    /// All relations of an entity were removed
+    RemoveEntity(Uuid),
 }
 
 /// Relation Graph
@@ -39,6 +66,10 @@ pub struct RelationGraph {
     
     /// All relations (source, relation, target)
     pub all_relations: Vec<(Uuid, String, Uuid)>,
+    
+    /// Relations outside the default namespace, by namespace
+    #[serde(default)]
+    pub namespaces: HashMap<String, HashSet<(Uuid, String, Uuid)>>,
 }
 
 impl MetadataStore {
@@ -64,6 +95,7 @@ impl MetadataStore {
                 forward: HashMap::new(),
                 backward: HashMap::new(),
                 all_relations: Vec::new(),
+                namespaces: HashMap::new(),
             }
         };
         
@@ -76,18 +108,87 @@ impl MetadataStore {
             0
         };
         
-        Ok(Self {
+        // Open write-ahead log
+        let wal = WriteAheadLog::open(path.join("wal.log"))?;
+        
+        let mut store = Self {
             path,
             max_size,
             current_size,
             relations: RwLock::new(relations),
-        })
+            wal,
+            checkpoint_threshold: DEFAULT_CHECKPOINT_THRESHOLD,
+        };
+        
+        // Replay changes made since the last checkpoint
+        let records: Vec<MetadataWalRecord> = store.wal.replay()?;
+        for record in &records {
+            store.apply_record(record);
+        }
+        
+        Ok(store)
     }
     
-    /// Store a relation between entities
-    pub fn store_relation(&mut self, source_id: Uuid, relation: &str, target_id: Uuid) -> Result<(), String> {
+    /// Apply a WAL record to the in-memory relation graph
+    fn apply_record(&self, record: &MetadataWalRecord) {
         let mut relations = self.relations.write();
         
+        match record {
+            MetadataWalRecord::Add(source_id, relation, target_id) => {
+                Self::add_relation(&mut relations, DEFAULT_NAMESPACE, *source_id, relation, *target_id);
+            }
+            MetadataWalRecord::AddInNamespace(namespace, source_id, relation, target_id) => {
+                Self::add_relation(&mut relations, namespace, *source_id, relation, *target_id);
+            }
+            MetadataWalRecord::Remove(source_id, relation, target_id) => {
+                Self::remove_relation(&mut relations, *source_id, relation, *target_id);
+            }
+            MetadataWalRecord::RemoveEntity(id) => {
+                Self::remove_entity(&mut relations, *id);
+            }
+        }
+    }
+    
+    /// Append a record to the WAL and apply it, checkpointing when the log grows too long
+    fn log_record(&mut self, record: MetadataWalRecord) -> Result<(), String> {
+        self.wal.append(&record)?;
+        self.apply_record(&record);
+        
+        if self.wal.record_count() >= self.checkpoint_threshold {
+            self.checkpoint()?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Write the relations snapshot and truncate the WAL
+    pub fn checkpoint(&mut self) -> Result<(), String> {
+        self.persist_relations()?;
+        self.wal.reset()
+    }
+    
+    /// Set the number of WAL records that triggers a checkpoint
+    pub fn set_checkpoint_threshold(&mut self, threshold: usize) {
+        self.checkpoint_threshold = threshold.max(1);
+    }
+    
+    /// Store a relation between entities in a namespace
+    pub fn store_relation(&mut self, namespace: &str, source_id: Uuid, relation: &str, target_id: Uuid) -> Result<(), String> {
+        // Check if relation already exists
+        if self.relation_exists(source_id, relation, target_id) {
+            return Ok(());
+        }
+        
+        // Record the change
+        if namespace == DEFAULT_NAMESPACE {
+            self.log_record(MetadataWalRecord::Add(source_id, relation.to_string(), target_id))
+        } else {
+            self.log_record(MetadataWalRecord::AddInNamespace(namespace.to_string(), source_id, relation.to_string(), target_id))
+        }
+    }
+    
+    /// Add a relation to the graph
+    fn add_relation(relations: &mut RelationGraph, namespace: &str, source_id: Uuid, relation: &str, target_id: Uuid) {
         // Check if relation already exists
         let already_exists = relations.forward
             .get(&source_id)
@@ -96,7 +197,7 @@ impl MetadataStore {
             .unwrap_or(false);
         
         if already_exists {
-            return Ok(());
+            return;
         }
         
         // Add to forward relations
@@ -118,37 +219,32 @@ impl MetadataStore {
         // Add to all relations
         relations.all_relations.push((source_id, relation.to_string(), target_id));
         
+        // Remember the namespace unless it is the default
+        if namespace != DEFAULT_NAMESPACE {
+            relations.namespaces
+                .entry(namespace.to_string())
+                .or_default()
+                .insert((source_id, relation.to_string(), target_id));
+        }
+        
         // Update count and version
         relations.count += 1;
         relations.version += 1;
-        
-        // Persist relations
-        drop(relations);
-        self.persist_relations()?;
-        
-        Ok(())
     }
     
     /// Persist relations to disk
-    fn persist_relations(&self) -> Result<(), String> {
+    fn persist_relations(&mut self) -> Result<(), String> {
         let relations_path = self.path.join("relations.json");
         let relations = self.relations.read();
         
-        let file = fs::File::create(&relations_path)
-            .map_err(|e| format!("Failed to create relations file: {}", e))?;
-        
-        serde_json::to_writer_pretty(file, &*relations)
-            .map_err(|e| format!("Failed to write relations file: {}", e))?;
+        write_json_atomic(&relations_path, &*relations)?;
+        drop(relations);
         
         // Update current size
-        let new_size = fs::metadata(&relations_path)
+        self.current_size = fs::metadata(&relations_path)
             .map_err(|e| format!("Failed to read file metadata: {}", e))?
             .len();
         
-        // This is not atomic but should be fine for this use case
-        let mut self_mut = unsafe { &mut *(self as *const Self as *mut Self) };
-        self_mut.current_size = new_size;
-        
         Ok(())
     }
     
@@ -229,8 +325,17 @@ impl MetadataStore {
     
     /// Delete a relation between entities
     pub fn delete_relation(&mut self, source_id: Uuid, relation: &str, target_id: Uuid) -> Result<(), String> {
-        let mut relations = self.relations.write();
+        // Check if relation exists
+        if !self.relation_exists(source_id, relation, target_id) {
+            return Ok(());
+        }
         
+        // Record the change
+        self.log_record(MetadataWalRecord::Remove(source_id, relation.to_string(), target_id))
+    }
+    
+    /// Remove a relation from the graph
+    fn remove_relation(relations: &mut RelationGraph, source_id: Uuid, relation: &str, target_id: Uuid) {
         // Check if relation exists
         let exists = relations.forward
             .get(&source_id)
@@ -239,7 +344,7 @@ impl MetadataStore {
             .unwrap_or(false);
         
         if !exists {
-            return Ok(());
+            return;
         }
         
         // Remove from forward relations
@@ -279,21 +384,36 @@ impl MetadataStore {
         // Remove from all relations
         relations.all_relations.retain(|&(s, ref r, t)| !(s == source_id && r == relation && t == target_id));
         
+        // Remove from its namespace
+        let key = (source_id, relation.to_string(), target_id);
+        relations.namespaces.retain(|_, members| {
+            members.remove(&key);
+            !members.is_empty()
+        });
+        
         // Update count and version
         relations.count -= 1;
         relations.version += 1;
-        
-        // Persist relations
-        drop(relations);
-        self.persist_relations()?;
-        
-        Ok(())
     }
     
     /// Delete all relations for an entity
     pub fn delete_entity_relations(&mut self, id: Uuid) -> Result<(), String> {
-        let mut relations = self.relations.write();
+        // Nothing to do if the entity has no relations
+        let has_relations = {
+            let relations = self.relations.read();
+            relations.forward.contains_key(&id) || relations.backward.contains_key(&id)
+        };
         
+        if !has_relations {
+            return Ok(());
+        }
+        
+        // Record the change
+        self.log_record(MetadataWalRecord::RemoveEntity(id))
+    }
+    
+    /// Remove all relations involving an entity from the graph
+    fn remove_entity(relations: &mut RelationGraph, id: Uuid) {
         // Get all relations involving this entity
         let mut to_delete = Vec::new();
         
@@ -355,15 +475,15 @@ impl MetadataStore {
         // Remove from all relations
         relations.all_relations.retain(|&(s, _, t)| s != id && t != id);
         
+        // Remove from their namespaces
+        relations.namespaces.retain(|_, members| {
+            members.retain(|(s, _, t)| *s != id && *t != id);
+            !members.is_empty()
+        });
+        
         // Update count and version
         relations.count -= to_delete.len();
         relations.version += 1;
-        
-        // Persist relations
-        drop(relations);
-        self.persist_relations()?;
-        
-        Ok(())
     }
     
     /// Get all relations
@@ -372,6 +492,32 @@ impl MetadataStore {
         relations.all_relations.clone()
     }
     
+    /// Get the namespace a relation belongs to
+    pub fn get_relation_namespace(&self, source_id: Uuid, relation: &str, target_id: Uuid) -> String {
+        let relations = self.relations.read();
+        let key = (source_id, relation.to_string(), target_id);
+        
+        relations.namespaces.iter()
+            .find(|(_, members)| members.contains(&key))
+            .map(|(namespace, _)| namespace.clone())
+            .unwrap_or_else(|| DEFAULT_NAMESPACE.to_string())
+    }
+    
+    /// Get the number of relations per namespace
+    pub fn get_namespace_relation_counts(&self) -> HashMap<String, usize> {
+        let relations = self.relations.read();
+        
+        let mut counts: HashMap<String, usize> = relations.namespaces.iter()
+            .map(|(namespace, members)| (namespace.clone(), members.len()))
+            .collect();
+        let namespaced: usize = counts.values().sum();
+        if relations.all_relations.len() > namespaced {
+            counts.insert(DEFAULT_NAMESPACE.to_string(), relations.all_relations.len() - namespaced);
+        }
+        
+        counts
+    }
+    
     /// Get relations by type
     pub fn get_relations_by_type(&self, relation_type: &str) -> Vec<(Uuid, Uuid)> {
         let relations = self.relations.read();
@@ -410,9 +556,77 @@ impl MetadataStore {
         result.into_iter().collect()
     }
     
+    /// Check the relation graph for dangling relations and count mismatches
+    ///
+    /// `known_ids` holds every entity ID present in the other stores. With `repair`
+    /// set, relations pointing at unknown entities are deleted and the count is fixed.
+    pub fn verify(&mut self, known_ids: &HashSet<Uuid>, repair: bool) -> Result<Vec<ConsistencyIssue>, String> {
+        let mut issues = check_temp_files(&self.path, "metadata", repair)?;
+        
+        // Relations pointing at unknown entities
+        let dangling: Vec<(Uuid, String, Uuid)> = self.relations.read()
+            .all_relations
+            .iter()
+            .filter(|(source, _, target)| !known_ids.contains(source) || !known_ids.contains(target))
+            .cloned()
+            .collect();
+        for (source_id, relation, target_id) in dangling {
+            if repair {
+                self.delete_relation(source_id, &relation, target_id)?;
+            }
+            issues.push(ConsistencyIssue::DanglingRelation {
+                source_id,
+                relation,
+                target_id,
+            });
+        }
+        
+        // Count out of sync with the relation list
+        let (count, actual) = {
+            let relations = self.relations.read();
+            (relations.count, relations.all_relations.len())
+        };
+        if count != actual {
+            if repair {
+                let mut relations = self.relations.write();
+                relations.count = relations.all_relations.len();
+                relations.version += 1;
+            }
+            issues.push(ConsistencyIssue::CountMismatch {
+                store: "metadata".to_string(),
+                count,
+                actual,
+            });
+        }
+        
+        // Make the repaired state durable
+        if repair && !issues.is_empty() {
+            self.checkpoint()?;
+        }
+        
+        Ok(issues)
+    }
+    
     /// Get the size of the store
     pub fn get_size(&self) -> u64 {
-        self.current_size
+        self.current_size + self.wal.size()
+    }
+    
+    /// Get the maximum size of the store
+    pub fn get_max_size(&self) -> u64 {
+        self.max_size
+    }
+    
+    /// Change the maximum size of the store
+    pub fn set_max_size(&mut self, max_size: u64) {
+        self.max_size = max_size;
+    }
+    
+    /// Describe the store for incremental backups
+    pub fn backup_state(&self) -> StoreBackupState {
+        StoreBackupState {
+            version: Some(self.relations.read().version),
+        }
     }
     
     /// Get the number of relations
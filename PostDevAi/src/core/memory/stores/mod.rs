@@ -4,11 +4,36 @@ mod code_store;
 mod history_store;
 mod metadata_store;
 mod memory_manager;
+mod wal;
+mod consistency;
+mod compression;
+mod segment;
+mod eviction;
+mod tiering;
+mod hnsw;
+mod matrix;
+mod collection;
+mod quantization;
+mod filter;
+mod lexical;
+mod chunking;
 
 // Public API
-pub use vector_store::VectorStore;
-pub use code_store::CodeStore;
-pub use history_store::HistoryStore;
+pub use vector_store::{VectorStore, NewEmbedding};
+pub use code_store::{CodeStore, CodeMetadata, FileRevision, RevisionRetention};
+pub use history_store::{HistoryStore, EventMetadata};
 pub use metadata_store::MetadataStore;
 pub use memory_manager::MemoryManager;
-pub use memory_manager::MemoryAllocationError;
\ No newline at end of file
+pub use memory_manager::MemoryAllocationError;
+pub use compression::BlobCompression;
+pub use segment::SegmentStorage;
+pub use tiering::{ColdTier, StorageTier};
+pub use hnsw::HnswConfig;
+pub use collection::{VectorCollection, VectorCollectionMetrics, DistanceMetric, DEFAULT_COLLECTION};
+pub use quantization::Quantization;
+pub(crate) use collection::default_collection;
+pub use filter::{EmbeddingSource, VectorFilter, CODE_EMBEDDING, EVENT_EMBEDDING, UNKNOWN_EMBEDDING};
+pub use lexical::{LexicalIndex, LexicalMatch};
+pub use chunking::{CodeChunk, chunk_code};
+pub use eviction::{EntityKind, EvictionPolicyKind, EvictionConfig, EvictionCandidate, EvictionPolicy, LruPolicy, PriorityPolicy, EvictionNeeds, EvictionEvent};
+pub(crate) use wal::WriteAheadLog;
\ No newline at end of file
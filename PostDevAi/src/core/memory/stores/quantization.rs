@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use super::collection::DistanceMetric;
+
+/// Centroids per subvector of a product quantization codebook
+const PRODUCT_CENTROIDS: usize = 256;
+
+/// Rows needed before a product quantization codebook is trained
+const MIN_TRAINING_ROWS: usize = PRODUCT_CENTROIDS;
+
+/// Rows a codebook is trained on at most
+const MAX_TRAINING_ROWS: usize = 16384;
+
+/// K-means iterations when training a codebook
+const TRAINING_ITERATIONS: usize = 8;
+
+/// How the resident vectors of a collection are compressed
+///
+/// Quantized collections are searched by scanning the compressed vectors and
+/// re-ranking the best candidates with the full-precision embeddings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Quantization {
+    /// Full-precision `f32` vectors
+    #[default]
+    None,
+
+    /// One signed byte per value with a scale per vector
+    Int8,
+
+    /// One byte per subvector, the index of its nearest trained centroid
+    ///
+    /// Until the collection has enough embeddings to train the codebook, its
+    /// vectors are kept as int8.
+    Product {
+        /// Number of subvectors each embedding is split into
+        subvectors: usize,
+    },
+}
+
+impl Quantization {
+    /// Check that the quantization fits embeddings of a dimension
+    pub fn validate(&self, dimension: usize) -> Result<(), String> {
+        if let Quantization::Product { subvectors } = *self {
+            if subvectors == 0 || subvectors > dimension {
+                return Err(format!(
+                    "Product quantization needs between 1 and {} subvectors, got {}",
+                    dimension, subvectors
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Trained centroids of each subvector
+#[derive(Debug, Clone)]
+struct Codebook {
+    /// Value range of each subvector
+    ranges: Vec<Range<usize>>,
+
+    /// Centroids of each subvector, one after another
+    centroids: Vec<Vec<f32>>,
+}
+
+impl Codebook {
+    /// Train a codebook with k-means on a sample of vectors
+    fn train(vectors: &[&[f32]], dimension: usize, subvectors: usize) -> Self {
+        let ranges: Vec<Range<usize>> = (0..subvectors)
+            .map(|i| i * dimension / subvectors..(i + 1) * dimension / subvectors)
+            .collect();
+        let step = vectors.len().div_ceil(MAX_TRAINING_ROWS).max(1);
+        let sample: Vec<&[f32]> = vectors.iter().step_by(step).copied().collect();
+        let count = sample.len().min(PRODUCT_CENTROIDS);
+
+        let centroids = ranges.iter()
+            .map(|range| {
+                let points: Vec<&[f32]> = sample.iter().map(|vector| &vector[range.clone()]).collect();
+                kmeans(&points, count)
+            })
+            .collect();
+
+        Self { ranges, centroids }
+    }
+
+    /// Centroid index of each subvector of a vector
+    fn encode(&self, vector: &[f32]) -> Vec<u8> {
+        self.ranges.iter()
+            .zip(&self.centroids)
+            .map(|(range, centroids)| nearest(&vector[range.clone()], centroids, range.len()) as u8)
+            .collect()
+    }
+
+    /// Per subvector and centroid: its dot product with the query, or its
+    /// squared distance to the query for L2
+    fn query_table(&self, query: &[f32], metric: DistanceMetric) -> Vec<Vec<f32>> {
+        self.ranges.iter()
+            .zip(&self.centroids)
+            .map(|(range, centroids)| {
+                let part = &query[range.clone()];
+                centroids.chunks_exact(range.len())
+                    .map(|centroid| match metric {
+                        DistanceMetric::L2 => squared_distance(part, centroid),
+                        DistanceMetric::Cosine | DistanceMetric::Dot => dot(part, centroid),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Number of stored values
+    fn len(&self) -> usize {
+        self.centroids.iter().map(Vec::len).sum()
+    }
+}
+
+/// Compressed vectors of one collection
+#[derive(Debug, Clone)]
+pub(super) struct QuantizedMatrix {
+    /// Dimension of the vectors
+    dimension: usize,
+
+    /// Subvectors requested for product quantization
+    subvectors: Option<usize>,
+
+    /// Trained codebook; int8 codes are used until there is one
+    codebook: Option<Codebook>,
+
+    /// Rows the codebook was trained with
+    trained_rows: usize,
+
+    /// Codes of each row, one row after another
+    codes: Vec<u8>,
+
+    /// Scale of each row's int8 codes
+    scales: Vec<f32>,
+
+    /// Full-precision Euclidean norm of each row
+    norms: Vec<f32>,
+
+    /// Embedding ID of each row
+    ids: Vec<Uuid>,
+
+    /// Row of each embedding ID
+    rows: HashMap<Uuid, usize>,
+}
+
+impl QuantizedMatrix {
+    /// Create an empty matrix for a quantization other than `None`
+    pub fn new(dimension: usize, quantization: Quantization) -> Self {
+        let subvectors = match quantization {
+            Quantization::Product { subvectors } => Some(subvectors),
+            Quantization::None | Quantization::Int8 => None,
+        };
+
+        Self {
+            dimension,
+            subvectors,
+            codebook: None,
+            trained_rows: 0,
+            codes: Vec::new(),
+            scales: Vec::new(),
+            norms: Vec::new(),
+            ids: Vec::new(),
+            rows: HashMap::new(),
+        }
+    }
+
+    /// Whether an embedding has a row
+    pub fn contains(&self, id: Uuid) -> bool {
+        self.rows.contains_key(&id)
+    }
+
+    /// IDs of the embeddings with a row
+    pub fn ids(&self) -> &[Uuid] {
+        &self.ids
+    }
+
+    /// Whether product quantization should (re)train its codebook
+    ///
+    /// The codebook is trained once enough rows exist and retrained whenever
+    /// the collection has doubled since.
+    pub fn needs_training(&self) -> bool {
+        self.subvectors.is_some()
+            && self.ids.len() >= MIN_TRAINING_ROWS
+            && (self.codebook.is_none() || self.ids.len() >= 2 * self.trained_rows)
+    }
+
+    /// Train the product quantization codebook and re-encode every row
+    ///
+    /// `vectors` holds the full-precision vector of every row.
+    pub fn train(&mut self, vectors: &HashMap<Uuid, Vec<f32>>) {
+        let Some(subvectors) = self.subvectors else {
+            return;
+        };
+        let sample: Vec<&[f32]> = self.ids.iter()
+            .filter_map(|id| vectors.get(id).map(Vec::as_slice))
+            .collect();
+        if sample.is_empty() {
+            return;
+        }
+
+        self.codebook = Some(Codebook::train(&sample, self.dimension, subvectors));
+        self.trained_rows = self.ids.len();
+
+        let ids = std::mem::take(&mut self.ids);
+        self.codes.clear();
+        self.scales.clear();
+        self.norms.clear();
+        self.rows.clear();
+        for id in ids {
+            if let Some(vector) = vectors.get(&id) {
+                self.insert(id, vector);
+            }
+        }
+    }
+
+    /// Add or replace the row of an embedding
+    pub fn insert(&mut self, id: Uuid, vector: &[f32]) {
+        self.remove(id);
+
+        match &self.codebook {
+            Some(codebook) => self.codes.extend(codebook.encode(vector)),
+            None => {
+                let max = vector.iter().fold(0.0f32, |max, value| max.max(value.abs()));
+                let scale = max / i8::MAX as f32;
+                self.codes.extend(vector.iter().map(|&value| {
+                    let code = if scale == 0.0 { 0.0 } else { (value / scale).round() };
+                    code as i8 as u8
+                }));
+                self.scales.push(scale);
+            }
+        }
+        self.norms.push(dot(vector, vector).sqrt());
+        self.rows.insert(id, self.ids.len());
+        self.ids.push(id);
+    }
+
+    /// Remove the row of an embedding, returning whether it had one
+    pub fn remove(&mut self, id: Uuid) -> bool {
+        let Some(row) = self.rows.remove(&id) else {
+            return false;
+        };
+
+        let width = self.code_len();
+        let last = self.ids.len() - 1;
+        if row != last {
+            self.codes.copy_within(last * width..(last + 1) * width, row * width);
+            self.rows.insert(self.ids[last], row);
+        }
+        self.ids.swap_remove(row);
+        self.norms.swap_remove(row);
+        if self.codebook.is_none() {
+            self.scales.swap_remove(row);
+        }
+        self.codes.truncate(last * width);
+
+        true
+    }
+
+    /// Find the `limit` rows most similar to a query by approximate similarity
+    ///
+    /// Only rows whose ID is accepted by `accept` are considered.
+    pub fn search(&self, query: &[f32], limit: usize, metric: DistanceMetric, accept: impl Fn(Uuid) -> bool) -> Vec<(Uuid, f32)> {
+        if limit == 0 || self.ids.is_empty() || query.len() != self.dimension {
+            return Vec::new();
+        }
+
+        let query_norm = dot(query, query).sqrt();
+        let table = self.codebook.as_ref().map(|codebook| codebook.query_table(query, metric));
+        let width = self.code_len();
+
+        let mut results: Vec<(usize, f32)> = (0..self.ids.len())
+            .filter(|&row| accept(self.ids[row]))
+            .map(|row| {
+                let codes = &self.codes[row * width..(row + 1) * width];
+                let similarity = match &table {
+                    Some(table) => {
+                        let sum: f32 = codes.iter().zip(table).map(|(&code, values)| values[code as usize]).sum();
+                        match metric {
+                            DistanceMetric::L2 => -sum.max(0.0).sqrt(),
+                            _ => metric.score(sum, query_norm, self.norms[row]),
+                        }
+                    }
+                    None => {
+                        let sum: f32 = codes.iter().zip(query).map(|(&code, value)| code as i8 as f32 * value).sum();
+                        metric.score(sum * self.scales[row], query_norm, self.norms[row])
+                    }
+                };
+                (row, similarity)
+            })
+            .collect();
+
+        if results.len() > limit {
+            results.select_nth_unstable_by(limit, |a, b| b.1.total_cmp(&a.1));
+            results.truncate(limit);
+        }
+        results.sort_by(|a, b| b.1.total_cmp(&a.1));
+        results.into_iter().map(|(row, similarity)| (self.ids[row], similarity)).collect()
+    }
+
+    /// Bytes held by the codes, scales, norms and codebook
+    pub fn size(&self) -> u64 {
+        let floats = self.scales.len() + self.norms.len() + self.codebook.as_ref().map_or(0, Codebook::len);
+        (self.codes.len() + floats * std::mem::size_of::<f32>()) as u64
+    }
+
+    /// Bytes a row takes with a quantization, once any codebook is trained
+    ///
+    /// The codebook itself is shared by every row and not included.
+    pub fn row_size(dimension: usize, quantization: Quantization) -> u64 {
+        let float = std::mem::size_of::<f32>();
+        match quantization {
+            Quantization::Product { subvectors } => (subvectors + float) as u64,
+            Quantization::None | Quantization::Int8 => (dimension + 2 * float) as u64,
+        }
+    }
+
+    /// Bytes of codes per row
+    fn code_len(&self) -> usize {
+        self.codebook.as_ref().map_or(self.dimension, |codebook| codebook.ranges.len())
+    }
+}
+
+/// K-means centroids of a set of points, one after another
+///
+/// Centroids start at evenly spaced points, so training is deterministic.
+fn kmeans(points: &[&[f32]], count: usize) -> Vec<f32> {
+    let width = points[0].len();
+    let step = points.len() / count;
+    let mut centroids: Vec<f32> = (0..count).flat_map(|i| points[i * step].iter().copied()).collect();
+
+    let mut assignments = vec![0; points.len()];
+    for _ in 0..TRAINING_ITERATIONS {
+        for (assignment, point) in assignments.iter_mut().zip(points) {
+            *assignment = nearest(point, &centroids, width);
+        }
+
+        let mut sums = vec![0.0; count * width];
+        let mut counts = vec![0usize; count];
+        for (&assignment, point) in assignments.iter().zip(points) {
+            counts[assignment] += 1;
+            for (sum, value) in sums[assignment * width..(assignment + 1) * width].iter_mut().zip(point.iter()) {
+                *sum += value;
+            }
+        }
+        // Empty clusters keep their centroid
+        for (i, &members) in counts.iter().enumerate().filter(|(_, &members)| members > 0) {
+            for (centroid, sum) in centroids[i * width..(i + 1) * width].iter_mut().zip(&sums[i * width..(i + 1) * width]) {
+                *centroid = sum / members as f32;
+            }
+        }
+    }
+
+    centroids
+}
+
+/// Index of the centroid closest to a point
+fn nearest(point: &[f32], centroids: &[f32], width: usize) -> usize {
+    centroids.chunks_exact(width)
+        .map(|centroid| squared_distance(point, centroid))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(i, _)| i)
+}
+
+fn squared_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
@@ -0,0 +1,576 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use memmap2::Mmap;
+use parking_lot::RwLock;
+use serde::{Serialize, Deserialize};
+
+use crate::utils::filesystem::temp_path_for;
+
+/// Extension of segment files
+const SEGMENT_EXTENSION: &str = "seg";
+
+/// Record storing a blob
+const RECORD_PUT: u8 = 1;
+
+/// Record deleting a blob
+const RECORD_DELETE: u8 = 2;
+
+/// Bytes before the key of a record: type, key length (u16) and data length (u32)
+const HEADER_SIZE: u64 = 7;
+
+/// Default size in bytes after which the active segment is sealed
+pub const DEFAULT_SEGMENT_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Segment storage settings of the vector, code and history stores
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentStorage {
+    /// Size in bytes after which the active segment is sealed
+    #[serde(default = "default_segment_size")]
+    pub segment_size: u64,
+
+    /// Compact a store once this fraction of its sealed segment bytes is reclaimable
+    #[serde(default = "default_compaction_ratio")]
+    pub compaction_ratio: f64,
+
+    /// Seconds between background compaction runs
+    #[serde(default = "default_compaction_interval")]
+    pub compaction_interval: u64,
+}
+
+fn default_segment_size() -> u64 {
+    DEFAULT_SEGMENT_SIZE
+}
+
+fn default_compaction_ratio() -> f64 {
+    0.5
+}
+
+fn default_compaction_interval() -> u64 {
+    300
+}
+
+impl Default for SegmentStorage {
+    fn default() -> Self {
+        Self {
+            segment_size: default_segment_size(),
+            compaction_ratio: default_compaction_ratio(),
+            compaction_interval: default_compaction_interval(),
+        }
+    }
+}
+
+/// Append-only segment storage for the blobs of a store
+///
+/// Blobs are appended as records to the active segment file, which is sealed
+/// once it reaches the segment size. Deleting a blob appends a tombstone. An
+/// in-memory offset index maps every live blob to its record; it is rebuilt by
+/// scanning the segments on open. Reads go through memory maps of the segments.
+///
+/// Compaction rewrites the live blobs of all sealed segments into one file
+/// named after the range of segments it replaces (`0000000001-0000000007.seg`).
+/// A tombstone only shadows records older than itself, which all lie in the
+/// same range, so compaction drops tombstones along with the records they
+/// delete. A segment whose range lies within another one is a leftover of an
+/// interrupted compaction and is removed on open.
+pub struct SegmentLog {
+    /// Store directory holding the segment files
+    path: PathBuf,
+
+    /// Size in bytes after which the active segment is sealed
+    segment_size: u64,
+
+    /// Segments keyed by the last segment ID they cover, oldest first
+    segments: BTreeMap<u32, Segment>,
+
+    /// Append handle of the active (newest) segment
+    active: fs::File,
+
+    /// Location of every live blob by key
+    index: HashMap<String, BlobLocation>,
+}
+
+/// A segment file
+struct Segment {
+    /// First segment ID the file covers
+    first: u32,
+
+    /// Path to the segment file
+    path: PathBuf,
+
+    /// Bytes written to the file
+    size: u64,
+
+    /// Bytes of superseded records, deleted records and tombstones
+    dead: u64,
+
+    /// Read-only map of the file, remapped when it no longer covers a read
+    map: RwLock<Option<Mmap>>,
+}
+
+/// Where a blob's record lives
+#[derive(Debug, Clone, Copy)]
+struct BlobLocation {
+    /// Segment holding the record (its last segment ID)
+    segment: u32,
+
+    /// Offset of the record in the segment
+    offset: u64,
+
+    /// Offset of the blob data in the segment
+    data_offset: u64,
+
+    /// Length of the blob data in bytes
+    len: u64,
+}
+
+impl BlobLocation {
+    /// Length of the whole record in bytes
+    fn record_len(&self) -> u64 {
+        self.data_offset + self.len - self.offset
+    }
+}
+
+/// A record parsed from a segment
+struct Record<'a> {
+    kind: u8,
+    key: &'a str,
+    location: BlobLocation,
+}
+
+impl Segment {
+    fn new(first: u32, path: PathBuf, size: u64) -> Self {
+        Self {
+            first,
+            path,
+            size,
+            dead: 0,
+            map: RwLock::new(None),
+        }
+    }
+
+    /// Map the segment file
+    fn map_file(&self) -> Result<Mmap, String> {
+        let file = fs::File::open(&self.path)
+            .map_err(|e| format!("Failed to open segment {}: {}", self.path.display(), e))?;
+
+        // SAFETY: segment files are only ever appended to while the store is open;
+        // the torn tail is truncated on open before any map exists and compaction
+        // replaces whole files, which leaves existing maps valid.
+        unsafe { Mmap::map(&file) }
+            .map_err(|e| format!("Failed to map segment {}: {}", self.path.display(), e))
+    }
+}
+
+/// File name of the segment covering IDs `first` to `last`
+fn segment_name(first: u32, last: u32) -> String {
+    format!("{:010}-{:010}.{}", first, last, SEGMENT_EXTENSION)
+}
+
+/// Parse the ID range from a segment file name
+fn parse_segment_name(name: &str) -> Option<(u32, u32)> {
+    let (first, last) = name.strip_suffix(&format!(".{}", SEGMENT_EXTENSION))?.split_once('-')?;
+    Some((first.parse().ok()?, last.parse().ok()?))
+}
+
+/// Serialize a record
+fn encode_record(kind: u8, key: &str, data: &[u8]) -> Vec<u8> {
+    let mut record = Vec::with_capacity(HEADER_SIZE as usize + key.len() + data.len());
+    record.push(kind);
+    record.extend_from_slice(&(key.len() as u16).to_le_bytes());
+    record.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    record.extend_from_slice(key.as_bytes());
+    record.extend_from_slice(data);
+    record
+}
+
+/// Parse the record at `offset`, or None at the end of the segment or a torn record
+fn decode_record(bytes: &[u8], segment: u32, offset: u64) -> Option<Record<'_>> {
+    let start = offset as usize;
+    let header = bytes.get(start..start + HEADER_SIZE as usize)?;
+    let kind = header[0];
+    if kind != RECORD_PUT && kind != RECORD_DELETE {
+        return None;
+    }
+
+    let key_len = u16::from_le_bytes([header[1], header[2]]) as u64;
+    let len = u32::from_le_bytes([header[3], header[4], header[5], header[6]]) as u64;
+    let data_offset = offset + HEADER_SIZE + key_len;
+    if data_offset + len > bytes.len() as u64 {
+        return None;
+    }
+
+    let key = std::str::from_utf8(&bytes[(offset + HEADER_SIZE) as usize..data_offset as usize]).ok()?;
+
+    Some(Record {
+        kind,
+        key,
+        location: BlobLocation {
+            segment,
+            offset,
+            data_offset,
+            len,
+        },
+    })
+}
+
+impl SegmentLog {
+    /// Open the segment log of a store directory, creating the first segment if needed
+    pub fn open(path: &Path, segment_size: u64) -> Result<Self, String> {
+        let mut ranges = Vec::new();
+        for entry in fs::read_dir(path).map_err(|e| format!("Failed to read store directory: {}", e))? {
+            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+            if let Some(range) = parse_segment_name(&entry.file_name().to_string_lossy()) {
+                ranges.push(range);
+            }
+        }
+
+        // Segments already merged by a compaction that was interrupted before removing them
+        let superseded: Vec<(u32, u32)> = ranges.iter()
+            .filter(|&&(first, last)| ranges.iter().any(|&(other_first, other_last)| {
+                (other_first, other_last) != (first, last) && other_first <= first && last <= other_last
+            }))
+            .cloned()
+            .collect();
+        for &(first, last) in &superseded {
+            fs::remove_file(path.join(segment_name(first, last)))
+                .map_err(|e| format!("Failed to remove compacted segment: {}", e))?;
+        }
+        ranges.retain(|range| !superseded.contains(range));
+        ranges.sort_by_key(|&(_, last)| last);
+
+        let mut segments = BTreeMap::new();
+        let mut index: HashMap<String, BlobLocation> = HashMap::new();
+
+        for (first, last) in ranges {
+            let segment_path = path.join(segment_name(first, last));
+            let segment = Segment::new(first, segment_path.clone(), 0);
+            let file_size = fs::metadata(&segment_path)
+                .map_err(|e| format!("Failed to read segment metadata: {}", e))?
+                .len();
+            let map = if file_size > 0 { Some(segment.map_file()?) } else { None };
+            let data: &[u8] = map.as_deref().unwrap_or_default();
+            segments.insert(last, segment);
+
+            // Rebuild the offset index, later records winning
+            let mut offset = 0;
+            while let Some(record) = decode_record(data, last, offset) {
+                let previous = match record.kind {
+                    RECORD_PUT => index.insert(record.key.to_string(), record.location),
+                    _ => {
+                        if let Some(segment) = segments.get_mut(&last) {
+                            segment.dead += record.location.record_len();
+                        }
+                        index.remove(record.key)
+                    }
+                };
+                if let Some(previous) = previous {
+                    if let Some(segment) = segments.get_mut(&previous.segment) {
+                        segment.dead += previous.record_len();
+                    }
+                }
+                offset += record.location.record_len();
+            }
+
+            drop(map);
+
+            // A record cut short by a crash was never acknowledged
+            if offset < file_size {
+                fs::OpenOptions::new()
+                    .write(true)
+                    .open(&segment_path)
+                    .and_then(|file| file.set_len(offset))
+                    .map_err(|e| format!("Failed to truncate segment {}: {}", segment_path.display(), e))?;
+            }
+            if let Some(segment) = segments.get_mut(&last) {
+                segment.size = offset;
+            }
+        }
+
+        if segments.is_empty() {
+            segments.insert(1, Segment::new(1, path.join(segment_name(1, 1)), 0));
+        }
+
+        let active = match segments.values().next_back() {
+            Some(segment) => Self::open_active(&segment.path)?,
+            None => return Err("Segment log has no segments".to_string()),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            segment_size,
+            segments,
+            active,
+            index,
+        })
+    }
+
+    /// Open a segment file for appending
+    fn open_active(path: &Path) -> Result<fs::File, String> {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open segment {}: {}", path.display(), e))
+    }
+
+    /// Set the size after which the active segment is sealed
+    pub fn set_segment_size(&mut self, segment_size: u64) {
+        self.segment_size = segment_size.max(1);
+    }
+
+    /// ID of the active segment
+    fn active_id(&self) -> u32 {
+        self.segments.keys().next_back().cloned().unwrap_or(1)
+    }
+
+    /// Seal the active segment and start a new one once it is full
+    fn roll_if_full(&mut self) -> Result<(), String> {
+        let id = self.active_id();
+        if self.segments.get(&id).map_or(0, |s| s.size) < self.segment_size {
+            return Ok(());
+        }
+
+        self.active.sync_data()
+            .map_err(|e| format!("Failed to sync segment: {}", e))?;
+
+        let next = id + 1;
+        let segment_path = self.path.join(segment_name(next, next));
+        self.active = Self::open_active(&segment_path)?;
+        self.segments.insert(next, Segment::new(next, segment_path, 0));
+
+        Ok(())
+    }
+
+    /// Append a record to the active segment
+    fn append(&mut self, kind: u8, key: &str, data: &[u8]) -> Result<BlobLocation, String> {
+        if key.len() > u16::MAX as usize {
+            return Err(format!("Blob key {} is too long", key));
+        }
+        if data.len() > u32::MAX as usize {
+            return Err(format!("Blob {} is too large for a segment", key));
+        }
+
+        self.roll_if_full()?;
+
+        let id = self.active_id();
+        let record = encode_record(kind, key, data);
+        let segment = self.segments.get_mut(&id)
+            .ok_or_else(|| "Segment log has no active segment".to_string())?;
+
+        if let Err(e) = self.active.write_all(&record) {
+            // Cut off a partial record so later appends stay aligned
+            let _ = self.active.set_len(segment.size);
+            return Err(format!("Failed to append to segment: {}", e));
+        }
+
+        let offset = segment.size;
+        segment.size += record.len() as u64;
+
+        Ok(BlobLocation {
+            segment: id,
+            offset,
+            data_offset: offset + HEADER_SIZE + key.len() as u64,
+            len: data.len() as u64,
+        })
+    }
+
+    /// Count a record as reclaimable by compaction
+    fn mark_dead(&mut self, location: BlobLocation) {
+        if let Some(segment) = self.segments.get_mut(&location.segment) {
+            segment.dead += location.record_len();
+        }
+    }
+
+    /// Store a blob, replacing any blob with the same key
+    pub fn put(&mut self, key: &str, data: &[u8]) -> Result<(), String> {
+        let location = self.append(RECORD_PUT, key, data)?;
+
+        if let Some(previous) = self.index.insert(key.to_string(), location) {
+            self.mark_dead(previous);
+        }
+
+        Ok(())
+    }
+
+    /// Read a blob
+    pub fn get(&self, key: &str) -> Result<Vec<u8>, String> {
+        let location = self.index.get(key)
+            .ok_or_else(|| format!("Blob {} not found", key))?;
+        let segment = self.segments.get(&location.segment)
+            .ok_or_else(|| format!("Segment of blob {} not found", key))?;
+        let (start, end) = (location.data_offset as usize, (location.data_offset + location.len) as usize);
+
+        {
+            let map = segment.map.read();
+            if let Some(map) = map.as_ref().filter(|map| map.len() >= end) {
+                return Ok(map[start..end].to_vec());
+            }
+        }
+
+        // The active segment grew since it was mapped
+        let mut map = segment.map.write();
+        if map.as_ref().is_none_or(|map| map.len() < end) {
+            *map = Some(segment.map_file()?);
+        }
+
+        map.as_ref()
+            .and_then(|map| map.get(start..end))
+            .map(|data| data.to_vec())
+            .ok_or_else(|| format!("Blob {} lies outside its segment", key))
+    }
+
+    /// Delete a blob, returning its size (zero if it did not exist)
+    pub fn remove(&mut self, key: &str) -> Result<u64, String> {
+        let location = match self.index.remove(key) {
+            Some(location) => location,
+            None => return Ok(0),
+        };
+
+        let tombstone = match self.append(RECORD_DELETE, key, &[]) {
+            Ok(tombstone) => tombstone,
+            Err(e) => {
+                self.index.insert(key.to_string(), location);
+                return Err(e);
+            }
+        };
+
+        self.mark_dead(location);
+        self.mark_dead(tombstone);
+
+        Ok(location.len)
+    }
+
+    /// Whether a blob exists
+    pub fn contains(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Size of a blob in bytes
+    pub fn blob_len(&self, key: &str) -> Option<u64> {
+        self.index.get(key).map(|location| location.len)
+    }
+
+    /// Total size of all live blobs in bytes
+    pub fn live_size(&self) -> u64 {
+        self.index.values().map(|location| location.len).sum()
+    }
+
+    /// Keys of all blobs
+    pub fn keys(&self) -> HashSet<String> {
+        self.index.keys().cloned().collect()
+    }
+
+    /// Move blobs kept in their own files (the layout before segments) into the log
+    ///
+    /// Keys already in the log or without a file are skipped. Returns the number
+    /// of blobs moved.
+    pub fn import_files(&mut self, keys: impl IntoIterator<Item = String>) -> Result<usize, String> {
+        let mut imported = 0;
+        for key in keys {
+            if self.contains(&key) {
+                continue;
+            }
+
+            let file_path = self.path.join(&key);
+            let data = match fs::read(&file_path) {
+                Ok(data) => data,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(format!("Failed to read {}: {}", key, e)),
+            };
+
+            self.put(&key, &data)?;
+            fs::remove_file(&file_path)
+                .map_err(|e| format!("Failed to remove {}: {}", key, e))?;
+            imported += 1;
+        }
+
+        Ok(imported)
+    }
+
+    /// Flush appended records to disk
+    pub fn sync(&self) -> Result<(), String> {
+        self.active.sync_data()
+            .map_err(|e| format!("Failed to sync segment: {}", e))
+    }
+
+    /// Rewrite the live blobs of the sealed segments into one segment
+    ///
+    /// Only runs when at least `min_dead_ratio` of the sealed bytes are
+    /// reclaimable. Returns the number of bytes reclaimed.
+    pub fn compact(&mut self, min_dead_ratio: f64) -> Result<u64, String> {
+        let active = self.active_id();
+        let sealed: Vec<u32> = self.segments.keys().filter(|&&id| id != active).cloned().collect();
+        let (first, last) = match (sealed.first(), sealed.last()) {
+            (Some(first), Some(&last)) => (self.segments[first].first, last),
+            _ => return Ok(0),
+        };
+
+        let size: u64 = sealed.iter().map(|id| self.segments[id].size).sum();
+        let dead: u64 = sealed.iter().map(|id| self.segments[id].dead).sum();
+        if dead == 0 || (dead as f64) < size as f64 * min_dead_ratio {
+            return Ok(0);
+        }
+
+        // Live blobs of the sealed segments in log order
+        let mut live: Vec<(String, BlobLocation)> = self.index.iter()
+            .filter(|(_, location)| location.segment <= last)
+            .map(|(key, location)| (key.clone(), *location))
+            .collect();
+        live.sort_by_key(|(_, location)| (location.segment, location.offset));
+
+        let target = self.path.join(segment_name(first, last));
+        let temp = temp_path_for(&target);
+        let mut relocated = Vec::with_capacity(live.len());
+        let result = (|| {
+            let file = fs::File::create(&temp)
+                .map_err(|e| format!("Failed to create compacted segment: {}", e))?;
+            let mut writer = BufWriter::new(file);
+
+            let mut offset = 0;
+            for (key, location) in &live {
+                let record = encode_record(RECORD_PUT, key, &self.get(key)?);
+                writer.write_all(&record)
+                    .map_err(|e| format!("Failed to write compacted segment: {}", e))?;
+                relocated.push((key.clone(), BlobLocation {
+                    segment: last,
+                    offset,
+                    data_offset: offset + HEADER_SIZE + key.len() as u64,
+                    len: location.len,
+                }));
+                offset += record.len() as u64;
+            }
+
+            writer.into_inner()
+                .map_err(|e| format!("Failed to write compacted segment: {}", e))?
+                .sync_all()
+                .map_err(|e| format!("Failed to sync compacted segment: {}", e))?;
+            fs::rename(&temp, &target)
+                .map_err(|e| format!("Failed to finalize compacted segment: {}", e))?;
+
+            Ok(offset)
+        })();
+        let compacted_size = match result {
+            Ok(compacted_size) => compacted_size,
+            Err(e) => {
+                let _ = fs::remove_file(&temp);
+                return Err(e);
+            }
+        };
+
+        // The compacted segment is in place, so the segments it replaces can go
+        for id in &sealed {
+            if let Some(segment) = self.segments.remove(id) {
+                if segment.path != target {
+                    fs::remove_file(&segment.path)
+                        .map_err(|e| format!("Failed to remove compacted segment: {}", e))?;
+                }
+            }
+        }
+        self.segments.insert(last, Segment::new(first, target, compacted_size));
+        self.index.extend(relocated);
+
+        Ok(size - compacted_size)
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+
+use super::segment::SegmentLog;
+
+/// Tier holding the blob of an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageTier {
+    /// Segments on the RAM disk, counted against the RAM-Lake size
+    Hot,
+
+    /// Segments on persistent disk
+    Cold,
+}
+
+/// Cold tier settings
+///
+/// Cold segments live outside the RAM disk, so they are not part of backups.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColdTier {
+    /// Demote entries that were not accessed for `demote_after_secs` to the cold tier
+    ///
+    /// Entries already in the cold tier stay readable while demotion is disabled.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Directory on persistent disk holding the cold segments of every store
+    #[serde(default = "default_cold_path")]
+    pub path: PathBuf,
+
+    /// Seconds without a read or write after which an entry is demoted
+    #[serde(default = "default_demote_after")]
+    pub demote_after_secs: u64,
+
+    /// Seconds between background demotion runs
+    #[serde(default = "default_check_interval")]
+    pub check_interval: u64,
+}
+
+fn default_cold_path() -> PathBuf {
+    PathBuf::from("/var/lib/ramlake/cold")
+}
+
+fn default_demote_after() -> u64 {
+    7 * 24 * 3600
+}
+
+fn default_check_interval() -> u64 {
+    600
+}
+
+impl Default for ColdTier {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_cold_path(),
+            demote_after_secs: default_demote_after(),
+            check_interval: default_check_interval(),
+        }
+    }
+}
+
+/// Blob storage split into a hot segment log on the RAM disk and an optional cold one
+///
+/// New blobs go to the hot tier. Reads fall back to the cold tier and
+/// removing a blob removes it from whichever tier holds it. A blob moved
+/// between tiers is written to its new tier before it is removed from the old
+/// one, so after a crash it may briefly exist in both; the hot copy wins.
+pub struct TieredLog {
+    /// Segments on the RAM disk
+    hot: SegmentLog,
+
+    /// Segments on persistent disk, if the cold tier is open
+    cold: Option<SegmentLog>,
+}
+
+impl TieredLog {
+    /// Open the hot segments of a store directory
+    pub fn open(path: &Path, segment_size: u64) -> Result<Self, String> {
+        Ok(Self {
+            hot: SegmentLog::open(path, segment_size)?,
+            cold: None,
+        })
+    }
+
+    /// Open the cold segments in a directory on persistent disk, creating it if needed
+    pub fn open_cold(&mut self, path: &Path, segment_size: u64) -> Result<(), String> {
+        fs::create_dir_all(path)
+            .map_err(|e| format!("Failed to create cold tier directory: {}", e))?;
+        self.cold = Some(SegmentLog::open(path, segment_size)?);
+
+        Ok(())
+    }
+
+    /// Set the size after which the active segments are sealed
+    pub fn set_segment_size(&mut self, segment_size: u64) {
+        self.hot.set_segment_size(segment_size);
+        if let Some(cold) = self.cold.as_mut() {
+            cold.set_segment_size(segment_size);
+        }
+    }
+
+    /// Store a blob in the hot tier, replacing any blob with the same key
+    pub fn put(&mut self, key: &str, data: &[u8]) -> Result<(), String> {
+        self.hot.put(key, data)?;
+        if let Some(cold) = self.cold.as_mut() {
+            cold.remove(key)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read a blob from whichever tier holds it
+    pub fn get(&self, key: &str) -> Result<Vec<u8>, String> {
+        match self.cold.as_ref() {
+            Some(cold) if self.is_cold(key) => cold.get(key),
+            _ => self.hot.get(key),
+        }
+    }
+
+    /// Delete a blob from both tiers, returning its size (zero if it did not exist)
+    pub fn remove(&mut self, key: &str) -> Result<u64, String> {
+        let mut removed = self.hot.remove(key)?;
+        if let Some(cold) = self.cold.as_mut() {
+            removed = removed.max(cold.remove(key)?);
+        }
+
+        Ok(removed)
+    }
+
+    /// Whether a blob exists in either tier
+    pub fn contains(&self, key: &str) -> bool {
+        self.tier(key).is_some()
+    }
+
+    /// Size of a blob in bytes
+    pub fn blob_len(&self, key: &str) -> Option<u64> {
+        self.hot.blob_len(key)
+            .or_else(|| self.cold.as_ref().and_then(|cold| cold.blob_len(key)))
+    }
+
+    /// Tier holding a blob
+    pub fn tier(&self, key: &str) -> Option<StorageTier> {
+        if self.hot.contains(key) {
+            Some(StorageTier::Hot)
+        } else if self.cold.as_ref().is_some_and(|cold| cold.contains(key)) {
+            Some(StorageTier::Cold)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a blob is held by the cold tier only
+    pub fn is_cold(&self, key: &str) -> bool {
+        self.tier(key) == Some(StorageTier::Cold)
+    }
+
+    /// Keys of all blobs in either tier
+    pub fn keys(&self) -> HashSet<String> {
+        let mut keys = self.hot.keys();
+        if let Some(cold) = self.cold.as_ref() {
+            keys.extend(cold.keys());
+        }
+        keys
+    }
+
+    /// Move blobs kept in their own files (the layout before segments) into the hot tier
+    pub fn import_files(&mut self, keys: impl IntoIterator<Item = String>) -> Result<usize, String> {
+        self.hot.import_files(keys)
+    }
+
+    /// Flush appended records of both tiers to disk
+    pub fn sync(&self) -> Result<(), String> {
+        self.hot.sync()?;
+        if let Some(cold) = self.cold.as_ref() {
+            cold.sync()?;
+        }
+
+        Ok(())
+    }
+
+    /// Compact the sealed segments of both tiers, returning the bytes reclaimed
+    pub fn compact(&mut self, min_dead_ratio: f64) -> Result<u64, String> {
+        let mut reclaimed = self.hot.compact(min_dead_ratio)?;
+        if let Some(cold) = self.cold.as_mut() {
+            reclaimed += cold.compact(min_dead_ratio)?;
+        }
+
+        Ok(reclaimed)
+    }
+
+    /// Move a blob from the hot tier to the cold tier, returning the bytes moved
+    ///
+    /// Returns zero when the blob is not in the hot tier.
+    pub fn demote(&mut self, key: &str) -> Result<u64, String> {
+        let cold = self.cold.as_mut()
+            .ok_or_else(|| "Cold tier is not configured".to_string())?;
+        if !self.hot.contains(key) {
+            return Ok(0);
+        }
+
+        let data = self.hot.get(key)?;
+        cold.put(key, &data)?;
+        self.hot.remove(key)
+    }
+
+    /// Move a blob from the cold tier back to the hot tier, returning the bytes moved
+    ///
+    /// Returns zero when the blob is not in the cold tier only.
+    pub fn promote(&mut self, key: &str) -> Result<u64, String> {
+        let cold = match self.cold.as_mut() {
+            Some(cold) if !self.hot.contains(key) && cold.contains(key) => cold,
+            _ => return Ok(0),
+        };
+
+        let data = cold.get(key)?;
+        self.hot.put(key, &data)?;
+        cold.remove(key)
+    }
+
+    /// Total size of the blobs in the cold tier in bytes
+    pub fn cold_size(&self) -> u64 {
+        self.cold.as_ref().map(|cold| cold.live_size()).unwrap_or(0)
+    }
+}
@@ -1,14 +1,38 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
-use std::io::{Read, Write};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 use serde::{Serialize, Deserialize};
 use parking_lot::RwLock;
 
+use super::wal::{WriteAheadLog, DEFAULT_CHECKPOINT_THRESHOLD};
+use super::consistency::check_temp_files;
+use super::segment::DEFAULT_SEGMENT_SIZE;
+use super::tiering::TieredLog;
+use super::hnsw::{HnswConfig, HnswIndex};
+use super::matrix::EmbeddingMatrix;
+use super::collection::{CollectionIndex, CollectionVectors, VectorCollection, VectorCollectionMetrics, DistanceMetric, DEFAULT_COLLECTION, default_collection};
+use super::quantization::Quantization;
+use super::filter::{EmbeddingSource, VectorFilter};
+use crate::core::memory::ramlake::{ConsistencyIssue, StoreBackupState, default_namespace};
+use crate::utils::filesystem::write_json_atomic;
+
+/// Candidates re-ranked at full precision per result of a quantized search
+const RERANK_FACTOR: usize = 4;
+
+/// Results compared per query when measuring recall
+const RECALL_DEPTH: usize = 10;
+
 /// Vector Store for RAM-Lake
 /// 
-/// Stores and indexes embeddings for vector search
+/// Stores and indexes embeddings for vector search. Embeddings are kept in
+/// an append-only segment log; embeddings demoted to the cold tier do not
+/// count against the store size. Embeddings are grouped into named
+/// collections, each with its own dimension and metric. Searches are answered
+/// from a collection's HNSW graph when one is configured and by scanning a
+/// resident matrix of its embeddings otherwise. Quantized collections keep
+/// compressed vectors resident and re-rank at full precision, reading the
+/// full-precision embeddings from the segment log without charging them.
 pub struct VectorStore {
     /// Path to store embeddings
     path: PathBuf,
@@ -16,7 +40,7 @@ pub struct VectorStore {
     /// Maximum size of the store in bytes
     max_size: u64,
     
-    /// Current size of the store in bytes
+    /// Size of the embedding blobs in the hot tier in bytes
     current_size: u64,
     
     /// Index of embeddings
@@ -25,17 +49,28 @@ pub struct VectorStore {
     /// Mapping of UUIDs to embedding metadata
     metadata: RwLock<HashMap<Uuid, EmbeddingMetadata>>,
     
-    /// FAISS index
-    // Tymczasowo wyłączone z powodu braku feature "static" w faiss
-    // #[cfg(feature = "faiss")]
-    // faiss_index: RwLock<Option<faiss::Index>>,
+    /// Embedding data
+    segments: TieredLog,
+    
+    /// Write-ahead log of changes since the last checkpoint
+    wal: WriteAheadLog,
+    
+    /// Number of WAL records that triggers a checkpoint
+    checkpoint_threshold: usize,
+    
+    /// HNSW settings, if searches use a graph
+    hnsw_config: Option<HnswConfig>,
+    
+    /// Search structures by collection name
+    collections: HashMap<String, CollectionIndex>,
 }
 
 /// Vector Index
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VectorIndex {
-    /// Dimension of embeddings
-    pub dimension: usize,
+    /// Collections by name
+    #[serde(default)]
+    pub collections: HashMap<String, VectorCollection>,
     
     /// Number of embeddings
     pub count: usize,
@@ -47,6 +82,25 @@ pub struct VectorIndex {
     pub ids: Vec<Uuid>,
 }
 
+/// Vector Store WAL record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum VectorWalRecord {
+    /// An embedding was stored
+    Put(Box<EmbeddingMetadata>),
+    
+    /// An embedding was deleted
+    Delete(Uuid),
+    
+    /// A collection was created
+    CreateCollection(VectorCollection),
+    
+    /// The settings of a collection changed
+    UpdateCollection(VectorCollection),
+    
+    /// An empty collection was deleted
+    DeleteCollection(String),
+}
+
 /// Embedding Metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingMetadata {
@@ -56,13 +110,45 @@ pub struct EmbeddingMetadata {
     /// Source ID (e.g., code ID, event ID)
     pub source_id: Uuid,
     
+    /// Namespace (project) the embedding belongs to
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    
+    /// Collection the embedding belongs to
+    #[serde(default = "default_collection")]
+    pub collection: String,
+    
     /// Type of embedding (e.g., "code", "text", "event")
     pub embedding_type: String,
     
+    /// Language of the source code, for code embeddings
+    #[serde(default)]
+    pub language: Option<String>,
+    
+    /// Path of the source file, for code embeddings
+    #[serde(default)]
+    pub path: Option<String>,
+    
+    /// Hash of the code the embedding was computed from, for code embeddings
+    #[serde(default)]
+    pub source_hash: Option<String>,
+    
+    /// First line of the code the embedding covers, from 1, for code chunk embeddings
+    #[serde(default)]
+    pub start_line: Option<usize>,
+    
+    /// Last line of the code the embedding covers, inclusive, for code chunk embeddings
+    #[serde(default)]
+    pub end_line: Option<usize>,
+    
+    /// Model (and version) that produced the embedding, if known
+    #[serde(default)]
+    pub model: Option<String>,
+    
     /// Dimension of the embedding
     pub dimension: usize,
     
-    /// Path to the embedding file
+    /// Name of the embedding blob in the store
     pub file_path: String,
     
     /// Size of the embedding in bytes
@@ -72,6 +158,37 @@ pub struct EmbeddingMetadata {
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// Embedding to store with `VectorStore::store_embeddings_batch`
+#[derive(Debug, Clone)]
+pub struct NewEmbedding {
+    /// ID of the embedding
+    pub id: Uuid,
+    
+    /// Namespace (project) the embedding belongs to
+    pub namespace: String,
+    
+    /// What the embedding was computed from
+    pub source: EmbeddingSource,
+    
+    /// Values of the embedding
+    pub embedding: Vec<f32>,
+}
+
+impl EmbeddingMetadata {
+    /// What the embedding was computed from
+    pub fn source(&self) -> EmbeddingSource {
+        EmbeddingSource {
+            source_id: self.source_id,
+            embedding_type: self.embedding_type.clone(),
+            language: self.language.clone(),
+            path: self.path.clone(),
+            hash: self.source_hash.clone(),
+            start_line: self.start_line,
+            end_line: self.end_line,
+        }
+    }
+}
+
 impl VectorStore {
     /// Create a new vector store
     pub fn new(path: PathBuf, max_size: u64) -> Result<Self, String> {
@@ -90,7 +207,7 @@ impl VectorStore {
                 .map_err(|e| format!("Failed to parse index file: {}", e))?
         } else {
             VectorIndex {
-                dimension: 0,
+                collections: HashMap::new(),
                 count: 0,
                 version: 1,
                 ids: Vec::new(),
@@ -108,160 +225,751 @@ impl VectorStore {
             HashMap::new()
         };
         
-        // Calculate current size
-        let mut current_size = 0;
-        for entry in fs::read_dir(&path).map_err(|e| format!("Failed to read vector store directory: {}", e))? {
-            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-            let metadata = entry.metadata().map_err(|e| format!("Failed to read file metadata: {}", e))?;
-            current_size += metadata.len();
-        }
-        
-        // Initialize FAISS index if enabled
-        #[cfg(feature = "faiss")]
-        let faiss_index = {
-            let faiss_path = path.join("faiss.index");
-            let index = if faiss_path.exists() && index.dimension > 0 {
-                let mut index = faiss::Index::new_with_dimension(index.dimension as i32)
-                    .map_err(|e| format!("Failed to create FAISS index: {}", e))?;
-                index.read_index(faiss_path.to_str().unwrap())
-                    .map_err(|e| format!("Failed to read FAISS index: {}", e))?;
-                Some(index)
-            } else {
-                None
-            };
-            RwLock::new(index)
-        };
+        // Open embedding segments
+        let segments = TieredLog::open(&path, DEFAULT_SEGMENT_SIZE)?;
+        
+        // Open write-ahead log
+        let wal = WriteAheadLog::open(path.join("wal.log"))?;
         
-        Ok(Self {
+        let mut store = Self {
             path,
             max_size,
-            current_size,
+            current_size: 0,
             index: RwLock::new(index),
             metadata: RwLock::new(metadata),
-            #[cfg(feature = "faiss")]
-            faiss_index,
-        })
+            segments,
+            wal,
+            checkpoint_threshold: DEFAULT_CHECKPOINT_THRESHOLD,
+            hnsw_config: None,
+            collections: HashMap::new(),
+        };
+        
+        // Replay changes made since the last checkpoint
+        let records: Vec<VectorWalRecord> = store.wal.replay()?;
+        for record in &records {
+            store.apply_record(record);
+        }
+        
+        // Embeddings stored before collections existed form the default collection
+        let uncollected: Vec<EmbeddingMetadata> = {
+            let index = store.index.read();
+            store.metadata.read().values()
+                .filter(|m| !index.collections.contains_key(&m.collection))
+                .cloned()
+                .collect()
+        };
+        for metadata in uncollected {
+            store.apply_record(&VectorWalRecord::Put(Box::new(metadata)));
+        }
+        
+        // Stores written before segments keep one file per embedding
+        let files: Vec<String> = store.metadata.read().values().map(|m| m.file_path.clone()).collect();
+        store.segments.import_files(files)?;
+        
+        store.current_size = store.hot_size();
+        store.load_matrix();
+        
+        Ok(store)
     }
     
-    /// Store an embedding
-    pub fn store_embedding(&mut self, id: Uuid, embedding: Vec<f32>) -> Result<(), String> {
-        // Check if embedding already exists
-        let metadata_lock = self.metadata.read();
-        if metadata_lock.contains_key(&id) {
-            return Err(format!("Embedding with ID {} already exists", id));
+    /// Load the hot embeddings missing from the collection matrices
+    ///
+    /// Embeddings in the cold tier are left out until they are promoted, as
+    /// are ones whose blob cannot be read.
+    fn load_matrix(&mut self) {
+        let entries: Vec<(Uuid, String)> = self.metadata.read()
+            .values()
+            .filter(|m| self.is_resident(m))
+            .map(|m| (m.id, m.collection.clone()))
+            .collect();
+        let mut loaded = HashSet::new();
+        for (id, collection) in entries {
+            if self.collections.get(&collection).is_some_and(|c| c.contains(id)) {
+                continue;
+            }
+            if let Ok(embedding) = self.load_embedding(id) {
+                self.collection_index(&collection).insert(id, &embedding);
+                loaded.insert(collection);
+            }
         }
-        drop(metadata_lock);
+        for collection in loaded {
+            self.train_quantizer(&collection);
+        }
+    }
+    
+    /// Train the product quantization codebook of a collection once it has grown enough
+    fn train_quantizer(&mut self, name: &str) {
+        let ids = match self.collections.get(name).map(|c| &c.vectors) {
+            Some(CollectionVectors::Quantized(matrix)) if matrix.needs_training() => matrix.ids().to_vec(),
+            _ => return,
+        };
         
-        // Calculate size
-        let embedding_size = (embedding.len() * std::mem::size_of::<f32>()) as u64;
+        let vectors: HashMap<Uuid, Vec<f32>> = ids.into_iter()
+            .filter_map(|id| self.load_embedding(id).ok().map(|embedding| (id, embedding)))
+            .collect();
+        if let Some(CollectionVectors::Quantized(matrix)) = self.collections.get_mut(name).map(|c| &mut c.vectors) {
+            matrix.train(&vectors);
+        }
+    }
+    
+    /// Size of the embedding blobs charged to the store
+    fn hot_size(&self) -> u64 {
+        self.metadata.read()
+            .values()
+            .filter(|m| self.is_blob_charged(m))
+            .map(|m| m.size)
+            .sum()
+    }
+    
+    /// Bytes held by the resident vectors of every collection
+    fn resident_size(&self) -> u64 {
+        self.collections.values().map(CollectionIndex::resident_size).sum()
+    }
+    
+    /// Bytes of RAM an embedding of a collection takes while hot: its blob and its search row
+    ///
+    /// Quantized collections only read their blobs to re-rank, so only the
+    /// row counts. A collection that does not exist yet is sized as a new
+    /// default collection of the given dimension.
+    pub fn new_embedding_size(&self, collection: &str, dimension: usize) -> u64 {
+        let blob_size = if self.is_quantized(collection) { 0 } else { (dimension * std::mem::size_of::<f32>()) as u64 };
+        blob_size + self.row_size(collection, dimension)
+    }
+    
+    /// Bytes of RAM an embedding takes while hot: its blob and its search row
+    pub fn embedding_size(&self, id: Uuid) -> u64 {
+        self.metadata.read()
+            .get(&id)
+            .map_or(0, |m| self.charged_size(m))
+    }
+    
+    /// Bytes of RAM the embedding with some metadata takes while hot
+    fn charged_size(&self, metadata: &EmbeddingMetadata) -> u64 {
+        let blob_size = if self.is_quantized(&metadata.collection) { 0 } else { metadata.size };
+        blob_size + self.row_size(&metadata.collection, metadata.dimension)
+    }
+    
+    /// Whether a collection keeps compressed vectors resident
+    fn is_quantized(&self, collection: &str) -> bool {
+        self.index.read().collections.get(collection)
+            .is_some_and(|c| c.quantization != Quantization::None)
+    }
+    
+    /// Whether an embedding belongs in its collection's search structures
+    ///
+    /// Embeddings of quantized collections always do, wherever their blob is.
+    /// Other embeddings do while their blob is hot.
+    fn is_resident(&self, metadata: &EmbeddingMetadata) -> bool {
+        self.is_quantized(&metadata.collection) || !self.segments.is_cold(&metadata.file_path)
+    }
+    
+    /// Whether the blob of an embedding counts against the store size
+    ///
+    /// Blobs of quantized collections stay in the hot segment log so backups
+    /// hold them, but they are only read to re-rank and are not charged.
+    fn is_blob_charged(&self, metadata: &EmbeddingMetadata) -> bool {
+        !self.is_quantized(&metadata.collection) && !self.segments.is_cold(&metadata.file_path)
+    }
+    
+    /// Bytes the search row of an embedding in a collection takes
+    fn row_size(&self, collection: &str, dimension: usize) -> u64 {
+        self.index.read().collections.get(collection)
+            .map_or_else(|| EmbeddingMatrix::row_size(dimension), VectorCollection::row_size)
+    }
+    
+    /// Open the cold tier of the store in a directory on persistent disk
+    pub fn set_cold_tier(&mut self, path: &Path) -> Result<(), String> {
+        self.segments.open_cold(path, DEFAULT_SEGMENT_SIZE)?;
+        self.current_size = self.hot_size();
         
-        // Check if we have enough space
-        if self.current_size + embedding_size > self.max_size {
-            return Err("Not enough space in vector store".to_string());
+        // Embeddings that turned out to be cold leave the resident matrices
+        let cold: Vec<Uuid> = self.metadata.read()
+            .values()
+            .filter(|m| !self.is_resident(m))
+            .map(|m| m.id)
+            .collect();
+        for id in cold {
+            self.remove_from_collections(id);
         }
+        self.load_matrix();
         
-        // Generate file path
-        let file_name = format!("{}.vec", id);
-        let file_path = self.path.join(&file_name);
+        Ok(())
+    }
+    
+    /// Enable or disable the HNSW index
+    ///
+    /// Graphs persisted with the same settings are loaded and brought up to
+    /// date with the stored embeddings; other graphs, including ones that
+    /// cannot be read, are rebuilt.
+    pub fn set_hnsw(&mut self, config: &HnswConfig) -> Result<(), String> {
+        let hnsw_path = self.path.join("hnsw.json");
+        if !config.enabled {
+            self.hnsw_config = None;
+            for collection in self.collections.values_mut() {
+                collection.hnsw = None;
+            }
+            if hnsw_path.exists() {
+                fs::remove_file(&hnsw_path)
+                    .map_err(|e| format!("Failed to remove HNSW index file: {}", e))?;
+            }
+            return Ok(());
+        }
         
-        // Write embedding to file
-        let mut file = fs::File::create(&file_path)
-            .map_err(|e| format!("Failed to create embedding file: {}", e))?;
+        let mut persisted: HashMap<String, HnswIndex> = fs::File::open(&hnsw_path)
+            .ok()
+            .and_then(|file| serde_json::from_reader(file).ok())
+            .unwrap_or_default();
         
-        // Write embedding dimensions as header
-        let dimension = embedding.len() as u32;
-        file.write_all(&dimension.to_le_bytes())
-            .map_err(|e| format!("Failed to write dimension header: {}", e))?;
+        let metrics: HashMap<String, DistanceMetric> = self.index.read().collections.iter()
+            .map(|(name, collection)| (name.clone(), collection.metric))
+            .collect();
+        for (name, collection) in self.collections.iter_mut() {
+            // Quantized collections are searched without a graph
+            let CollectionVectors::Full(matrix) = &collection.vectors else {
+                collection.hnsw = None;
+                continue;
+            };
+            
+            let metric = metrics.get(name).copied().unwrap_or_default();
+            let mut hnsw = match persisted.remove(name) {
+                Some(mut hnsw) if hnsw.is_built_with(config, metric) => {
+                    hnsw.restore_vectors(|id| matrix.get(id));
+                    hnsw
+                }
+                _ => HnswIndex::new(config, metric),
+            };
+            hnsw.set_ef_search(config.ef_search);
+            
+            // Catch up with changes made after the graph was persisted
+            for id in hnsw.ids() {
+                if !matrix.contains(id) {
+                    hnsw.remove(id);
+                }
+            }
+            for &id in matrix.ids() {
+                if let (false, Some(embedding)) = (hnsw.contains(id), matrix.get(id)) {
+                    hnsw.insert(id, &embedding);
+                }
+            }
+            
+            collection.hnsw = Some(hnsw);
+        }
+        self.hnsw_config = Some(config.clone());
         
-        // Write embedding data
-        for &value in &embedding {
-            file.write_all(&value.to_le_bytes())
-                .map_err(|e| format!("Failed to write embedding data: {}", e))?;
+        Ok(())
+    }
+    
+    /// Search structures of a collection, created on first use
+    fn collection_index(&mut self, name: &str) -> &mut CollectionIndex {
+        let hnsw_config = self.hnsw_config.as_ref();
+        let index = self.index.read();
+        self.collections.entry(name.to_string()).or_insert_with(|| {
+            let collection = index.collections.get(name).cloned().unwrap_or_else(|| VectorCollection {
+                name: name.to_string(),
+                dimension: 0,
+                metric: DistanceMetric::default(),
+                model: None,
+                quantization: Quantization::default(),
+            });
+            let mut collection_index = CollectionIndex::new(&collection);
+            if collection.quantization == Quantization::None {
+                collection_index.hnsw = hnsw_config.map(|config| HnswIndex::new(config, collection.metric));
+            }
+            collection_index
+        })
+    }
+    
+    /// Create a collection for embeddings of one dimension
+    ///
+    /// Creating a collection that already exists with the same settings does nothing.
+    pub fn create_collection(&mut self, name: &str, dimension: usize, metric: DistanceMetric, model: Option<String>) -> Result<(), String> {
+        VectorCollection::validate_name(name)?;
+        if dimension == 0 {
+            return Err("Vector collection dimension must be greater than zero".to_string());
         }
         
-        // Create metadata
-        let metadata = EmbeddingMetadata {
-            id,
-            source_id: id, // Default to same ID, can be updated later
-            embedding_type: "unknown".to_string(),
-            dimension: embedding.len(),
-            file_path: file_name,
-            size: embedding_size,
-            created_at: chrono::Utc::now(),
+        let collection = VectorCollection {
+            name: name.to_string(),
+            dimension,
+            metric,
+            model,
+            quantization: Quantization::default(),
         };
+        if let Some(existing) = self.index.read().collections.get(name) {
+            if *existing == collection {
+                return Ok(());
+            }
+            return Err(format!("Vector collection {} already exists with different settings", name));
+        }
         
-        // Update index
-        {
-            let mut index = self.index.write();
-            
-            // Set dimension if this is the first embedding
-            if index.count == 0 {
-                index.dimension = embedding.len();
-            } else if index.dimension != embedding.len() {
-                return Err(format!(
-                    "Embedding dimension mismatch. Expected {}, got {}",
-                    index.dimension, embedding.len()
-                ));
+        self.log_record(VectorWalRecord::CreateCollection(collection))
+    }
+    
+    /// Change the model that produces the embeddings of a collection
+    ///
+    /// Embeddings stored afterwards record the new model; those produced by
+    /// another model become stale.
+    pub fn set_collection_model(&mut self, name: &str, model: Option<String>) -> Result<(), String> {
+        let mut collection = self.get_collection(name)?;
+        if collection.model == model {
+            return Ok(());
+        }
+        
+        collection.model = model;
+        self.log_record(VectorWalRecord::UpdateCollection(collection))
+    }
+    
+    /// Change how the resident vectors of a collection are compressed
+    ///
+    /// Quantized collections keep their full-precision embeddings in the hot
+    /// segment log without charging them; returning to full precision charges
+    /// them again. The collection's search structures are rebuilt from the
+    /// stored embeddings and its recall is measured again.
+    pub fn set_quantization(&mut self, name: &str, quantization: Quantization) -> Result<(), String> {
+        let mut collection = self.get_collection(name)?;
+        quantization.validate(collection.dimension)?;
+        if collection.quantization == quantization {
+            return Ok(());
+        }
+        
+        let entries: Vec<(String, u64)> = self.metadata.read()
+            .values()
+            .filter(|m| m.collection == name)
+            .map(|m| (m.file_path.clone(), m.size))
+            .collect();
+        if quantization == Quantization::None {
+            let blobs: u64 = entries.iter().map(|(_, size)| size).sum();
+            let rows = EmbeddingMatrix::row_size(collection.dimension) * entries.len() as u64;
+            let resident = self.collections.get(name).map_or(0, CollectionIndex::resident_size);
+            if self.get_size() + blobs + rows > self.max_size + resident {
+                return Err(format!("Not enough space in vector store to keep collection {} at full precision", name));
             }
-            
-            index.ids.push(id);
-            index.count += 1;
-            index.version += 1;
         }
         
-        // Add to FAISS index if enabled
-        #[cfg(feature = "faiss")]
-        {
-            let mut faiss_index = self.faiss_index.write();
-            
-            // Create FAISS index if it doesn't exist
-            if faiss_index.is_none() {
-                *faiss_index = Some(
-                    faiss::Index::new_with_dimension(embedding.len() as i32)
-                        .map_err(|e| format!("Failed to create FAISS index: {}", e))?,
-                );
+        // Every blob of the collection is brought back to the hot segment log,
+        // which backups copy
+        for (file_path, _) in &entries {
+            self.segments.promote(file_path)?;
+        }
+        
+        collection.quantization = quantization;
+        self.log_record(VectorWalRecord::UpdateCollection(collection))?;
+        self.current_size = self.hot_size();
+        self.collections.remove(name);
+        self.load_matrix();
+        
+        if self.collections.contains_key(name) {
+            self.measure_recall(name, RECALL_DEPTH)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Measure the recall of a collection's searches against exact searches
+    ///
+    /// Up to `samples` stored embeddings are used as queries. Returns the share
+    /// of the exact top results that searches found, which is also reported in
+    /// the collection metrics.
+    pub fn measure_recall(&mut self, name: &str, samples: usize) -> Result<f32, String> {
+        let ids = self.collections.get(name).map(|c| c.ids().to_vec()).unwrap_or_default();
+        if ids.is_empty() {
+            return Err(format!("Vector collection {} has no embeddings to measure recall with", name));
+        }
+        
+        let filter = VectorFilter::default();
+        let (mut found, mut total) = (0, 0);
+        for &id in ids.iter().step_by((ids.len() / samples.max(1)).max(1)).take(samples.max(1)) {
+            let query = self.load_embedding(id)?;
+            let exact = self.search_exact(name, query.clone(), RECALL_DEPTH, &filter)?;
+            let approximate = self.search_similar(name, query, RECALL_DEPTH, &filter)?;
+            total += exact.len();
+            found += approximate.iter().filter(|(id, _)| exact.iter().any(|(exact_id, _)| exact_id == id)).count();
+        }
+        
+        let recall = if total == 0 { 1.0 } else { found as f32 / total as f32 };
+        if let Some(collection) = self.collections.get_mut(name) {
+            collection.recall = Some(recall);
+        }
+        
+        Ok(recall)
+    }
+    
+    /// Get the search metrics of every collection
+    pub fn get_collection_metrics(&self) -> HashMap<String, VectorCollectionMetrics> {
+        let mut hot_blobs: HashMap<String, u64> = HashMap::new();
+        for m in self.metadata.read().values() {
+            if self.is_blob_charged(m) {
+                *hot_blobs.entry(m.collection.clone()).or_default() += m.size;
             }
-            
-            // Add embedding to FAISS index
-            if let Some(index) = faiss_index.as_mut() {
-                index.add_with_ids(
-                    &embedding,
-                    &[index.ntotal() as i64],
-                ).map_err(|e| format!("Failed to add embedding to FAISS index: {}", e))?;
+        }
+        
+        self.index.read().collections.values()
+            .map(|collection| {
+                let index = self.collections.get(&collection.name);
+                let entries = index.map_or(0, |index| index.ids().len());
+                let full_precision_row = (collection.dimension * std::mem::size_of::<f32>()) as u64 + EmbeddingMatrix::row_size(collection.dimension);
+                let metrics = VectorCollectionMetrics {
+                    entries,
+                    metric: collection.metric,
+                    quantization: collection.quantization,
+                    full_precision_size: full_precision_row * entries as u64,
+                    resident_size: hot_blobs.get(&collection.name).copied().unwrap_or(0) + index.map_or(0, CollectionIndex::resident_size),
+                    recall: index.and_then(|index| index.recall),
+                };
+                (collection.name.clone(), metrics)
+            })
+            .collect()
+    }
+    
+    /// Delete a collection that holds no embeddings
+    pub fn delete_collection(&mut self, name: &str) -> Result<(), String> {
+        self.get_collection(name)?;
+        
+        let count = self.metadata.read().values().filter(|m| m.collection == name).count();
+        if count > 0 {
+            return Err(format!("Vector collection {} still holds {} embeddings", name, count));
+        }
+        
+        self.log_record(VectorWalRecord::DeleteCollection(name.to_string()))?;
+        self.collections.remove(name);
+        
+        Ok(())
+    }
+    
+    /// Get a collection by name
+    pub fn get_collection(&self, name: &str) -> Result<VectorCollection, String> {
+        self.index.read().collections.get(name)
+            .cloned()
+            .ok_or_else(|| format!("Vector collection {} does not exist", name))
+    }
+    
+    /// List all collections, ordered by name
+    pub fn list_collections(&self) -> Vec<VectorCollection> {
+        let mut collections: Vec<_> = self.index.read().collections.values().cloned().collect();
+        collections.sort_by(|a, b| a.name.cmp(&b.name));
+        collections
+    }
+    
+    /// Whether an embedding is held by the cold tier and left out of searches
+    ///
+    /// Embeddings of quantized collections are never cold: their compressed
+    /// vector stays resident.
+    pub fn is_cold(&self, id: Uuid) -> bool {
+        self.metadata.read()
+            .get(&id)
+            .is_some_and(|m| !self.is_resident(m))
+    }
+    
+    /// Move an embedding to the cold tier, returning the bytes freed in the store
+    ///
+    /// The embedding leaves its collection's search structures until it is
+    /// promoted. Embeddings of quantized collections stay where they are.
+    pub fn demote_embedding(&mut self, id: Uuid) -> Result<u64, String> {
+        let metadata = self.get_embedding_metadata(id)?;
+        if self.is_quantized(&metadata.collection) || self.segments.demote(&metadata.file_path)? == 0 {
+            return Ok(0);
+        }
+        
+        self.current_size = self.current_size.saturating_sub(metadata.size);
+        self.remove_from_collections(id);
+        Ok(self.charged_size(&metadata))
+    }
+    
+    /// Move an embedding back from the cold tier, returning the bytes added to the store
+    ///
+    /// The embedding rejoins its collection's search structures.
+    pub fn promote_embedding(&mut self, id: Uuid) -> Result<u64, String> {
+        let metadata = self.get_embedding_metadata(id)?;
+        if self.is_resident(&metadata) {
+            return Ok(0);
+        }
+        let size = self.charged_size(&metadata);
+        if self.get_size() + size > self.max_size {
+            return Err("Not enough space in vector store".to_string());
+        }
+        
+        let embedding = self.load_embedding(id)?;
+        self.segments.promote(&metadata.file_path)?;
+        self.current_size += metadata.size;
+        self.collection_index(&metadata.collection).insert(id, &embedding);
+        self.train_quantizer(&metadata.collection);
+        Ok(size)
+    }
+    
+    /// Get the size of the embeddings in the cold tier
+    pub fn get_cold_size(&self) -> u64 {
+        self.segments.cold_size()
+    }
+    
+    /// Apply a WAL record to the in-memory index and metadata
+    fn apply_record(&self, record: &VectorWalRecord) {
+        let mut index = self.index.write();
+        let mut metadata = self.metadata.write();
+        
+        match record {
+            VectorWalRecord::Put(embedding_metadata) => {
+                let id = embedding_metadata.id;
+                if metadata.insert(id, (**embedding_metadata).clone()).is_none() {
+                    index.ids.push(id);
+                    index.count += 1;
+                }
+                
+                // The default collection is created by its first embedding
+                let name = &embedding_metadata.collection;
+                if !index.collections.contains_key(name) {
+                    index.collections.insert(name.clone(), VectorCollection {
+                        name: name.clone(),
+                        dimension: embedding_metadata.dimension,
+                        metric: DistanceMetric::default(),
+                        model: None,
+                        quantization: Quantization::default(),
+                    });
+                }
+            }
+            VectorWalRecord::Delete(id) => {
+                if metadata.remove(id).is_some() {
+                    index.ids.retain(|i| i != id);
+                    index.count -= 1;
+                }
+            }
+            VectorWalRecord::CreateCollection(collection) | VectorWalRecord::UpdateCollection(collection) => {
+                index.collections.insert(collection.name.clone(), collection.clone());
+            }
+            VectorWalRecord::DeleteCollection(name) => {
+                index.collections.remove(name);
             }
         }
         
-        // Update metadata
-        {
-            let mut metadata_lock = self.metadata.write();
-            metadata_lock.insert(id, metadata);
+        index.version += 1;
+    }
+    
+    /// Append a record to the WAL and apply it, checkpointing when the log grows too long
+    fn log_record(&mut self, record: VectorWalRecord) -> Result<(), String> {
+        self.wal.append(&record)?;
+        self.apply_record(&record);
+        
+        if self.wal.record_count() >= self.checkpoint_threshold {
+            self.checkpoint()?;
         }
         
-        // Update size
-        self.current_size += embedding_size;
+        Ok(())
+    }
+    
+    /// Append several records to the WAL with one write and apply them, checkpointing at most once
+    fn log_records(&mut self, records: Vec<VectorWalRecord>) -> Result<(), String> {
+        self.wal.append_all(&records)?;
+        for record in &records {
+            self.apply_record(record);
+        }
         
-        // Persist index and metadata
+        if self.wal.record_count() >= self.checkpoint_threshold {
+            self.checkpoint()?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Write the index and metadata snapshots and truncate the WAL
+    pub fn checkpoint(&mut self) -> Result<(), String> {
+        self.segments.sync()?;
         self.persist_index()?;
         self.persist_metadata()?;
+        self.persist_hnsw()?;
+        
+        self.wal.reset()
+    }
+    
+    /// Set the number of WAL records that triggers a checkpoint
+    pub fn set_checkpoint_threshold(&mut self, threshold: usize) {
+        self.checkpoint_threshold = threshold.max(1);
+    }
+    
+    /// Set the size after which the active segment is sealed
+    pub fn set_segment_size(&mut self, segment_size: u64) {
+        self.segments.set_segment_size(segment_size);
+    }
+    
+    /// Compact the sealed segments once `min_dead_ratio` of them is reclaimable
+    ///
+    /// Returns the number of bytes reclaimed.
+    pub fn compact(&mut self, min_dead_ratio: f64) -> Result<u64, String> {
+        self.segments.compact(min_dead_ratio)
+    }
+    
+    /// Store an embedding in a collection
+    ///
+    /// The default collection is created by its first embedding; other
+    /// collections have to be created first.
+    pub fn store_embedding(&mut self, id: Uuid, collection: &str, namespace: &str, source: EmbeddingSource, embedding: Vec<f32>) -> Result<(), String> {
+        self.store_embeddings_batch(collection, vec![NewEmbedding {
+            id,
+            namespace: namespace.to_string(),
+            source,
+            embedding,
+        }])
+    }
+    
+    /// Store several embeddings in a collection
+    ///
+    /// Every embedding is checked before any is written, so a batch is stored
+    /// whole or not at all. The batch is logged with a single WAL write and
+    /// the collection's search structures are updated once.
+    pub fn store_embeddings_batch(&mut self, collection: &str, embeddings: Vec<NewEmbedding>) -> Result<(), String> {
+        self.put_embeddings(collection, embeddings, false)
+    }
+    
+    /// Store an embedding in a collection, replacing any embedding with the same ID
+    pub fn upsert_embedding(&mut self, id: Uuid, collection: &str, namespace: &str, source: EmbeddingSource, embedding: Vec<f32>) -> Result<(), String> {
+        self.upsert_embeddings_batch(collection, vec![NewEmbedding {
+            id,
+            namespace: namespace.to_string(),
+            source,
+            embedding,
+        }])
+    }
+    
+    /// Store several embeddings in a collection, replacing any with the same IDs
+    ///
+    /// A replaced embedding in another collection moves to this one. Like
+    /// `store_embeddings_batch`, the batch is stored whole or not at all.
+    pub fn upsert_embeddings_batch(&mut self, collection: &str, embeddings: Vec<NewEmbedding>) -> Result<(), String> {
+        self.put_embeddings(collection, embeddings, true)
+    }
+    
+    /// Write a batch of embeddings, replacing existing ones only if `replace` is set
+    ///
+    /// Each embedding records the collection's current model.
+    fn put_embeddings(&mut self, collection: &str, embeddings: Vec<NewEmbedding>, replace: bool) -> Result<(), String> {
+        if embeddings.is_empty() {
+            return Ok(());
+        }
+        
+        // Check for existing embeddings, noting the space replaced ones free
+        let metadata_lock = self.metadata.read();
+        let mut ids = HashSet::with_capacity(embeddings.len());
+        let mut replaced_size = 0;
+        let mut replaced_blobs = 0;
+        let mut moved = Vec::new();
+        for new in &embeddings {
+            if !ids.insert(new.id) {
+                return Err(format!("Embedding with ID {} appears more than once in the batch", new.id));
+            }
+            let Some(existing) = metadata_lock.get(&new.id) else {
+                continue;
+            };
+            if !replace {
+                return Err(format!("Embedding with ID {} already exists", new.id));
+            }
+            if self.is_resident(existing) {
+                replaced_size += self.charged_size(existing);
+            }
+            if self.is_blob_charged(existing) {
+                replaced_blobs += existing.size;
+            }
+            if existing.collection != collection {
+                moved.push(new.id);
+            }
+        }
+        drop(metadata_lock);
+        
+        // Check dimension; a new default collection takes the first embedding's
+        let (dimension, model) = match self.get_collection(collection) {
+            Ok(collection) => (collection.dimension, collection.model),
+            Err(_) if collection == DEFAULT_COLLECTION => (embeddings[0].embedding.len(), None),
+            Err(e) => return Err(e),
+        };
+        if let Some(new) = embeddings.iter().find(|new| new.embedding.len() != dimension) {
+            return Err(format!(
+                "Embedding dimension mismatch. Expected {}, got {}",
+                dimension, new.embedding.len()
+            ));
+        }
+        
+        // Calculate size; each embedding takes a search row and, unless quantized, its blob
+        let quantized = self.is_quantized(collection);
+        let embedding_size = (dimension * std::mem::size_of::<f32>()) as u64;
+        let hot_blob_size = if quantized { 0 } else { embedding_size };
+        let batch_size = self.new_embedding_size(collection, dimension) * embeddings.len() as u64;
+        
+        // Check if we have enough space
+        if self.get_size() + batch_size > self.max_size + replaced_size {
+            return Err("Not enough space in vector store".to_string());
+        }
+        
+        let mut records = Vec::with_capacity(embeddings.len());
+        for new in &embeddings {
+            // Generate blob name
+            let file_name = format!("{}.vec", new.id);
+            
+            // Embedding dimensions as header, then the values
+            let mut data = Vec::with_capacity(4 + new.embedding.len() * std::mem::size_of::<f32>());
+            data.extend_from_slice(&(new.embedding.len() as u32).to_le_bytes());
+            for &value in &new.embedding {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            
+            // Write embedding to the active segment; quantized collections only read it to re-rank
+            self.segments.put(&file_name, &data)?;
+            
+            // Create metadata
+            let source = new.source.clone();
+            records.push(VectorWalRecord::Put(Box::new(EmbeddingMetadata {
+                id: new.id,
+                source_id: source.source_id,
+                namespace: new.namespace.clone(),
+                collection: collection.to_string(),
+                embedding_type: source.embedding_type,
+                language: source.language,
+                path: source.path,
+                source_hash: source.hash,
+                start_line: source.start_line,
+                end_line: source.end_line,
+                model: model.clone(),
+                dimension,
+                file_path: file_name,
+                size: embedding_size,
+                created_at: chrono::Utc::now(),
+            })));
+        }
+        
+        // Update size
+        self.current_size = self.current_size - replaced_blobs + hot_blob_size * embeddings.len() as u64;
         
-        #[cfg(feature = "faiss")]
-        self.persist_faiss_index()?;
+        // Record the changes
+        self.log_records(records)?;
+        
+        for id in moved {
+            self.remove_from_collections(id);
+        }
+        let collection_index = self.collection_index(collection);
+        for new in &embeddings {
+            collection_index.insert(new.id, &new.embedding);
+        }
+        self.train_quantizer(collection);
         
         Ok(())
     }
     
+    /// Replace the source attributes of an embedding
+    pub fn set_source(&mut self, id: Uuid, source: EmbeddingSource) -> Result<(), String> {
+        let mut metadata = self.get_embedding_metadata(id)?;
+        metadata.source_id = source.source_id;
+        metadata.embedding_type = source.embedding_type;
+        metadata.language = source.language;
+        metadata.path = source.path;
+        metadata.source_hash = source.hash;
+        metadata.start_line = source.start_line;
+        metadata.end_line = source.end_line;
+        
+        self.log_record(VectorWalRecord::Put(Box::new(metadata)))
+    }
+    
     /// Persist index to disk
     fn persist_index(&self) -> Result<(), String> {
         let index_path = self.path.join("index.json");
         let index = self.index.read();
         
-        let file = fs::File::create(&index_path)
-            .map_err(|e| format!("Failed to create index file: {}", e))?;
-        
-        serde_json::to_writer_pretty(file, &*index)
-            .map_err(|e| format!("Failed to write index file: {}", e))?;
-        
-        Ok(())
+        write_json_atomic(&index_path, &*index)
     }
     
     /// Persist metadata to disk
@@ -269,27 +977,19 @@ impl VectorStore {
         let metadata_path = self.path.join("metadata.json");
         let metadata = self.metadata.read();
         
-        let file = fs::File::create(&metadata_path)
-            .map_err(|e| format!("Failed to create metadata file: {}", e))?;
-        
-        serde_json::to_writer_pretty(file, &*metadata)
-            .map_err(|e| format!("Failed to write metadata file: {}", e))?;
-        
-        Ok(())
+        write_json_atomic(&metadata_path, &*metadata)
     }
     
-    /// Persist FAISS index to disk
-    #[cfg(feature = "faiss")]
-    fn persist_faiss_index(&self) -> Result<(), String> {
-        let faiss_path = self.path.join("faiss.index");
-        let faiss_index = self.faiss_index.read();
-        
-        if let Some(index) = faiss_index.as_ref() {
-            index.write_index(faiss_path.to_str().unwrap())
-                .map_err(|e| format!("Failed to write FAISS index: {}", e))?;
+    /// Persist the HNSW graph to disk
+    fn persist_hnsw(&self) -> Result<(), String> {
+        if self.hnsw_config.is_none() {
+            return Ok(());
         }
         
-        Ok(())
+        let graphs: HashMap<&String, &HnswIndex> = self.collections.iter()
+            .filter_map(|(name, collection)| collection.hnsw.as_ref().map(|hnsw| (name, hnsw)))
+            .collect();
+        write_json_atomic(self.path.join("hnsw.json"), &graphs)
     }
     
     /// Load embedding from disk
@@ -299,19 +999,17 @@ impl VectorStore {
         let metadata = metadata_lock.get(&id)
             .ok_or_else(|| format!("Embedding with ID {} not found", id))?;
         
-        // Open file
-        let file_path = self.path.join(&metadata.file_path);
-        let mut file = fs::File::open(&file_path)
-            .map_err(|e| format!("Failed to open embedding file: {}", e))?;
+        // Read blob
+        let data = self.segments.get(&metadata.file_path)?;
         
         // Read dimension header
-        let mut dimension_bytes = [0u8; 4];
-        file.read_exact(&mut dimension_bytes)
-            .map_err(|e| format!("Failed to read dimension header: {}", e))?;
-        let dimension = u32::from_le_bytes(dimension_bytes) as usize;
+        let header: [u8; 4] = data.get(..4)
+            .and_then(|header| header.try_into().ok())
+            .ok_or_else(|| "Failed to read dimension header".to_string())?;
+        let dimension = u32::from_le_bytes(header) as usize;
         
         // Verify dimension
-        if dimension != metadata.dimension {
+        if dimension != metadata.dimension || data.len() != 4 + dimension * 4 {
             return Err(format!(
                 "Embedding dimension mismatch. Expected {}, got {}",
                 metadata.dimension, dimension
@@ -319,109 +1017,322 @@ impl VectorStore {
         }
         
         // Read embedding data
-        let mut embedding = Vec::with_capacity(dimension);
-        for _ in 0..dimension {
-            let mut value_bytes = [0u8; 4];
-            file.read_exact(&mut value_bytes)
-                .map_err(|e| format!("Failed to read embedding data: {}", e))?;
-            let value = f32::from_le_bytes(value_bytes);
-            embedding.push(value);
-        }
+        let embedding = data[4..].chunks_exact(4)
+            .map(|value| f32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+            .collect();
         
         Ok(embedding)
     }
     
-    /// Search for similar embeddings
-    pub fn search_similar(&self, embedding: Vec<f32>, limit: usize) -> Result<Vec<(Uuid, f32)>, String> {
+    /// Search a collection for the embeddings matching a filter that are most similar to a query
+    pub fn search_similar(&self, collection: &str, embedding: Vec<f32>, limit: usize, filter: &VectorFilter) -> Result<Vec<(Uuid, f32)>, String> {
         // Check dimension
-        let index = self.index.read();
-        if index.dimension != embedding.len() {
-            return Err(format!(
-                "Embedding dimension mismatch. Expected {}, got {}",
-                index.dimension, embedding.len()
-            ));
-        }
+        let metric = self.check_query(collection, &embedding)?;
         
         // If no embeddings, return empty results
-        if index.count == 0 {
+        let Some(collection_index) = self.collections.get(collection) else {
             return Ok(Vec::new());
-        }
+        };
         
-        // Use FAISS if enabled
-        #[cfg(feature = "faiss")]
-        {
-            let faiss_index = self.faiss_index.read();
-            if let Some(index) = faiss_index.as_ref() {
-                let (distances, indices) = index.search(&embedding, limit as i64)
-                    .map_err(|e| format!("Failed to search with FAISS: {}", e))?;
-                
-                // Convert results
-                let mut results = Vec::with_capacity(limit);
-                for i in 0..indices.len() {
-                    let idx = indices[i];
-                    if idx >= 0 && idx < index.ids.len() as i64 {
-                        let id = index.ids[idx as usize];
-                        let distance = distances[i];
-                        // Convert distance to similarity score (lower distance = higher similarity)
-                        let similarity = 1.0 / (1.0 + distance);
-                        results.push((id, similarity));
-                    }
-                }
-                
-                return Ok(results);
-            }
+        if let Some(hnsw) = collection_index.hnsw.as_ref() {
+            let results = if filter.is_empty() {
+                hnsw.search(&embedding, limit, |_| true)
+            } else {
+                let metadata = self.metadata.read();
+                hnsw.search(&embedding, limit, |id| metadata.get(&id).is_some_and(|m| filter.matches(m)))
+            };
+            return Ok(results);
         }
         
         // Fall back to brute force search
-        self.brute_force_search(embedding, limit)
+        let results = match &collection_index.vectors {
+            CollectionVectors::Full(_) => self.brute_force_search(collection, embedding, limit, metric, filter),
+            CollectionVectors::Quantized(matrix) => {
+                // Scan the compressed vectors, then rank the best candidates at full precision
+                let candidates = {
+                    let metadata = self.metadata.read();
+                    matrix.search(&embedding, limit * RERANK_FACTOR, metric, |id| filter.is_empty() || metadata.get(&id).is_some_and(|m| filter.matches(m)))
+                };
+                self.rerank(candidates.into_iter().map(|(id, _)| id), &embedding, limit, metric)
+            }
+        };
+        
+        Ok(results)
     }
     
-    /// Brute force search for similar embeddings
-    fn brute_force_search(&self, embedding: Vec<f32>, limit: usize) -> Result<Vec<(Uuid, f32)>, String> {
-        let index = self.index.read();
-        let mut results = Vec::with_capacity(index.count.min(limit));
+    /// Search a collection for the embeddings most similar to each of several queries
+    ///
+    /// Results are in the order of the queries. Every query is checked before
+    /// any is searched, and full-precision collections without HNSW answer all
+    /// queries in a single scan of their matrix.
+    pub fn search_similar_batch(&self, collection: &str, queries: Vec<Vec<f32>>, limit: usize, filter: &VectorFilter) -> Result<Vec<Vec<(Uuid, f32)>>, String> {
+        let mut metric = DistanceMetric::default();
+        for query in &queries {
+            metric = self.check_query(collection, query)?;
+        }
         
-        // Calculate similarity for each embedding
-        for &id in &index.ids {
-            let stored_embedding = self.load_embedding(id)?;
-            let similarity = self.cosine_similarity(&embedding, &stored_embedding);
-            results.push((id, similarity));
+        let Some(collection_index) = self.collections.get(collection) else {
+            return Ok(vec![Vec::new(); queries.len()]);
+        };
+        
+        let metadata = self.metadata.read();
+        let accept = |id: Uuid| filter.is_empty() || metadata.get(&id).is_some_and(|m| filter.matches(m));
+        
+        if let Some(hnsw) = collection_index.hnsw.as_ref() {
+            return Ok(queries.iter().map(|query| hnsw.search(query, limit, accept)).collect());
         }
         
-        // Sort by similarity (descending)
-        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        let results = match &collection_index.vectors {
+            CollectionVectors::Full(matrix) => {
+                let queries: Vec<&[f32]> = queries.iter().map(Vec::as_slice).collect();
+                matrix.search_batch(&queries, limit, metric, accept)
+            }
+            CollectionVectors::Quantized(matrix) => {
+                // Scan the compressed vectors, then rank the best candidates at full precision
+                let candidates: Vec<Vec<(Uuid, f32)>> = queries.iter()
+                    .map(|query| matrix.search(query, limit * RERANK_FACTOR, metric, accept))
+                    .collect();
+                drop(metadata);
+                candidates.into_iter()
+                    .zip(&queries)
+                    .map(|(candidates, query)| self.rerank(candidates.into_iter().map(|(id, _)| id), query, limit, metric))
+                    .collect()
+            }
+        };
+        
+        Ok(results)
+    }
+    
+    /// Search a collection for similar embeddings by comparing the query with every embedding
+    ///
+    /// Gives the exact results that HNSW and quantized searches approximate.
+    pub fn search_exact(&self, collection: &str, embedding: Vec<f32>, limit: usize, filter: &VectorFilter) -> Result<Vec<(Uuid, f32)>, String> {
+        let metric = self.check_query(collection, &embedding)?;
         
-        // Limit results
-        results.truncate(limit);
+        let results = match self.collections.get(collection).map(|c| &c.vectors) {
+            Some(CollectionVectors::Quantized(_)) => {
+                let ids: Vec<Uuid> = self.metadata.read()
+                    .values()
+                    .filter(|m| m.collection == collection && filter.matches(m))
+                    .map(|m| m.id)
+                    .collect();
+                self.rerank(ids, &embedding, limit, metric)
+            }
+            _ => self.brute_force_search(collection, embedding, limit, metric, filter),
+        };
         
         Ok(results)
     }
     
-    /// Calculate cosine similarity
-    fn cosine_similarity(&self, a: &[f32], b: &[f32]) -> f32 {
-        let mut dot_product = 0.0;
-        let mut norm_a = 0.0;
-        let mut norm_b = 0.0;
+    /// Check that a query has the dimension of the collection it searches, returning its metric
+    fn check_query(&self, collection: &str, embedding: &[f32]) -> Result<DistanceMetric, String> {
+        let collection = self.get_collection(collection)?;
+        if collection.dimension != embedding.len() {
+            return Err(format!(
+                "Embedding dimension mismatch. Expected {}, got {}",
+                collection.dimension, embedding.len()
+            ));
+        }
+        
+        Ok(collection.metric)
+    }
+    
+    /// Brute force search of the full-precision matrix of a collection
+    fn brute_force_search(&self, collection: &str, embedding: Vec<f32>, limit: usize, metric: DistanceMetric, filter: &VectorFilter) -> Vec<(Uuid, f32)> {
+        let Some(CollectionVectors::Full(matrix)) = self.collections.get(collection).map(|c| &c.vectors) else {
+            return Vec::new();
+        };
+        
+        // Only consider embeddings matching the filter
+        if filter.is_empty() {
+            matrix.search(&embedding, limit, metric, |_| true)
+        } else {
+            let metadata = self.metadata.read();
+            matrix.search(&embedding, limit, metric, |id| metadata.get(&id).is_some_and(|m| filter.matches(m)))
+        }
+    }
+    
+    /// Rank embeddings by their full-precision similarity to a query, keeping the best `limit`
+    ///
+    /// Embeddings that cannot be read are left out.
+    fn rerank(&self, ids: impl IntoIterator<Item = Uuid>, query: &[f32], limit: usize, metric: DistanceMetric) -> Vec<(Uuid, f32)> {
+        let mut results: Vec<(Uuid, f32)> = ids.into_iter()
+            .filter_map(|id| self.load_embedding(id).ok().map(|embedding| (id, metric.similarity(query, &embedding))))
+            .collect();
+        results.sort_by(|a, b| b.1.total_cmp(&a.1));
+        results.truncate(limit);
+        results
+    }
+    
+    /// Get the number of embeddings and the size of those in the hot tier per namespace
+    pub fn get_namespace_stats(&self) -> HashMap<String, (usize, u64)> {
+        let mut stats: HashMap<String, (usize, u64)> = HashMap::new();
+        for metadata in self.metadata.read().values() {
+            let entry = stats.entry(metadata.namespace.clone()).or_default();
+            entry.0 += 1;
+            if self.is_resident(metadata) {
+                entry.1 += self.charged_size(metadata);
+            }
+        }
+        stats
+    }
+    
+    /// Get the UUIDs of the embeddings computed from a code file or event, in any collection
+    pub fn get_ids_by_source(&self, source_id: Uuid) -> Vec<Uuid> {
+        let mut ids: Vec<Uuid> = self.metadata.read().values()
+            .filter(|metadata| metadata.source_id == source_id)
+            .map(|metadata| metadata.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+    
+    /// Get the UUIDs of all embeddings
+    pub fn get_ids(&self) -> Vec<Uuid> {
+        self.index.read().ids.clone()
+    }
+    
+    /// Describe the store for incremental backups
+    pub fn backup_state(&self) -> StoreBackupState {
+        StoreBackupState {
+            version: Some(self.index.read().version),
+        }
+    }
+    
+    /// Check the store for orphaned blobs, missing blobs and index mismatches
+    ///
+    /// With `repair` set, orphaned blobs and temporary files are removed, entries
+    /// whose blob is missing are dropped and the index is rebuilt from the metadata.
+    pub fn verify(&mut self, repair: bool) -> Result<Vec<ConsistencyIssue>, String> {
+        let mut issues = check_temp_files(&self.path, "vectors", repair)?;
+        
+        let blobs = self.segments.keys();
+        let referenced: HashSet<String> = self.metadata.read()
+            .values()
+            .map(|m| m.file_path.clone())
+            .collect();
         
-        for i in 0..a.len() {
-            dot_product += a[i] * b[i];
-            norm_a += a[i] * a[i];
-            norm_b += b[i] * b[i];
+        // Blobs without metadata
+        for file in blobs.difference(&referenced) {
+            if repair {
+                self.segments.remove(file)?;
+            }
+            issues.push(ConsistencyIssue::OrphanedBlob {
+                store: "vectors".to_string(),
+                file: file.clone(),
+            });
         }
         
-        let norm_a = norm_a.sqrt();
-        let norm_b = norm_b.sqrt();
+        // Metadata without blobs
+        let missing: Vec<(Uuid, u64)> = self.metadata.read()
+            .values()
+            .filter(|m| !blobs.contains(&m.file_path))
+            .map(|m| (m.id, if self.is_blob_charged(m) { m.size } else { 0 }))
+            .collect();
+        for (id, size) in missing {
+            if repair {
+                self.current_size = self.current_size.saturating_sub(size);
+                self.log_record(VectorWalRecord::Delete(id))?;
+                self.remove_from_collections(id);
+            }
+            issues.push(ConsistencyIssue::MissingBlob {
+                store: "vectors".to_string(),
+                id,
+            });
+        }
+        
+        // Index count out of sync with the indexed IDs
+        let (count, actual) = {
+            let index = self.index.read();
+            let metadata = self.metadata.read();
+            let unique_ids: HashSet<&Uuid> = index.ids.iter().collect();
+            let in_sync = index.count == index.ids.len()
+                && unique_ids.len() == index.ids.len()
+                && index.ids.iter().all(|id| metadata.contains_key(id))
+                && metadata.len() == index.ids.len();
+            if in_sync {
+                (None, metadata.len())
+            } else {
+                (Some(index.count), metadata.len())
+            }
+        };
+        if let Some(count) = count {
+            if repair {
+                self.rebuild_index();
+            }
+            issues.push(ConsistencyIssue::CountMismatch {
+                store: "vectors".to_string(),
+                count,
+                actual,
+            });
+        }
         
-        if norm_a == 0.0 || norm_b == 0.0 {
-            return 0.0;
+        // Make the repaired state durable
+        if repair && !issues.is_empty() {
+            self.checkpoint()?;
         }
         
-        dot_product / (norm_a * norm_b)
+        Ok(issues)
+    }
+    
+    /// Rebuild the index from the metadata
+    fn rebuild_index(&self) {
+        let mut index = self.index.write();
+        let metadata = self.metadata.read();
+        
+        // Keep the existing order for known entries, append the rest by creation time
+        let mut seen = HashSet::new();
+        let mut ids: Vec<Uuid> = index.ids.iter()
+            .filter(|id| metadata.contains_key(id) && seen.insert(**id))
+            .cloned()
+            .collect();
+        let mut remaining: Vec<_> = metadata.values()
+            .filter(|m| !seen.contains(&m.id))
+            .collect();
+        remaining.sort_by_key(|m| m.created_at);
+        ids.extend(remaining.into_iter().map(|m| m.id));
+        
+        index.count = ids.len();
+        index.ids = ids;
+        index.version += 1;
+    }
+    
+    /// Remove an embedding from the search structures of its collection
+    fn remove_from_collections(&mut self, id: Uuid) {
+        for collection in self.collections.values_mut() {
+            if collection.remove(id) {
+                return;
+            }
+        }
+    }
+    
+    /// Get embedding metadata by UUID
+    pub fn get_embedding_metadata(&self, id: Uuid) -> Result<EmbeddingMetadata, String> {
+        let metadata_lock = self.metadata.read();
+        metadata_lock.get(&id)
+            .cloned()
+            .ok_or_else(|| format!("Embedding with ID {} not found", id))
+    }
+    
+    /// Get all embedding metadata
+    pub fn get_all_metadata(&self) -> Vec<EmbeddingMetadata> {
+        let metadata_lock = self.metadata.read();
+        metadata_lock.values().cloned().collect()
     }
     
-    /// Get the size of the store
+    /// Get the size of the store: the hot blobs and the resident vectors
     pub fn get_size(&self) -> u64 {
-        self.current_size
+        self.current_size + self.resident_size()
+    }
+    
+    /// Get the maximum size of the store
+    pub fn get_max_size(&self) -> u64 {
+        self.max_size
+    }
+    
+    /// Change the maximum size of the store
+    pub fn set_max_size(&mut self, max_size: u64) {
+        self.max_size = max_size;
     }
     
     /// Get the number of entries
@@ -432,62 +1343,24 @@ impl VectorStore {
     /// Delete an embedding
     pub fn delete_embedding(&mut self, id: Uuid) -> Result<(), String> {
         // Get metadata
-        let mut metadata_lock = self.metadata.write();
+        let metadata_lock = self.metadata.read();
         let metadata = metadata_lock.get(&id)
             .ok_or_else(|| format!("Embedding with ID {} not found", id))?;
         
-        // Calculate size
-        let embedding_size = metadata.size;
-        
-        // Remove file
-        let file_path = self.path.join(&metadata.file_path);
-        fs::remove_file(&file_path)
-            .map_err(|e| format!("Failed to remove embedding file: {}", e))?;
+        // Calculate size; uncharged blobs take no space in the store
+        let file_name = metadata.file_path.clone();
+        let embedding_size = if self.is_blob_charged(metadata) { metadata.size } else { 0 };
         
-        // Update index
-        {
-            let mut index = self.index.write();
-            index.ids.retain(|&i| i != id);
-            index.count -= 1;
-            index.version += 1;
-        }
-        
-        // Remove from metadata
-        metadata_lock.remove(&id);
+        // Remove blob
+        drop(metadata_lock);
+        self.segments.remove(&file_name)?;
         
         // Update size
         self.current_size -= embedding_size;
         
-        // Rebuild FAISS index if enabled
-        #[cfg(feature = "faiss")]
-        {
-            // Rebuilding FAISS index means adding all embeddings again
-            let mut faiss_index = self.faiss_index.write();
-            if faiss_index.is_some() {
-                let dimension = self.index.read().dimension;
-                *faiss_index = Some(
-                    faiss::Index::new_with_dimension(dimension as i32)
-                        .map_err(|e| format!("Failed to create FAISS index: {}", e))?,
-                );
-                
-                // Re-add all embeddings
-                let index = self.index.read();
-                for (i, &id) in index.ids.iter().enumerate() {
-                    let embedding = self.load_embedding(id)?;
-                    faiss_index.as_mut().unwrap().add_with_ids(
-                        &embedding,
-                        &[i as i64],
-                    ).map_err(|e| format!("Failed to add embedding to FAISS index: {}", e))?;
-                }
-            }
-        }
-        
-        // Persist index and metadata
-        self.persist_index()?;
-        self.persist_metadata()?;
-        
-        #[cfg(feature = "faiss")]
-        self.persist_faiss_index()?;
+        // Record the change
+        self.log_record(VectorWalRecord::Delete(id))?;
+        self.remove_from_collections(id);
         
         Ok(())
     }
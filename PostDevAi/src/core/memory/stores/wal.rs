@@ -0,0 +1,161 @@
+use std::path::PathBuf;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Default number of WAL records after which a store checkpoints its snapshot files
+pub const DEFAULT_CHECKPOINT_THRESHOLD: usize = 1000;
+
+/// Write-Ahead Log for RAM-Lake stores
+///
+/// Append-only log of store mutations (one JSON record per line). Stores append
+/// a record for every change instead of rewriting their snapshot files, replay
+/// the log on startup and truncate it once the snapshot has been checkpointed.
+pub struct WriteAheadLog {
+    /// Path to the log file
+    path: PathBuf,
+
+    /// Open handle used for appending
+    file: fs::File,
+
+    /// Number of records since the last checkpoint
+    records: usize,
+
+    /// Size of the log file in bytes
+    size: u64,
+}
+
+impl WriteAheadLog {
+    /// Open (or create) a write-ahead log
+    pub fn open(path: PathBuf) -> Result<Self, String> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to open WAL file: {}", e))?;
+
+        let size = file.metadata()
+            .map_err(|e| format!("Failed to read WAL metadata: {}", e))?
+            .len();
+
+        Ok(Self {
+            path,
+            file,
+            records: 0,
+            size,
+        })
+    }
+
+    /// Read all records from the log
+    ///
+    /// A trailing record that cannot be parsed is treated as a torn write from a
+    /// crash: it is dropped and the log is truncated back to the last good record.
+    pub fn replay<T: DeserializeOwned>(&mut self) -> Result<Vec<T>, String> {
+        let file = fs::File::open(&self.path)
+            .map_err(|e| format!("Failed to open WAL file: {}", e))?;
+        let mut reader = BufReader::new(file);
+
+        let mut records = Vec::new();
+        let mut valid_len = 0u64;
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)
+                .map_err(|e| format!("Failed to read WAL file: {}", e))?;
+            if read == 0 {
+                break;
+            }
+
+            // A record without its newline was never completely written
+            if !line.ends_with('\n') {
+                break;
+            }
+
+            match serde_json::from_str(line.trim_end()) {
+                Ok(record) => {
+                    records.push(record);
+                    valid_len += read as u64;
+                }
+                Err(_) => break,
+            }
+        }
+
+        // Drop anything after the last good record
+        if valid_len < self.size {
+            self.file.set_len(valid_len)
+                .map_err(|e| format!("Failed to truncate WAL file: {}", e))?;
+            self.size = valid_len;
+        }
+
+        self.records = records.len();
+
+        Ok(records)
+    }
+
+    /// Append a record to the log
+    ///
+    /// The record is synced to the file before returning, so it survives a
+    /// crash once the call succeeds.
+    pub fn append<T: Serialize>(&mut self, record: &T) -> Result<(), String> {
+        let mut line = serde_json::to_vec(record)
+            .map_err(|e| format!("Failed to serialize WAL record: {}", e))?;
+        line.push(b'\n');
+
+        self.file.write_all(&line)
+            .map_err(|e| format!("Failed to write WAL record: {}", e))?;
+        self.sync()?;
+
+        self.records += 1;
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    /// Append several records to the log with a single write and sync
+    pub fn append_all<T: Serialize>(&mut self, records: &[T]) -> Result<(), String> {
+        let mut lines = Vec::new();
+        for record in records {
+            serde_json::to_writer(&mut lines, record)
+                .map_err(|e| format!("Failed to serialize WAL record: {}", e))?;
+            lines.push(b'\n');
+        }
+
+        self.file.write_all(&lines)
+            .map_err(|e| format!("Failed to write WAL records: {}", e))?;
+        self.sync()?;
+
+        self.records += records.len();
+        self.size += lines.len() as u64;
+
+        Ok(())
+    }
+
+    /// Discard all records (called after a checkpoint)
+    pub fn reset(&mut self) -> Result<(), String> {
+        self.file.set_len(0)
+            .map_err(|e| format!("Failed to truncate WAL file: {}", e))?;
+
+        self.records = 0;
+        self.size = 0;
+
+        Ok(())
+    }
+
+    /// Flush appended records to the underlying storage
+    fn sync(&self) -> Result<(), String> {
+        self.file.sync_data()
+            .map_err(|e| format!("Failed to sync WAL file: {}", e))
+    }
+
+    /// Number of records since the last checkpoint
+    pub fn record_count(&self) -> usize {
+        self.records
+    }
+
+    /// Size of the log file in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
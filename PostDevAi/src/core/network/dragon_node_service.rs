@@ -5,28 +5,28 @@
 use std::sync::Arc;
 use parking_lot::RwLock;
 use tonic::{Request, Response, Status};
-use uuid::Uuid;
 
-use crate::core::memory::ramlake::RamLake;
+use crate::core::memory::ramlake::AsyncRamLake;
+use crate::core::network::ramlake_service::RamLakeService;
 use crate::mlx::models::MLXModelManager;
 
 // Import our mocked proto types
 use crate::proto::postdevai::*;
-use crate::proto::{search_similar_response, get_related_response};
+use crate::proto::get_related_response;
 
 // Import mocked service definition
 pub use crate::proto::DragonNodeService;
 
 // Empty DragonNodeServiceImpl struct to make the compiler happy
 pub struct DragonNodeServiceImpl {
-    ram_lake: Arc<RwLock<RamLake>>,
+    ram_lake: RamLakeService,
     model_manager: Arc<RwLock<MLXModelManager>>,
 }
 
 impl DragonNodeServiceImpl {
-    pub fn new(ram_lake: Arc<RwLock<RamLake>>, model_manager: Arc<RwLock<MLXModelManager>>) -> Self {
+    pub fn new(ram_lake: AsyncRamLake, model_manager: Arc<RwLock<MLXModelManager>>) -> Self {
         Self {
-            ram_lake,
+            ram_lake: RamLakeService::new(ram_lake),
             model_manager,
         }
     }
@@ -34,5 +34,15 @@ impl DragonNodeServiceImpl {
 
 #[tonic::async_trait]
 impl DragonNodeService for DragonNodeServiceImpl {
-    // Implementation temporarily removed for individual branch build
+    async fn search_similar(&self, request: Request<SearchSimilarRequest>) -> Result<Response<SearchSimilarResponse>, Status> {
+        self.ram_lake.search_similar(request).await
+    }
+
+    async fn store_embeddings_batch(&self, request: Request<StoreEmbeddingsBatchRequest>) -> Result<Response<StoreEmbeddingsBatchResponse>, Status> {
+        self.ram_lake.store_embeddings_batch(request).await
+    }
+
+    async fn search_similar_batch(&self, request: Request<SearchSimilarBatchRequest>) -> Result<Response<SearchSimilarBatchResponse>, Status> {
+        self.ram_lake.search_similar_batch(request).await
+    }
 }
\ No newline at end of file
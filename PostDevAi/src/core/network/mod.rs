@@ -1,2 +1,3 @@
 // Network module exports
-pub mod dragon_node_service;
\ No newline at end of file
+pub mod dragon_node_service;
+pub mod ramlake_service;
\ No newline at end of file
@@ -0,0 +1,136 @@
+// RAM-Lake RPCs of the Dragon Node service
+//
+// Kept apart from `DragonNodeServiceImpl` so the handlers build and are
+// tested without the MLX model manager.
+
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+use crate::core::memory::ramlake::{AsyncRamLake, VectorCollection, DEFAULT_COLLECTION};
+
+use crate::proto::postdevai::*;
+use crate::proto::search_similar_response;
+
+pub use crate::proto::DragonNodeService;
+
+/// Serves the RAM-Lake RPCs of the Dragon Node service through an `AsyncRamLake`
+pub struct RamLakeService {
+    ram_lake: AsyncRamLake,
+}
+
+impl RamLakeService {
+    pub fn new(ram_lake: AsyncRamLake) -> Self {
+        Self { ram_lake }
+    }
+}
+
+/// Collection named by a request; empty names the default collection
+fn collection_name(collection: String) -> String {
+    if collection.is_empty() {
+        DEFAULT_COLLECTION.to_string()
+    } else {
+        collection
+    }
+}
+
+/// Check that embeddings match the dimension of a known collection
+///
+/// The default collection is created by its first embedding, so it may not exist yet.
+fn check_dimensions<'a>(collections: &[VectorCollection], collection: &str, mut embeddings: impl Iterator<Item = &'a Vec<f32>>) -> Result<(), String> {
+    let Some(known) = collections.iter().find(|known| known.name == collection) else {
+        return if collection == DEFAULT_COLLECTION {
+            Ok(())
+        } else {
+            Err(format!("Unknown vector collection '{}'", collection))
+        };
+    };
+
+    match embeddings.find(|embedding| embedding.len() != known.dimension) {
+        Some(embedding) => Err(format!(
+            "Embedding has dimension {} but collection '{}' expects {}",
+            embedding.len(), collection, known.dimension
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Parse a UUID sent by a client
+fn parse_uuid(id: Option<crate::proto::postdevai::Uuid>) -> Result<Uuid, String> {
+    let id = id.ok_or_else(|| "Missing UUID".to_string())?;
+    Uuid::parse_str(&id.value)
+        .map_err(|e| format!("Invalid UUID {}: {}", id.value, e))
+}
+
+/// Number of results a request asks for
+fn result_limit(limit: i32) -> Result<usize, String> {
+    usize::try_from(limit)
+        .map_err(|_| format!("Invalid result limit {}", limit))
+}
+
+/// Convert search results to their response
+fn search_response(results: Vec<(Uuid, f32)>) -> SearchSimilarResponse {
+    SearchSimilarResponse {
+        results: results.into_iter()
+            .map(|(id, score)| search_similar_response::Result {
+                id: Some(crate::proto::postdevai::Uuid { value: id.to_string() }),
+                score,
+            })
+            .collect(),
+    }
+}
+
+#[tonic::async_trait]
+impl DragonNodeService for RamLakeService {
+    async fn search_similar(&self, request: Request<SearchSimilarRequest>) -> Result<Response<SearchSimilarResponse>, Status> {
+        let request = request.into_inner();
+        let limit = result_limit(request.limit).map_err(Status::invalid_argument)?;
+        let collection = collection_name(request.collection);
+        let collections = self.ram_lake.list_vector_collections().await
+            .map_err(Status::internal)?;
+        check_dimensions(&collections, &collection, [&request.embedding].into_iter())
+            .map_err(Status::invalid_argument)?;
+
+        let results = self.ram_lake.search_similar(collection, request.embedding, limit).await
+            .map_err(Status::internal)?;
+
+        Ok(Response::new(search_response(results)))
+    }
+
+    async fn store_embeddings_batch(&self, request: Request<StoreEmbeddingsBatchRequest>) -> Result<Response<StoreEmbeddingsBatchResponse>, Status> {
+        let request = request.into_inner();
+        let embeddings = request.embeddings.into_iter()
+            .map(|entry| Ok((parse_uuid(entry.id)?, entry.embedding)))
+            .collect::<Result<Vec<_>, String>>()
+            .map_err(Status::invalid_argument)?;
+        let collection = collection_name(request.collection);
+        let collections = self.ram_lake.list_vector_collections().await
+            .map_err(Status::internal)?;
+        check_dimensions(&collections, &collection, embeddings.iter().map(|(_, embedding)| embedding))
+            .map_err(Status::invalid_argument)?;
+        let stored = embeddings.len() as u32;
+
+        // The batch is stored whole or not at all
+        self.ram_lake.store_embeddings_batch(collection, embeddings).await
+            .map_err(Status::internal)?;
+
+        Ok(Response::new(StoreEmbeddingsBatchResponse { stored }))
+    }
+
+    async fn search_similar_batch(&self, request: Request<SearchSimilarBatchRequest>) -> Result<Response<SearchSimilarBatchResponse>, Status> {
+        let request = request.into_inner();
+        let limit = result_limit(request.limit).map_err(Status::invalid_argument)?;
+        let collection = collection_name(request.collection);
+        let queries: Vec<Vec<f32>> = request.queries.into_iter().map(|query| query.embedding).collect();
+        let collections = self.ram_lake.list_vector_collections().await
+            .map_err(Status::internal)?;
+        check_dimensions(&collections, &collection, queries.iter())
+            .map_err(Status::invalid_argument)?;
+
+        let results = self.ram_lake.search_similar_batch(collection, queries, limit).await
+            .map_err(Status::internal)?;
+
+        Ok(Response::new(SearchSimilarBatchResponse {
+            responses: results.into_iter().map(search_response).collect(),
+        }))
+    }
+}
@@ -7,11 +7,13 @@ pub mod proto;
 pub mod core {
     pub mod memory {
         pub mod ramlake;
+        pub mod stores;
     }
     pub mod indexing;
     pub mod monitoring;
     pub mod network {
         pub mod dragon_node_service;
+        pub mod ramlake_service;
     }
 }
 
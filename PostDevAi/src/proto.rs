@@ -33,12 +33,74 @@ pub mod postdevai {
         pub value: String,
     }
     
-    // Empty mock implementations for the services
+    // Search similar request
+    #[derive(Debug, Clone)]
+    pub struct SearchSimilarRequest {
+        pub embedding: Vec<f32>,
+        pub limit: i32,
+        pub collection: String,
+    }
+    
+    // Search similar response
+    #[derive(Debug, Clone)]
+    pub struct SearchSimilarResponse {
+        pub results: Vec<search_similar_response::Result>,
+    }
+    
+    // Store embeddings batch request
+    pub mod store_embeddings_batch_request {
+        #[derive(Debug, Clone)]
+        pub struct Entry {
+            pub id: Option<super::Uuid>,
+            pub embedding: Vec<f32>,
+        }
+    }
+    
+    #[derive(Debug, Clone)]
+    pub struct StoreEmbeddingsBatchRequest {
+        pub collection: String,
+        pub embeddings: Vec<store_embeddings_batch_request::Entry>,
+    }
+    
+    // Store embeddings batch response
+    #[derive(Debug, Clone)]
+    pub struct StoreEmbeddingsBatchResponse {
+        pub stored: u32,
+    }
+    
+    // Search similar batch request
+    pub mod search_similar_batch_request {
+        #[derive(Debug, Clone)]
+        pub struct Query {
+            pub embedding: Vec<f32>,
+        }
+    }
+    
+    #[derive(Debug, Clone)]
+    pub struct SearchSimilarBatchRequest {
+        pub collection: String,
+        pub queries: Vec<search_similar_batch_request::Query>,
+        pub limit: i32,
+    }
+    
+    // Search similar batch response
+    #[derive(Debug, Clone)]
+    pub struct SearchSimilarBatchResponse {
+        pub responses: Vec<SearchSimilarResponse>,
+    }
+    
+    // Mock service definitions, limited to the RPCs that are implemented
     pub mod dragon_node_service_server {
         use tonic::{Request, Response, Status};
         
         #[tonic::async_trait]
-        pub trait DragonNodeService {}
+        pub trait DragonNodeService: Send + Sync + 'static {
+            async fn search_similar(&self, request: Request<super::SearchSimilarRequest>) -> Result<Response<super::SearchSimilarResponse>, Status>;
+            
+            async fn store_embeddings_batch(&self, request: Request<super::StoreEmbeddingsBatchRequest>) -> Result<Response<super::StoreEmbeddingsBatchResponse>, Status>;
+            
+            async fn search_similar_batch(&self, request: Request<super::SearchSimilarBatchRequest>) -> Result<Response<super::SearchSimilarBatchResponse>, Status>;
+        }
         
         pub struct DragonNodeServiceServer<T>(pub T);
     }
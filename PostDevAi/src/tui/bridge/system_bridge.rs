@@ -202,10 +202,18 @@ impl SystemBridge {
                 vector_store_size: 1024 * 1024 * 1024 * 20,  // 20 GB
                 code_store_size: 1024 * 1024 * 1024 * 15,    // 15 GB
                 history_store_size: 1024 * 1024 * 1024 * 10, // 10 GB
+                code_logical_size: 1024 * 1024 * 1024 * 15,  // 15 GB
+                history_logical_size: 1024 * 1024 * 1024 * 10, // 10 GB
                 metadata_store_size: 1024 * 1024 * 1024 * 5, // 5 GB
                 indexed_files: 1256,
                 vector_entries: 25789,
                 history_events: 3467,
+                namespaces: HashMap::new(),
+                evicted_entries: 128,
+                evicted_size: 1024 * 1024 * 512, // 512 MB
+                recent_evictions: Vec::new(),
+                cold_size: 0,
+                vector_collections: HashMap::new(),
             }
         }
     }
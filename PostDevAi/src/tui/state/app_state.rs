@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::time::{Instant, Duration};
 use serde::{Serialize, Deserialize};
 
@@ -129,10 +129,18 @@ impl AppState {
                 vector_store_size: 0,
                 code_store_size: 0,
                 history_store_size: 0,
+                code_logical_size: 0,
+                history_logical_size: 0,
                 metadata_store_size: 0,
                 indexed_files: 0,
                 vector_entries: 0,
                 history_events: 0,
+                namespaces: HashMap::new(),
+                evicted_entries: 0,
+                evicted_size: 0,
+                recent_evictions: Vec::new(),
+                cold_size: 0,
+                vector_collections: HashMap::new(),
             },
             system_state: SystemState::default(),
             loaded_models: Vec::new(),
@@ -6,8 +6,10 @@ use ratatui::text::{Text, Span, Spans};
 use ratatui::symbols;
 
 use crate::tui::state::app_state::AppState;
+use crate::core::memory::ramlake::EntityKind;
 
 const GB: u64 = 1024 * 1024 * 1024;
+const KB: u64 = 1024;
 
 /// Render the RAM-Lake view
 pub fn render_ramlake<B: ratatui::backend::Backend>(frame: &mut Frame<B>, state: &AppState) {
@@ -158,6 +160,10 @@ fn render_stores_breakdown<B: ratatui::backend::Backend>(frame: &mut Frame<B>, s
             Span::styled("Free Space: ", Style::default().add_modifier(Modifier::BOLD)),
             Span::raw(format!("{:.2} GB", (metrics.total_size - metrics.used_size) as f64 / GB as f64)),
         ]),
+        Spans::from(vec![
+            Span::styled("Cold Tier: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(format!("{:.2} GB", metrics.cold_size as f64 / GB as f64)),
+        ]),
     ];
     
     // Create paragraphs for each column
@@ -178,6 +184,21 @@ fn render_stores_breakdown<B: ratatui::backend::Backend>(frame: &mut Frame<B>, s
 
 /// Render the store details and charts
 fn render_store_details<B: ratatui::backend::Backend>(frame: &mut Frame<B>, state: &AppState, area: Rect) {
+    // Split the area into the size chart and the eviction log
+    let detail_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(60),  // Store size chart
+            Constraint::Percentage(40),  // Recent evictions
+        ].as_ref())
+        .split(area);
+    
+    render_store_chart(frame, state, detail_chunks[0]);
+    render_evictions(frame, state, detail_chunks[1]);
+}
+
+/// Render the store size distribution chart
+fn render_store_chart<B: ratatui::backend::Backend>(frame: &mut Frame<B>, state: &AppState, area: Rect) {
     let metrics = &state.ramlake_metrics;
     
     // Create data for the barchart
@@ -206,4 +227,38 @@ fn render_store_details<B: ratatui::backend::Backend>(frame: &mut Frame<B>, stat
         
     // Render the bar chart
     frame.render_widget(barchart, area);
-}
\ No newline at end of file
+}
+
+/// Render the entries evicted to make room, newest first
+fn render_evictions<B: ratatui::backend::Backend>(frame: &mut Frame<B>, state: &AppState, area: Rect) {
+    let metrics = &state.ramlake_metrics;
+    
+    let mut lines = vec![
+        Spans::from(vec![
+            Span::styled("Evicted: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(format!("{} entries, {:.2} GB", metrics.evicted_entries, metrics.evicted_size as f64 / GB as f64)),
+        ]),
+    ];
+    
+    for eviction in &metrics.recent_evictions {
+        let (kind, color) = match eviction.kind {
+            EntityKind::Code => ("code", Color::Green),
+            EntityKind::Embedding => ("embedding", Color::Blue),
+            EntityKind::Event => ("event", Color::Yellow),
+        };
+        
+        lines.push(Spans::from(vec![
+            Span::raw(format!("{} ", eviction.timestamp.format("%H:%M:%S"))),
+            Span::styled(format!("{:<10}", kind), Style::default().fg(color)),
+            Span::raw(format!("{} ", eviction.namespace)),
+            Span::styled(format!("{} KB", eviction.size / KB), Style::default().fg(Color::Gray)),
+        ]));
+    }
+    
+    let evictions_paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Recent Evictions"))
+        .alignment(ratatui::layout::Alignment::Left)
+        .wrap(Wrap { trim: true });
+    
+    frame.render_widget(evictions_paragraph, area);
+}
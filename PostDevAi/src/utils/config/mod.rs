@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use serde::{Serialize, Deserialize};
@@ -25,6 +26,318 @@ pub struct RamLakeConfig {
     pub backup_interval: u64,
     pub backup_path: String,
     pub allocation: StoreAllocationConfig,
+    #[serde(default = "default_wal_checkpoint_threshold")]
+    pub wal_checkpoint_threshold: usize,
+    #[serde(default)]
+    pub repair_on_startup: bool,
+    #[serde(default)]
+    pub backup_retention: BackupRetentionConfig,
+    #[serde(default)]
+    pub restore_on_empty: bool,
+    #[serde(default)]
+    pub incremental_backup: IncrementalBackupConfig,
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout: u64,
+    #[serde(default)]
+    pub namespace_quotas: HashMap<String, u64>,
+    #[serde(default)]
+    pub revision_retention: RevisionRetentionConfig,
+    #[serde(default)]
+    pub code_compression: BlobCompressionConfig,
+    #[serde(default)]
+    pub history_compression: BlobCompressionConfig,
+    #[serde(default)]
+    pub segment_storage: SegmentStorageConfig,
+    #[serde(default)]
+    pub eviction: EvictionConfig,
+    #[serde(default)]
+    pub cold_tier: ColdTierConfig,
+    #[serde(default)]
+    pub adaptive_allocation: AdaptiveAllocationConfig,
+    #[serde(default)]
+    pub hnsw: HnswConfig,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackupRetentionConfig {
+    pub keep_last: Option<usize>,
+    pub max_age_secs: Option<u64>,
+    pub max_total_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevisionRetentionConfig {
+    #[serde(default = "default_keep_revisions")]
+    pub keep_last: Option<usize>,
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+}
+
+impl Default for RevisionRetentionConfig {
+    fn default() -> Self {
+        Self {
+            keep_last: default_keep_revisions(),
+            max_age_secs: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlobCompressionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_compression_level")]
+    pub level: i32,
+    #[serde(default)]
+    pub dictionary: Option<String>,
+}
+
+impl Default for BlobCompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            level: default_compression_level(),
+            dictionary: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentStorageConfig {
+    #[serde(default = "default_segment_size")]
+    pub segment_size: u64,
+    #[serde(default = "default_compaction_ratio")]
+    pub compaction_ratio: f64,
+    #[serde(default = "default_compaction_interval")]
+    pub compaction_interval: u64,
+}
+
+impl Default for SegmentStorageConfig {
+    fn default() -> Self {
+        Self {
+            segment_size: default_segment_size(),
+            compaction_ratio: default_compaction_ratio(),
+            compaction_interval: default_compaction_interval(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvictionConfig {
+    #[serde(default = "default_eviction_policy")]
+    pub policy: String,
+    #[serde(default = "default_eviction_priorities")]
+    pub priorities: HashMap<String, u32>,
+}
+
+impl Default for EvictionConfig {
+    fn default() -> Self {
+        Self {
+            policy: default_eviction_policy(),
+            priorities: default_eviction_priorities(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColdTierConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_cold_tier_path")]
+    pub path: String,
+    #[serde(default = "default_demote_after_secs")]
+    pub demote_after_secs: u64,
+    #[serde(default = "default_tiering_check_interval")]
+    pub check_interval: u64,
+}
+
+impl Default for ColdTierConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_cold_tier_path(),
+            demote_after_secs: default_demote_after_secs(),
+            check_interval: default_tiering_check_interval(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdaptiveAllocationConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_rebalance_interval")]
+    pub interval: u64,
+    #[serde(default = "default_growth_horizon_secs")]
+    pub growth_horizon_secs: u64,
+    #[serde(default = "default_allocation_headroom")]
+    pub headroom: f32,
+    #[serde(default = "default_min_allocation")]
+    pub min: StoreAllocationConfig,
+    #[serde(default = "default_max_allocation")]
+    pub max: StoreAllocationConfig,
+}
+
+impl Default for AdaptiveAllocationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: default_rebalance_interval(),
+            growth_horizon_secs: default_growth_horizon_secs(),
+            headroom: default_allocation_headroom(),
+            min: default_min_allocation(),
+            max: default_max_allocation(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HnswConfig {
+    #[serde(default = "default_hnsw_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_hnsw_m")]
+    pub m: usize,
+    #[serde(default = "default_hnsw_ef_construction")]
+    pub ef_construction: usize,
+    #[serde(default = "default_hnsw_ef_search")]
+    pub ef_search: usize,
+}
+
+impl Default for HnswConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_hnsw_enabled(),
+            m: default_hnsw_m(),
+            ef_construction: default_hnsw_ef_construction(),
+            ef_search: default_hnsw_ef_search(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncrementalBackupConfig {
+    #[serde(default = "default_incremental_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_full_every")]
+    pub full_every: usize,
+    #[serde(default = "default_compression_level")]
+    pub compression_level: i32,
+}
+
+impl Default for IncrementalBackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_incremental_enabled(),
+            full_every: default_full_every(),
+            compression_level: default_compression_level(),
+        }
+    }
+}
+
+fn default_wal_checkpoint_threshold() -> usize {
+    1000
+}
+
+fn default_shutdown_timeout() -> u64 {
+    30
+}
+
+fn default_keep_revisions() -> Option<usize> {
+    Some(10)
+}
+
+fn default_incremental_enabled() -> bool {
+    true
+}
+
+fn default_full_every() -> usize {
+    24
+}
+
+fn default_compression_level() -> i32 {
+    3
+}
+
+fn default_segment_size() -> u64 {
+    64 * 1024 * 1024
+}
+
+fn default_compaction_ratio() -> f64 {
+    0.5
+}
+
+fn default_compaction_interval() -> u64 {
+    300
+}
+
+fn default_eviction_policy() -> String {
+    "lru".to_string()
+}
+
+fn default_cold_tier_path() -> String {
+    "/var/lib/ramlake/cold".to_string()
+}
+
+fn default_demote_after_secs() -> u64 {
+    7 * 24 * 3600
+}
+
+fn default_tiering_check_interval() -> u64 {
+    600
+}
+
+fn default_hnsw_enabled() -> bool {
+    true
+}
+
+fn default_hnsw_m() -> usize {
+    16
+}
+
+fn default_hnsw_ef_construction() -> usize {
+    200
+}
+
+fn default_hnsw_ef_search() -> usize {
+    64
+}
+
+fn default_rebalance_interval() -> u64 {
+    60
+}
+
+fn default_growth_horizon_secs() -> u64 {
+    3600
+}
+
+fn default_allocation_headroom() -> f32 {
+    0.2
+}
+
+fn default_min_allocation() -> StoreAllocationConfig {
+    StoreAllocationConfig {
+        vector_store: 0.05,
+        code_store: 0.05,
+        history_store: 0.05,
+        metadata_store: 0.02,
+    }
+}
+
+fn default_max_allocation() -> StoreAllocationConfig {
+    StoreAllocationConfig {
+        vector_store: 0.7,
+        code_store: 0.7,
+        history_store: 0.5,
+        metadata_store: 0.3,
+    }
+}
+
+fn default_eviction_priorities() -> HashMap<String, u32> {
+    HashMap::from([
+        ("event".to_string(), 0),
+        ("embedding".to_string(), 1),
+        ("code".to_string(), 2),
+    ])
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -139,6 +452,64 @@ pub fn create_default_config() -> Config {
                 history_store: 0.2,
                 metadata_store: 0.1,
             },
+            wal_checkpoint_threshold: 1000,
+            repair_on_startup: true,
+            backup_retention: BackupRetentionConfig {
+                keep_last: Some(24),
+                max_age_secs: Some(7 * 24 * 3600), // 1 week
+                max_total_bytes: None,
+            },
+            restore_on_empty: true,
+            incremental_backup: IncrementalBackupConfig {
+                enabled: true,
+                full_every: 24, // One full backup per day
+                compression_level: 3,
+            },
+            shutdown_timeout: 30, // Seconds for the final flush on exit
+            namespace_quotas: HashMap::new(), // No per-project limits
+            revision_retention: RevisionRetentionConfig {
+                keep_last: Some(20),
+                max_age_secs: Some(30 * 24 * 3600), // 30 days
+            },
+            code_compression: BlobCompressionConfig {
+                enabled: true,
+                level: 3,
+                dictionary: None,
+            },
+            history_compression: BlobCompressionConfig {
+                enabled: true,
+                level: 3,
+                dictionary: None,
+            },
+            segment_storage: SegmentStorageConfig {
+                segment_size: 256 * 1024 * 1024, // 256MB
+                compaction_ratio: 0.5,
+                compaction_interval: 300, // 5 minutes
+            },
+            eviction: EvictionConfig {
+                policy: "priority".to_string(), // Drop events before embeddings before code
+                priorities: default_eviction_priorities(),
+            },
+            cold_tier: ColdTierConfig {
+                enabled: true,
+                path: "/var/lib/ramlake/cold".to_string(),
+                demote_after_secs: 3 * 24 * 3600, // 3 days
+                check_interval: 600, // 10 minutes
+            },
+            adaptive_allocation: AdaptiveAllocationConfig {
+                enabled: true,
+                interval: 60, // Rebalance every minute
+                growth_horizon_secs: 3600, // Room for an hour of growth
+                headroom: 0.2,
+                min: default_min_allocation(),
+                max: default_max_allocation(),
+            },
+            hnsw: HnswConfig {
+                enabled: true,
+                m: 16,
+                ef_construction: 200,
+                ef_search: 64,
+            },
         },
         models: ModelsConfig {
             device: "gpu".to_string(),
@@ -1,7 +1,9 @@
 // Basic file system operations
 
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use serde::Serialize;
 
 /// Check if a path exists
 pub fn path_exists<P: AsRef<Path>>(path: P) -> bool {
@@ -33,6 +35,52 @@ pub fn write_file<P: AsRef<Path>>(path: P, content: &str) -> Result<(), String>
     fs::write(path, content).map_err(|e| format!("Failed to write file: {}", e))
 }
 
+/// Write bytes to a file atomically
+///
+/// The content is written to a temporary file next to the target, flushed to
+/// disk and renamed over the target, so readers never observe a partial file.
+pub fn write_atomic<P: AsRef<Path>>(path: P, content: &[u8]) -> Result<(), String> {
+    let path = path.as_ref();
+    let tmp_path = temp_path_for(path);
+    
+    let mut file = fs::File::create(&tmp_path)
+        .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+    file.write_all(content)
+        .map_err(|e| format!("Failed to write temporary file: {}", e))?;
+    file.sync_all()
+        .map_err(|e| format!("Failed to sync temporary file: {}", e))?;
+    drop(file);
+    
+    fs::rename(&tmp_path, path)
+        .map_err(|e| format!("Failed to rename temporary file: {}", e))?;
+    
+    // Persist the rename itself; not every platform supports syncing directories
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = fs::File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    
+    Ok(())
+}
+
+/// Serialize a value as pretty-printed JSON and write it atomically
+pub fn write_json_atomic<P: AsRef<Path>, T: Serialize>(path: P, value: &T) -> Result<(), String> {
+    let content = serde_json::to_vec_pretty(value)
+        .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+    write_atomic(path, &content)
+}
+
+/// Path of the temporary file used by `write_atomic` for a target path
+pub fn temp_path_for<P: AsRef<Path>>(path: P) -> PathBuf {
+    let path = path.as_ref();
+    let mut file_name = path.file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    file_name.push(".tmp");
+    path.with_file_name(file_name)
+}
+
 /// Get the absolute path from a relative path
 pub fn absolute_path<P: AsRef<Path>>(path: P) -> Result<PathBuf, String> {
     std::env::current_dir()
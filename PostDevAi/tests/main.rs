@@ -9,6 +9,37 @@ mod tui {
     pub mod view_helper_test;
 }
 
+// RAM-Lake memory tests
+#[cfg(test)]
+mod memory {
+    // Shared test helpers
+    pub mod common;
+
+    pub mod wal_test;
+    pub mod verify_test;
+    pub mod backup_test;
+    pub mod ramlake_test;
+    pub mod async_ramlake_test;
+    pub mod ramlake_service_test;
+    pub mod transaction_test;
+    pub mod namespace_test;
+    pub mod revision_test;
+    pub mod compression_test;
+    pub mod segment_test;
+    pub mod eviction_test;
+    pub mod tiering_test;
+    pub mod rebalance_test;
+    pub mod hnsw_test;
+    pub mod matrix_test;
+    pub mod collection_test;
+    pub mod filter_test;
+    pub mod quantization_test;
+    pub mod batch_test;
+    pub mod reembed_test;
+    pub mod search_test;
+    pub mod chunk_test;
+}
+
 // Make sure the TUI function exports work
 #[test]
 fn test_tui_exports() {
@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use postdevai::core::memory::ramlake::{RamLake, AsyncRamLake, CancelToken, WriteOp};
+use super::common::test_config;
+
+/// Open an async RAM-Lake in temporary directories
+fn open_async(ramdisk: &Path, backups: &Path) -> AsyncRamLake {
+    AsyncRamLake::new(RamLake::new(ramdisk.to_path_buf(), test_config(backups)).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test single async calls round-trip through the blocking pool
+    #[tokio::test]
+    async fn test_async_store_and_get() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = open_async(ramdisk.path(), backups.path());
+
+        let id = ram_lake.store_code("src/main.rs".to_string(), "fn main() {}".to_string(), "rust".to_string()).await.unwrap();
+        ram_lake.index_code(id, vec![1.0, 0.0]).await.unwrap();
+
+        let (path, content, language) = ram_lake.get_code(id).await.unwrap();
+        assert_eq!((path.as_str(), content.as_str(), language.as_str()), ("src/main.rs", "fn main() {}", "rust"));
+
+        let results = ram_lake.search_similar("default".to_string(), vec![1.0, 0.0], 5).await.unwrap();
+        assert_eq!(results[0].0, id);
+        assert_eq!(ram_lake.list_vector_collections().await.unwrap().len(), 1);
+        assert!((ram_lake.get_allocation().await.unwrap().code_store - 0.3).abs() < 1e-6);
+    }
+
+    /// Test that a batch applies every write and reports each result
+    #[tokio::test]
+    async fn test_write_batch() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = open_async(ramdisk.path(), backups.path());
+
+        let results = ram_lake.write_batch(vec![
+            WriteOp::StoreCode { path: "a.rs".to_string(), content: "a".to_string(), language: "rust".to_string() },
+            WriteOp::StoreEvent { event_type: "build".to_string(), content: "ok".to_string() },
+            WriteOp::DeleteCode { id: uuid::Uuid::new_v4() },
+        ]).await.unwrap();
+
+        assert_eq!(results.len(), 3);
+        let code_id = results[0].clone().unwrap().unwrap();
+        let event_id = results[1].clone().unwrap().unwrap();
+        assert!(results[2].is_err());
+
+        assert_eq!(ram_lake.get_code(code_id).await.unwrap().1, "a");
+        assert_eq!(ram_lake.get_event(event_id).await.unwrap().1, "ok");
+    }
+
+    /// Test that a cancelled batch skips its writes
+    #[tokio::test]
+    async fn test_cancelled_batch() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = open_async(ramdisk.path(), backups.path());
+
+        let token = CancelToken::new();
+        token.cancel();
+
+        let result = ram_lake.write_batch_with_cancel(vec![
+            WriteOp::StoreEvent { event_type: "build".to_string(), content: "skipped".to_string() },
+        ], token).await;
+
+        assert!(result.is_err());
+        assert_eq!(ram_lake.inner().get_memory_usage(), 0);
+    }
+}
@@ -0,0 +1,272 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, BackupInfo, BackupManifest, SegmentStorage};
+use super::common::test_config;
+
+/// Create a fake backup directory containing a single code file
+fn make_backup(backup_path: &Path, id: &str, content: &str) {
+    let code_dir = backup_path.join(format!("ramlake_backup_{}", id)).join("code");
+    fs::create_dir_all(&code_dir).unwrap();
+    fs::write(code_dir.join("marker.txt"), content).unwrap();
+}
+
+/// Read the manifest of an incremental backup
+fn read_manifest(info: &BackupInfo) -> BackupManifest {
+    let content = fs::read_to_string(info.path.join("manifest.json")).unwrap();
+    serde_json::from_str(&content).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test creating, listing and restoring a backup
+    #[test]
+    fn test_create_list_restore() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        let marker = ramdisk.path().join("code").join("marker.txt");
+        fs::write(&marker, "before").unwrap();
+
+        let info = ram_lake.create_backup().unwrap();
+        assert!(info.size > 0);
+
+        let listed = ram_lake.list_backups().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, info.id);
+
+        // Change the RAM disk and roll back
+        fs::write(&marker, "after").unwrap();
+        fs::write(ramdisk.path().join("code").join("extra.txt"), "extra").unwrap();
+        ram_lake.restore_backup(&info.id).unwrap();
+
+        assert_eq!(fs::read_to_string(&marker).unwrap(), "before");
+        assert!(!ramdisk.path().join("code").join("extra.txt").exists());
+        assert!(ram_lake.restore_backup("19700101_000000").is_err());
+    }
+
+    /// Test that pinned entities are backed up and restored with the stores
+    #[test]
+    fn test_pins_survive_restore() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        let id = ram_lake.store_code("main.rs", "fn main() {}", "rust").unwrap();
+        ram_lake.pin(id).unwrap();
+        let info = ram_lake.create_backup().unwrap();
+
+        ram_lake.unpin(id).unwrap();
+        ram_lake.restore_backup(&info.id).unwrap();
+        assert!(ram_lake.is_pinned(id));
+
+        // The restored pins are the ones loaded on the next start
+        drop(ram_lake);
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        assert!(ram_lake.is_pinned(id));
+    }
+
+    /// Test pruning backups with a keep-last policy
+    #[test]
+    fn test_prune_keep_last() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let mut config = test_config(backups.path());
+        config.backup_retention.keep_last = Some(2);
+
+        make_backup(backups.path(), "20240101_000000", "oldest");
+        make_backup(backups.path(), "20240102_000000", "middle");
+        make_backup(backups.path(), "20240103_000000", "newest");
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), config).unwrap();
+        let removed = ram_lake.prune_backups().unwrap();
+
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].id, "20240101_000000");
+
+        let remaining: Vec<String> = ram_lake.list_backups().unwrap().into_iter().map(|b| b.id).collect();
+        assert_eq!(remaining, vec!["20240103_000000", "20240102_000000"]);
+    }
+
+    /// Test that the newest backup survives even an age limit it violates
+    #[test]
+    fn test_prune_max_age_keeps_newest() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let mut config = test_config(backups.path());
+        config.backup_retention.max_age_secs = Some(60);
+
+        make_backup(backups.path(), "20240101_000000", "old");
+        make_backup(backups.path(), "20240102_000000", "newer");
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), config).unwrap();
+        ram_lake.prune_backups().unwrap();
+
+        let remaining = ram_lake.list_backups().unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, "20240102_000000");
+    }
+
+    /// Test warm start from the latest backup when the RAM disk is empty
+    #[test]
+    fn test_restore_on_empty() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let mut config = test_config(backups.path());
+        config.restore_on_empty = true;
+
+        make_backup(backups.path(), "20240101_000000", "old");
+        make_backup(backups.path(), "20240102_000000", "latest");
+
+        let _ram_lake = RamLake::new(ramdisk.path().to_path_buf(), config).unwrap();
+
+        let marker = ramdisk.path().join("code").join("marker.txt");
+        assert_eq!(fs::read_to_string(marker).unwrap(), "latest");
+    }
+
+    /// Test that an incremental backup only archives changed files and restores its chain
+    #[test]
+    fn test_incremental_backup_chain() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        let code_dir = ramdisk.path().join("code");
+        fs::write(code_dir.join("a.code"), "unchanged").unwrap();
+        fs::write(code_dir.join("b.code"), "first version").unwrap();
+        let full = ram_lake.create_backup().unwrap();
+
+        fs::write(code_dir.join("b.code"), "second version").unwrap();
+        fs::write(code_dir.join("c.code"), "new file").unwrap();
+        let incremental = ram_lake.create_backup().unwrap();
+
+        let manifest = read_manifest(&incremental);
+        assert_eq!(manifest.parent.as_deref(), Some(full.id.as_str()));
+        assert_eq!(manifest.chain_length, 1);
+        assert_eq!(manifest.files["code/a.code"].archive, full.id);
+        assert_eq!(manifest.files["code/b.code"].archive, incremental.id);
+        assert_eq!(manifest.files["code/c.code"].archive, incremental.id);
+
+        // Restoring the increment pulls unchanged content from the full backup
+        fs::remove_file(code_dir.join("a.code")).unwrap();
+        ram_lake.restore_backup(&incremental.id).unwrap();
+        assert_eq!(fs::read_to_string(code_dir.join("a.code")).unwrap(), "unchanged");
+        assert_eq!(fs::read_to_string(code_dir.join("b.code")).unwrap(), "second version");
+        assert_eq!(fs::read_to_string(code_dir.join("c.code")).unwrap(), "new file");
+
+        ram_lake.restore_backup(&full.id).unwrap();
+        assert_eq!(fs::read_to_string(code_dir.join("b.code")).unwrap(), "first version");
+        assert!(!code_dir.join("c.code").exists());
+    }
+
+    /// Test that identical files are stored once and restored everywhere
+    #[test]
+    fn test_incremental_backup_dedup() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        let content = "fn main() {}\n".repeat(1000);
+        fs::write(ramdisk.path().join("code").join("one.code"), &content).unwrap();
+        fs::write(ramdisk.path().join("history").join("two.event"), &content).unwrap();
+        let info = ram_lake.create_backup().unwrap();
+
+        let manifest = read_manifest(&info);
+        assert_eq!(manifest.files["code/one.code"].digest, manifest.files["history/two.event"].digest);
+
+        // Compressed and stored once, so far smaller than the two copies
+        assert!(info.size < content.len() as u64);
+
+        fs::remove_file(ramdisk.path().join("code").join("one.code")).unwrap();
+        fs::remove_file(ramdisk.path().join("history").join("two.event")).unwrap();
+        ram_lake.restore_backup(&info.id).unwrap();
+        assert_eq!(fs::read_to_string(ramdisk.path().join("code").join("one.code")).unwrap(), content);
+        assert_eq!(fs::read_to_string(ramdisk.path().join("history").join("two.event")).unwrap(), content);
+    }
+
+    /// Test that pruning keeps backups an incremental backup still depends on
+    #[test]
+    fn test_prune_keeps_referenced_backups() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let mut config = test_config(backups.path());
+        config.backup_retention.keep_last = Some(1);
+        config.incremental_backup.full_every = 2;
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), config).unwrap();
+
+        let marker = ramdisk.path().join("code").join("marker.txt");
+        fs::write(&marker, "base").unwrap();
+        let full = ram_lake.create_backup().unwrap();
+
+        fs::write(ramdisk.path().join("code").join("extra.txt"), "extra").unwrap();
+        let incremental = ram_lake.create_backup().unwrap();
+
+        // The full backup holds the marker content for the increment
+        let ids: Vec<String> = ram_lake.list_backups().unwrap().into_iter().map(|b| b.id).collect();
+        assert_eq!(ids, vec![incremental.id.clone(), full.id.clone()]);
+
+        // The chain limit forces a new full backup, after which the old chain can go
+        let new_full = ram_lake.create_backup().unwrap();
+        assert!(read_manifest(&new_full).parent.is_none());
+
+        let ids: Vec<String> = ram_lake.list_backups().unwrap().into_iter().map(|b| b.id).collect();
+        assert_eq!(ids, vec![new_full.id.clone()]);
+
+        ram_lake.restore_backup(&new_full.id).unwrap();
+        assert_eq!(fs::read_to_string(&marker).unwrap(), "base");
+    }
+
+    /// Test that backups taken while writes and compaction run restore to consistent stores
+    #[test]
+    fn test_backup_during_writes_and_compaction() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let config = RamLakeConfig {
+            segment_storage: SegmentStorage {
+                segment_size: 256,
+                ..SegmentStorage::default()
+            },
+            ..test_config(backups.path())
+        };
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), config).unwrap();
+
+        let content = |i: usize| format!("cargo build --release #{:04}", i);
+        let done = AtomicBool::new(false);
+        let (ids, infos) = std::thread::scope(|scope| {
+            let writer = scope.spawn(|| {
+                let mut ids = Vec::new();
+                for i in 0..300 {
+                    ids.push(ram_lake.store_event("command", &content(i)).unwrap());
+                    if i >= 5 {
+                        ram_lake.delete_event(ids[i - 5]).unwrap();
+                    }
+                    if i % 10 == 0 {
+                        ram_lake.compact_segments().unwrap();
+                    }
+                }
+                done.store(true, Ordering::SeqCst);
+                ids
+            });
+
+            let mut infos = Vec::new();
+            while !done.load(Ordering::SeqCst) || infos.is_empty() {
+                infos.push(ram_lake.create_backup().unwrap());
+            }
+            (writer.join().unwrap(), infos)
+        });
+
+        for info in infos {
+            ram_lake.restore_backup(&info.id).unwrap();
+            assert!(ram_lake.verify(false).unwrap().is_clean(), "backup {} is torn", info.id);
+            for (i, id) in ids.iter().enumerate() {
+                if let Ok((_, restored, _)) = ram_lake.get_event(*id) {
+                    assert_eq!(restored, content(i));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, HnswConfig, DEFAULT_COLLECTION};
+use super::common::{self, random_vectors};
+
+/// Build a RAM-Lake config for tests with the given HNSW settings
+fn test_config(backup_path: &Path, hnsw: HnswConfig) -> RamLakeConfig {
+    RamLakeConfig {
+        hnsw,
+        ..common::test_config(backup_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that a batch is stored whole and answers batched queries like single ones
+    #[test]
+    fn test_batch_store_and_search() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+
+        for hnsw in [HnswConfig::default(), HnswConfig { enabled: false, ..HnswConfig::default() }] {
+            let ramdisk = tempfile::tempdir_in(ramdisk.path()).unwrap();
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), hnsw)).unwrap();
+
+            let vectors = random_vectors(300, 16, 7);
+            let batch: Vec<_> = vectors.iter()
+                .enumerate()
+                .map(|(i, vector)| (ram_lake.store_code(&format!("src/file_{}.rs", i), "fn f() {}", "rust").unwrap(), vector.clone()))
+                .collect();
+            ram_lake.store_embeddings_batch(DEFAULT_COLLECTION, batch.clone()).unwrap();
+            assert_eq!(ram_lake.get_vector_collection_metrics()[DEFAULT_COLLECTION].entries, 300);
+
+            let queries = random_vectors(20, 16, 99);
+            let results = ram_lake.search_similar_batch(DEFAULT_COLLECTION, queries.clone(), 5).unwrap();
+            assert_eq!(results.len(), 20);
+            for (query, results) in queries.into_iter().zip(results) {
+                assert_eq!(results, ram_lake.search_similar(DEFAULT_COLLECTION, query, 5).unwrap());
+            }
+            assert_eq!(ram_lake.search_similar_batch(DEFAULT_COLLECTION, vec![vectors[12].clone()], 1).unwrap()[0][0].0, batch[12].0);
+        }
+    }
+
+    /// Test that a batch with a bad embedding stores nothing and a good one survives a restart
+    #[test]
+    fn test_batch_is_atomic_and_durable() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+
+        let vectors = random_vectors(50, 8, 3);
+        let batch: Vec<_> = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), HnswConfig::default())).unwrap();
+            let batch: Vec<_> = vectors.iter()
+                .map(|vector| (ram_lake.store_event("build", "cargo build").unwrap(), vector.clone()))
+                .collect();
+            let usage = ram_lake.get_memory_usage();
+
+            // A wrong dimension or a repeated ID rejects the whole batch
+            let mut wrong_dimension = batch.clone();
+            wrong_dimension[49].1.push(0.0);
+            assert!(ram_lake.store_embeddings_batch(DEFAULT_COLLECTION, wrong_dimension).is_err());
+            let mut repeated = batch.clone();
+            repeated.push(batch[0].clone());
+            assert!(ram_lake.store_embeddings_batch(DEFAULT_COLLECTION, repeated).is_err());
+            assert!(ram_lake.list_vector_collections().is_empty());
+            assert_eq!(ram_lake.get_memory_usage(), usage);
+
+            ram_lake.store_embeddings_batch(DEFAULT_COLLECTION, batch.clone()).unwrap();
+            // Each embedding takes its blob and a 32-byte row with its norm
+            assert_eq!(ram_lake.get_memory_usage(), usage + 50 * (8 * 4 + 36));
+            batch
+        };
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), HnswConfig::default())).unwrap();
+        let queries: Vec<Vec<f32>> = vectors.iter().take(10).cloned().collect();
+        let results = ram_lake.search_similar_batch(DEFAULT_COLLECTION, queries, 1).unwrap();
+        for (i, results) in results.iter().enumerate() {
+            assert_eq!(results[0].0, batch[i].0);
+        }
+        assert!(ram_lake.search_similar_batch(DEFAULT_COLLECTION, vec![vec![0.0; 8], vec![0.0; 4]], 1).is_err());
+    }
+}
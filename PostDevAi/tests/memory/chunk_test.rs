@@ -0,0 +1,121 @@
+use postdevai::core::memory::ramlake::{RamLake, SearchQuery, DEFAULT_COLLECTION, chunk_code};
+use super::common::test_config;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that code is chunked at definitions with their comments, else by overlapping windows
+    #[test]
+    fn test_chunk_code_boundaries() {
+        let content = [
+            "use std::fmt;",
+            "",
+            "/// A point",
+            "#[derive(Debug)]",
+            "pub struct Point {",
+            "    x: i32,",
+            "}",
+            "",
+            "impl<T> Point {",
+            "    pub fn new() -> Self {",
+            "        Self { x: 0 }",
+            "    }",
+            "}",
+            "",
+            "pub(crate) fn origin() -> Point {",
+            "    Point::new()",
+            "}",
+        ].join("\n");
+        let spans: Vec<(usize, usize)> = chunk_code(&content, "rust").iter()
+            .map(|chunk| (chunk.start_line, chunk.end_line))
+            .collect();
+        assert_eq!(spans, vec![(1, 1), (3, 7), (9, 13), (15, 17)]);
+        assert_eq!(chunk_code(&content, "rust")[1].text.lines().next(), Some("/// A point"));
+
+        // Without known definitions the file is split into windows sharing 10 lines
+        let text: Vec<String> = (1..=130).map(|i| format!("line {}", i)).collect();
+        let spans: Vec<(usize, usize)> = chunk_code(&text.join("\n"), "plaintext").iter()
+            .map(|chunk| (chunk.start_line, chunk.end_line))
+            .collect();
+        assert_eq!(spans, vec![(1, 60), (51, 110), (101, 130)]);
+    }
+
+    /// Test that a long impl block is chunked at its methods rather than by windows
+    #[test]
+    fn test_chunk_code_long_impl() {
+        let mut lines = vec![
+            "struct Parser;".to_string(),
+            String::new(),
+            "/// Parsing".to_string(),
+            "impl Parser {".to_string(),
+            "    const LIMIT: usize = 1;".to_string(),
+            String::new(),
+        ];
+        for method in 0..3 {
+            lines.push(format!("    /// Method {}", method));
+            lines.push(format!("    pub fn method_{}(&self) {{", method));
+            lines.extend((0..78).map(|_| "        step();".to_string()));
+            lines.push("    }".to_string());
+            lines.push(String::new());
+        }
+        lines.push("}".to_string());
+
+        let chunks = chunk_code(&lines.join("\n"), "rust");
+        let spans: Vec<(usize, usize)> = chunks.iter()
+            .map(|chunk| (chunk.start_line, chunk.end_line))
+            .collect();
+        assert_eq!(spans, vec![(1, 1), (3, 5), (7, 87), (89, 169), (171, 253)]);
+        assert_eq!(chunks[1].text.lines().nth(1), Some("impl Parser {"));
+        assert_eq!(chunks[2].text.lines().next(), Some("    /// Method 0"));
+    }
+
+    /// Test that chunk embeddings replace a whole-file embedding, resolve to lines and go with their file
+    #[test]
+    fn test_chunk_embeddings_resolve_to_lines() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        let content = "fn parse() {\n    todo!()\n}\n\nfn render() {\n    todo!()\n}\n";
+        let code_id = ram_lake.store_code("src/lib.rs", content, "rust").unwrap();
+        ram_lake.index_code(code_id, vec![0.6, 0.8]).unwrap();
+
+        let chunks = ram_lake.chunk_code(code_id).unwrap();
+        assert_eq!(chunks.len(), 2);
+        let embedded = chunks.into_iter().zip([vec![1.0, 0.0], vec![0.0, 1.0]]).collect();
+        let ids = ram_lake.index_code_chunks(DEFAULT_COLLECTION, code_id, embedded).unwrap();
+        assert_eq!(ids.len(), 2);
+        assert!(!ids.contains(&code_id));
+
+        // The whole-file embedding is gone; each hit points at the lines of a chunk
+        let hits = ram_lake.search_code(DEFAULT_COLLECTION, vec![0.1, 1.0], 5).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].embedding_id, ids[1]);
+        assert_eq!((hits[0].code_id, hits[0].path.as_str()), (code_id, "src/lib.rs"));
+        assert_eq!((hits[0].start_line, hits[0].end_line), (5, 7));
+        assert_eq!((hits[1].start_line, hits[1].end_line), (1, 3));
+
+        // Hybrid search ranks the file by its best chunk
+        let query = SearchQuery {
+            text: "render".to_string(),
+            embedding: Some(vec![0.0, 1.0]),
+            ..SearchQuery::default()
+        };
+        let hits = ram_lake.search(&query).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].id, hits[0].start_line, hits[0].end_line), (code_id, Some(5), Some(7)));
+
+        // Lines outside the file are rejected
+        let bad = ram_lake.chunk_code(code_id).unwrap().into_iter()
+            .map(|mut chunk| {
+                chunk.end_line = 99;
+                (chunk, vec![1.0, 0.0])
+            })
+            .collect();
+        assert!(ram_lake.index_code_chunks(DEFAULT_COLLECTION, code_id, bad).is_err());
+
+        ram_lake.delete_code(code_id).unwrap();
+        assert!(ram_lake.search_similar(DEFAULT_COLLECTION, vec![1.0, 0.0], 5).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,58 @@
+use postdevai::core::memory::ramlake::{RamLake, DistanceMetric, DEFAULT_COLLECTION};
+use super::common::test_config;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that collections of different dimensions are searched independently and survive a restart
+    #[test]
+    fn test_collections_with_own_dimension() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+
+        let (code_id, event_id) = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+            ram_lake.create_vector_collection("events", 2, DistanceMetric::Cosine, Some("event-encoder-v1".to_string())).unwrap();
+
+            let code_id = ram_lake.store_code("src/main.rs", "fn main() {}", "rust").unwrap();
+            ram_lake.index_code(code_id, vec![0.1, 0.2, 0.3, 0.4]).unwrap();
+            let event_id = ram_lake.store_event("build", "cargo build").unwrap();
+            ram_lake.index_entity("events", event_id, vec![1.0, 0.0]).unwrap();
+
+            // Each collection only accepts its own dimension
+            assert!(ram_lake.index_entity("events", event_id, vec![1.0, 0.0, 0.0]).is_err());
+            assert!(ram_lake.search_similar("events", vec![0.1, 0.2, 0.3, 0.4], 5).is_err());
+            assert!(ram_lake.search_similar("missing", vec![1.0, 0.0], 5).is_err());
+            assert!(ram_lake.index_entity("missing", event_id, vec![1.0, 0.0]).is_err());
+
+            (code_id, event_id)
+        };
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        let collections = ram_lake.list_vector_collections();
+        assert_eq!(collections.iter().map(|c| (c.name.as_str(), c.dimension)).collect::<Vec<_>>(), vec![(DEFAULT_COLLECTION, 4), ("events", 2)]);
+        assert_eq!(collections[1].model.as_deref(), Some("event-encoder-v1"));
+
+        assert_eq!(ram_lake.search_similar(DEFAULT_COLLECTION, vec![0.1, 0.2, 0.3, 0.4], 5).unwrap().iter().map(|r| r.0).collect::<Vec<_>>(), vec![code_id]);
+        assert_eq!(ram_lake.search_similar("events", vec![0.9, 0.1], 5).unwrap().iter().map(|r| r.0).collect::<Vec<_>>(), vec![event_id]);
+    }
+
+    /// Test that only empty collections can be deleted
+    #[test]
+    fn test_delete_collection() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        ram_lake.create_vector_collection("events", 2, DistanceMetric::Cosine, None).unwrap();
+        assert!(ram_lake.create_vector_collection("events", 3, DistanceMetric::Cosine, None).is_err());
+        let event_id = ram_lake.store_event("build", "cargo build").unwrap();
+        ram_lake.index_entity("events", event_id, vec![1.0, 0.0]).unwrap();
+        assert!(ram_lake.delete_vector_collection("events").is_err());
+
+        ram_lake.delete_event(event_id).unwrap();
+        ram_lake.delete_vector_collection("events").unwrap();
+        assert!(ram_lake.list_vector_collections().is_empty());
+    }
+}
@@ -0,0 +1,29 @@
+use std::path::Path;
+
+use postdevai::core::memory::ramlake::RamLakeConfig;
+
+/// Build a 64 MB RAM-Lake config for tests; variants override fields with struct update syntax
+pub fn test_config(backup_path: &Path) -> RamLakeConfig {
+    RamLakeConfig {
+        max_size: 64 * 1024 * 1024,
+        backup_path: backup_path.to_path_buf(),
+        ..RamLakeConfig::default()
+    }
+}
+
+/// Deterministic pseudo-random vectors
+pub fn random_vectors(count: usize, dimension: usize, seed: u64) -> Vec<Vec<f32>> {
+    let mut state = seed;
+    (0..count)
+        .map(|_| {
+            (0..dimension)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    (state % 2000) as f32 / 1000.0 - 1.0
+                })
+                .collect()
+        })
+        .collect()
+}
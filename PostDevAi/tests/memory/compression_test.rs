@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, RevisionRetention, BlobCompression, DEFAULT_NAMESPACE};
+use super::common;
+
+/// Build a RAM-Lake config for tests
+fn test_config(backup_path: &Path, compression: BlobCompression) -> RamLakeConfig {
+    RamLakeConfig {
+        revision_retention: RevisionRetention {
+            keep_last: Some(1),
+            max_age_secs: None,
+        },
+        code_compression: compression.clone(),
+        history_compression: compression,
+        ..common::test_config(backup_path)
+    }
+}
+
+/// Source file with a lot of repetition
+fn sample_code(n: usize) -> String {
+    (0..200).map(|i| format!("pub fn handler_{}_{}(request: &Request) -> Response {{\n    Response::ok()\n}}\n", n, i)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that compressed blobs read back unchanged and account their compressed size
+    #[test]
+    fn test_compressed_round_trip() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let compression = BlobCompression { enabled: true, ..BlobCompression::default() };
+        let code = sample_code(0);
+        let log = "warning: unused variable\n".repeat(100);
+
+        let (code_id, event_id, usage) = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), compression)).unwrap();
+            let code_id = ram_lake.store_code("src/handlers.rs", &code, "rust").unwrap();
+            let event_id = ram_lake.store_event("build", &log).unwrap();
+
+            let usage = ram_lake.get_memory_usage();
+            assert!(usage < (code.len() + log.len()) as u64 / 4);
+            assert_eq!(ram_lake.get_code(code_id).unwrap().1, code);
+            assert_eq!(ram_lake.get_event(event_id).unwrap().1, log);
+            (code_id, event_id, usage)
+        };
+
+        // Compressed blobs stay readable with compression turned off
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), BlobCompression::default())).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), usage);
+        assert_eq!(ram_lake.get_code(code_id).unwrap().1, code);
+        assert_eq!(ram_lake.get_event(event_id).unwrap().1, log);
+
+        ram_lake.delete_code(code_id).unwrap();
+        ram_lake.delete_event(event_id).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 0);
+    }
+
+    /// Test compressing code with a dictionary trained on the stored files
+    #[test]
+    fn test_trained_dictionary() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let dictionary = ramdisk.path().join("code.dict");
+
+        {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), BlobCompression::default())).unwrap();
+            for n in 0..20 {
+                ram_lake.store_code(&format!("src/handlers_{}.rs", n), &sample_code(n), "rust").unwrap();
+            }
+            ram_lake.train_code_dictionary(&dictionary, 4096).unwrap();
+        }
+
+        let compression = BlobCompression {
+            enabled: true,
+            dictionary: Some(dictionary),
+            ..BlobCompression::default()
+        };
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), compression)).unwrap();
+        let code = sample_code(99);
+        let id = ram_lake.store_code("src/handlers_99.rs", &code, "rust").unwrap();
+        assert_eq!(ram_lake.get_code(id).unwrap().1, code);
+        let revisions = ram_lake.list_code_revisions(DEFAULT_NAMESPACE, "src/handlers_99.rs");
+        assert!(revisions[0].blob_size() < code.len() as u64);
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::Path;
+
+use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, StoreAllocation, EvictionConfig, EvictionPolicyKind, EntityKind};
+use super::common;
+
+/// Build a 1000 byte RAM-Lake config for tests
+fn test_config(backup_path: &Path, allocation: StoreAllocation, policy: EvictionPolicyKind) -> RamLakeConfig {
+    RamLakeConfig {
+        max_size: 1000,
+        allocation,
+        eviction: EvictionConfig {
+            policy,
+            ..EvictionConfig::default()
+        },
+        ..common::test_config(backup_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that a full code store evicts the least recently used unpinned file
+    #[test]
+    fn test_lru_eviction_skips_pinned() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let allocation = StoreAllocation {
+            vector_store: 0.1,
+            code_store: 0.6,
+            history_store: 0.3,
+            metadata_store: 0.0,
+        };
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), allocation.clone(), EvictionPolicyKind::Lru)).unwrap();
+
+        let a = ram_lake.store_code("a.rs", &"a".repeat(250), "rust").unwrap();
+        let b = ram_lake.store_code("b.rs", &"b".repeat(250), "rust").unwrap();
+        ram_lake.get_code(a).unwrap();
+
+        // b is the least recently used file
+        let c = ram_lake.store_code("c.rs", &"c".repeat(250), "rust").unwrap();
+        assert!(ram_lake.get_code(b).is_err());
+        assert!(ram_lake.list_code_revisions("default", "b.rs").is_empty());
+
+        // a is older than c but pinned, also after a restart
+        ram_lake.pin(a).unwrap();
+        drop(ram_lake);
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), allocation, EvictionPolicyKind::Lru)).unwrap();
+        assert!(ram_lake.is_pinned(a));
+
+        ram_lake.store_code("d.rs", &"d".repeat(250), "rust").unwrap();
+        assert!(ram_lake.get_code(a).is_ok());
+        assert!(ram_lake.get_code(c).is_err());
+        assert_eq!(ram_lake.get_memory_usage(), 500);
+
+        let evictions = ram_lake.get_recent_evictions(10);
+        assert_eq!(evictions.len(), 1);
+        assert_eq!((evictions[0].id, evictions[0].kind, evictions[0].size), (c, EntityKind::Code, 250));
+    }
+
+    /// Test that the priority policy evicts events with their relations before older code
+    #[test]
+    fn test_priority_eviction_across_stores() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let allocation = StoreAllocation {
+            vector_store: 1.0,
+            code_store: 1.0,
+            history_store: 1.0,
+            metadata_store: 1.0,
+        };
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), allocation, EvictionPolicyKind::Priority)).unwrap();
+
+        let code = ram_lake.store_code("main.rs", &"x".repeat(400), "rust").unwrap();
+        let event = ram_lake.store_event("build", &"y".repeat(400)).unwrap();
+        ram_lake.store_metadata(event, "built", code).unwrap();
+
+        // The RAM-Lake is full; the event goes first although the code is older
+        let newer = ram_lake.store_event("build", &"z".repeat(300)).unwrap();
+        assert!(ram_lake.get_event(event).is_err());
+        assert!(ram_lake.get_related(code, None).unwrap().is_empty());
+        assert!(ram_lake.get_code(code).is_ok());
+        assert!(ram_lake.get_event(newer).is_ok());
+        assert_eq!(ram_lake.get_memory_usage(), 700);
+
+        // Without eviction the write fails instead
+        ram_lake.set_eviction_policy(None);
+        assert!(ram_lake.store_event("build", &"w".repeat(400)).is_err());
+        assert_eq!(ram_lake.get_memory_usage(), 700);
+    }
+
+    /// Test that data over a smaller RAM-Lake size is accounted on open and evicted
+    #[test]
+    fn test_reopen_with_smaller_size() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let allocation = StoreAllocation {
+            vector_store: 1.0,
+            code_store: 1.0,
+            history_store: 1.0,
+            metadata_store: 1.0,
+        };
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), allocation.clone(), EvictionPolicyKind::Lru)).unwrap();
+        let a = ram_lake.store_code("a.rs", &"a".repeat(300), "rust").unwrap();
+        let b = ram_lake.store_code("b.rs", &"b".repeat(300), "rust").unwrap();
+        let c = ram_lake.store_code("c.rs", &"c".repeat(300), "rust").unwrap();
+        drop(ram_lake);
+
+        // Without eviction the data stays over the limit
+        let config = RamLakeConfig {
+            max_size: 500,
+            ..test_config(backups.path(), allocation.clone(), EvictionPolicyKind::Disabled)
+        };
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), config).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 900);
+        drop(ram_lake);
+
+        // With eviction the oldest files go until it fits
+        let config = RamLakeConfig {
+            max_size: 500,
+            ..test_config(backups.path(), allocation, EvictionPolicyKind::Lru)
+        };
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), config).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 300);
+        assert!(ram_lake.get_code(a).is_err());
+        assert!(ram_lake.get_code(b).is_err());
+        assert!(ram_lake.get_code(c).is_ok());
+    }
+}
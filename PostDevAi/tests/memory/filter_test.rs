@@ -0,0 +1,97 @@
+use std::path::Path;
+
+use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, HnswConfig, VectorFilter, DEFAULT_COLLECTION};
+use super::common;
+
+/// Build a RAM-Lake config for tests, optionally searching through an HNSW graph
+fn test_config(backup_path: &Path, hnsw: bool) -> RamLakeConfig {
+    RamLakeConfig {
+        hnsw: HnswConfig {
+            enabled: hnsw,
+            ..HnswConfig::default()
+        },
+        ..common::test_config(backup_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that filters select by type, language and path before the results are cut to the limit
+    #[test]
+    fn test_filter_applied_during_search() {
+        for hnsw in [false, true] {
+            let ramdisk = tempfile::tempdir().unwrap();
+            let backups = tempfile::tempdir().unwrap();
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), hnsw)).unwrap();
+
+            // Rust files are all closer to the query than the Python ones
+            for i in 0..20 {
+                let id = ram_lake.store_code(&format!("src/lib{}.rs", i), "fn f() {}", "rust").unwrap();
+                ram_lake.index_code(id, vec![1.0, 0.01 * i as f32, 0.0]).unwrap();
+            }
+            let mut python = Vec::new();
+            for i in 0..4 {
+                let dir = if i < 2 { "scripts" } else { "tools" };
+                let id = ram_lake.store_code(&format!("{}/run{}.py", dir, i), "pass", "python").unwrap();
+                ram_lake.index_code(id, vec![0.5, 0.5 + 0.1 * i as f32, 0.0]).unwrap();
+                python.push(id);
+            }
+            let event = ram_lake.store_event("build", "cargo build").unwrap();
+            ram_lake.index_code(event, vec![0.9, 0.0, 0.1]).unwrap();
+            let query = vec![1.0, 0.0, 0.0];
+
+            let filter = VectorFilter {
+                language: Some("python".to_string()),
+                ..VectorFilter::default()
+            };
+            let results = ram_lake.search_similar_where(DEFAULT_COLLECTION, &filter, query.clone(), 3).unwrap();
+            assert_eq!(results.iter().map(|r| r.0).collect::<Vec<_>>(), python[..3].to_vec());
+
+            let filter = VectorFilter {
+                path_prefix: Some("tools/".to_string()),
+                ..VectorFilter::default()
+            };
+            let results = ram_lake.search_similar_where(DEFAULT_COLLECTION, &filter, query.clone(), 10).unwrap();
+            assert_eq!(results.iter().map(|r| r.0).collect::<Vec<_>>(), python[2..].to_vec());
+
+            let filter = VectorFilter {
+                embedding_type: Some("event".to_string()),
+                ..VectorFilter::default()
+            };
+            let results = ram_lake.search_similar_where(DEFAULT_COLLECTION, &filter, query, 10).unwrap();
+            assert_eq!(results.iter().map(|r| r.0).collect::<Vec<_>>(), vec![event]);
+        }
+    }
+
+    /// Test filtering by creation time and that attributes survive a restart
+    #[test]
+    fn test_filter_created_after_across_restart() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+
+        let (old, new, cutoff) = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), false)).unwrap();
+            let old = ram_lake.store_code("src/old.rs", "fn old() {}", "rust").unwrap();
+            ram_lake.index_code(old, vec![1.0, 0.0]).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            let cutoff = chrono::Utc::now();
+            let new = ram_lake.store_code("src/new.rs", "fn new() {}", "rust").unwrap();
+            ram_lake.index_code(new, vec![0.0, 1.0]).unwrap();
+            (old, new, cutoff)
+        };
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), false)).unwrap();
+        let filter = VectorFilter {
+            embedding_type: Some("code".to_string()),
+            language: Some("rust".to_string()),
+            path_prefix: Some("src/".to_string()),
+            created_after: Some(cutoff),
+            ..VectorFilter::default()
+        };
+        let results = ram_lake.search_similar_where(DEFAULT_COLLECTION, &filter, vec![1.0, 0.0], 10).unwrap();
+        assert_eq!(results.iter().map(|r| r.0).collect::<Vec<_>>(), vec![new]);
+        assert_eq!(ram_lake.search_similar(DEFAULT_COLLECTION, vec![1.0, 0.0], 1).unwrap()[0].0, old);
+    }
+}
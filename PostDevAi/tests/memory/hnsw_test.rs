@@ -0,0 +1,120 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, HnswConfig, DEFAULT_COLLECTION};
+use super::common::{self, random_vectors};
+
+/// Build a RAM-Lake config for tests with the given HNSW settings
+fn test_config(backup_path: &Path, hnsw: HnswConfig) -> RamLakeConfig {
+    RamLakeConfig {
+        hnsw,
+        ..common::test_config(backup_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that HNSW search finds nearly all of the exact nearest neighbours
+    #[test]
+    fn test_hnsw_recall() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), HnswConfig::default())).unwrap();
+
+        for (i, vector) in random_vectors(500, 16, 42).into_iter().enumerate() {
+            let id = ram_lake.store_code(&format!("src/file_{}.rs", i), "fn f() {}", "rust").unwrap();
+            ram_lake.index_code(id, vector).unwrap();
+        }
+
+        let mut found = 0;
+        for query in random_vectors(20, 16, 7) {
+            let exact: HashSet<_> = ram_lake.search_similar_exact(DEFAULT_COLLECTION, query.clone(), 10).unwrap()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+            let approximate = ram_lake.search_similar(DEFAULT_COLLECTION, query, 10).unwrap();
+            assert_eq!(approximate.len(), 10);
+            found += approximate.iter().filter(|(id, _)| exact.contains(id)).count();
+        }
+
+        assert!(found as f32 / 200.0 >= 0.9, "recall {} / 200", found);
+    }
+
+    /// Test that recall holds after deletes and inserts into the freed slots
+    #[test]
+    fn test_hnsw_recall_after_churn() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), HnswConfig::default())).unwrap();
+
+        let vectors = random_vectors(450, 16, 11);
+        let ids: Vec<_> = vectors[..300].iter()
+            .enumerate()
+            .map(|(i, vector)| {
+                let id = ram_lake.store_code(&format!("src/file_{}.rs", i), "fn f() {}", "rust").unwrap();
+                ram_lake.index_code(id, vector.clone()).unwrap();
+                id
+            })
+            .collect();
+        for id in ids.iter().step_by(2) {
+            ram_lake.delete_code(*id).unwrap();
+        }
+        for (i, vector) in vectors[300..].iter().enumerate() {
+            let id = ram_lake.store_code(&format!("src/new_{}.rs", i), "fn f() {}", "rust").unwrap();
+            ram_lake.index_code(id, vector.clone()).unwrap();
+        }
+
+        let mut found = 0;
+        for query in random_vectors(20, 16, 5) {
+            let exact: HashSet<_> = ram_lake.search_similar_exact(DEFAULT_COLLECTION, query.clone(), 10).unwrap()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+            let approximate = ram_lake.search_similar(DEFAULT_COLLECTION, query, 10).unwrap();
+            found += approximate.iter().filter(|(id, _)| exact.contains(id)).count();
+        }
+
+        assert!(found as f32 / 200.0 >= 0.9, "recall {} / 200", found);
+    }
+
+    /// Test that the graph follows deletes and survives a restart
+    #[test]
+    fn test_hnsw_persisted_and_updated() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let vectors = random_vectors(50, 8, 3);
+
+        let ids: Vec<_> = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), HnswConfig::default())).unwrap();
+            let ids: Vec<_> = vectors.iter()
+                .enumerate()
+                .map(|(i, vector)| {
+                    let id = ram_lake.store_code(&format!("src/file_{}.rs", i), "fn f() {}", "rust").unwrap();
+                    ram_lake.index_code(id, vector.clone()).unwrap();
+                    id
+                })
+                .collect();
+            ram_lake.delete_code(ids[0]).unwrap();
+            ram_lake.checkpoint().unwrap();
+            ids
+        };
+        assert!(ramdisk.path().join("vectors").join("hnsw.json").exists());
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), HnswConfig::default())).unwrap();
+        let results = ram_lake.search_similar(DEFAULT_COLLECTION, vectors[1].clone(), 5).unwrap();
+        assert_eq!(results[0].0, ids[1]);
+        assert!(results.iter().all(|(id, _)| *id != ids[0]));
+
+        // Disabling the graph switches to exact search
+        drop(ram_lake);
+        let exact = HnswConfig {
+            enabled: false,
+            ..HnswConfig::default()
+        };
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), exact)).unwrap();
+        assert_eq!(ram_lake.search_similar(DEFAULT_COLLECTION, vectors[2].clone(), 5).unwrap(), ram_lake.search_similar_exact(DEFAULT_COLLECTION, vectors[2].clone(), 5).unwrap());
+        assert!(!ramdisk.path().join("vectors").join("hnsw.json").exists());
+    }
+}
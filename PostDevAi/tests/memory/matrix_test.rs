@@ -0,0 +1,72 @@
+use std::path::Path;
+
+use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, HnswConfig, DEFAULT_COLLECTION};
+use super::common;
+
+/// Build a RAM-Lake config for tests that answers every search by scanning
+fn test_config(backup_path: &Path) -> RamLakeConfig {
+    RamLakeConfig {
+        hnsw: HnswConfig {
+            enabled: false,
+            ..HnswConfig::default()
+        },
+        ..common::test_config(backup_path)
+    }
+}
+
+/// Cosine similarity computed the straightforward way
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm_a = a.iter().map(|a| a * a).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|b| b * b).sum::<f32>().sqrt();
+    dot / (norm_a * norm_b)
+}
+
+/// Vector of an odd dimension, so rows do not fill whole SIMD lanes
+fn vector(i: usize) -> Vec<f32> {
+    (0..13).map(|j| ((i * 31 + j * 17) % 101) as f32 / 50.0 - 1.0).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that scanning the resident matrix matches a naive scan, before and after a restart
+    #[test]
+    fn test_matrix_scan_matches_naive() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let query = vector(7777);
+
+        let mut expected = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+            // Enough rows to split the scan across threads
+            let mut expected = Vec::new();
+            for i in 0..4200 {
+                let namespace = if i % 2 == 0 { "even" } else { "odd" };
+                let id = ram_lake.store_code_in(namespace, &format!("src/file_{}.rs", i), "fn f() {}", "rust").unwrap();
+                ram_lake.index_code(id, vector(i)).unwrap();
+                expected.push((id, cosine(&query, &vector(i)), namespace));
+            }
+
+            let removed = expected.remove(10);
+            ram_lake.delete_code(removed.0).unwrap();
+            expected
+        };
+        expected.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        let results = ram_lake.search_similar(DEFAULT_COLLECTION, query.clone(), 20).unwrap();
+        assert_eq!(results.len(), 20);
+        for (result, expected) in results.iter().zip(&expected) {
+            assert!((result.1 - expected.1).abs() < 1e-5);
+        }
+
+        let odd: Vec<_> = expected.iter().filter(|e| e.2 == "odd").take(5).collect();
+        let results = ram_lake.search_similar_in(DEFAULT_COLLECTION, "odd", query, 5).unwrap();
+        for (result, expected) in results.iter().zip(odd) {
+            assert!((result.1 - expected.1).abs() < 1e-5);
+        }
+    }
+}
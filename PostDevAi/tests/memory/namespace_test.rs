@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, RevisionRetention, DEFAULT_NAMESPACE, DEFAULT_COLLECTION};
+use super::common;
+
+/// Build a RAM-Lake config for tests
+fn test_config(backup_path: &Path) -> RamLakeConfig {
+    RamLakeConfig {
+        // Keep only the live revision so usage tracks the live data
+        revision_retention: RevisionRetention {
+            keep_last: Some(1),
+            max_age_secs: None,
+        },
+        ..common::test_config(backup_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that the same path in two namespaces stays separate, including search
+    #[test]
+    fn test_namespaces_partition_code_and_search() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        let alpha = ram_lake.store_code_in("alpha", "src/main.rs", "fn alpha() {}", "rust").unwrap();
+        let beta = ram_lake.store_code_in("beta", "src/main.rs", "fn beta() {}", "rust").unwrap();
+        ram_lake.index_code(alpha, vec![1.0, 0.0, 0.0]).unwrap();
+        ram_lake.index_code(beta, vec![0.9, 0.1, 0.0]).unwrap();
+
+        assert_eq!(ram_lake.get_code(alpha).unwrap().1, "fn alpha() {}");
+        assert_eq!(ram_lake.find_code("beta", "src/main.rs"), Some(beta));
+        assert_eq!(ram_lake.find_code(DEFAULT_NAMESPACE, "src/main.rs"), None);
+
+        let results = ram_lake.search_similar_in(DEFAULT_COLLECTION, "beta", vec![1.0, 0.0, 0.0], 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, beta);
+        assert_eq!(ram_lake.search_similar(DEFAULT_COLLECTION, vec![1.0, 0.0, 0.0], 10).unwrap().len(), 2);
+
+        let metrics = ram_lake.get_namespace_metrics();
+        assert_eq!(metrics["alpha"].indexed_files, 1);
+        assert_eq!(metrics["alpha"].vector_entries, 1);
+        assert_eq!(metrics["alpha"].used_size, 13 + 12 + 36);
+        assert_eq!(ram_lake.list_namespaces(), vec!["alpha".to_string(), "beta".to_string()]);
+    }
+
+    /// Test that identical content in two namespaces shares one blob
+    #[test]
+    fn test_identical_content_shared_across_namespaces() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        let alpha = ram_lake.store_code_in("alpha", "lib.rs", "pub fn shared() {}", "rust").unwrap();
+        let beta = ram_lake.store_code_in("beta", "lib.rs", "pub fn shared() {}", "rust").unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 18);
+
+        // The blob stays while the other namespace still references it
+        ram_lake.delete_code(alpha).unwrap();
+        assert_eq!(ram_lake.get_code(beta).unwrap().1, "pub fn shared() {}");
+    }
+
+    /// Test that quotas limit a namespace without affecting others
+    #[test]
+    fn test_namespace_quota() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let mut config = test_config(backups.path());
+        config.namespace_quotas.insert("small".to_string(), 16);
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), config).unwrap();
+
+        let id = ram_lake.store_code_in("small", "a.rs", "0123456789", "rust").unwrap();
+        assert!(ram_lake.store_event_in("small", "build", "0123456789").is_err());
+        ram_lake.store_event("build", "0123456789").unwrap();
+        assert_eq!(ram_lake.get_namespace_usage("small"), 10);
+
+        // Freeing space in the namespace makes room again
+        ram_lake.delete_code(id).unwrap();
+        ram_lake.store_event_in("small", "build", "0123456789").unwrap();
+
+        ram_lake.set_namespace_quota("small", None);
+        ram_lake.store_code_in("small", "b.rs", "0123456789", "rust").unwrap();
+        assert_eq!(ram_lake.get_namespace_usage("small"), 20);
+    }
+
+    /// Test that relations follow their entities' namespace across a restart
+    #[test]
+    fn test_namespaced_relations_survive_restart() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+
+        let (code_id, event_id) = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+            let code_id = ram_lake.store_code_in("alpha", "lib.rs", "pub fn lib() {}", "rust").unwrap();
+            let event_id = ram_lake.store_event("build", "cargo build").unwrap();
+            ram_lake.store_metadata(code_id, "built_by", event_id).unwrap();
+            (code_id, event_id)
+        };
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        assert_eq!(ram_lake.get_related_in("alpha", event_id, None).unwrap().len(), 1);
+        assert!(ram_lake.get_related_in(DEFAULT_NAMESPACE, event_id, None).unwrap().is_empty());
+
+        // Code content plus the relation (two UUIDs and its name)
+        assert_eq!(ram_lake.get_namespace_usage("alpha"), 15 + 32 + 8);
+        ram_lake.delete_code(code_id).unwrap();
+        assert_eq!(ram_lake.get_namespace_usage("alpha"), 0);
+        assert_eq!(ram_lake.get_namespace_usage(DEFAULT_NAMESPACE), 11);
+    }
+}
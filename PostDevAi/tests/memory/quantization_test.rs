@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, HnswConfig, DistanceMetric, Quantization, DEFAULT_COLLECTION};
+use super::common::{self, random_vectors};
+
+/// Build a RAM-Lake config for tests with the given HNSW settings
+fn test_config(backup_path: &Path, hnsw: HnswConfig) -> RamLakeConfig {
+    RamLakeConfig {
+        hnsw,
+        ..common::test_config(backup_path)
+    }
+}
+
+/// HNSW settings that leave every search to the matrices
+fn no_hnsw() -> HnswConfig {
+    HnswConfig {
+        enabled: false,
+        ..HnswConfig::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that each collection ranks its embeddings by its own metric
+    #[test]
+    fn test_distance_metrics() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), HnswConfig::default())).unwrap();
+
+        ram_lake.create_vector_collection("cosine", 2, DistanceMetric::Cosine, None).unwrap();
+        ram_lake.create_vector_collection("dot", 2, DistanceMetric::Dot, None).unwrap();
+        ram_lake.create_vector_collection("l2", 2, DistanceMetric::L2, None).unwrap();
+
+        // Embeddings share the ID of their event, so each collection gets its own events
+        let mut ids = HashMap::new();
+        for collection in ["cosine", "dot", "l2"] {
+            let near = ram_lake.store_event("near", "near").unwrap();
+            let long = ram_lake.store_event("long", "long").unwrap();
+            ram_lake.index_entity(collection, near, vec![1.0, 0.1]).unwrap();
+            ram_lake.index_entity(collection, long, vec![4.0, 3.0]).unwrap();
+            ids.insert(collection, (near, long));
+        }
+
+        let query = vec![1.0, 0.0];
+        let ranked = |collection: &str| ram_lake.search_similar(collection, query.clone(), 2).unwrap();
+        assert_eq!(ranked("cosine")[0].0, ids["cosine"].0);
+        assert_eq!(ranked("dot")[0], (ids["dot"].1, 4.0));
+        assert_eq!(ranked("l2")[0].0, ids["l2"].0);
+        assert!((ranked("l2")[1].1 + 18.0f32.sqrt()).abs() < 1e-5);
+
+        // Quantized collections re-rank by the same metric without a cold tier
+        ram_lake.set_vector_quantization("dot", Quantization::Int8).unwrap();
+        assert_eq!(ranked("dot")[0], (ids["dot"].1, 4.0));
+    }
+
+    /// Test that quantized collections shrink their resident vectors and keep recall
+    #[test]
+    fn test_quantization_savings_and_recall() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+
+        let vectors = random_vectors(1000, 32, 42);
+        {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), no_hnsw())).unwrap();
+            for (i, vector) in vectors.iter().enumerate() {
+                let id = ram_lake.store_code(&format!("src/file_{}.rs", i), "fn f() {}", "rust").unwrap();
+                ram_lake.index_code(id, vector.clone()).unwrap();
+            }
+            let full_usage = ram_lake.get_memory_usage();
+
+            // A full-precision embedding takes a 128-byte blob and a 132-byte row;
+            // quantized ones keep only their codes, scale and norm in RAM
+            for (quantization, row_size) in [(Quantization::Int8, 32 + 8), (Quantization::Product { subvectors: 8 }, 8 + 4)] {
+                ram_lake.set_vector_quantization(DEFAULT_COLLECTION, quantization).unwrap();
+                assert_eq!(full_usage - ram_lake.get_memory_usage(), 1000 * (128 + 132 - row_size));
+
+                let metrics = &ram_lake.get_vector_collection_metrics()[DEFAULT_COLLECTION];
+                assert_eq!(metrics.quantization, quantization);
+                assert_eq!(metrics.full_precision_size, 1000 * (128 + 132));
+                assert!(metrics.resident_size * 2 < metrics.full_precision_size);
+                assert!(metrics.recall.unwrap() >= 0.8, "recall {:?}", metrics.recall);
+
+                // Results carry their full-precision similarity
+                let results = ram_lake.search_similar(DEFAULT_COLLECTION, vectors[3].clone(), 5).unwrap();
+                assert_eq!(results[0].0, ram_lake.search_similar_exact(DEFAULT_COLLECTION, vectors[3].clone(), 1).unwrap()[0].0);
+                assert!((results[0].1 - 1.0).abs() < 1e-5);
+            }
+
+            // Full precision charges the blobs again
+            ram_lake.set_vector_quantization(DEFAULT_COLLECTION, Quantization::None).unwrap();
+            assert_eq!(ram_lake.get_memory_usage(), full_usage);
+            ram_lake.set_vector_quantization(DEFAULT_COLLECTION, Quantization::Product { subvectors: 8 }).unwrap();
+        }
+
+        // The setting survives a restart and new embeddings are quantized too
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), no_hnsw())).unwrap();
+        let id = ram_lake.store_code("src/new.rs", "fn new() {}", "rust").unwrap();
+        ram_lake.index_code(id, vectors[0].iter().map(|value| -value).collect()).unwrap();
+        let metrics = &ram_lake.get_vector_collection_metrics()[DEFAULT_COLLECTION];
+        assert_eq!(metrics.quantization, Quantization::Product { subvectors: 8 });
+        assert_eq!(metrics.entries, 1001);
+        assert!(metrics.resident_size * 2 < metrics.full_precision_size);
+        assert!(ram_lake.measure_vector_recall(DEFAULT_COLLECTION, 20).unwrap() >= 0.8);
+        assert!(ram_lake.set_vector_quantization(DEFAULT_COLLECTION, Quantization::Product { subvectors: 33 }).is_err());
+    }
+    /// Test that backups hold the full-precision embeddings of quantized collections
+    #[test]
+    fn test_quantized_backup_restore() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), no_hnsw())).unwrap();
+
+        let vectors = random_vectors(100, 16, 7);
+        let mut ids = Vec::new();
+        for (i, vector) in vectors.iter().enumerate() {
+            let id = ram_lake.store_code(&format!("src/file_{}.rs", i), "fn f() {}", "rust").unwrap();
+            ram_lake.index_code(id, vector.clone()).unwrap();
+            ids.push(id);
+        }
+        ram_lake.set_vector_quantization(DEFAULT_COLLECTION, Quantization::Int8).unwrap();
+        let expected = ram_lake.search_similar(DEFAULT_COLLECTION, vectors[5].clone(), 10).unwrap();
+        assert_eq!(expected[0].0, ids[5]);
+
+        let info = ram_lake.create_backup().unwrap();
+
+        // Change the embeddings the search re-ranks, then roll back
+        ram_lake.delete_embedding(ids[5]).unwrap();
+        ram_lake.upsert_embedding(DEFAULT_COLLECTION, expected[1].0, vectors[5].iter().map(|value| -value).collect()).unwrap();
+        assert_ne!(ram_lake.search_similar(DEFAULT_COLLECTION, vectors[5].clone(), 10).unwrap(), expected);
+        ram_lake.restore_backup(&info.id).unwrap();
+
+        let results = ram_lake.search_similar(DEFAULT_COLLECTION, vectors[5].clone(), 10).unwrap();
+        assert_eq!(results, expected);
+        assert!((results[0].1 - 1.0).abs() < 1e-5);
+
+        // The restored store opens the same way
+        drop(ram_lake);
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), no_hnsw())).unwrap();
+        assert_eq!(ram_lake.search_similar(DEFAULT_COLLECTION, vectors[5].clone(), 10).unwrap(), expected);
+    }
+}
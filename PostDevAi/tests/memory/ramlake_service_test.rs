@@ -0,0 +1,114 @@
+use std::path::Path;
+use tonic::{Code, Request};
+use uuid::Uuid;
+
+use postdevai::core::memory::ramlake::{RamLake, AsyncRamLake, DistanceMetric};
+use postdevai::core::network::ramlake_service::{RamLakeService, DragonNodeService};
+use postdevai::proto::postdevai::{
+    SearchSimilarRequest, StoreEmbeddingsBatchRequest, SearchSimilarBatchRequest,
+    store_embeddings_batch_request, search_similar_batch_request,
+};
+use super::common::test_config;
+
+/// Open the RAM-Lake RPC handlers over a RAM-Lake in temporary directories
+fn open_service(ramdisk: &Path, backups: &Path) -> (RamLakeService, AsyncRamLake) {
+    let ram_lake = AsyncRamLake::new(RamLake::new(ramdisk.to_path_buf(), test_config(backups)).unwrap());
+    (RamLakeService::new(ram_lake.clone()), ram_lake)
+}
+
+/// Wire form of a UUID
+fn wire_uuid(id: Uuid) -> Option<postdevai::proto::postdevai::Uuid> {
+    Some(postdevai::proto::postdevai::Uuid { value: id.to_string() })
+}
+
+/// Request storing embeddings in a collection
+fn store_request(collection: &str, embeddings: Vec<(Uuid, Vec<f32>)>) -> Request<StoreEmbeddingsBatchRequest> {
+    Request::new(StoreEmbeddingsBatchRequest {
+        collection: collection.to_string(),
+        embeddings: embeddings.into_iter()
+            .map(|(id, embedding)| store_embeddings_batch_request::Entry { id: wire_uuid(id), embedding })
+            .collect(),
+    })
+}
+
+/// Request searching a collection
+fn search_request(collection: &str, embedding: Vec<f32>, limit: i32) -> Request<SearchSimilarRequest> {
+    Request::new(SearchSimilarRequest {
+        embedding,
+        limit,
+        collection: collection.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that stored batches are found by single and batched searches, in the default collection by default
+    #[tokio::test]
+    async fn test_store_and_search() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let (service, ram_lake) = open_service(ramdisk.path(), backups.path());
+
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let stored = service.store_embeddings_batch(store_request("", vec![(first, vec![1.0, 0.0]), (second, vec![0.0, 1.0])])).await.unwrap();
+        assert_eq!(stored.into_inner().stored, 2);
+
+        let response = service.search_similar(search_request("", vec![0.0, 1.0], 1)).await.unwrap().into_inner();
+        assert_eq!(response.results.len(), 1);
+        assert_eq!(response.results[0].id.as_ref().unwrap().value, second.to_string());
+
+        let response = service.search_similar_batch(Request::new(SearchSimilarBatchRequest {
+            collection: "default".to_string(),
+            queries: vec![
+                search_similar_batch_request::Query { embedding: vec![1.0, 0.0] },
+                search_similar_batch_request::Query { embedding: vec![0.0, 1.0] },
+            ],
+            limit: 2,
+        })).await.unwrap().into_inner();
+        let best: Vec<String> = response.responses.iter().map(|r| r.results[0].id.as_ref().unwrap().value.clone()).collect();
+        assert_eq!(best, vec![first.to_string(), second.to_string()]);
+
+        // Named collections are kept apart
+        ram_lake.create_vector_collection("events".to_string(), 3, DistanceMetric::Cosine, None).await.unwrap();
+        service.store_embeddings_batch(store_request("events", vec![(Uuid::new_v4(), vec![0.0, 0.0, 1.0])])).await.unwrap();
+        let response = service.search_similar(search_request("events", vec![0.0, 0.0, 1.0], 5)).await.unwrap().into_inner();
+        assert_eq!(response.results.len(), 1);
+    }
+
+    /// Test that invalid requests are rejected as invalid arguments and store nothing
+    #[tokio::test]
+    async fn test_invalid_requests() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let (service, ram_lake) = open_service(ramdisk.path(), backups.path());
+        service.store_embeddings_batch(store_request("", vec![(Uuid::new_v4(), vec![1.0, 0.0])])).await.unwrap();
+
+        let wrong_dimension = store_request("", vec![(Uuid::new_v4(), vec![1.0, 0.0]), (Uuid::new_v4(), vec![1.0, 0.0, 0.0])]);
+        let unknown_collection = store_request("missing", vec![(Uuid::new_v4(), vec![1.0, 0.0])]);
+        let bad_uuid = Request::new(StoreEmbeddingsBatchRequest {
+            collection: String::new(),
+            embeddings: vec![store_embeddings_batch_request::Entry {
+                id: Some(postdevai::proto::postdevai::Uuid { value: "not-a-uuid".to_string() }),
+                embedding: vec![1.0, 0.0],
+            }],
+        });
+        for request in [wrong_dimension, unknown_collection, bad_uuid] {
+            assert_eq!(service.store_embeddings_batch(request).await.unwrap_err().code(), Code::InvalidArgument);
+        }
+        assert_eq!(ram_lake.search_similar("default".to_string(), vec![1.0, 0.0], 10).await.unwrap().len(), 1);
+
+        let status = service.search_similar(search_request("", vec![1.0, 0.0, 0.0], 1)).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(service.search_similar(search_request("", vec![1.0, 0.0], -1)).await.unwrap_err().code(), Code::InvalidArgument);
+        assert_eq!(service.search_similar(search_request("missing", vec![1.0, 0.0], 1)).await.unwrap_err().code(), Code::InvalidArgument);
+
+        let status = service.search_similar_batch(Request::new(SearchSimilarBatchRequest {
+            collection: String::new(),
+            queries: vec![search_similar_batch_request::Query { embedding: vec![1.0] }],
+            limit: 1,
+        })).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,184 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, RevisionRetention, DEFAULT_COLLECTION};
+use super::common;
+
+/// Build a RAM-Lake config for tests
+fn test_config(backup_path: &Path) -> RamLakeConfig {
+    RamLakeConfig {
+        // Keep only the live revision so usage tracks the live data
+        revision_retention: RevisionRetention {
+            keep_last: Some(1),
+            max_age_secs: None,
+        },
+        ..common::test_config(backup_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that deleting code releases its content, embedding and relations
+    #[test]
+    fn test_delete_code_releases_memory() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        let code_id = ram_lake.store_code("src/main.rs", "fn main() {}", "rust").unwrap();
+        ram_lake.index_code(code_id, vec![0.1, 0.2, 0.3, 0.4]).unwrap();
+        let event_id = ram_lake.store_event("build", "cargo build").unwrap();
+        ram_lake.store_metadata(event_id, "built", code_id).unwrap();
+        assert!(ram_lake.get_memory_usage() > 0);
+
+        ram_lake.delete_event(event_id).unwrap();
+        ram_lake.delete_code(code_id).unwrap();
+
+        assert_eq!(ram_lake.get_memory_usage(), 0);
+        assert!(ram_lake.get_code(code_id).is_err());
+        assert!(ram_lake.get_event(event_id).is_err());
+        assert!(ram_lake.search_similar(DEFAULT_COLLECTION, vec![0.1, 0.2, 0.3, 0.4], 10).unwrap().is_empty());
+    }
+
+    /// Test that updates and re-stores adjust usage by the size difference
+    #[test]
+    fn test_update_and_replace_code() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        let id = ram_lake.store_code("lib.rs", "short", "rust").unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 5);
+
+        ram_lake.update_code(id, "a bit longer").unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 12);
+        assert_eq!(ram_lake.get_code(id).unwrap().1, "a bit longer");
+
+        // Storing the same path again replaces the previous file
+        let new_id = ram_lake.store_code("lib.rs", "new", "rust").unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 3);
+        assert!(ram_lake.get_code(id).is_err());
+        assert_eq!(ram_lake.get_code(new_id).unwrap().1, "new");
+    }
+
+    /// Test that a re-store that does not fit keeps the previous file's embeddings and relations
+    #[test]
+    fn test_failed_replace_keeps_previous_file() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        let code_id = ram_lake.store_code_in("small", "lib.rs", "pub fn lib() {}", "rust").unwrap();
+        ram_lake.index_code(code_id, vec![1.0, 0.0, 0.0, 0.0]).unwrap();
+        let event_id = ram_lake.store_event("build", "cargo build").unwrap();
+        ram_lake.store_metadata(event_id, "built", code_id).unwrap();
+        let usage = ram_lake.get_memory_usage();
+
+        ram_lake.set_namespace_quota("small", Some(ram_lake.get_namespace_usage("small") + 4));
+        assert!(ram_lake.store_code_in("small", "lib.rs", "pub fn rewritten() {}", "rust").is_err());
+
+        assert_eq!(ram_lake.get_code(code_id).unwrap().1, "pub fn lib() {}");
+        assert_eq!(ram_lake.search_similar(DEFAULT_COLLECTION, vec![1.0, 0.0, 0.0, 0.0], 1).unwrap()[0].0, code_id);
+        assert_eq!(ram_lake.get_related(code_id, None).unwrap(), vec![(event_id, "built".to_string(), code_id)]);
+        assert_eq!(ram_lake.get_memory_usage(), usage);
+    }
+
+    /// Test that files with identical content share one blob
+    #[test]
+    fn test_identical_content_shares_blob() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let content = "pub fn shared() {}";
+        let size = content.len() as u64;
+
+        {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+            ram_lake.store_code("a.rs", content, "rust").unwrap();
+            let b = ram_lake.store_code("b.rs", "other", "rust").unwrap();
+
+            // Updating into identical content drops the old blob
+            ram_lake.update_code(b, content).unwrap();
+            assert_eq!(ram_lake.get_memory_usage(), size);
+            assert_eq!(ram_lake.get_code(b).unwrap().1, content);
+        }
+
+        // The shared content was written to the code segments once
+        let copies: usize = std::fs::read_dir(ramdisk.path().join("code")).unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "seg"))
+            .map(|path| {
+                let data = std::fs::read(path).unwrap();
+                data.windows(content.len()).filter(|w| *w == content.as_bytes()).count()
+            })
+            .sum();
+        assert_eq!(copies, 1);
+
+        // Reopening counts the shared blob once; it is freed with its last file
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), size);
+
+        let a = ram_lake.find_code("default", "a.rs").unwrap();
+        ram_lake.delete_code(a).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), size);
+
+        let b = ram_lake.find_code("default", "b.rs").unwrap();
+        ram_lake.delete_code(b).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 0);
+    }
+
+    /// Test relation and embedding deletion and accounting across a restart
+    #[test]
+    fn test_relations_embeddings_and_restart() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let (source, target) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let usage = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+            ram_lake.store_metadata(source, "imports", target).unwrap();
+            ram_lake.store_metadata(source, "imports", target).unwrap();
+            ram_lake.store_metadata(source, "calls", target).unwrap();
+            ram_lake.index_code(source, vec![1.0; 8]).unwrap();
+
+            ram_lake.delete_relation(source, "calls", target).unwrap();
+            ram_lake.delete_relation(source, "calls", target).unwrap();
+            ram_lake.get_memory_usage()
+        };
+
+        // Reopening accounts for the data already in the stores
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), usage);
+        assert_eq!(ram_lake.get_related(source, None).unwrap().len(), 1);
+
+        ram_lake.delete_embedding(source).unwrap();
+        ram_lake.delete_relation(source, "imports", target).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 0);
+    }
+
+    /// Test that shutdown stops the background tasks and takes a final backup
+    #[test]
+    fn test_shutdown_flushes_and_backs_up() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        let handle = ram_lake.start().unwrap();
+        let id = ram_lake.store_code("src/lib.rs", "pub fn lib() {}", "rust").unwrap();
+
+        let started = Instant::now();
+        handle.shutdown().unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // The final backup holds the data written since start
+        let backups = ram_lake.list_backups().unwrap();
+        assert_eq!(backups.len(), 1);
+
+        ram_lake.delete_code(id).unwrap();
+        ram_lake.restore_backup(&backups[0].id).unwrap();
+        assert_eq!(ram_lake.get_code(id).unwrap().1, "pub fn lib() {}");
+    }
+}
@@ -0,0 +1,92 @@
+use std::path::Path;
+
+use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, StoreAllocation, EvictionConfig, EvictionPolicyKind};
+use super::common;
+
+/// Build a 1000 byte RAM-Lake config for tests that never evicts
+fn test_config(backup_path: &Path) -> RamLakeConfig {
+    RamLakeConfig {
+        max_size: 1000,
+        allocation: StoreAllocation {
+            vector_store: 0.4,
+            code_store: 0.2,
+            history_store: 0.3,
+            metadata_store: 0.1,
+        },
+        eviction: EvictionConfig {
+            policy: EvictionPolicyKind::Disabled,
+            ..EvictionConfig::default()
+        },
+        ..common::test_config(backup_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that rebalancing gives a filling store capacity from idle ones
+    #[test]
+    fn test_rebalance_grows_full_store() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let config = test_config(backups.path());
+        let min = config.adaptive_allocation.min.clone();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), config).unwrap();
+
+        ram_lake.store_code("big.rs", &"a".repeat(180), "rust").unwrap();
+        assert!(ram_lake.store_code("more.rs", &"b".repeat(150), "rust").is_err());
+
+        let allocation = ram_lake.rebalance().unwrap();
+        assert!(allocation.code_store > 0.2);
+        assert!(allocation.vector_store >= min.vector_store);
+        assert!(allocation.history_store >= min.history_store);
+        assert!(allocation.metadata_store >= min.metadata_store);
+        assert!(allocation.vector_store + allocation.code_store + allocation.history_store + allocation.metadata_store <= 1.0 + 1e-6);
+
+        ram_lake.store_code("more.rs", &"b".repeat(150), "rust").unwrap();
+    }
+
+    /// Test that the allocation can be changed at runtime but never below usage
+    #[test]
+    fn test_set_allocation() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        ram_lake.store_code("big.rs", &"a".repeat(180), "rust").unwrap();
+
+        let too_large = StoreAllocation {
+            vector_store: 0.5,
+            code_store: 0.4,
+            history_store: 0.2,
+            metadata_store: 0.1,
+        };
+        assert!(ram_lake.set_allocation(too_large).is_err());
+
+        let below_usage = StoreAllocation {
+            vector_store: 0.6,
+            code_store: 0.1,
+            history_store: 0.2,
+            metadata_store: 0.1,
+        };
+        assert!(ram_lake.set_allocation(below_usage).is_err());
+        assert!((ram_lake.get_allocation().code_store - 0.2).abs() < 1e-6);
+
+        let allocation = StoreAllocation {
+            vector_store: 0.3,
+            code_store: 0.4,
+            history_store: 0.2,
+            metadata_store: 0.1,
+        };
+        ram_lake.set_allocation(allocation).unwrap();
+        assert!((ram_lake.get_allocation().code_store - 0.4).abs() < 1e-6);
+        assert!((ram_lake.get_allocation().vector_store - 0.3).abs() < 1e-6);
+        ram_lake.store_code("more.rs", &"b".repeat(150), "rust").unwrap();
+
+        // The allocation set at runtime is reapplied on the next start
+        drop(ram_lake);
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        assert!((ram_lake.get_allocation().code_store - 0.4).abs() < 1e-6);
+        assert!((ram_lake.get_allocation().vector_store - 0.3).abs() < 1e-6);
+    }
+}
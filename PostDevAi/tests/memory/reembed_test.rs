@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use postdevai::core::memory::ramlake::{RamLake, DistanceMetric, Embedder, StaleEmbedding, StaleReason, DEFAULT_COLLECTION};
+use super::common::test_config;
+
+/// Embedder mapping a text to its length and a version-specific constant
+struct LengthEmbedder {
+    version: u32,
+}
+
+impl Embedder for LengthEmbedder {
+    fn model(&self) -> String {
+        format!("length-v{}", self.version)
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        Ok(texts.iter().map(|text| vec![text.len() as f32, self.version as f32]).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that upserts replace embeddings in place and move them between collections
+    #[test]
+    fn test_upsert_embedding() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        let id = ram_lake.store_code("src/main.rs", "fn main() {}", "rust").unwrap();
+        ram_lake.index_code(id, vec![1.0, 0.0]).unwrap();
+        assert!(ram_lake.index_code(id, vec![0.0, 1.0]).is_err());
+        let usage = ram_lake.get_memory_usage();
+
+        ram_lake.upsert_embedding(DEFAULT_COLLECTION, id, vec![0.0, 1.0]).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), usage);
+        assert_eq!(ram_lake.search_similar(DEFAULT_COLLECTION, vec![0.0, 1.0], 1).unwrap(), vec![(id, 1.0)]);
+
+        // Upserting into another collection moves the embedding there
+        ram_lake.create_vector_collection("wide", 4, DistanceMetric::Cosine, None).unwrap();
+        ram_lake.upsert_embedding("wide", id, vec![0.0, 0.0, 1.0, 0.0]).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), usage + 8);
+        assert!(ram_lake.search_similar(DEFAULT_COLLECTION, vec![0.0, 1.0], 1).unwrap().is_empty());
+        assert_eq!(ram_lake.search_similar("wide", vec![0.0, 0.0, 1.0, 0.0], 1).unwrap(), vec![(id, 1.0)]);
+    }
+
+    /// Test that changed code and a new embedder make embeddings stale until they are re-embedded
+    #[test]
+    fn test_stale_embeddings_reembedded() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+
+        let (code_id, event_id) = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+            ram_lake.create_vector_collection(DEFAULT_COLLECTION, 2, DistanceMetric::Dot, None).unwrap();
+            ram_lake.set_embedder(DEFAULT_COLLECTION, Some(Arc::new(LengthEmbedder { version: 1 }))).unwrap();
+
+            let code_id = ram_lake.store_code("src/lib.rs", "pub fn lib() {}", "rust").unwrap();
+            let event_id = ram_lake.store_event("build", "cargo build").unwrap();
+            ram_lake.store_embeddings_batch(DEFAULT_COLLECTION, vec![(code_id, vec![15.0, 1.0]), (event_id, vec![11.0, 1.0])]).unwrap();
+            assert!(ram_lake.stale_embeddings(DEFAULT_COLLECTION).unwrap().is_empty());
+
+            ram_lake.update_code(code_id, "pub fn lib() { todo!() }").unwrap();
+            assert_eq!(ram_lake.stale_embeddings(DEFAULT_COLLECTION).unwrap(), vec![StaleEmbedding { id: code_id, reason: StaleReason::SourceChanged }]);
+            (code_id, event_id)
+        };
+
+        // Staleness survives a restart; a new model makes every embedding stale
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        ram_lake.set_embedder(DEFAULT_COLLECTION, Some(Arc::new(LengthEmbedder { version: 2 }))).unwrap();
+        let reasons: HashMap<_, _> = ram_lake.stale_embeddings(DEFAULT_COLLECTION).unwrap().into_iter().map(|stale| (stale.id, stale.reason)).collect();
+        assert_eq!(reasons, HashMap::from([(code_id, StaleReason::SourceChanged), (event_id, StaleReason::ModelChanged)]));
+
+        assert_eq!(ram_lake.reembed_stale(1).unwrap(), 1);
+        assert_eq!(ram_lake.reembed_stale(10).unwrap(), 1);
+        assert!(ram_lake.stale_embeddings(DEFAULT_COLLECTION).unwrap().is_empty());
+        assert_eq!(ram_lake.search_similar(DEFAULT_COLLECTION, vec![1.0, 0.0], 2).unwrap(), vec![(code_id, 24.0), (event_id, 11.0)]);
+    }
+}
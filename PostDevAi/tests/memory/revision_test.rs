@@ -0,0 +1,86 @@
+use std::path::Path;
+
+use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, RevisionRetention, DEFAULT_NAMESPACE};
+use super::common;
+
+/// Build a RAM-Lake config for tests
+fn test_config(backup_path: &Path, keep_last: Option<usize>) -> RamLakeConfig {
+    RamLakeConfig {
+        revision_retention: RevisionRetention {
+            keep_last,
+            max_age_secs: None,
+        },
+        ..common::test_config(backup_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test point-in-time reads and diffs across updates, re-stores and deletion
+    #[test]
+    fn test_file_history() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), None)).unwrap();
+
+        let before = chrono::Utc::now();
+        let id = ram_lake.store_code("src/lib.rs", "fn a() {}\n", "rust").unwrap();
+        let first = chrono::Utc::now();
+        ram_lake.update_code(id, "fn a() {}\nfn b() {}\n").unwrap();
+        let second = chrono::Utc::now();
+        let new_id = ram_lake.store_code("src/lib.rs", "fn b() {}\n", "rust").unwrap();
+        let third = chrono::Utc::now();
+        ram_lake.delete_code(new_id).unwrap();
+
+        let revisions = ram_lake.list_code_revisions(DEFAULT_NAMESPACE, "src/lib.rs");
+        assert_eq!(revisions.iter().map(|r| r.revision).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert_eq!(revisions[2].id, new_id);
+        assert!(revisions[3].deleted);
+
+        let content_at = |timestamp| ram_lake.get_code_at(DEFAULT_NAMESPACE, "src/lib.rs", timestamp).unwrap().map(|(_, content)| content);
+        assert_eq!(content_at(before), None);
+        assert_eq!(content_at(first).as_deref(), Some("fn a() {}\n"));
+        assert_eq!(content_at(second).as_deref(), Some("fn a() {}\nfn b() {}\n"));
+        assert_eq!(content_at(third).as_deref(), Some("fn b() {}\n"));
+        assert_eq!(content_at(chrono::Utc::now()), None);
+
+        let diff = ram_lake.diff_code_revisions(DEFAULT_NAMESPACE, "src/lib.rs", 2, 3).unwrap();
+        assert!(diff.contains("-fn a() {}"));
+        assert!(!diff.contains("-fn b() {}"));
+    }
+
+    /// Test that retention drops old revisions and releases their memory, also after a restart
+    #[test]
+    fn test_revision_retention() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+
+        {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), Some(2))).unwrap();
+            let id = ram_lake.store_code("main.rs", "one", "rust").unwrap();
+            ram_lake.update_code(id, "two!").unwrap();
+            ram_lake.update_code(id, "three").unwrap();
+
+            // Revision 1 was pruned; "two!" and "three" remain
+            assert_eq!(ram_lake.get_memory_usage(), 9);
+        }
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), Some(2))).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 9);
+
+        let revisions = ram_lake.list_code_revisions(DEFAULT_NAMESPACE, "main.rs");
+        assert_eq!(revisions.iter().map(|r| r.revision).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(ram_lake.get_code_revision(DEFAULT_NAMESPACE, "main.rs", 2).unwrap().1, "two!");
+        assert!(ram_lake.get_code_revision(DEFAULT_NAMESPACE, "main.rs", 1).is_err());
+
+        // Deleting keeps the last content as history until it falls out of retention
+        let id = ram_lake.find_code(DEFAULT_NAMESPACE, "main.rs").unwrap();
+        ram_lake.delete_code(id).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 5);
+
+        ram_lake.store_code("main.rs", "four", "rust").unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 4);
+    }
+}
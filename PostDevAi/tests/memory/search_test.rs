@@ -0,0 +1,96 @@
+use postdevai::core::memory::ramlake::{RamLake, SearchQuery, SearchWeights};
+use super::common::test_config;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that exact identifiers rank first by BM25 and the breakdown explains each hit
+    #[test]
+    fn test_hybrid_search_fuses_rankings() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        let exact = ram_lake.store_code("src/history.rs", "fn remove_oldest_events(&mut self) { self.events.pop_front(); }", "rust").unwrap();
+        let similar = ram_lake.store_code("src/cleanup.rs", "fn prune(&mut self) { self.entries.clear(); }", "rust").unwrap();
+        let other = ram_lake.store_code("src/main.rs", "fn main() { println!(\"events\"); }", "rust").unwrap();
+        let error = ram_lake.store_event("build", "error[E0432]: unresolved import").unwrap();
+        ram_lake.index_code(exact, vec![0.0, 1.0]).unwrap();
+        ram_lake.index_code(similar, vec![1.0, 0.0]).unwrap();
+        ram_lake.index_code(other, vec![0.6, 0.8]).unwrap();
+
+        let query = SearchQuery {
+            text: "remove_oldest_events".to_string(),
+            embedding: Some(vec![1.0, 0.0]),
+            limit: 3,
+            ..SearchQuery::default()
+        };
+        let hits = ram_lake.search(&query).unwrap();
+        assert_eq!(hits[0].id, exact);
+        assert_eq!(hits[0].breakdown.lexical_rank, Some(1));
+        assert_eq!(hits[0].breakdown.semantic_rank, Some(3));
+        assert!(hits[0].breakdown.matched_terms.contains(&"remove_oldest_events".to_string()));
+        assert!((hits[0].score - (1.0 / 61.0 + 1.0 / 63.0)).abs() < 1e-6);
+        let similar_hit = hits.iter().find(|hit| hit.id == similar).unwrap();
+        assert_eq!(similar_hit.breakdown.lexical_rank, None);
+        assert_eq!(similar_hit.breakdown.semantic_rank, Some(1));
+
+        // Without the lexical ranking the closest embedding wins
+        let semantic_only = SearchQuery {
+            weights: SearchWeights { lexical: 0.0, ..SearchWeights::default() },
+            ..query.clone()
+        };
+        assert_eq!(ram_lake.search(&semantic_only).unwrap()[0].id, similar);
+
+        // Error codes are found in events, which have no embedding
+        let hits = ram_lake.search(&SearchQuery { text: "E0432".to_string(), ..SearchQuery::default() }).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, error);
+        assert!(ram_lake.search(&SearchQuery { weights: SearchWeights { rrf_k: 0.0, ..SearchWeights::default() }, ..query }).is_err());
+    }
+
+    /// Test that the BM25 index follows writes, deletes and namespaces, and is rebuilt on open
+    #[test]
+    fn test_lexical_index_follows_stores() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let text = |text: &str| SearchQuery { text: text.to_string(), ..SearchQuery::default() };
+
+        let (id, event_id) = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+            let id = ram_lake.store_code_in("alpha", "src/lib.rs", "fn parseConfig() {}", "rust").unwrap();
+            let beta = ram_lake.store_code_in("beta", "src/lib.rs", "fn parse_config() {}", "rust").unwrap();
+            assert_eq!(ram_lake.search(&text("config")).unwrap().len(), 2);
+            let in_beta = SearchQuery { namespace: Some("beta".to_string()), ..text("config") };
+            assert_eq!(ram_lake.search(&in_beta).unwrap()[0].id, beta);
+            assert_eq!(ram_lake.search(&in_beta).unwrap().len(), 1);
+
+            ram_lake.update_code(id, "fn load_settings() {}").unwrap();
+            assert_eq!(ram_lake.search(&text("parseconfig")).unwrap().len(), 0);
+            assert_eq!(ram_lake.search(&text("settings")).unwrap()[0].id, id);
+
+            ram_lake.delete_code(beta).unwrap();
+            assert!(ram_lake.search(&text("config")).unwrap().is_empty());
+
+            // Storing a path again replaces the file that had it
+            let replaced = ram_lake.store_code_in("beta", "src/lib.rs", "fn parse_config() {}", "rust").unwrap();
+            let id_replacing = ram_lake.store_code_in("beta", "src/lib.rs", "fn read_manifest() {}", "rust").unwrap();
+            assert!(ram_lake.search(&text("config")).unwrap().is_empty());
+            assert_eq!(ram_lake.search(&text("manifest")).unwrap()[0].id, id_replacing);
+            assert_ne!(replaced, id_replacing);
+
+            let event_id = ram_lake.store_event("build", "error[E0599]: no method named `frobnicate`").unwrap();
+            assert_eq!(ram_lake.search(&text("frobnicate")).unwrap()[0].id, event_id);
+            (id, event_id)
+        };
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        assert_eq!(ram_lake.search(&text("settings")).unwrap()[0].id, id);
+        assert_eq!(ram_lake.search(&text("frobnicate")).unwrap()[0].id, event_id);
+
+        ram_lake.delete_event(event_id).unwrap();
+        assert!(ram_lake.search(&text("frobnicate")).unwrap().is_empty());
+    }
+}
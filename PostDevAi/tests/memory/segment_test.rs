@@ -0,0 +1,72 @@
+use std::path::Path;
+
+use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, RevisionRetention, SegmentStorage};
+use super::common;
+
+/// Build a RAM-Lake config for tests with tiny segments
+fn test_config(backup_path: &Path) -> RamLakeConfig {
+    RamLakeConfig {
+        revision_retention: RevisionRetention {
+            keep_last: Some(1),
+            max_age_secs: None,
+        },
+        segment_storage: SegmentStorage {
+            segment_size: 256,
+            ..SegmentStorage::default()
+        },
+        ..common::test_config(backup_path)
+    }
+}
+
+/// Count the segment files of a store
+fn segment_count(dir: &Path) -> usize {
+    std::fs::read_dir(dir).unwrap()
+        .filter(|e| e.as_ref().unwrap().path().extension().is_some_and(|ext| ext == "seg"))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that compaction reclaims deleted entries and the survivors outlive a restart
+    #[test]
+    fn test_compaction_reclaims_deleted_entries() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let history = ramdisk.path().join("history");
+
+        let ids = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+            let ids: Vec<_> = (0..20)
+                .map(|i| ram_lake.store_event("command", &format!("cargo build --release #{:04}", i)).unwrap())
+                .collect();
+            for id in &ids[..15] {
+                ram_lake.delete_event(*id).unwrap();
+            }
+
+            let segments = segment_count(&history);
+            assert!(segments > 1);
+
+            assert!(ram_lake.compact_segments().unwrap() > 0);
+            assert!(segment_count(&history) < segments);
+
+            // Nothing is left to reclaim
+            assert_eq!(ram_lake.compact_segments().unwrap(), 0);
+
+            ids
+        };
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        for (i, id) in ids.iter().enumerate() {
+            match ram_lake.get_event(*id) {
+                Ok((_, content, _)) => assert_eq!(content, format!("cargo build --release #{:04}", i)),
+                Err(_) => assert!(i < 15, "event {} was lost", i),
+            }
+        }
+        for id in &ids[..15] {
+            assert!(ram_lake.get_event(*id).is_err());
+        }
+    }
+}
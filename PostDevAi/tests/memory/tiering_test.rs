@@ -0,0 +1,91 @@
+use std::path::Path;
+
+use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, RevisionRetention, ColdTier, DEFAULT_COLLECTION};
+use super::common;
+
+/// Build a RAM-Lake config for tests that demotes every entry not accessed right now
+fn test_config(backup_path: &Path, cold_path: &Path) -> RamLakeConfig {
+    RamLakeConfig {
+        revision_retention: RevisionRetention {
+            keep_last: Some(1),
+            max_age_secs: None,
+        },
+        cold_tier: ColdTier {
+            enabled: true,
+            path: cold_path.to_path_buf(),
+            demote_after_secs: 0,
+            check_interval: 3600,
+        },
+        ..common::test_config(backup_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that idle entries leave RAM and come back when they are read
+    #[test]
+    fn test_demote_idle_and_promote_on_read() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let cold = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), cold.path())).unwrap();
+
+        let code_id = ram_lake.store_code("src/main.rs", "fn main() {}", "rust").unwrap();
+        ram_lake.index_code(code_id, vec![0.5; 4]).unwrap();
+        let event_id = ram_lake.store_event("build", "cargo build").unwrap();
+        let pinned_id = ram_lake.store_event("test", "cargo test").unwrap();
+        ram_lake.pin(pinned_id).unwrap();
+
+        // Everything but the pinned event moves to the cold tier
+        ram_lake.demote_idle().unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 10);
+
+        // Cold embeddings leave the resident matrix and are not searched
+        assert!(ram_lake.search_similar(DEFAULT_COLLECTION, vec![0.5; 4], 1).unwrap().is_empty());
+
+        // Reads are served from either tier and promote the entry with its embeddings;
+        // an embedding takes its blob and a 32-byte row with its norm
+        assert_eq!(ram_lake.get_code(code_id).unwrap().1, "fn main() {}");
+        assert_eq!(ram_lake.get_event(event_id).unwrap().1, "cargo build");
+        assert_eq!(ram_lake.search_similar(DEFAULT_COLLECTION, vec![0.5; 4], 1).unwrap()[0].0, code_id);
+        assert_eq!(ram_lake.get_memory_usage(), 12 + (16 + 36) + 11 + 10);
+    }
+
+    /// Test that cold entries take no RAM across a restart and free none when deleted
+    #[test]
+    fn test_cold_entries_across_restart() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let cold = tempfile::tempdir().unwrap();
+
+        let (code_id, event_id) = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path(), cold.path())).unwrap();
+            let code_id = ram_lake.store_code("lib.rs", "pub fn lib() {}", "rust").unwrap();
+            let event_id = ram_lake.store_event("build", "cargo build").unwrap();
+            ram_lake.demote_idle().unwrap();
+            (code_id, event_id)
+        };
+
+        // Demotion disabled: cold entries stay readable
+        let config = RamLakeConfig {
+            cold_tier: ColdTier {
+                enabled: false,
+                ..test_config(backups.path(), cold.path()).cold_tier
+            },
+            ..test_config(backups.path(), cold.path())
+        };
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), config).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 0);
+
+        ram_lake.delete_event(event_id).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 0);
+        assert!(ram_lake.get_event(event_id).is_err());
+
+        assert_eq!(ram_lake.get_code(code_id).unwrap().1, "pub fn lib() {}");
+        assert_eq!(ram_lake.get_memory_usage(), 15);
+        ram_lake.delete_code(code_id).unwrap();
+        assert_eq!(ram_lake.get_memory_usage(), 0);
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::Path;
+use uuid::Uuid;
+
+use postdevai::core::memory::ramlake::{RamLake, Transaction, RamLakeConfig, RevisionRetention, DistanceMetric, DEFAULT_COLLECTION};
+use super::common;
+
+/// Build a RAM-Lake config for tests
+fn test_config(backup_path: &Path) -> RamLakeConfig {
+    RamLakeConfig {
+        // Keep only the live revision so usage tracks the live data
+        revision_retention: RevisionRetention {
+            keep_last: Some(1),
+            max_age_secs: None,
+        },
+        ..common::test_config(backup_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that a transaction applies writes across all stores
+    #[test]
+    fn test_commit_spans_stores() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        let (code_id, event_id) = ram_lake.transaction(|tx| {
+            let code_id = tx.store_code("src/main.rs", "fn main() {}", "rust");
+            tx.index_code(code_id, vec![0.1, 0.2, 0.3, 0.4]);
+            let event_id = tx.store_event("edit", "saved src/main.rs");
+            tx.store_metadata(event_id, "edited", code_id);
+            Ok((code_id, event_id))
+        }).unwrap();
+
+        assert_eq!(ram_lake.get_code(code_id).unwrap().1, "fn main() {}");
+        assert_eq!(ram_lake.get_event(event_id).unwrap().1, "saved src/main.rs");
+        assert_eq!(ram_lake.get_related(code_id, Some("edited")).unwrap().len(), 1);
+        assert_eq!(ram_lake.search_similar(DEFAULT_COLLECTION, vec![0.1, 0.2, 0.3, 0.4], 1).unwrap()[0].0, code_id);
+        assert!(ram_lake.get_memory_usage() > 0);
+    }
+
+    /// Test that a failing write undoes the earlier writes of the transaction
+    #[test]
+    fn test_failed_write_rolls_back() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        let code_id = ram_lake.store_code("lib.rs", "pub fn lib() {}", "rust").unwrap();
+        ram_lake.index_code(code_id, vec![1.0, 0.0, 0.0, 0.0]).unwrap();
+        let event_id = ram_lake.store_event("build", "cargo build").unwrap();
+        ram_lake.store_metadata(event_id, "built", code_id).unwrap();
+        let usage = ram_lake.get_memory_usage();
+
+        let mut tx = Transaction::new();
+        tx.update_code(code_id, "pub fn changed() {}");
+        tx.delete_code(code_id);
+        let new_id = tx.store_code("new.rs", "fn new() {}", "rust");
+        tx.store_event("edit", "rewrote lib.rs");
+        tx.delete_event(Uuid::new_v4());
+
+        let error = ram_lake.commit(tx).unwrap_err();
+        assert!(error.contains("rolled back"));
+
+        // Every store is back to its state before the transaction
+        assert_eq!(ram_lake.get_code(code_id).unwrap().1, "pub fn lib() {}");
+        assert!(ram_lake.get_code(new_id).is_err());
+        assert_eq!(ram_lake.search_similar(DEFAULT_COLLECTION, vec![1.0, 0.0, 0.0, 0.0], 10).unwrap().len(), 1);
+        assert_eq!(ram_lake.get_related(code_id, None).unwrap(), vec![(event_id, "built".to_string(), code_id)]);
+        assert_eq!(ram_lake.get_memory_usage(), usage);
+    }
+
+    /// Test that a transaction indexes into a named collection and undoes it on rollback
+    #[test]
+    fn test_index_into_collection() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        ram_lake.create_vector_collection("events", 2, DistanceMetric::Cosine, None).unwrap();
+
+        let event_id = ram_lake.transaction(|tx| {
+            let event_id = tx.store_event("build", "cargo build");
+            tx.index_entity("events", event_id, vec![1.0, 0.0]);
+            Ok(event_id)
+        }).unwrap();
+        assert_eq!(ram_lake.search_similar("events", vec![1.0, 0.0], 1).unwrap()[0].0, event_id);
+        let usage = ram_lake.get_memory_usage();
+
+        let mut tx = Transaction::new();
+        let other_id = tx.store_event("test", "cargo test");
+        tx.index_entity("events", other_id, vec![0.0, 1.0]);
+        tx.delete_event(Uuid::new_v4());
+        assert!(ram_lake.commit(tx).is_err());
+
+        assert_eq!(ram_lake.search_similar("events", vec![0.0, 1.0], 10).unwrap().len(), 1);
+        assert_eq!(ram_lake.get_memory_usage(), usage);
+
+        // The embedding must match the collection's dimension, not the default one
+        let mut tx = Transaction::new();
+        tx.index_entity("events", Uuid::new_v4(), vec![1.0, 0.0, 0.0]);
+        assert!(ram_lake.commit(tx).is_err());
+    }
+
+    /// Test that a transaction interrupted by a crash is undone on reopen
+    #[test]
+    fn test_interrupted_transaction_recovered() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+
+        let code_id = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+            ram_lake.store_code("main.rs", "fn main() {}", "rust").unwrap()
+        };
+
+        // Simulate a crash right after the journaled write of an uncommitted transaction
+        std::fs::write(
+            ramdisk.path().join("metadata").join("transaction.log"),
+            format!("{{\"RemoveCode\":\"{}\"}}\n", code_id),
+        ).unwrap();
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        assert!(ram_lake.get_code(code_id).is_err());
+        assert_eq!(ram_lake.get_memory_usage(), 0);
+        assert_eq!(std::fs::metadata(ramdisk.path().join("metadata").join("transaction.log")).unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::Path;
+use serde_json::Value;
+use uuid::Uuid;
+
+use postdevai::core::memory::ramlake::{RamLake, RamLakeConfig, ConsistencyIssue};
+use super::common::test_config;
+
+/// Read a JSON snapshot file of a store
+fn read_json(path: &Path) -> Value {
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+/// Write a JSON snapshot file of a store
+fn write_json(path: &Path, value: &Value) {
+    fs::write(path, serde_json::to_string(value).unwrap()).unwrap()
+}
+
+/// Fill a RAM-Lake and damage its files in every way `verify` detects
+///
+/// Returns the issues planted and the IDs of the event that lost its blob
+/// and of the one that stays intact.
+fn plant_issues(ramdisk: &Path, backups: &Path) -> (Vec<ConsistencyIssue>, Uuid, Uuid) {
+    let (orphaned, missing, intact, code_id, unknown) = {
+        let ram_lake = RamLake::new(ramdisk.to_path_buf(), test_config(backups)).unwrap();
+        let orphaned = ram_lake.store_event("build", "cargo build").unwrap();
+        let missing = ram_lake.store_event("test", "cargo test").unwrap();
+        let intact = ram_lake.store_event("run", "cargo run").unwrap();
+        let code_id = ram_lake.store_code("src/main.rs", "fn main() {}", "rust").unwrap();
+
+        // A relation to an entity no store knows
+        let unknown = Uuid::new_v4();
+        ram_lake.store_metadata(code_id, "calls", unknown).unwrap();
+
+        ram_lake.checkpoint().unwrap();
+        (orphaned, missing, intact, code_id, unknown)
+    };
+
+    let history = ramdisk.join("history");
+    let mut metadata = read_json(&history.join("metadata.json"));
+    let mut index = read_json(&history.join("index.json"));
+
+    // Drop the metadata of one event but keep its blob
+    let orphaned_key = orphaned.to_string();
+    let orphaned_file = metadata[&orphaned_key]["file_path"].as_str().unwrap().to_string();
+    metadata.as_object_mut().unwrap().remove(&orphaned_key);
+    index["ids"].as_array_mut().unwrap().retain(|id| id.as_str() != Some(orphaned_key.as_str()));
+    for ids in index["type_map"].as_object_mut().unwrap().values_mut() {
+        ids.as_array_mut().unwrap().retain(|id| id.as_str() != Some(orphaned_key.as_str()));
+    }
+    index["count"] = Value::from(2);
+
+    // Point the metadata of another event at a blob that does not exist, orphaning its own
+    let missing_key = missing.to_string();
+    let missing_file = metadata[&missing_key]["file_path"].as_str().unwrap().to_string();
+    metadata[&missing_key]["file_path"] = Value::from("missing.event");
+
+    write_json(&history.join("metadata.json"), &metadata);
+    write_json(&history.join("index.json"), &index);
+
+    // Code index count out of sync with its IDs
+    let code_index_path = ramdisk.join("code").join("index.json");
+    let mut code_index = read_json(&code_index_path);
+    code_index["count"] = Value::from(99);
+    write_json(&code_index_path, &code_index);
+
+    // Leftover of an interrupted atomic write
+    fs::write(ramdisk.join("metadata").join("relations.json.tmp"), "{").unwrap();
+
+    let issues = vec![
+        ConsistencyIssue::CountMismatch { store: "code".to_string(), count: 99, actual: 1 },
+        ConsistencyIssue::OrphanedBlob { store: "history".to_string(), file: orphaned_file },
+        ConsistencyIssue::OrphanedBlob { store: "history".to_string(), file: missing_file },
+        ConsistencyIssue::MissingBlob { store: "history".to_string(), id: missing },
+        ConsistencyIssue::StaleTempFile { store: "metadata".to_string(), file: "relations.json.tmp".to_string() },
+        ConsistencyIssue::DanglingRelation { source_id: code_id, relation: "calls".to_string(), target_id: unknown },
+    ];
+
+    (issues, missing, intact)
+}
+
+/// Assert that two lists hold the same issues in any order
+fn assert_same_issues(found: &[ConsistencyIssue], expected: &[ConsistencyIssue]) {
+    assert_eq!(found.len(), expected.len(), "found {:?}", found);
+    for issue in expected {
+        assert!(found.contains(issue), "{:?} not found in {:?}", issue, found);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that verify reports every kind of issue and repairs them only when asked
+    #[test]
+    fn test_verify_reports_and_repairs() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let (expected, missing, intact) = plant_issues(ramdisk.path(), backups.path());
+        let temp_file = ramdisk.path().join("metadata").join("relations.json.tmp");
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+
+        // Reporting changes nothing
+        for _ in 0..2 {
+            let report = ram_lake.verify(false).unwrap();
+            assert!(!report.repaired);
+            assert_same_issues(&report.issues, &expected);
+        }
+        assert!(temp_file.exists());
+
+        let report = ram_lake.verify(true).unwrap();
+        assert!(report.repaired);
+        assert_same_issues(&report.issues, &expected);
+        assert!(ram_lake.verify(false).unwrap().is_clean());
+
+        assert!(!temp_file.exists());
+        assert!(ram_lake.get_event(missing).is_err());
+        assert_eq!(ram_lake.get_event(intact).unwrap().1, "cargo run");
+
+        // The repairs are durable
+        drop(ram_lake);
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        assert!(ram_lake.verify(false).unwrap().is_clean());
+    }
+
+    /// Test that a RAM-Lake configured to repair on startup opens clean
+    #[test]
+    fn test_repair_on_startup() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let (_, missing, intact) = plant_issues(ramdisk.path(), backups.path());
+
+        let config = RamLakeConfig {
+            repair_on_startup: true,
+            ..test_config(backups.path())
+        };
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), config).unwrap();
+        assert!(ram_lake.verify(false).unwrap().is_clean());
+        assert!(!ramdisk.path().join("metadata").join("relations.json.tmp").exists());
+        assert!(ram_lake.get_event(missing).is_err());
+        assert_eq!(ram_lake.get_event(intact).unwrap().1, "cargo run");
+    }
+}
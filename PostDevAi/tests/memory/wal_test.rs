@@ -0,0 +1,113 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use uuid::Uuid;
+
+use postdevai::core::memory::ramlake::{RamLake, DEFAULT_COLLECTION};
+use super::common::test_config;
+
+/// Size of a store's write-ahead log
+fn wal_size(ramdisk: &Path, store: &str) -> u64 {
+    fs::metadata(ramdisk.join(store).join("wal.log")).unwrap().len()
+}
+
+/// Store one entity in each store and return the code and event IDs
+fn populate(ram_lake: &RamLake) -> (Uuid, Uuid) {
+    let code_id = ram_lake.store_code("src/main.rs", "fn main() {}", "rust").unwrap();
+    ram_lake.index_code(code_id, vec![1.0, 0.0, 0.0, 0.0]).unwrap();
+    let event_id = ram_lake.store_event("build", "cargo build").unwrap();
+    ram_lake.store_metadata(event_id, "built", code_id).unwrap();
+    (code_id, event_id)
+}
+
+/// Assert that the entities stored by `populate` are all there
+fn assert_populated(ram_lake: &RamLake, code_id: Uuid, event_id: Uuid) {
+    assert_eq!(ram_lake.get_code(code_id).unwrap().1, "fn main() {}");
+    assert_eq!(ram_lake.get_event(event_id).unwrap().1, "cargo build");
+    assert_eq!(ram_lake.search_similar(DEFAULT_COLLECTION, vec![1.0, 0.0, 0.0, 0.0], 1).unwrap()[0].0, code_id);
+    assert_eq!(ram_lake.get_related(code_id, None).unwrap(), vec![(event_id, "built".to_string(), code_id)]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that writes never checkpointed are replayed from the logs of every store
+    #[test]
+    fn test_replay_without_checkpoint() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+
+        let ((code_id, event_id), usage) = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+            (populate(&ram_lake), ram_lake.get_memory_usage())
+        };
+        for store in ["vectors", "code", "history", "metadata"] {
+            assert!(wal_size(ramdisk.path(), store) > 0, "{} was checkpointed", store);
+        }
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        assert_populated(&ram_lake, code_id, event_id);
+        assert_eq!(ram_lake.get_memory_usage(), usage);
+    }
+
+    /// Test that a half-written record at the end of a log is dropped and the log truncated
+    #[test]
+    fn test_torn_tail_truncated() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+
+        let (code_id, event_id) = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+            populate(&ram_lake)
+        };
+
+        // Simulate a crash in the middle of appending a record
+        let sizes: Vec<u64> = ["code", "history"].iter().map(|store| wal_size(ramdisk.path(), store)).collect();
+        for store in ["code", "history"] {
+            let mut wal = fs::OpenOptions::new().append(true).open(ramdisk.path().join(store).join("wal.log")).unwrap();
+            wal.write_all(br#"{"Put":{"id":"8d4c"#).unwrap();
+        }
+
+        let later_id = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+            assert_populated(&ram_lake, code_id, event_id);
+            assert_eq!(wal_size(ramdisk.path(), "code"), sizes[0]);
+            assert_eq!(wal_size(ramdisk.path(), "history"), sizes[1]);
+
+            // Records appended after the truncation replay cleanly
+            ram_lake.store_event("test", "cargo test").unwrap()
+        };
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        assert_populated(&ram_lake, code_id, event_id);
+        assert_eq!(ram_lake.get_event(later_id).unwrap().1, "cargo test");
+    }
+
+    /// Test that a checkpoint folds the logs into the snapshots and empties them
+    #[test]
+    fn test_checkpoint_resets_logs() {
+        let ramdisk = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+
+        let (code_id, event_id) = {
+            let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+            let ids = populate(&ram_lake);
+            ram_lake.checkpoint().unwrap();
+            ids
+        };
+        for store in ["vectors", "code", "history", "metadata"] {
+            assert_eq!(wal_size(ramdisk.path(), store), 0, "{} log was not reset", store);
+        }
+
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        assert_populated(&ram_lake, code_id, event_id);
+
+        // A delete after the checkpoint is logged and replayed over the snapshot
+        ram_lake.delete_event(event_id).unwrap();
+        drop(ram_lake);
+        let ram_lake = RamLake::new(ramdisk.path().to_path_buf(), test_config(backups.path())).unwrap();
+        assert!(ram_lake.get_event(event_id).is_err());
+        assert!(ram_lake.get_related(code_id, None).unwrap().is_empty());
+    }
+}
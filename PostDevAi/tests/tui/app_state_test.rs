@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::time::{Duration, Instant};
 use chrono::{DateTime, Utc, Local};
 use uuid::Uuid;
@@ -66,10 +67,18 @@ mod tests {
             vector_store_size: 20 * 1024 * 1024 * 1024, // 20 GB
             code_store_size: 15 * 1024 * 1024 * 1024,   // 15 GB
             history_store_size: 10 * 1024 * 1024 * 1024, // 10 GB
+            code_logical_size: 15 * 1024 * 1024 * 1024,  // 15 GB
+            history_logical_size: 10 * 1024 * 1024 * 1024, // 10 GB
             metadata_store_size: 5 * 1024 * 1024 * 1024, // 5 GB
             indexed_files: 1000,
             vector_entries: 50000,
             history_events: 5000,
+            namespaces: HashMap::new(),
+            evicted_entries: 10,
+            evicted_size: 1024 * 1024, // 1 MB
+            recent_evictions: Vec::new(),
+            cold_size: 0,
+            vector_collections: HashMap::new(),
         };
         
         // Update metrics